@@ -0,0 +1,91 @@
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use unblock::level::BlockDir;
+use unblock::solver::SolverConfig;
+use unblock::{pack, solver};
+
+/// The built-in pack repeated until it is the size of a large community pack.
+fn large_pack() -> Vec<u8> {
+    let mut data = Vec::new();
+    while data.len() < 500 * 80 {
+        data.extend_from_slice(pack::BUILTIN);
+        data.push(b'\n');
+    }
+    data
+}
+
+fn parse(c: &mut Criterion) {
+    let data = large_pack();
+    c.bench_function("parse large pack", |b| {
+        b.iter(|| pack::parse(black_box(&data)))
+    });
+}
+
+/// Slides every movable block of every level as far as it goes both ways.
+fn slide_limit(c: &mut Criterion) {
+    let levels = pack::parse(pack::BUILTIN);
+    c.bench_function("slide limit sweep", |b| {
+        b.iter(|| {
+            for level in &levels {
+                for (index, block) in level.blocks.iter().enumerate() {
+                    if block.dir != BlockDir::Static {
+                        black_box(level.slide_limit(index, -8));
+                        black_box(level.slide_limit(index, 8));
+                    }
+                }
+            }
+        })
+    });
+}
+
+fn solve(c: &mut Criterion) {
+    let levels = pack::parse(pack::BUILTIN);
+    // Level 8 takes the most moves of the built-in levels.
+    let hard = &levels[7];
+    c.bench_function("solve hardest level", |b| {
+        b.iter(|| solver::solve(black_box(hard), SolverConfig::default()))
+    });
+    let ida = SolverConfig {
+        strategy: solver::Strategy::IdaStar,
+        ..SolverConfig::default()
+    };
+    c.bench_function("solve hardest level with IDA*", |b| {
+        b.iter(|| solver::solve_with(black_box(hard), ida))
+    });
+    c.bench_function("solve built-in pack", |b| {
+        b.iter(|| {
+            for level in &levels {
+                black_box(solver::solve(level, SolverConfig::default()));
+            }
+        })
+    });
+}
+
+/// What the HUD pays: the first question about a level, and one after a
+/// move off the shortest path.
+fn remaining(c: &mut Criterion) {
+    let levels = pack::parse(pack::BUILTIN);
+    let mut warm = solver::Solver::new();
+    warm.remaining(&levels[7]);
+    let best = solver::solve(&levels[7], SolverConfig::default())
+        .unwrap()
+        .steps[0];
+    let wrong = solver::moves(&levels[7])
+        .into_iter()
+        .find(|step| *step != best)
+        .unwrap();
+    let mut moved = levels[7].clone();
+    moved.move_block(wrong.block, (wrong.x, wrong.y));
+    c.bench_function("remaining from the start", |b| {
+        b.iter(|| solver::Solver::new().remaining(black_box(&levels[7])))
+    });
+    c.bench_function("remaining after a wrong move", |b| {
+        b.iter_batched(
+            || warm.clone(),
+            |mut solver| solver.remaining(black_box(&moved)),
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+criterion_group!(benches, parse, slide_limit, solve, remaining);
+criterion_main!(benches);
@@ -0,0 +1,16 @@
+//! Any bytes read as a pack, and the levels in it looked over, as a pack
+//! downloaded from anywhere would be.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use unblock::{pack, validate};
+
+fuzz_target!(|data: &[u8]| {
+    let _ = pack::info(data);
+    for mut level in pack::parse(data) {
+        let _ = validate::check(&level);
+        let _ = level.canonical_hash();
+        level.reset();
+    }
+});
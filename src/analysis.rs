@@ -0,0 +1,75 @@
+use crate::jobs::Worker;
+use std::time::{Duration, Instant};
+use unblock::level::{Cell, Level};
+use unblock::limits;
+use unblock::solver::{self, Solution, Solver};
+
+type Position = [Cell; 64];
+
+/// Works out how many moves the position being played still needs, and one
+/// way of making them, on a background thread, so the update loop never
+/// waits on the solver. Levels too big to work out, and phases before the
+/// last, are never answered.
+pub struct Analysis {
+    worker: Worker<Level, (Position, Option<Option<Solution>>, Duration)>,
+    asked: Option<Position>,
+    latest: Option<(Position, Option<Option<Solution>>)>,
+}
+
+impl Analysis {
+    pub fn start() -> Analysis {
+        let mut solver = Solver::with_budget(limits::SOLVER_POSITIONS);
+        Analysis {
+            worker: Worker::start(move |level: Level| {
+                let started = Instant::now();
+                let solution = solver.solution(&level);
+                let known = !solver.gave_up(&level) && solver::applies(&level);
+                (level.data, known.then_some(solution), started.elapsed())
+            }),
+            asked: None,
+            latest: None,
+        }
+    }
+
+    /// Asks about `level` if it changed since the last call, and collects
+    /// any answers that have come back, returning how long working out each
+    /// of them took.
+    pub fn update(&mut self, level: &Level) -> Vec<Duration> {
+        if self.asked != Some(level.data) {
+            self.asked = Some(level.data);
+            self.worker.send(level.clone());
+        }
+        let mut took = Vec::new();
+        while let Some((position, solution, time)) = self.worker.poll() {
+            self.latest = Some((position, solution));
+            took.push(time);
+        }
+        took
+    }
+
+    /// The fewest moves that solve `level` from here: None while that is
+    /// still being worked out, Some(None) if it cannot be solved.
+    pub fn remaining(&self, level: &Level) -> Option<Option<usize>> {
+        self.solution(level)
+            .map(|solution| solution.map(|solution| solution.len()))
+    }
+
+    /// How many times each of `level`'s blocks moves in a shortest solution
+    /// from here, by block: None while that is still being worked out or if
+    /// it cannot be solved.
+    pub fn heat(&self, level: &Level) -> Option<Vec<usize>> {
+        let solution = self.solution(level)??;
+        let mut heat = vec![0; level.blocks.len()];
+        for step in &solution.steps {
+            heat[step.block] += 1;
+        }
+        Some(heat)
+    }
+
+    fn solution(&self, level: &Level) -> Option<Option<&Solution>> {
+        match &self.latest {
+            Some((position, Some(solution))) if *position == level.data => Some(solution.as_ref()),
+            _ => None,
+        }
+    }
+}
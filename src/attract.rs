@@ -0,0 +1,181 @@
+//! Attract mode: left a minute on a level not yet started, the game plays
+//! the pack by itself, the walkthrough the pack gives for each level, with
+//! its notes, or else the solver's shortest solution, made a move at a time
+//! on a dimmed board, until the mouse or keyboard is touched. 1, 2 and 3
+//! play it at half, normal and double speed and Space stops it to make one
+//! move a press, none of which count as touching.
+
+use crate::i18n;
+use crate::input::UnblockInput;
+use crate::jobs::Worker;
+use crate::render::{leading, Color, Rectangle, Renderer, Sprite};
+use crate::text;
+use crate::theme::Theme;
+use crate::timing::{Playback, Speed};
+use crate::{Board, Viewport};
+use coffee::input::keyboard::KeyCode;
+use std::time::{Duration, Instant};
+use unblock::level::Level;
+use unblock::limits;
+use unblock::replay::Replay;
+use unblock::solver::{self, SolverConfig};
+
+/// How long nothing has to be touched for before attract mode starts.
+pub const IDLE: Duration = Duration::from_secs(60);
+/// Time between the moves played, and how long a solved level stays up.
+const STEP: Duration = Duration::from_millis(600);
+const LINGER: Duration = Duration::from_secs(2);
+
+pub struct Attract {
+    levels: Vec<Level>,
+    index: usize,
+    board: Board,
+    // The level's solution, once the solver has it, and the clock it is
+    // played by.
+    solution: Option<Replay>,
+    playback: Playback,
+    solver: Worker<Level, (u64, Replay)>,
+    // When attract mode started, or the playback keys were last touched, so
+    // touching anything after ends it.
+    started: Instant,
+}
+
+impl Attract {
+    /// Plays `levels` in turn from the one at `index`.
+    pub fn start(levels: Vec<Level>, index: usize) -> Attract {
+        let solver = Worker::start(|level: Level| {
+            if let Some(walkthrough) = &level.walkthrough {
+                return (level.id(), walkthrough.clone());
+            }
+            let mut replay = Replay::new();
+            let config = SolverConfig {
+                budget: Some(limits::SOLVER_POSITIONS),
+                ..SolverConfig::default()
+            };
+            let steps = solver::solve(&level, config).map_or_else(Vec::new, |s| s.steps);
+            for (i, step) in steps.into_iter().enumerate() {
+                replay.record(STEP * (i as u32 + 1), step.block, (step.x, step.y));
+            }
+            (level.id(), replay)
+        });
+        let level = levels[index].clone();
+        solver.send(level.clone());
+        Attract {
+            levels,
+            index,
+            board: Board::new(level),
+            solution: None,
+            playback: Playback::new(),
+            solver,
+            started: Instant::now(),
+        }
+    }
+
+    /// Handles the playback keys. Returns false once anything else is
+    /// touched, which ends the demo.
+    pub fn interact(&mut self, input: &UnblockInput) -> bool {
+        let speeds = [
+            (KeyCode::Key1, Speed::Half),
+            (KeyCode::Key2, Speed::Normal),
+            (KeyCode::Key3, Speed::Double),
+        ];
+        let keys = speeds.iter().map(|(key, _)| *key).chain([KeyCode::Space]);
+        let mut playing = false;
+        for key in keys {
+            playing |= input.is_key_pressed(key) || input.was_key_released(key);
+        }
+        if !playing {
+            return input.last_touched() <= self.started;
+        }
+        self.started = input.last_touched();
+        for (key, speed) in speeds {
+            if input.was_key_released(key) {
+                self.playback.set_speed(speed);
+            }
+        }
+        if input.was_key_released(KeyCode::Space) {
+            self.step();
+        }
+        true
+    }
+
+    /// Stops playback and makes the next move, or moves on to the next
+    /// level once this one is solved.
+    fn step(&mut self) {
+        let replay = match &self.solution {
+            Some(replay) => replay,
+            None => return,
+        };
+        let elapsed = self.playback.elapsed();
+        let next = match replay.steps.get(replay.steps_at(elapsed)) {
+            Some((at, _)) => *at,
+            None => replay.duration() + LINGER,
+        };
+        self.playback.step_to(next);
+    }
+
+    pub fn update(&mut self, viewport: Viewport) {
+        if let Some((id, replay)) = self.solver.poll() {
+            if id == self.levels[self.index].id() {
+                self.solution = Some(replay);
+                self.playback.restart();
+            }
+        }
+        if let Some(replay) = &self.solution {
+            let elapsed = self.playback.elapsed();
+            if elapsed >= replay.duration() + LINGER {
+                self.index = (self.index + 1) % self.levels.len();
+                let level = self.levels[self.index].clone();
+                self.solver.send(level.clone());
+                self.board = Board::new(level);
+                self.solution = None;
+            } else {
+                let steps = replay.steps_at(elapsed);
+                if steps != self.board.level.moves.len() {
+                    self.board.level = replay.play(&self.levels[self.index], steps);
+                    self.board.redraw();
+                }
+            }
+        }
+        self.board.update(viewport);
+    }
+
+    pub fn draw(&mut self, renderer: &mut impl Renderer<Sprite = Sprite>, theme: &Theme) {
+        self.board.draw(renderer, theme);
+        let screen = Rectangle {
+            x: 0.0,
+            y: 0.0,
+            width: renderer.width(),
+            height: renderer.height(),
+        };
+        renderer.draw_rect(
+            screen,
+            Color {
+                a: 0.5,
+                ..theme.background
+            },
+            None,
+        );
+        renderer.draw_text(
+            &i18n::fill(
+                "demo.hud",
+                &[&(self.index + 1), &self.playback.speed().label()],
+            ),
+            leading(10.0, renderer.width() - 10.0, 10.0),
+            text::SMALL,
+            Color::WHITE,
+        );
+        if let Some(note) = self
+            .solution
+            .as_ref()
+            .and_then(|replay| replay.note(self.board.level.moves.len()))
+        {
+            renderer.draw_text(
+                note,
+                leading(10.0, renderer.width() - 10.0, renderer.height() - 30.0),
+                text::SMALL,
+                Color::WHITE,
+            );
+        }
+    }
+}
@@ -0,0 +1,92 @@
+#[cfg(feature = "audio")]
+use rodio::{source::SineWave, OutputStream, Sink, Source};
+#[cfg(feature = "audio")]
+use std::time::Duration;
+
+/// Quiet background pad played in zen mode.
+///
+/// Audio is best effort: when no output device is available the game stays
+/// silent rather than failing to start.
+#[cfg(feature = "audio")]
+pub struct Music {
+    // The stream has to outlive the sink or playback stops.
+    _stream: Option<OutputStream>,
+    sink: Option<Sink>,
+}
+
+#[cfg(feature = "audio")]
+impl Music {
+    /// Music that never plays, for games driven without a window.
+    pub fn silent() -> Music {
+        Music {
+            _stream: None,
+            sink: None,
+        }
+    }
+
+    pub fn new() -> Music {
+        let (stream, handle) = match OutputStream::try_default() {
+            Ok(output) => output,
+            Err(e) => {
+                println!("audio unavailable: {}", e);
+                return Music {
+                    _stream: None,
+                    sink: None,
+                };
+            }
+        };
+        let sink = match Sink::try_new(&handle) {
+            Ok(sink) => sink,
+            Err(e) => {
+                println!("audio unavailable: {}", e);
+                return Music {
+                    _stream: Some(stream),
+                    sink: None,
+                };
+            }
+        };
+        sink.pause();
+        // A soft A minor chord.
+        let pad = SineWave::new(220.0)
+            .mix(SineWave::new(261.63))
+            .mix(SineWave::new(329.63))
+            .amplify(0.02)
+            .fade_in(Duration::from_secs(3));
+        sink.append(pad);
+        Music {
+            _stream: Some(stream),
+            sink: Some(sink),
+        }
+    }
+
+    pub fn play(&self) {
+        if let Some(sink) = &self.sink {
+            sink.play();
+        }
+    }
+
+    pub fn stop(&self) {
+        if let Some(sink) = &self.sink {
+            sink.pause();
+        }
+    }
+}
+
+/// Built without the audio feature, zen mode is silent.
+#[cfg(not(feature = "audio"))]
+pub struct Music;
+
+#[cfg(not(feature = "audio"))]
+impl Music {
+    pub fn silent() -> Music {
+        Music
+    }
+
+    pub fn new() -> Music {
+        Music
+    }
+
+    pub fn play(&self) {}
+
+    pub fn stop(&self) {}
+}
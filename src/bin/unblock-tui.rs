@@ -0,0 +1,265 @@
+//! The game in a terminal: the board as colored cells, played with the
+//! keyboard. Handy for trying out a pack over SSH.
+//!
+//! Usage: unblock-tui [PACK]
+
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Color, Print, ResetColor, SetBackgroundColor};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+use std::io::{self, Write};
+use std::process;
+use unblock::level::{Block, BlockDir, BlockType, Level, TILES_HIGH, TILES_WIDE};
+use unblock::pack;
+use unblock::win::WinCondition;
+
+const HELP: &[&str] = &[
+    "Tab / Shift+Tab  pick a block",
+    "h j k l / arrows move it one cell",
+    "H J K L          slide it all the way",
+    "u undo   y redo   r reset   [ ] level   q quit",
+];
+
+fn color(block: &Block) -> Color {
+    match block.r#type {
+        BlockType::Player => Color::Red,
+        BlockType::Wall => Color::White,
+        BlockType::Exit => Color::Yellow,
+        BlockType::Other(_) => match block.dir {
+            BlockDir::LeftRight => Color::Blue,
+            BlockDir::UpDown => Color::Green,
+            _ => panic!("No Static + Other blocks exist"),
+        },
+    }
+}
+
+/// Puts the terminal back the way it was found, however the game ends.
+struct Terminal;
+
+impl Terminal {
+    fn enter() -> io::Result<Terminal> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), EnterAlternateScreen, Hide)?;
+        Ok(Terminal)
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// The pack being played and where the player is in it.
+struct Game {
+    levels: Vec<Level>,
+    current: usize,
+    // Index into the level's blocks of the block the keys move.
+    selected: usize,
+}
+
+impl Game {
+    fn new(levels: Vec<Level>) -> Game {
+        let mut game = Game {
+            levels,
+            current: 0,
+            selected: 0,
+        };
+        game.select_player();
+        game
+    }
+
+    fn level(&mut self) -> &mut Level {
+        &mut self.levels[self.current]
+    }
+
+    fn go_to(&mut self, index: usize) {
+        self.current = index;
+        self.level().reset();
+        self.select_player();
+    }
+
+    fn select_player(&mut self) {
+        let level = &self.levels[self.current];
+        self.selected = level
+            .blocks
+            .iter()
+            .position(|b| b.r#type == BlockType::Player)
+            .unwrap_or(0);
+    }
+
+    /// Picks the next movable block, or the previous one going `back`.
+    fn cycle(&mut self, back: bool) {
+        let blocks = &self.levels[self.current].blocks;
+        let count = blocks.len();
+        for step in 1..=count {
+            let index = if back {
+                (self.selected + count * step - step) % count
+            } else {
+                (self.selected + step) % count
+            };
+            if blocks[index].dir != BlockDir::Static {
+                self.selected = index;
+                return;
+            }
+        }
+    }
+
+    /// Slides the selected block `delta` cells along its axis if the key's
+    /// direction is along it, going as far as it can.
+    fn slide(&mut self, horizontal: bool, delta: isize) {
+        let index = self.selected;
+        let level = self.level();
+        let along = match level.blocks[index].dir {
+            BlockDir::LeftRight => horizontal,
+            BlockDir::UpDown => !horizontal,
+            BlockDir::Static => false,
+        };
+        if along {
+            let phase = level.phase;
+            let target = level.slide_limit(index, delta);
+            level.move_block(index, target);
+            // Blocks are numbered afresh in a new phase.
+            if self.level().phase != phase {
+                self.select_player();
+            }
+        }
+    }
+
+    /// Handles a key press. Returns false once the player wants to quit.
+    fn key(&mut self, key: KeyEvent) -> bool {
+        let far = (TILES_WIDE.max(TILES_HIGH)) as isize;
+        let solved = self.levels[self.current].solved;
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            // Raw mode swallows the usual interrupt.
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return false,
+            KeyCode::Enter if solved && self.current + 1 < self.levels.len() => {
+                self.go_to(self.current + 1)
+            }
+            KeyCode::Char(']') if self.current + 1 < self.levels.len() => {
+                self.go_to(self.current + 1)
+            }
+            KeyCode::Char('[') if self.current > 0 => self.go_to(self.current - 1),
+            KeyCode::Char('r') => {
+                self.level().reset();
+            }
+            KeyCode::Char('u') if !solved => {
+                self.level().undo();
+            }
+            KeyCode::Char('y') if !solved => {
+                self.level().redo();
+            }
+            _ if solved => {}
+            KeyCode::Tab => self.cycle(false),
+            KeyCode::BackTab => self.cycle(true),
+            KeyCode::Char('h') | KeyCode::Left => self.slide(true, -1),
+            KeyCode::Char('l') | KeyCode::Right => self.slide(true, 1),
+            KeyCode::Char('k') | KeyCode::Up => self.slide(false, -1),
+            KeyCode::Char('j') | KeyCode::Down => self.slide(false, 1),
+            KeyCode::Char('H') => self.slide(true, -far),
+            KeyCode::Char('L') => self.slide(true, far),
+            KeyCode::Char('K') => self.slide(false, -far),
+            KeyCode::Char('J') => self.slide(false, far),
+            _ => {}
+        }
+        true
+    }
+
+    /// Draws the board two characters per cell so cells come out roughly
+    /// square, the selected block marked with brackets.
+    fn draw(&self, out: &mut impl Write) -> io::Result<()> {
+        let level = &self.levels[self.current];
+        let mut cells = [(Color::DarkGrey, false); TILES_WIDE * TILES_HIGH];
+        // Walls and the exit first so whatever covers them wins, and
+        // earlier blocks on top as in the game window.
+        let mut order: Vec<usize> = (0..level.blocks.len()).rev().collect();
+        order.sort_by_key(|&i| level.blocks[i].dir != BlockDir::Static);
+        for index in order {
+            let block = &level.blocks[index];
+            for y in block.y1..=block.y2 {
+                for x in block.x1..=block.x2 {
+                    cells[y * TILES_WIDE + x] = (color(block), index == self.selected);
+                }
+            }
+        }
+        queue!(out, Clear(ClearType::All))?;
+        for y in 0..TILES_HIGH {
+            queue!(out, MoveTo(2, 1 + y as u16))?;
+            for x in 0..TILES_WIDE {
+                let (color, selected) = cells[y * TILES_WIDE + x];
+                let mark = if selected { "[]" } else { "  " };
+                queue!(out, SetBackgroundColor(color), Print(mark))?;
+            }
+            queue!(out, ResetColor)?;
+        }
+        let mut status = format!(
+            "Level {}/{}  Moves {}  Cells {}",
+            self.current + 1,
+            self.levels.len(),
+            level.moves.len(),
+            level.cells_moved()
+        );
+        if let Some(par) = level.par {
+            status += &format!("  Par {}", par);
+        }
+        if level.phases() > 1 {
+            status += &format!("  Phase {}/{}", level.phase + 1, level.phases());
+        }
+        if level.gravity() {
+            status += "  Gravity";
+        }
+        if level.win != WinCondition::Exit {
+            status += &format!("  Goal: {}", level.win);
+        }
+        if level.solved {
+            status += "  Solved!";
+            if self.current + 1 < self.levels.len() {
+                status += " Enter for the next level";
+            }
+        }
+        let top = 2 + TILES_HIGH as u16;
+        queue!(out, MoveTo(2, top), Print(status))?;
+        for (row, line) in HELP.iter().enumerate() {
+            queue!(out, MoveTo(2, top + 2 + row as u16), Print(line))?;
+        }
+        out.flush()
+    }
+}
+
+fn run(game: &mut Game) -> io::Result<()> {
+    let _terminal = Terminal::enter()?;
+    let mut out = io::stdout();
+    loop {
+        game.draw(&mut out)?;
+        if let Event::Key(key) = event::read()? {
+            // Windows reports releases too.
+            if key.kind != KeyEventKind::Release && !game.key(key) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+fn main() {
+    let levels = match std::env::args().nth(1) {
+        Some(path) => match std::fs::read(&path) {
+            Ok(data) => pack::parse(&data),
+            Err(err) => {
+                eprintln!("Could not read {}: {}", path, err);
+                process::exit(2);
+            }
+        },
+        None => pack::parse(pack::BUILTIN),
+    };
+    if levels.is_empty() {
+        eprintln!("No levels found");
+        process::exit(1);
+    }
+    if let Err(err) = run(&mut Game::new(levels)) {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,339 @@
+use crate::level::{pos_to_xy, Block, BlockDir, BlockType, Cell, TILES_WIDE};
+use crate::rng::Rng;
+use std::cmp::Reverse;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+/// One bit per cell of the board, bit `y * 8 + x`.
+pub type Bits = u64;
+
+const CELLS: usize = 64;
+
+/// Mixed into every Zobrist key, so keys are the same on every run.
+const ZOBRIST_SEED: u64 = 0x756e_626c_6f63_6b21;
+
+/// How a movable block may move: its length, its axis and, precomputed for
+/// every cell its top left corner could be on, the cells it then covers
+/// (0 where it would hang off the board) and its Zobrist key there.
+struct Piece {
+    len: usize,
+    horizontal: bool,
+    masks: [Bits; CELLS],
+    keys: [u64; CELLS],
+}
+
+/// The parts of a level that moves never change, shared by every position
+/// of it.
+struct Layout {
+    // Cells nothing can move onto: walls and anything unrecognised.
+    fixed: Bits,
+    exit: Bits,
+    player: Option<usize>,
+    // Whether vertical blocks fall after every move.
+    gravity: bool,
+    // By block index; None for blocks that do not move.
+    pieces: Vec<Option<Piece>>,
+}
+
+/// A position of a level as the solver sees it: where each block's top left
+/// corner is, which cells are occupied, and a Zobrist hash of the position
+/// kept up to date move by move. Cloning is cheap, as the layout is shared.
+///
+/// Blocks are numbered as in `Level::blocks`.
+#[derive(Clone)]
+pub struct Bitboard {
+    layout: Arc<Layout>,
+    cells: [u8; CELLS],
+    occupied: Bits,
+    hash: u64,
+}
+
+impl Bitboard {
+    /// The position of `blocks` on a board whose cells hold `data` (which
+    /// only matters for cells no block covers). With `gravity`, `settle`
+    /// drops vertical blocks as far as they go.
+    pub fn new(data: &[Cell; CELLS], blocks: &[Block], gravity: bool) -> Bitboard {
+        let mut layout = Layout {
+            fixed: 0,
+            exit: 0,
+            player: None,
+            gravity,
+            pieces: Vec::with_capacity(blocks.len()),
+        };
+        let mut cells = [0; CELLS];
+        let mut occupied = 0;
+        let mut hash = 0;
+        for (index, block) in blocks.iter().enumerate() {
+            let cell = block.y1 * TILES_WIDE + block.x1;
+            cells[index] = cell as u8;
+            if block.r#type == BlockType::Player {
+                layout.player = Some(index);
+            }
+            let piece = match block.dir {
+                BlockDir::Static => None,
+                BlockDir::LeftRight => Some(piece(index, 1 + block.x2 - block.x1, true)),
+                BlockDir::UpDown => Some(piece(index, 1 + block.y2 - block.y1, false)),
+            };
+            match &piece {
+                Some(piece) => {
+                    occupied |= piece.masks[cell];
+                    hash ^= piece.keys[cell];
+                }
+                None if block.r#type == BlockType::Exit => layout.exit |= 1 << cell,
+                None => {}
+            }
+            layout.pieces.push(piece);
+        }
+        for (cell, &on) in data.iter().enumerate() {
+            if on == Cell::Wall && occupied & (1 << cell) == 0 {
+                layout.fixed |= 1 << cell;
+            }
+        }
+        Bitboard {
+            layout: Arc::new(layout),
+            cells,
+            occupied,
+            hash,
+        }
+    }
+
+    /// Grid cell of the top left corner of the block at `index`.
+    pub fn position(&self, index: usize) -> (usize, usize) {
+        pos_to_xy(self.cells[index] as usize)
+    }
+
+    /// True once the player covers the exit.
+    pub fn is_solved(&self) -> bool {
+        match self.layout.player {
+            Some(player) => {
+                self.covers(player, self.cells[player] as usize) & self.layout.exit != 0
+            }
+            None => false,
+        }
+    }
+
+    /// At least how many moves solving takes from here: the player has to
+    /// move, and so does every block between it and the exit (the blocking
+    /// car heuristic). 0 once solved.
+    pub fn lower_bound(&self) -> usize {
+        if self.is_solved() {
+            return 0;
+        }
+        // Blocks in the way may fall out of it without a move being made.
+        if self.layout.gravity {
+            return 1;
+        }
+        let (player, piece) = match self.layout.player {
+            Some(player) => match &self.layout.pieces[player] {
+                Some(piece) => (player, piece),
+                None => return 1,
+            },
+            None => return 1,
+        };
+        // With more than one exit, any of them may be the one used.
+        if self.layout.exit.count_ones() != 1 {
+            return 1;
+        }
+        let exit = self.layout.exit.trailing_zeros() as usize;
+        let stride = if piece.horizontal { 1 } else { TILES_WIDE };
+        let first = self.cells[player] as usize;
+        let last = first + (piece.len - 1) * stride;
+        let in_lane = if piece.horizontal {
+            exit / TILES_WIDE == first / TILES_WIDE
+        } else {
+            exit % TILES_WIDE == first % TILES_WIDE
+        };
+        if !in_lane {
+            return 1;
+        }
+        let (from, to) = if exit > last {
+            (last + stride, exit)
+        } else {
+            (exit + stride, first)
+        };
+        let between = (from..to)
+            .step_by(stride)
+            .fold(0, |mask: Bits, cell| mask | 1 << cell);
+        let blocking = (0..self.layout.pieces.len())
+            .filter(|&index| index != player)
+            .filter(|&index| match &self.layout.pieces[index] {
+                Some(piece) => piece.masks[self.cells[index] as usize] & between != 0,
+                None => false,
+            })
+            .count();
+        1 + blocking
+    }
+
+    /// Zobrist hash of the position: the same for the same position
+    /// however it was reached, and cheap to update after each move.
+    pub fn zobrist(&self) -> u64 {
+        self.hash
+    }
+
+    fn covers(&self, index: usize, cell: usize) -> Bits {
+        self.layout.pieces[index]
+            .as_ref()
+            .map_or(1 << cell, |piece| piece.masks[cell])
+    }
+
+    /// Cells the block at `index` cannot move onto. Only the player may go
+    /// through the exit.
+    fn blocked(&self, index: usize) -> Bits {
+        let own = self.covers(index, self.cells[index] as usize);
+        let exit = if self.layout.player == Some(index) {
+            0
+        } else {
+            self.layout.exit
+        };
+        (self.occupied & !own) | self.layout.fixed | exit
+    }
+
+    /// The cell one step from `cell` for the block at `index`, towards the
+    /// bottom right if `forward`, if the block fits there.
+    fn step(&self, index: usize, cell: usize, forward: bool, blocked: Bits) -> Option<usize> {
+        let piece = self.layout.pieces[index].as_ref()?;
+        let stride = if piece.horizontal { 1 } else { TILES_WIDE };
+        let next = if forward {
+            cell + stride
+        } else {
+            cell.checked_sub(stride)?
+        };
+        // Going left from the first column would wrap onto the row above.
+        if next >= CELLS || (piece.horizontal && next / TILES_WIDE != cell / TILES_WIDE) {
+            return None;
+        }
+        let mask = piece.masks[next];
+        if mask == 0 || mask & blocked != 0 {
+            return None;
+        }
+        Some(next)
+    }
+
+    /// The cell the block at `index` ends up on when slid `delta` cells
+    /// along its axis (negative is left / up), stopping early at the first
+    /// obstacle.
+    pub fn slide_limit(&self, index: usize, delta: isize) -> usize {
+        let blocked = self.blocked(index);
+        let mut cell = self.cells[index] as usize;
+        for _ in 0..delta.unsigned_abs() {
+            match self.step(index, cell, delta > 0, blocked) {
+                Some(next) => cell = next,
+                None => break,
+            }
+        }
+        cell
+    }
+
+    /// Every single move available: each block and a cell it can slide to.
+    pub fn moves(&self) -> Vec<(usize, usize)> {
+        let mut moves = Vec::new();
+        for index in 0..self.layout.pieces.len() {
+            if self.layout.pieces[index].is_none() {
+                continue;
+            }
+            let blocked = self.blocked(index);
+            for &forward in &[false, true] {
+                let mut cell = self.cells[index] as usize;
+                while let Some(next) = self.step(index, cell, forward, blocked) {
+                    moves.push((index, next));
+                    cell = next;
+                }
+            }
+        }
+        moves
+    }
+
+    /// Moves the block at `index` so its top left corner is on `cell`. The
+    /// caller is responsible for checking the move is legal.
+    pub fn play(&mut self, index: usize, cell: usize) {
+        let from = self.cells[index] as usize;
+        self.cells[index] = cell as u8;
+        // Blocks that do not move are not part of the hash or occupancy.
+        if let Some(piece) = &self.layout.pieces[index] {
+            self.occupied = (self.occupied & !piece.masks[from]) | piece.masks[cell];
+            self.hash ^= piece.keys[from] ^ piece.keys[cell];
+        }
+    }
+
+    /// Where vertical blocks fall to with gravity on: each block that falls
+    /// and the cell it lands on, lowest first, so playing them in order
+    /// never puts two blocks on the same cell. Empty with gravity off.
+    pub fn falls(&self) -> Vec<(usize, usize)> {
+        let mut falls = Vec::new();
+        if !self.layout.gravity {
+            return falls;
+        }
+        let mut vertical: Vec<usize> = (0..self.layout.pieces.len())
+            .filter(|&index| matches!(&self.layout.pieces[index], Some(piece) if !piece.horizontal))
+            .collect();
+        vertical.sort_by_key(|&index| Reverse(self.rect(index).3));
+        // A block falling only makes room for those above it, which come
+        // later, so one pass is enough.
+        let mut position = self.clone();
+        for index in vertical {
+            let cell = position.slide_limit(index, CELLS as isize);
+            if cell != position.cells[index] as usize {
+                position.play(index, cell);
+                falls.push((index, cell));
+            }
+        }
+        falls
+    }
+
+    /// Plays `falls`: what happens after every move with gravity on.
+    pub fn settle(&mut self) {
+        for (index, cell) in self.falls() {
+            self.play(index, cell);
+        }
+    }
+
+    /// The cells covered by the block at `index`, as (x1, y1, x2, y2).
+    pub fn rect(&self, index: usize) -> (usize, usize, usize, usize) {
+        let (x, y) = self.position(index);
+        match &self.layout.pieces[index] {
+            Some(piece) if piece.horizontal => (x, y, x + piece.len - 1, y),
+            Some(piece) => (x, y, x, y + piece.len - 1),
+            None => (x, y, x, y),
+        }
+    }
+}
+
+/// Positions of the same level are equal when every block is in the same
+/// place.
+impl PartialEq for Bitboard {
+    fn eq(&self, other: &Bitboard) -> bool {
+        self.cells == other.cells
+    }
+}
+
+impl Eq for Bitboard {}
+
+impl Hash for Bitboard {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.hash);
+    }
+}
+
+fn piece(index: usize, len: usize, horizontal: bool) -> Piece {
+    let mut piece = Piece {
+        len,
+        horizontal,
+        masks: [0; CELLS],
+        keys: [0; CELLS],
+    };
+    for cell in 0..CELLS {
+        let (x, y) = pos_to_xy(cell);
+        let fits = if horizontal {
+            x + len <= TILES_WIDE
+        } else {
+            y + len <= CELLS / TILES_WIDE
+        };
+        if fits {
+            let stride = if horizontal { 1 } else { TILES_WIDE };
+            piece.masks[cell] = (0..len).fold(0, |mask, i| mask | 1 << (cell + i * stride));
+        }
+        let key = (index * CELLS + cell) as u64;
+        piece.keys[cell] = Rng::new(ZOBRIST_SEED ^ key).next_u64();
+    }
+    piece
+}
@@ -0,0 +1,144 @@
+//! The blocking graph of a position: which blocks stand in the way of the
+//! player getting out, which stand in the way of those moving aside, and
+//! so on, read off the occupancy grid without searching.
+
+use crate::level::{xy_to_pos, BlockDir, BlockId, BlockType, Cell, Level, TILES_HIGH, TILES_WIDE};
+use std::collections::VecDeque;
+
+/// Edges from a block to each block directly in its way, starting from the
+/// player. The player is in the way of nothing; a block in the player's lane
+/// has to clear the lane, so it is blocked by whatever covers the cells it
+/// would move onto to get out of it, either way along its axis that the
+/// walls leave room for, and so on out from there. Each block is followed
+/// once, for the first cells it was found in the way of. Empty once solved
+/// or if the player is not lined up with an exit.
+pub fn graph(level: &Level) -> Vec<(BlockId, BlockId)> {
+    let player = match level
+        .blocks
+        .iter()
+        .position(|block| block.r#type == BlockType::Player)
+    {
+        Some(player) if !level.solved => player,
+        _ => return Vec::new(),
+    };
+    let lane = match lane(level, player) {
+        Some(lane) => lane,
+        None => return Vec::new(),
+    };
+    let mut edges = Vec::new();
+    let mut seen = vec![player];
+    let mut queue = VecDeque::new();
+    for blocker in occupants(level, &lane) {
+        edges.push((player, blocker));
+        if !seen.contains(&blocker) {
+            seen.push(blocker);
+            queue.push_back((blocker, lane.clone()));
+        }
+    }
+    while let Some((block, cells)) = queue.pop_front() {
+        for way in ways_out(level, block, &cells) {
+            for blocker in occupants(level, &way) {
+                if blocker == block || edges.contains(&(block, blocker)) {
+                    continue;
+                }
+                edges.push((block, blocker));
+                if !seen.contains(&blocker) {
+                    seen.push(blocker);
+                    queue.push_back((blocker, way.clone()));
+                }
+            }
+        }
+    }
+    edges
+}
+
+/// The cells between the player and the nearest exit along its axis, if
+/// one is lined up with it.
+fn lane(level: &Level, player: BlockId) -> Option<Vec<(usize, usize)>> {
+    let block = level.block(player);
+    let exit = |x: usize, y: usize| level.data[xy_to_pos(x, y)] == Cell::Exit;
+    let (before, after): (Vec<_>, Vec<_>) = match block.dir {
+        BlockDir::LeftRight => (
+            (0..block.x1).rev().map(|x| (x, block.y1)).collect(),
+            (block.x2 + 1..TILES_WIDE).map(|x| (x, block.y1)).collect(),
+        ),
+        BlockDir::UpDown => (
+            (0..block.y1).rev().map(|y| (block.x1, y)).collect(),
+            (block.y2 + 1..TILES_HIGH).map(|y| (block.x1, y)).collect(),
+        ),
+        BlockDir::Static => return None,
+    };
+    vec![before, after]
+        .into_iter()
+        .filter_map(|cells| {
+            let end = cells.iter().position(|&(x, y)| exit(x, y))?;
+            Some(cells[..end].to_vec())
+        })
+        .min_by_key(Vec::len)
+}
+
+/// The cells `block` would have to move onto to stop covering any of
+/// `cells`, one set for each way along its axis there is room to go.
+fn ways_out(level: &Level, block: BlockId, cells: &[(usize, usize)]) -> Vec<Vec<(usize, usize)>> {
+    let block = level.block(block);
+    let covered: Vec<(usize, usize)> = cells
+        .iter()
+        .copied()
+        .filter(|&(x, y)| block.covers(x, y))
+        .collect();
+    let (first, last, start, end, size) = match block.dir {
+        BlockDir::LeftRight => {
+            let xs = covered.iter().map(|&(x, _)| x);
+            (xs.clone().min(), xs.max(), block.x1, block.x2, TILES_WIDE)
+        }
+        BlockDir::UpDown => {
+            let ys = covered.iter().map(|&(_, y)| y);
+            (ys.clone().min(), ys.max(), block.y1, block.y2, TILES_HIGH)
+        }
+        BlockDir::Static => return Vec::new(),
+    };
+    let (first, last) = match (first, last) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return Vec::new(),
+    };
+    let length = end - start + 1;
+    let mut ways = Vec::new();
+    // Back, so its far end is just short of the first cell covered.
+    if first >= length {
+        ways.push((first - length..start).collect::<Vec<_>>());
+    }
+    // Forward, so its near end is just past the last.
+    if last + length < size {
+        ways.push((end + 1..=last + length).collect());
+    }
+    ways.into_iter()
+        .map(|line| {
+            line.into_iter()
+                .map(|along| match block.dir {
+                    BlockDir::LeftRight => (along, block.y1),
+                    _ => (block.x1, along),
+                })
+                .collect()
+        })
+        .filter(|way: &Vec<(usize, usize)>| {
+            way.iter().all(|&(x, y)| {
+                level
+                    .occupant(x, y)
+                    .is_none_or(|id| level.block(id).dir != BlockDir::Static)
+            })
+        })
+        .collect()
+}
+
+/// The movable blocks covering any of `cells`, each once, in order.
+fn occupants(level: &Level, cells: &[(usize, usize)]) -> Vec<BlockId> {
+    let mut found = Vec::new();
+    for &(x, y) in cells {
+        if let Some(id) = level.occupant(x, y) {
+            if level.block(id).dir != BlockDir::Static && !found.contains(&id) {
+                found.push(id);
+            }
+        }
+    }
+    found
+}
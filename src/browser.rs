@@ -0,0 +1,436 @@
+//! Community packs listed in a catalog on the web, downloaded into the mods
+//! folder from a menu (B).
+//!
+//! A catalog is JSON with a `packs` list, each pack having a `name` and the
+//! `url` of its `.dat` file (relative to the catalog's own, or absolute),
+//! and optionally the `sha256` of that file, its ed25519 `signature` (both
+//! in hex), an `author` and how many `levels` it has:
+//!
+//! ```json
+//! { "packs": [ { "name": "Traffic jam", "url": "jam.dat", "sha256": "9f86...", "levels": 40 } ] }
+//! ```
+//!
+//! Downloads go into the `downloads` mod, so they play with K like any
+//! other mod's packs. A file that does not match its checksum or signature
+//! is not kept, and one with neither (or a signature but no key to check it
+//! with) is only kept if the player says so. Each download's checksum and
+//! signature are kept beside it in a `.sum` file, so the pack can be
+//! checked again before it is played.
+
+use crate::input::UnblockInput;
+use crate::jobs::Worker;
+use crate::mods;
+use crate::render::{Color, Place, Point, Rectangle, Renderer};
+use crate::storage;
+use crate::text;
+use coffee::input::keyboard::KeyCode;
+use ring::signature::{UnparsedPublicKey, ED25519};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use unblock::limits;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+// Far more than any pack needs, so a wrong URL cannot fill the disk.
+const LIMIT: u64 = 10 * 1024 * 1024;
+const WIDTH: f32 = 420.0;
+const LINE: f32 = 24.0;
+// Packs shown at once; the list scrolls to keep the selected one in view.
+const VISIBLE: usize = 10;
+/// The mod downloaded packs are installed in.
+const MOD: &str = "downloads";
+
+const GREY: Color = Color {
+    r: 0.6,
+    g: 0.6,
+    b: 0.6,
+    a: 1.0,
+};
+
+/// A pack listed in the catalog.
+#[derive(Clone)]
+struct Entry {
+    name: String,
+    url: String,
+    sha256: Option<String>,
+    signature: Option<String>,
+    author: Option<String>,
+    levels: Option<usize>,
+}
+
+impl Entry {
+    /// Where the pack is installed: its name with anything that would not
+    /// make a good file name left out.
+    fn path(&self) -> Option<PathBuf> {
+        let stem: String = self
+            .name
+            .chars()
+            .map(|c| {
+                if c == ' ' {
+                    '-'
+                } else {
+                    c.to_ascii_lowercase()
+                }
+            })
+            .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+            .collect();
+        if stem.is_empty() {
+            return None;
+        }
+        mods::dir().map(|dir| dir.join(MOD).join(stem).with_extension("dat"))
+    }
+}
+
+enum Job {
+    Catalog(String),
+    /// A pack and the public key to check its signature with, in hex.
+    Download(Entry, String),
+}
+
+/// The pack's name as K lists it ("downloads/file") and where it is.
+type Installed = Result<(String, PathBuf), String>;
+
+enum Done {
+    Catalog(Result<Vec<Entry>, String>),
+    Download(Installed),
+    /// A pack that downloaded fine but that nothing vouches for, and why.
+    Unverified(Entry, Vec<u8>, String),
+}
+
+/// The menu of the catalog's packs. Up / Down pick one, Enter downloads it
+/// and Escape closes the menu.
+pub struct Browser {
+    // None while the catalog is being fetched.
+    entries: Option<Result<Vec<Entry>, String>>,
+    selected: usize,
+    downloading: bool,
+    key: String,
+    // A download waiting for the player to say whether to keep it, and one
+    // they kept, waiting for `poll`.
+    unverified: Option<(Entry, Vec<u8>, String)>,
+    installed: Option<Installed>,
+    jobs: Worker<Job, Done>,
+}
+
+impl Browser {
+    /// Opens the menu and starts fetching the catalog at `url`. Signatures
+    /// are checked with `key`, an ed25519 public key in hex.
+    pub fn open(url: &str, key: &str) -> Browser {
+        let jobs = Worker::start(|job| match job {
+            Job::Catalog(url) => Done::Catalog(catalog(&url)),
+            Job::Download(entry, key) => match fetch(&entry, &key) {
+                Ok((data, None)) => Done::Download(install(&entry, &data)),
+                Ok((data, Some(warning))) => Done::Unverified(entry, data, warning),
+                Err(e) => Done::Download(Err(e)),
+            },
+        });
+        jobs.send(Job::Catalog(url.to_string()));
+        Browser {
+            entries: None,
+            selected: 0,
+            downloading: false,
+            key: key.to_string(),
+            unverified: None,
+            installed: None,
+            jobs,
+        }
+    }
+
+    /// Returns false once the menu is closed.
+    pub fn interact(&mut self, input: &UnblockInput) -> bool {
+        if let Some((entry, data, _)) = &self.unverified {
+            if input.was_key_released(KeyCode::Y) {
+                self.installed = Some(install(entry, data));
+                self.unverified = None;
+            } else if input.was_key_released(KeyCode::N) || input.was_key_released(KeyCode::Escape)
+            {
+                self.unverified = None;
+            }
+            return true;
+        }
+        if input.was_key_released(KeyCode::Escape) || input.was_key_released(KeyCode::B) {
+            return false;
+        }
+        let entries = match &self.entries {
+            Some(Ok(entries)) if !entries.is_empty() => entries,
+            _ => return true,
+        };
+        if input.was_key_released(KeyCode::Down) {
+            self.selected = (self.selected + 1) % entries.len();
+        }
+        if input.was_key_released(KeyCode::Up) {
+            self.selected = (self.selected + entries.len() - 1) % entries.len();
+        }
+        if input.was_key_released(KeyCode::Return) && !self.downloading {
+            self.downloading = true;
+            self.jobs.send(Job::Download(
+                entries[self.selected].clone(),
+                self.key.clone(),
+            ));
+        }
+        true
+    }
+
+    /// Collects finished work: a pack that finished downloading, as its
+    /// name and path, or why it could not be.
+    pub fn poll(&mut self) -> Option<Installed> {
+        if let Some(installed) = self.installed.take() {
+            return Some(installed);
+        }
+        match self.jobs.poll()? {
+            Done::Catalog(entries) => {
+                self.entries = Some(entries);
+                None
+            }
+            Done::Download(installed) => {
+                self.downloading = false;
+                Some(installed)
+            }
+            Done::Unverified(entry, data, warning) => {
+                self.downloading = false;
+                self.unverified = Some((entry, data, warning));
+                None
+            }
+        }
+    }
+
+    pub fn draw(&self, renderer: &mut impl Renderer) {
+        let (width, height) = (renderer.width(), renderer.height());
+        renderer.draw_rect(
+            Rectangle {
+                x: 0.0,
+                y: 0.0,
+                width,
+                height,
+            },
+            Color {
+                a: 0.5,
+                ..Color::BLACK
+            },
+            None,
+        );
+        let area = Rectangle {
+            x: (width - WIDTH).max(0.0) / 2.0,
+            y: 20.0,
+            width: WIDTH.min(width),
+            height: LINE * (VISIBLE as f32 + 3.0) + 20.0,
+        };
+        renderer.draw_rect(area, Color::BLACK, Some((Color::WHITE, 2)));
+        let x = area.x + 15.0;
+        let line = |row: usize| area.y + 10.0 + row as f32 * LINE;
+        renderer.draw_text(
+            "Community packs",
+            Place::At(Point::new(x, line(0))),
+            text::MEDIUM,
+            Color::WHITE,
+        );
+        let entries = match &self.entries {
+            None => {
+                renderer.draw_text(
+                    "Fetching the catalog...",
+                    Place::At(Point::new(x, line(1))),
+                    text::SMALL,
+                    GREY,
+                );
+                return;
+            }
+            Some(Err(e)) => {
+                renderer.draw_text(e, Place::At(Point::new(x, line(1))), text::SMALL, GREY);
+                return;
+            }
+            Some(Ok(entries)) if entries.is_empty() => {
+                renderer.draw_text(
+                    "The catalog lists no packs",
+                    Place::At(Point::new(x, line(1))),
+                    text::SMALL,
+                    GREY,
+                );
+                return;
+            }
+            Some(Ok(entries)) => entries,
+        };
+        let first = (self.selected + 1).saturating_sub(VISIBLE);
+        for (row, (index, entry)) in entries
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(VISIBLE)
+            .enumerate()
+        {
+            let mut label = entry.name.clone();
+            if let Some(levels) = entry.levels {
+                label += &format!(" - {} levels", levels);
+            }
+            if let Some(author) = &entry.author {
+                label += &format!(" - by {}", author);
+            }
+            if entry.path().is_some_and(|path| path.exists()) {
+                label += " - installed";
+            }
+            let selected = index == self.selected;
+            renderer.draw_text(
+                &format!("{} {}", if selected { ">" } else { " " }, label),
+                Place::At(Point::new(x, line(row + 1))),
+                text::SMALL,
+                if selected { Color::WHITE } else { GREY },
+            );
+        }
+        let (hint, color) = match &self.unverified {
+            Some((_, _, warning)) => (format!("{}. Keep it? Y / N", warning), Color::WHITE),
+            None if self.downloading => (String::from("Downloading..."), GREY),
+            None => (String::from("Enter download, Escape close"), GREY),
+        };
+        renderer.draw_text(
+            &hint,
+            Place::At(Point::new(x, line(VISIBLE + 2))),
+            text::SMALL,
+            color,
+        );
+    }
+}
+
+/// The body of `url`, up to `LIMIT` bytes.
+fn get(url: &str) -> Result<Vec<u8>, String> {
+    let response = ureq::get(url)
+        .timeout(TIMEOUT)
+        .call()
+        .map_err(|e| format!("{}: {}", url, e))?;
+    let mut body = Vec::new();
+    response
+        .into_reader()
+        .take(LIMIT)
+        .read_to_end(&mut body)
+        .map_err(|e| format!("{}: {}", url, e))?;
+    Ok(body)
+}
+
+/// The packs listed in the catalog at `url`, with their URLs made absolute.
+fn catalog(url: &str) -> Result<Vec<Entry>, String> {
+    let body = get(url)?;
+    let json: serde_json::Value =
+        serde_json::from_slice(&body).map_err(|e| format!("Bad catalog: {}", e))?;
+    let packs = json["packs"]
+        .as_array()
+        .ok_or_else(|| String::from("Bad catalog: no packs list"))?;
+    // Relative URLs are next to the catalog.
+    let base = &url[..url.rfind('/').map_or(url.len(), |slash| slash + 1)];
+    packs
+        .iter()
+        .map(|pack| {
+            let field = |key: &str| pack[key].as_str().map(String::from);
+            let missing = |key: &str| format!("Bad catalog: a pack has no {}", key);
+            let link = field("url").ok_or_else(|| missing("url"))?;
+            Ok(Entry {
+                name: field("name").ok_or_else(|| missing("name"))?,
+                url: if link.contains("://") {
+                    link
+                } else {
+                    format!("{}{}", base, link)
+                },
+                sha256: field("sha256").map(|sum| sum.to_ascii_lowercase()),
+                signature: field("signature"),
+                author: field("author"),
+                levels: pack["levels"].as_u64().map(|levels| levels as usize),
+            })
+        })
+        .collect()
+}
+
+/// Downloads `entry`, checking it has levels and matches its checksum
+/// and signature. Also returns why it cannot be trusted, if nothing vouches
+/// for it.
+fn fetch(entry: &Entry, key: &str) -> Result<(Vec<u8>, Option<String>), String> {
+    let data = get(&entry.url)?;
+    let levels = limits::parse(&data).map_err(|e| format!("{} is too big: {}", entry.name, e))?;
+    if levels.is_empty() {
+        return Err(format!("{} has no levels", entry.name));
+    }
+    if let Some(sum) = &entry.sha256 {
+        if *sum != sha256(&data) {
+            return Err(format!("{} does not match its checksum", entry.name));
+        }
+    }
+    let warning = match &entry.signature {
+        Some(signature) if !key.is_empty() => {
+            verify(&data, signature, key).map_err(|e| format!("{} {}", entry.name, e))?;
+            None
+        }
+        Some(_) => Some(format!(
+            "{} is signed, but there is no key to check",
+            entry.name
+        )),
+        None if entry.sha256.is_none() => {
+            Some(format!("{} has no checksum or signature", entry.name))
+        }
+        None => None,
+    };
+    Ok((data, warning))
+}
+
+/// Saves `data` as `entry` in the downloads mod, with its seal beside it.
+fn install(entry: &Entry, data: &[u8]) -> Installed {
+    let path = entry
+        .path()
+        .ok_or_else(|| format!("Nowhere to put {}", entry.name))?;
+    let mut seal = format!("sha256={}\n", sha256(data));
+    if let Some(signature) = &entry.signature {
+        seal += &format!("signature={}\n", signature);
+    }
+    let written = storage::save_atomic(&path, data)
+        .and_then(|()| storage::save_atomic(&path.with_extension("sum"), seal.as_bytes()));
+    if let Err(e) = written {
+        return Err(format!("Could not save {}: {}", path.display(), e));
+    }
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    Ok((format!("{}/{}", MOD, stem), path))
+}
+
+/// Checks the pack at `path` is still what was downloaded, against the
+/// `.sum` file beside it, and its signature (if it has one) with `key`
+/// (if there is one). Packs without a `.sum` file were not downloaded and
+/// pass.
+pub fn check(path: &Path, key: &str) -> Result<(), String> {
+    let seal = match fs::read_to_string(path.with_extension("sum")) {
+        Ok(seal) => seal,
+        Err(_) => return Ok(()),
+    };
+    let name = path.file_stem().unwrap_or_default().to_string_lossy();
+    let data = fs::read(path).map_err(|e| format!("Could not read {}: {}", name, e))?;
+    for line in seal.lines() {
+        match line.split_once('=') {
+            Some(("sha256", sum)) if sum != sha256(&data) => {
+                return Err(format!("{} has changed since it was downloaded", name));
+            }
+            Some(("signature", signature)) if !key.is_empty() => {
+                verify(&data, signature, key).map_err(|e| format!("{} {}", name, e))?;
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn sha256(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Whether `signature` is `key`'s ed25519 signature of `data`, both in hex.
+fn verify(data: &[u8], signature: &str, key: &str) -> Result<(), String> {
+    let key = unhex(key).ok_or_else(|| String::from("cannot be checked: the key is not hex"))?;
+    let signature = unhex(signature).ok_or_else(|| String::from("has a garbled signature"))?;
+    UnparsedPublicKey::new(&ED25519, key)
+        .verify(data, &signature)
+        .map_err(|_| String::from("does not match its signature"))
+}
+
+fn unhex(text: &str) -> Option<Vec<u8>> {
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
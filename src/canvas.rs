@@ -0,0 +1,168 @@
+//! A `Renderer` that draws into memory rather than a window, for pictures
+//! of boards made from the command line. It has no font, so text is left
+//! out.
+
+use crate::render::{corners, Color, Place, Point, Rectangle, Renderer, Sketch};
+use png::HasParameters;
+
+pub struct Canvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+}
+
+impl Canvas {
+    pub fn new(width: usize, height: usize) -> Canvas {
+        Canvas {
+            width,
+            height,
+            pixels: vec![Color::BLACK; width * height],
+        }
+    }
+
+    /// Paints the pixels whose centres are in `area`, mixing in `color` by
+    /// its alpha.
+    fn fill(&mut self, area: Rectangle<f32>, color: Color) {
+        let first = |at: f32| (at - 0.5).ceil().max(0.0) as usize;
+        let (x1, y1) = (first(area.x), first(area.y));
+        let x2 = first(area.x + area.width).min(self.width);
+        let y2 = first(area.y + area.height).min(self.height);
+        for y in y1..y2 {
+            for x in x1..x2 {
+                let pixel = &mut self.pixels[y * self.width + x];
+                let mix = |under: f32, over: f32| under + (over - under) * color.a;
+                *pixel = Color {
+                    r: mix(pixel.r, color.r),
+                    g: mix(pixel.g, color.g),
+                    b: mix(pixel.b, color.b),
+                    a: 1.0,
+                };
+            }
+        }
+    }
+
+    /// The picture as a PNG file.
+    pub fn png(&self) -> Result<Vec<u8>, String> {
+        let mut data = Vec::with_capacity(self.pixels.len() * 3);
+        for pixel in &self.pixels {
+            for channel in &[pixel.r, pixel.g, pixel.b] {
+                data.push((channel.clamp(0.0, 1.0) * 255.0).round() as u8);
+            }
+        }
+        let mut file = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut file, self.width as u32, self.height as u32);
+            encoder.set(png::ColorType::RGB).set(png::BitDepth::Eight);
+            let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+            writer.write_image_data(&data).map_err(|e| e.to_string())?;
+        }
+        Ok(file)
+    }
+}
+
+impl Renderer for Canvas {
+    type Sprite = Sketch;
+
+    fn width(&self) -> f32 {
+        self.width as f32
+    }
+
+    fn height(&self) -> f32 {
+        self.height as f32
+    }
+
+    fn clear(&mut self, color: Color) {
+        for pixel in self.pixels.iter_mut() {
+            *pixel = Color { a: 1.0, ..color };
+        }
+    }
+
+    fn draw_rect(&mut self, area: Rectangle<f32>, fill: Color, border: Option<(Color, u16)>) {
+        self.fill(area, fill);
+        if let Some((color, width)) = border {
+            self.draw_line(corners(area), color, width);
+        }
+    }
+
+    /// Lines are as wide as `width` on both axes, which is exact for the
+    /// straight ones boards are made of.
+    fn draw_line(&mut self, points: Vec<Point>, color: Color, width: u16) {
+        let half = f32::from(width) / 2.0;
+        for pair in points.windows(2) {
+            let (from, to) = (pair[0], pair[1]);
+            if from.x == to.x || from.y == to.y {
+                let area = Rectangle {
+                    x: from.x.min(to.x) - half,
+                    y: from.y.min(to.y) - half,
+                    width: (to.x - from.x).abs() + half * 2.0,
+                    height: (to.y - from.y).abs() + half * 2.0,
+                };
+                self.fill(area, color);
+            } else {
+                // Square dabs along the line, a pixel apart.
+                let steps = (to.x - from.x).abs().max((to.y - from.y).abs()).ceil();
+                for step in 0..=steps as usize {
+                    let along = step as f32 / steps;
+                    let centre = Point::new(
+                        from.x + (to.x - from.x) * along,
+                        from.y + (to.y - from.y) * along,
+                    );
+                    let dab = Rectangle {
+                        x: centre.x - half,
+                        y: centre.y - half,
+                        width: half * 2.0,
+                        height: half * 2.0,
+                    };
+                    self.fill(dab, color);
+                }
+            }
+        }
+    }
+
+    /// Row by row, filling between each pair of edges the row's middle
+    /// crosses.
+    fn draw_polygon(&mut self, points: Vec<Point>, fill: Color) {
+        let edges: Vec<(Point, Point)> = points
+            .iter()
+            .zip(points.iter().cycle().skip(1))
+            .map(|(&from, &to)| (from, to))
+            .collect();
+        let top = points.iter().map(|p| p.y).fold(f32::INFINITY, f32::min);
+        let bottom = points.iter().map(|p| p.y).fold(0.0, f32::max);
+        let rows = top.max(0.0) as usize..(bottom.ceil() as usize).min(self.height);
+        for row in rows {
+            let middle = row as f32 + 0.5;
+            let mut crossings: Vec<f32> = edges
+                .iter()
+                .filter(|(from, to)| (from.y <= middle) != (to.y <= middle))
+                .map(|(from, to)| from.x + (middle - from.y) / (to.y - from.y) * (to.x - from.x))
+                .collect();
+            crossings.sort_by(f32::total_cmp);
+            for pair in crossings.chunks_exact(2) {
+                let span = Rectangle {
+                    x: pair[0],
+                    y: row as f32,
+                    width: pair[1] - pair[0],
+                    height: 1.0,
+                };
+                self.fill(span, fill);
+            }
+        }
+    }
+
+    fn sprite(&mut self, sketch: &Sketch) -> Sketch {
+        sketch.clone()
+    }
+
+    fn draw_sprite(&mut self, sprite: &Sketch) {
+        sprite.replay(self);
+    }
+
+    fn draw_text(&mut self, _content: &str, _place: Place, _size: f32, _color: Color) {}
+
+    fn measure_text(&mut self, _content: &str, _size: f32) -> (f32, f32) {
+        (0.0, 0.0)
+    }
+
+    fn present(&mut self) {}
+}
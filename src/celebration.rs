@@ -0,0 +1,99 @@
+use crate::i18n;
+use crate::input::UnblockInput;
+use crate::render::{Color, Place, Rectangle, Renderer};
+use crate::text;
+use coffee::input::keyboard::KeyCode;
+use std::time::Instant;
+
+// How many pieces of confetti fall at once, and how fast in pixels a second.
+const PIECES: usize = 60;
+const SPEED: f32 = 120.0;
+
+/// Shown when the last unsolved level of a pack is solved: confetti in the
+/// theme's colors and how the pack went, until Enter, Escape or a click.
+/// With no colors there is no confetti, for reduced motion.
+pub struct Celebration {
+    started: Instant,
+    summary: String,
+    colors: Vec<Color>,
+}
+
+impl Celebration {
+    pub fn new(summary: String, colors: Vec<Color>) -> Celebration {
+        Celebration {
+            started: Instant::now(),
+            summary,
+            colors,
+        }
+    }
+
+    /// Returns false once the screen has been dismissed.
+    pub fn interact(&self, input: &UnblockInput) -> bool {
+        !(input.was_key_released(KeyCode::Return)
+            || input.was_key_released(KeyCode::Escape)
+            || !input.left_clicks().is_empty())
+    }
+
+    pub fn draw(&self, renderer: &mut impl Renderer) {
+        let (width, height) = (renderer.width(), renderer.height());
+        let screen = Rectangle {
+            x: 0.0,
+            y: 0.0,
+            width,
+            height,
+        };
+        renderer.draw_rect(
+            screen,
+            Color {
+                a: 0.8,
+                ..Color::BLACK
+            },
+            None,
+        );
+        let elapsed = self.started.elapsed().as_secs_f32();
+        let pieces = if self.colors.is_empty() { 0 } else { PIECES };
+        for piece in 0..pieces {
+            // Scattered across the screen and in speed by a cheap hash, so
+            // every piece keeps to its own column as it falls and wraps.
+            let hash = (piece as u32).wrapping_mul(2_654_435_761);
+            let column = (hash % 1000) as f32 / 1000.0;
+            let speed = SPEED * (0.5 + ((hash >> 10) % 100) as f32 / 100.0);
+            let y = (elapsed * speed + (hash >> 20) as f32) % (height + 20.0) - 20.0;
+            renderer.draw_rect(
+                Rectangle {
+                    x: column * width,
+                    y,
+                    width: 8.0,
+                    height: 12.0,
+                },
+                self.colors[piece % self.colors.len()],
+                None,
+            );
+        }
+        renderer.draw_text(
+            &i18n::text("celebration.title"),
+            Place::Centered(Rectangle {
+                height: height / 2.0,
+                ..screen
+            }),
+            text::LARGE,
+            Color::WHITE,
+        );
+        renderer.draw_text(
+            &self.summary,
+            Place::Centered(screen),
+            text::MEDIUM,
+            Color::WHITE,
+        );
+        renderer.draw_text(
+            &i18n::text("celebration.continue"),
+            Place::Centered(Rectangle {
+                y: height / 2.0,
+                height: height / 2.0,
+                ..screen
+            }),
+            text::SMALL,
+            Color::WHITE,
+        );
+    }
+}
@@ -0,0 +1,724 @@
+//! Commands that run without opening a window.
+
+use crate::harness;
+#[cfg(feature = "journal")]
+use crate::journal;
+use crate::mods::Mod;
+use crate::save::Save;
+use crate::storage;
+use crate::svg::{self, Svg};
+use crate::theme::Theme;
+#[cfg(feature = "journal")]
+use crate::timing;
+use crate::Board;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process;
+use unblock::level::{Level, TILES_WIDE};
+use unblock::solver::{Solution, SolverConfig, Step, Strategy, Trace};
+use unblock::{pack, solver, validate};
+
+/// Reports a problem with the command line and exits.
+pub fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(2);
+}
+
+/// Parses a window size like "800x600".
+pub fn parse_size(size: &str) -> Option<(u32, u32)> {
+    let (width, height) = size.split_once('x')?;
+    match (width.parse(), height.parse()) {
+        (Ok(width), Ok(height)) if width > 0 && height > 0 => Some((width, height)),
+        _ => None,
+    }
+}
+
+/// Prints the shortest solution of level `only`, or of every level, then
+/// exits, along with what each search cost. With `proof`, also writes each
+/// solution to that file as the boards after every move, plus every
+/// position the search explored if `trace` is set (which always searches
+/// breadth first), so the solution can be checked by hand.
+pub fn solve(
+    levels: &[Level],
+    only: Option<usize>,
+    config: SolverConfig,
+    proof: Option<&str>,
+    trace: bool,
+) -> ! {
+    let (first, levels) = match only {
+        Some(index) => (index, &levels[index..index + 1]),
+        None => (0, levels),
+    };
+    let results = analyze(levels, |level| {
+        if trace {
+            let (solution, trace) = solver::solve_traced(level);
+            (solution, Some(trace), None)
+        } else {
+            let (solution, stats) = solver::solve_with(level, config);
+            (solution, None, Some(stats))
+        }
+    });
+    let mut failed = false;
+    let mut written = String::new();
+    for (index, (level, (solution, trace, stats))) in levels.iter().zip(results).enumerate() {
+        let cost = match stats {
+            Some(stats) => format!(
+                " ({} positions searched, {} stored)",
+                stats.nodes, stats.stored
+            ),
+            None => String::new(),
+        };
+        let index = first + index;
+        if proof.is_some() {
+            written += &prove(index, level, solution.as_ref(), trace.as_ref());
+        }
+        let solution = match solution {
+            Some(solution) => solution,
+            None => {
+                println!("Level {}: no solution{}", index + 1, cost);
+                failed = true;
+                continue;
+            }
+        };
+        println!("Level {}: {} moves{}", index + 1, solution.len(), cost);
+        let mut level = level.clone();
+        for (number, step) in solution.steps.iter().enumerate() {
+            println!("  {}. {}", number + 1, describe(&level, step));
+            level.move_block(step.block, (step.x, step.y));
+        }
+    }
+    if let Some(path) = proof {
+        if let Err(err) = fs::write(path, written) {
+            fail(&format!("Could not write {}: {}", path, err));
+        }
+    }
+    process::exit(if failed { 1 } else { 0 });
+}
+
+/// A level's entry in a proof file. Each board is preceded by a comment
+/// saying what it is, so the file reads as a pack and can be played.
+fn prove(
+    index: usize,
+    level: &Level,
+    solution: Option<&Solution>,
+    trace: Option<&Trace>,
+) -> String {
+    let mut text = String::new();
+    match solution {
+        Some(solution) => {
+            text += &format!("# Level {}: {} moves\n", index + 1, solution.len());
+            text += &board(&level.serialize());
+            let mut level = level.clone();
+            for (number, step) in solution.steps.iter().enumerate() {
+                text += &format!(
+                    "# Level {}, move {}: {}\n",
+                    index + 1,
+                    number + 1,
+                    describe(&level, step)
+                );
+                level.move_block(step.block, (step.x, step.y));
+                text += &board(&level.serialize());
+            }
+        }
+        None => text += &format!("# Level {}: no solution\n", index + 1),
+    }
+    let trace = match trace {
+        Some(trace) => trace,
+        None => return text,
+    };
+    let searched = match solution {
+        Some(solution) => trace.layers.iter().take(solution.len()).map(Vec::len).sum(),
+        None => trace.len(),
+    };
+    text += &format!(
+        "# Level {}: searched {} positions {}, none of them solved\n",
+        index + 1,
+        searched,
+        match solution {
+            Some(solution) => format!("fewer than {} moves from the start", solution.len()),
+            None => String::from("reachable from the start"),
+        }
+    );
+    for (depth, layer) in trace.layers.iter().enumerate() {
+        for (number, position) in layer.iter().enumerate() {
+            text += &format!(
+                "# Level {}, {} moves from the start, position {} of {}\n",
+                index + 1,
+                depth,
+                number + 1,
+                layer.len()
+            );
+            text += &board(&level.serialize_at(position));
+        }
+    }
+    text
+}
+
+/// A step as "B a2 -> c2", naming the block by its letter and the cell its
+/// top left corner moves from and to.
+fn describe(level: &Level, step: &Step) -> String {
+    let block = &level.blocks[step.block];
+    let from = cell(block.x1, block.y1);
+    let to = cell(step.x, step.y);
+    match level.label(step.block) {
+        Some(label) => format!("{} {} -> {}", label, from, to),
+        None => format!("{} -> {}", from, to),
+    }
+}
+
+/// A board as the eight lines it takes in a pack.
+pub fn board(data: &[u8; 64]) -> String {
+    data.chunks(TILES_WIDE)
+        .map(|row| format!("{}\n", String::from_utf8_lossy(row)))
+        .collect()
+}
+
+/// Checks every level can be played, printing the fewest moves each takes,
+/// then exits with a failure status if any cannot.
+pub fn validate(levels: &[Level]) -> ! {
+    if levels.is_empty() {
+        println!("No levels found");
+        process::exit(1);
+    }
+    let mut failed = 0;
+    for (index, result) in analyze(levels, validate::check).into_iter().enumerate() {
+        match result {
+            Ok(Some(moves)) => println!("Level {}: ok, {} moves", index + 1, moves),
+            Ok(None) => println!("Level {}: ok", index + 1),
+            Err(problems) => {
+                failed += 1;
+                let problems: Vec<String> = problems.iter().map(|p| p.to_string()).collect();
+                println!("Level {}: {}", index + 1, problems.join("; "));
+            }
+        }
+    }
+    for (first, duplicate) in pack::duplicates(levels) {
+        println!(
+            "Level {}: warning, the same puzzle as level {}",
+            duplicate + 1,
+            first + 1
+        );
+    }
+    if failed > 0 {
+        println!("{} of {} levels failed", failed, levels.len());
+        process::exit(1);
+    }
+    println!("All {} levels ok", levels.len());
+    process::exit(0);
+}
+
+/// Plays each input script at `paths` (see `harness`) to its board, printing
+/// how it went, then exits with a failure status if any went wrong.
+pub fn input_test(paths: &[&str]) -> ! {
+    let mut failed = 0;
+    for path in paths {
+        let played = fs::read_to_string(path)
+            .map_err(|err| err.to_string())
+            .and_then(|script| harness::run(&script));
+        match played {
+            Ok(checked) => println!(
+                "{}: ok, {} check{}",
+                path,
+                checked,
+                if checked == 1 { "" } else { "s" }
+            ),
+            Err(problem) => {
+                failed += 1;
+                println!("{}: {}", path, problem);
+            }
+        }
+    }
+    process::exit(if failed > 0 { 1 } else { 0 });
+}
+
+/// Solves every level in the pack at `path` and writes the fewest moves
+/// back into it as par comments, then exits.
+pub fn annotate(path: &str) -> ! {
+    let data = match fs::read(path) {
+        Ok(data) => data,
+        Err(err) => fail(&format!("Could not read {}: {}", path, err)),
+    };
+    let levels = pack::parse(&data);
+    let pars = analyze(&levels, fewest);
+    let mut unsolved = 0;
+    for (index, (level, par)) in levels.iter().zip(&pars).enumerate() {
+        match par {
+            Some(par) => println!("Level {}: par {}", index + 1, par),
+            None if !level.win.needs_exit() => {
+                println!("Level {}: goal is not getting out, left alone", index + 1)
+            }
+            None if !solver::applies(level) => {
+                println!("Level {}: more than one phase, left alone", index + 1)
+            }
+            None => {
+                unsolved += 1;
+                println!("Level {}: no solution, left alone", index + 1);
+            }
+        }
+    }
+    if let Err(err) = storage::save_atomic(Path::new(path), &pack::annotate(&data, &pars)) {
+        fail(&format!("Could not write {}: {}", path, err));
+    }
+    process::exit(if unsolved > 0 { 1 } else { 0 });
+}
+
+/// The fewest moves `level` takes, if the solver can work that out: it only
+/// knows how to get out.
+fn fewest(level: &Level) -> Option<usize> {
+    if !level.win.needs_exit() {
+        return None;
+    }
+    solver::solve(level, SolverConfig::default()).map(|solution| solution.len())
+}
+
+/// Prints the title, author and description of the pack at `path` and the
+/// names of its levels, after changing any given, then exits. An empty
+/// value removes the field. `order` lists each level once, counting from 1,
+/// in the order they should be played, like "3,1,2".
+pub fn meta(
+    path: &str,
+    title: Option<&str>,
+    author: Option<&str>,
+    description: Option<&str>,
+    order: Option<&str>,
+) -> ! {
+    let data = match fs::read(path) {
+        Ok(data) => data,
+        Err(err) => fail(&format!("Could not read {}: {}", path, err)),
+    };
+    let mut info = pack::info(&data);
+    let count = pack::parse(&data).len();
+    let order: Vec<usize> = match order {
+        Some(order) => {
+            let order: Vec<usize> = order
+                .split(',')
+                .map(|n| match n.trim().parse::<usize>() {
+                    Ok(n) if 1 <= n && n <= count => n - 1,
+                    _ => fail(&format!(
+                        "--order: {} is not a level between 1 and {}",
+                        n, count
+                    )),
+                })
+                .collect();
+            let mut sorted = order.clone();
+            sorted.sort_unstable();
+            if sorted != (0..count).collect::<Vec<_>>() {
+                fail(&format!(
+                    "--order must list each of the {} levels once",
+                    count
+                ));
+            }
+            order
+        }
+        None => (0..count).collect(),
+    };
+    let changing = title.is_some() || author.is_some() || description.is_some();
+    for (field, value) in [
+        (&mut info.title, title),
+        (&mut info.author, author),
+        (&mut info.description, description),
+    ] {
+        if let Some(value) = value {
+            *field = Some(value.trim().to_string()).filter(|value| !value.is_empty());
+        }
+    }
+    let data = if changing || order.iter().enumerate().any(|(i, &index)| i != index) {
+        let data = pack::rewrite(&data, &info, &order);
+        if let Err(err) = storage::save_atomic(Path::new(path), &data) {
+            fail(&format!("Could not write {}: {}", path, err));
+        }
+        data
+    } else {
+        data
+    };
+    for (label, value) in [
+        ("Title", &info.title),
+        ("Author", &info.author),
+        ("Description", &info.description),
+    ] {
+        println!("{}: {}", label, value.as_deref().unwrap_or("-"));
+    }
+    for (index, level) in pack::parse(&data).iter().enumerate() {
+        match &level.name {
+            Some(name) => println!("{}. {}", index + 1, name),
+            None => println!("{}.", index + 1),
+        }
+    }
+    process::exit(0);
+}
+
+/// Reads a level from each PNG picture in `dir`, in name order, and writes
+/// them as a pack to `out`, or prints it, then exits. Pictures that cannot
+/// be read are reported and left out.
+#[cfg(feature = "import")]
+pub fn import(dir: &str, out: Option<&str>) -> ! {
+    use unblock::import;
+
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) => fail(&format!("Could not read {}: {}", dir, err)),
+    };
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("png"))
+        })
+        .collect();
+    paths.sort();
+    let (mut pack, mut failed) = (String::new(), 0);
+    for path in &paths {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let level = fs::read(path)
+            .map_err(|err| err.to_string())
+            .and_then(|bytes| import::decode_png(&bytes))
+            .and_then(|image| import::level(&image));
+        match level {
+            Ok(level) => {
+                if !pack.is_empty() {
+                    pack.push('\n');
+                }
+                pack += &format!("# Imported from {}\n", name);
+                pack += &board(&level.serialize());
+            }
+            Err(err) => {
+                failed += 1;
+                eprintln!("{}: {}", name, err);
+            }
+        }
+    }
+    eprintln!(
+        "Imported {} of {} pictures",
+        paths.len() - failed,
+        paths.len()
+    );
+    match out {
+        Some(out) => {
+            if let Err(err) = fs::write(out, pack) {
+                fail(&format!("Could not write {}: {}", out, err));
+            }
+        }
+        None => print!("{}", pack),
+    }
+    process::exit(if failed > 0 { 1 } else { 0 });
+}
+
+/// Writes a web page showing every level of the pack at `path` in `theme`,
+/// with its name, fewest moves and how hard that makes it, to `out` or
+/// standard output, then exits. Levels without a par comment are solved.
+pub fn gallery(path: &str, out: Option<&str>, theme: &Theme) -> ! {
+    let data = match fs::read(path) {
+        Ok(data) => data,
+        Err(err) => fail(&format!("Could not read {}: {}", path, err)),
+    };
+    let levels = pack::parse(&data);
+    let fewest = analyze(&levels, |level| level.par.or_else(|| fewest(level)));
+    let info = pack::info(&data);
+    let stem = Path::new(path).file_stem().unwrap_or_default();
+    let title = svg::escape(&info.title.unwrap_or_else(|| stem.to_string_lossy().into()));
+    let mut about = format!("{} levels", levels.len());
+    if let Some(author) = info.author {
+        about += &format!(" by {}", author);
+    }
+    if let Some(description) = info.description {
+        about += &format!(". {}", description);
+    }
+    let mut page = format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{0}</title><style>\n\
+         body {{ font-family: sans-serif; background: #222; color: #eee; }}\n\
+         main {{ display: flex; flex-wrap: wrap; gap: 1em; }}\n\
+         figure {{ width: 200px; margin: 0; }}\n\
+         </style></head><body><h1>{0}</h1><p>{1}</p><main>\n",
+        title,
+        svg::escape(&about)
+    );
+    let (width, height) = Board::picture_size();
+    for (index, (level, fewest)) in levels.iter().zip(&fewest).enumerate() {
+        let mut picture = Svg::new(width as f32, height as f32);
+        Board::picture(level, theme, &mut picture);
+        let name = match &level.name {
+            Some(name) => format!("{}. {}", index + 1, name),
+            None => format!("Level {}", index + 1),
+        };
+        // The solver only knows how to get out.
+        let about = match fewest {
+            _ if !level.win.needs_exit() => format!("Goal: {}", level.win),
+            Some(moves) => format!("{} moves, {}", moves, difficulty(*moves)),
+            None if !solver::applies(level) => format!("{} phases", level.phases()),
+            None => String::from("No solution"),
+        };
+        page += &format!(
+            "<figure>{}<figcaption><b>{}</b><br>{}</figcaption></figure>\n",
+            picture.finish(),
+            svg::escape(&name),
+            svg::escape(&about)
+        );
+    }
+    page += "</main></body></html>\n";
+    match out {
+        Some(out) => {
+            if let Err(err) = fs::write(out, page) {
+                fail(&format!("Could not write {}: {}", out, err));
+            }
+            eprintln!("Wrote {} levels to {}", levels.len(), out);
+        }
+        None => print!("{}", page),
+    }
+    process::exit(0);
+}
+
+/// Sums up playtesters' journals of the pack at `path` (the built-in one if
+/// None) for its author, then exits: by level, how many moves its solves
+/// took on average and how far over par that is, how often it was reset
+/// and how long was spent on it, then the levels reset most and the time
+/// played in all.
+#[cfg(feature = "journal")]
+pub fn report(path: Option<&str>, journals: &[&str]) -> ! {
+    let levels = pack::parse(&read_pack(path));
+    let summary = journal::summarize(&levels, &read_journals(journals));
+    let par = analyze(&levels, |level| level.par.or_else(|| fewest(level)));
+    println!(
+        "{:>5}  {:>6}  {:>9}  {:>4}  {:>8}  {:>6}  {:>6}",
+        "Level", "Solves", "Avg moves", "Par", "Over par", "Resets", "Time"
+    );
+    for (index, (played, par)) in summary.levels.iter().zip(&par).enumerate() {
+        let solves = played.solves.len();
+        let average = Some(solves)
+            .filter(|&solves| solves > 0)
+            .map(|solves| played.solves.iter().sum::<usize>() as f64 / solves as f64);
+        let over = match (average, par) {
+            (Some(average), Some(par)) => format!("{:+.1}", average - *par as f64),
+            _ => String::from("-"),
+        };
+        println!(
+            "{:>5}  {:>6}  {:>9}  {:>4}  {:>8}  {:>6}  {:>6}",
+            index + 1,
+            solves,
+            average.map_or(String::from("-"), |average| format!("{:.1}", average)),
+            par.map_or(String::from("-"), |par| par.to_string()),
+            over,
+            played.resets,
+            timing::format(played.time)
+        );
+    }
+    let mut reset: Vec<(usize, usize)> = summary
+        .levels
+        .iter()
+        .map(|played| played.resets)
+        .enumerate()
+        .filter(|&(_, resets)| resets > 0)
+        .collect();
+    reset.sort_by_key(|&(index, resets)| (std::cmp::Reverse(resets), index));
+    if !reset.is_empty() {
+        let most: Vec<String> = reset
+            .iter()
+            .take(3)
+            .map(|(index, resets)| format!("level {} ({})", index + 1, resets))
+            .collect();
+        println!("Most resets: {}", most.join(", "));
+    }
+    println!(
+        "Played for {} over {} session{}",
+        timing::format(summary.time),
+        summary.sessions,
+        if summary.sessions == 1 { "" } else { "s" }
+    );
+    if summary.skipped > 0 {
+        println!(
+            "{} events were on levels not in this pack and were left out",
+            summary.skipped
+        );
+    }
+    process::exit(0);
+}
+
+/// Sets star thresholds for the pack at `path` from playtesters' journals
+/// and writes them into it as `# stars: A B` comments, then exits. Three
+/// stars take no more moves than the median solve and two no more than
+/// the solve a quarter of the way from the slowest, never below par.
+/// Levels solved fewer than `least` times are left alone.
+#[cfg(feature = "journal")]
+pub fn calibrate(path: &str, journals: &[&str], least: usize) -> ! {
+    let data = read_pack(Some(path));
+    let levels = pack::parse(&data);
+    let summary = journal::summarize(&levels, &read_journals(journals));
+    let mut stars = Vec::new();
+    for (index, (played, level)) in summary.levels.iter().zip(&levels).enumerate() {
+        let mut solves = played.solves.clone();
+        solves.sort_unstable();
+        if solves.is_empty() || solves.len() < least {
+            println!(
+                "Level {}: {} solve{}, left alone",
+                index + 1,
+                solves.len(),
+                if solves.len() == 1 { "" } else { "s" }
+            );
+            stars.push(None);
+            continue;
+        }
+        let median = solves[(solves.len() - 1) / 2];
+        let three = level.par.map_or(median, |par| median.max(par));
+        let two = solves[solves.len() * 3 / 4].max(three);
+        println!(
+            "Level {}: {} solves, median {} moves{}: 3 stars up to {}, 2 up to {}",
+            index + 1,
+            solves.len(),
+            median,
+            level
+                .par
+                .map_or(String::new(), |par| format!(" against par {}", par)),
+            three,
+            two
+        );
+        stars.push(Some((three, two)));
+    }
+    if let Err(err) = storage::save_atomic(Path::new(path), &pack::set_stars(&data, &stars)) {
+        fail(&format!("Could not write {}: {}", path, err));
+    }
+    process::exit(0);
+}
+
+/// The pack at `path`, or the built-in one if None, exiting if it cannot
+/// be read.
+#[cfg(feature = "journal")]
+fn read_pack(path: Option<&str>) -> Vec<u8> {
+    match path {
+        Some(path) => match fs::read(path) {
+            Ok(data) => data,
+            Err(err) => fail(&format!("Could not read {}: {}", path, err)),
+        },
+        None => pack::BUILTIN.to_vec(),
+    }
+}
+
+/// The events of each journal at `paths`, exiting if one cannot be read.
+#[cfg(feature = "journal")]
+fn read_journals(paths: &[&str]) -> Vec<Vec<(std::time::Duration, journal::Event)>> {
+    paths
+        .iter()
+        .map(|path| match journal::read(Path::new(path)) {
+            Ok(events) => events,
+            Err(problem) => fail(&format!("Could not read {}: {}", path, problem)),
+        })
+        .collect()
+}
+
+/// How hard a level that takes `moves` at best is to a person.
+fn difficulty(moves: usize) -> &'static str {
+    match moves {
+        0..=9 => "easy",
+        10..=19 => "medium",
+        20..=34 => "hard",
+        _ => "expert",
+    }
+}
+
+/// Lists the installed mods, or with `enable` or `disable` switches one on
+/// or off in the save file, then exits.
+pub fn mods(mods: &[Mod], enable: Option<&str>, disable: Option<&str>) -> ! {
+    if enable.is_some() || disable.is_some() {
+        let name = enable.or(disable).unwrap();
+        if !mods.iter().any(|m| m.name == name) {
+            fail(&format!("No mod called {} is installed", name));
+        }
+        let mut save = Save::load(None);
+        if enable.is_some() {
+            save.settings.disabled_mods.remove(name);
+        } else {
+            save.settings.disabled_mods.insert(name.to_string());
+        }
+        if let Err(err) = save.store_now() {
+            fail(&format!("Could not save: {}", err));
+        }
+        process::exit(0);
+    }
+    match crate::mods::dir() {
+        Some(dir) if mods.is_empty() => println!("No mods in {}", dir.display()),
+        Some(dir) => println!("Mods in {}:", dir.display()),
+        None => fail("There is no data folder to look for mods in"),
+    }
+    let disabled = Save::load(None).settings.disabled_mods;
+    for m in mods {
+        let state = if disabled.contains(&m.name) {
+            "disabled"
+        } else {
+            "enabled"
+        };
+        println!("{} ({})", m.name, state);
+        for (name, path) in &m.packs {
+            match fs::read(path) {
+                Ok(data) => println!("  pack {}: {} levels", name, pack::parse(&data).len()),
+                Err(err) => println!("  pack {}: {}", name, err),
+            }
+        }
+        if let Some(theme) = &m.theme {
+            println!("  theme {}", theme.name);
+        }
+        for problem in &m.problems {
+            println!("  problem: {}", problem);
+        }
+    }
+    process::exit(0);
+}
+
+/// Writes everything saved to `export`, or merges in a file written that
+/// way on another machine from `import`, then exits.
+pub fn progress(export: Option<&str>, import: Option<&str>) -> ! {
+    let mut save = Save::load(None);
+    if let Some(path) = export {
+        if let Err(err) = storage::save_atomic(Path::new(path), save.export().as_bytes()) {
+            fail(&format!("Could not write {}: {}", path, err));
+        }
+        println!("Progress written to {}", path);
+    }
+    if let Some(path) = import {
+        let contents = match fs::read(path) {
+            Ok(contents) => contents,
+            Err(err) => fail(&format!("Could not read {}: {}", path, err)),
+        };
+        if let Err(problem) = save.merge(&contents) {
+            fail(&format!("{} {}", path, problem));
+        }
+        if let Err(err) = save.store_now() {
+            fail(&format!("Could not save: {}", err));
+        }
+        println!("Progress from {} added", path);
+    }
+    process::exit(0);
+}
+
+/// Parses the name of a solver strategy given on the command line.
+pub fn parse_strategy(name: &str) -> Option<SolverConfig> {
+    let strategy = match name {
+        "bfs" => Strategy::BreadthFirst,
+        "ida" => Strategy::IdaStar,
+        _ => return None,
+    };
+    Some(SolverConfig {
+        strategy,
+        ..SolverConfig::default()
+    })
+}
+
+/// `pack::analyze` with a running count of finished levels on stderr, which
+/// is cleared again once they are all done.
+fn analyze<T: Send, W: Fn(&Level) -> T + Sync>(levels: &[Level], work: W) -> Vec<T> {
+    let total = levels.len();
+    let results = pack::analyze(levels, work, |done| {
+        eprint!("\rSolving {}/{}", done, total);
+        let _ = io::stderr().flush();
+    });
+    if total > 0 {
+        eprint!(
+            "\r{}\r",
+            " ".repeat(format!("Solving {}/{}", total, total).len())
+        );
+    }
+    results
+}
+
+/// Chess style cell names, "a1" being the top left corner.
+fn cell(x: usize, y: usize) -> String {
+    format!("{}{}", (b'a' + x as u8) as char, y + 1)
+}
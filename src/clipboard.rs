@@ -0,0 +1,61 @@
+//! The system clipboard, through whichever of the usual command line tools
+//! is installed: `wl-copy` / `wl-paste` on Wayland, `xclip` or `xsel` on
+//! X11, `pbcopy` / `pbpaste` on macOS and `clip` / PowerShell on Windows.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+const PASTE: [(&str, &[&str]); 5] = [
+    ("wl-paste", &["--no-newline"]),
+    ("xclip", &["-selection", "clipboard", "-out"]),
+    ("xsel", &["--clipboard", "--output"]),
+    ("pbpaste", &[]),
+    (
+        "powershell",
+        &["-NoProfile", "-Command", "Get-Clipboard -Raw"],
+    ),
+];
+
+const COPY: [(&str, &[&str]); 5] = [
+    ("wl-copy", &[]),
+    ("xclip", &["-selection", "clipboard", "-in"]),
+    ("xsel", &["--clipboard", "--input"]),
+    ("pbcopy", &[]),
+    ("clip", &[]),
+];
+
+/// What is on the clipboard, if there is text there and a tool to read it.
+pub fn read() -> Option<String> {
+    PASTE.iter().find_map(|(program, args)| {
+        let output = Command::new(program)
+            .args(*args)
+            .stderr(Stdio::null())
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        String::from_utf8(output.stdout).ok()
+    })
+}
+
+/// Puts `text` on the clipboard. Returns false if no tool would take it.
+pub fn write(text: &str) -> bool {
+    COPY.iter().any(|(program, args)| {
+        let child = Command::new(program)
+            .args(*args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        let mut child = match child {
+            Ok(child) => child,
+            Err(_) => return false,
+        };
+        let written = child
+            .stdin
+            .take()
+            .is_some_and(|mut stdin| stdin.write_all(text.as_bytes()).is_ok());
+        child.wait().is_ok_and(|status| status.success()) && written
+    })
+}
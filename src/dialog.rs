@@ -0,0 +1,161 @@
+use crate::i18n;
+use crate::render::{contains, Color, Place, Rectangle, Renderer};
+use crate::text;
+use crate::UnblockInput;
+use coffee::input::keyboard::KeyCode;
+
+const WIDTH: f32 = 320.0;
+const HEIGHT: f32 = 140.0;
+const BUTTON_WIDTH: f32 = 90.0;
+const BUTTON_HEIGHT: f32 = 36.0;
+
+const GREY: Color = Color {
+    r: 0.3,
+    g: 0.3,
+    b: 0.3,
+    a: 1.0,
+};
+
+pub enum Answer<T> {
+    Pending,
+    Yes(T),
+    No,
+}
+
+/// A modal Yes/No question. `T` identifies what to do when the player
+/// answers Yes.
+///
+/// Keyboard: Y / N, Escape for No, Left / Right / Tab to change the
+/// highlighted button and Enter to pick it. The mouse can click either button.
+pub struct Dialog<T> {
+    question: String,
+    action: T,
+    yes_selected: bool,
+}
+
+impl<T: Copy> Dialog<T> {
+    pub fn new<S: Into<String>>(question: S, action: T) -> Dialog<T> {
+        Dialog {
+            question: question.into(),
+            action,
+            yes_selected: false,
+        }
+    }
+
+    pub fn action(&self) -> T {
+        self.action
+    }
+
+    pub fn interact(&mut self, input: &UnblockInput, width: f32, height: f32) -> Answer<T> {
+        if input.was_key_released(KeyCode::Y) {
+            return Answer::Yes(self.action);
+        }
+        if input.was_key_released(KeyCode::N) || input.was_key_released(KeyCode::Escape) {
+            return Answer::No;
+        }
+        if input.was_key_released(KeyCode::Left)
+            || input.was_key_released(KeyCode::Right)
+            || input.was_key_released(KeyCode::Tab)
+        {
+            self.yes_selected = !self.yes_selected;
+        }
+        if input.was_key_released(KeyCode::Return) {
+            return self.selected();
+        }
+        let (yes, no) = buttons(width, height);
+        for click in input.left_clicks() {
+            if contains(&yes, *click) {
+                return Answer::Yes(self.action);
+            }
+            if contains(&no, *click) {
+                return Answer::No;
+            }
+        }
+        Answer::Pending
+    }
+
+    fn selected(&self) -> Answer<T> {
+        if self.yes_selected {
+            Answer::Yes(self.action)
+        } else {
+            Answer::No
+        }
+    }
+
+    pub fn draw(&self, renderer: &mut impl Renderer) {
+        let (width, height) = (renderer.width(), renderer.height());
+        // Dim the game behind the dialog.
+        renderer.draw_rect(
+            Rectangle {
+                x: 0.0,
+                y: 0.0,
+                width,
+                height,
+            },
+            Color {
+                a: 0.5,
+                ..Color::BLACK
+            },
+            None,
+        );
+        let area = bounds(width, height);
+        renderer.draw_rect(area, Color::BLACK, Some((Color::WHITE, 2)));
+        renderer.draw_text(
+            &self.question,
+            Place::Centered(Rectangle {
+                height: HEIGHT - BUTTON_HEIGHT - 20.0,
+                ..area
+            }),
+            text::MEDIUM,
+            Color::WHITE,
+        );
+        let (yes, no) = buttons(width, height);
+        for (button, label, selected) in &[
+            (yes, "dialog.yes", self.yes_selected),
+            (no, "dialog.no", !self.yes_selected),
+        ] {
+            let border = if *selected {
+                Some((Color::WHITE, 2))
+            } else {
+                None
+            };
+            renderer.draw_rect(*button, GREY, border);
+            renderer.draw_text(
+                &i18n::text(label),
+                Place::Centered(*button),
+                text::MEDIUM,
+                Color::WHITE,
+            );
+        }
+    }
+}
+
+fn bounds(width: f32, height: f32) -> Rectangle<f32> {
+    Rectangle {
+        x: (width - WIDTH) / 2.0,
+        y: (height - HEIGHT) / 2.0,
+        width: WIDTH,
+        height: HEIGHT,
+    }
+}
+
+fn buttons(width: f32, height: f32) -> (Rectangle<f32>, Rectangle<f32>) {
+    let area = bounds(width, height);
+    let y = area.y + HEIGHT - BUTTON_HEIGHT - 15.0;
+    let button = |x| Rectangle {
+        x,
+        y,
+        width: BUTTON_WIDTH,
+        height: BUTTON_HEIGHT,
+    };
+    let (first, second) = (
+        button(area.x + WIDTH / 2.0 - BUTTON_WIDTH - 10.0),
+        button(area.x + WIDTH / 2.0 + 10.0),
+    );
+    // Yes comes first in reading order.
+    if i18n::right_to_left() {
+        (second, first)
+    } else {
+        (first, second)
+    }
+}
@@ -0,0 +1,785 @@
+//! The level editor (`--edit FILE`): the level being drawn on the left half
+//! of the window and a copy of it to play on the right, made afresh with
+//! every change, so a level can be tried the moment it is touched. Test
+//! (T) plays it on the whole window, and coming back says how that went
+//! next to the fewest moves the solver finds.
+//!
+//! Each change is checked in the background as it is made, and what is
+//! wrong is marked on the board; a pack with a level that cannot be played
+//! is not saved.
+//!
+//! Symmetric boards are quicker to draw with a mirror on (M), which paints
+//! each cell's reflections too, and a whole level can be flipped (F) or
+//! turned (O).
+//!
+//! Work not yet saved is written beside the pack every minute and on every
+//! change of level, and opening the pack again after a crash offers it
+//! back.
+//!
+//! Levels can be duplicated (Ctrl+D), deleted (Delete), moved along the
+//! pack (Ctrl+PageUp/PageDown), copied (Ctrl+C) and pasted in after the
+//! current one (Ctrl+V), as pack text so they go to and from a text editor
+//! or another pack.
+//!
+//! The player and block brushes lay a whole piece along a drag rather than
+//! painting cells, sliding the way it was dragged and drawn with whichever
+//! character keeps it apart from its neighbours.
+
+use crate::clipboard;
+use crate::dialog::{Answer, Dialog};
+use crate::input::UnblockInput;
+use crate::jobs::Worker;
+use crate::render::{contains, Color, Place, Point, Rectangle, Renderer, Sprite};
+use crate::storage;
+use crate::text;
+use crate::theme::Theme;
+use crate::toast;
+use crate::{Board, Bounds, InputScheme, Sizing, Viewport};
+use coffee::input::keyboard::KeyCode;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use unblock::generate;
+use unblock::level::{
+    xy_to_pos, BlockType, Level, EXIT, FLOOR, LEFTRIGHT1, LEFTRIGHT2, PLAYER, TILES_HIGH,
+    TILES_WIDE, UPDOWN1, UPDOWN2, WALL,
+};
+use unblock::pack;
+use unblock::validate::{self, Problem};
+
+/// How often unsaved work is written to the autosave file.
+const AUTOSAVE: Duration = Duration::from_secs(60);
+
+// The Test button, at the top right of the window.
+const BUTTON_WIDTH: f32 = 60.0;
+const BUTTON_HEIGHT: f32 = 24.0;
+
+const RED: Color = Color {
+    r: 1.0,
+    g: 0.2,
+    b: 0.2,
+    a: 1.0,
+};
+
+/// Which reflections of a cell painting also paints.
+#[derive(Clone, Copy, PartialEq)]
+enum Mirror {
+    Off,
+    LeftRight,
+    TopBottom,
+    Both,
+}
+
+impl Mirror {
+    fn next(self) -> Mirror {
+        match self {
+            Mirror::Off => Mirror::LeftRight,
+            Mirror::LeftRight => Mirror::TopBottom,
+            Mirror::TopBottom => Mirror::Both,
+            Mirror::Both => Mirror::Off,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Mirror::Off => "off",
+            Mirror::LeftRight => "left to right",
+            Mirror::TopBottom => "top to bottom",
+            Mirror::Both => "both ways",
+        }
+    }
+
+    /// `(x, y)` and the cells it reflects to.
+    fn cells(self, (x, y): (usize, usize)) -> Vec<(usize, usize)> {
+        let (across, down) = (TILES_WIDE - 1 - x, TILES_HIGH - 1 - y);
+        let mut cells = vec![(x, y)];
+        if matches!(self, Mirror::LeftRight | Mirror::Both) {
+            cells.push((across, y));
+        }
+        if matches!(self, Mirror::TopBottom | Mirror::Both) {
+            cells.push((x, down));
+        }
+        if self == Mirror::Both {
+            cells.push((across, down));
+        }
+        cells
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Brush {
+    /// Paints cells with a level character.
+    Cell(u8),
+    /// Lays the player along a drag, taking it from wherever it was.
+    Player,
+    /// Lays a block along a drag.
+    Block,
+}
+
+/// What the number keys paint with, and what each is called.
+const BRUSHES: [(KeyCode, Brush, &str); 9] = [
+    (KeyCode::Key1, Brush::Cell(FLOOR), "floor"),
+    (KeyCode::Key2, Brush::Cell(WALL), "wall"),
+    (KeyCode::Key3, Brush::Cell(EXIT), "exit"),
+    (KeyCode::Key4, Brush::Player, "player"),
+    (KeyCode::Key5, Brush::Block, "block"),
+    (KeyCode::Key6, Brush::Cell(LEFTRIGHT1), "horizontal -"),
+    (KeyCode::Key7, Brush::Cell(LEFTRIGHT2), "horizontal _"),
+    (KeyCode::Key8, Brush::Cell(UPDOWN1), "vertical |"),
+    (KeyCode::Key9, Brush::Cell(UPDOWN2), "vertical ("),
+];
+
+pub struct Editor {
+    path: PathBuf,
+    // The pack as edited, and as it was last read or written.
+    data: Vec<u8>,
+    saved: Vec<u8>,
+    index: usize,
+    brush: usize,
+    mirror: Mirror,
+    // Where a drag laying a piece started and has got to.
+    laying: Option<((usize, usize), (usize, usize))>,
+    // The level as drawn, never played, and the copy being played.
+    template: Board,
+    play: Board,
+    // Escape was pressed once with changes unsaved.
+    leaving: bool,
+    // The level being tested on the whole window, and how the last test of
+    // the level as it is now went: whether it was solved, in how many moves.
+    testing: Option<Board>,
+    tested: Option<(bool, usize)>,
+    // What `validate::check` makes of a level, by its grid: the latest back
+    // from the worker.
+    checker: Worker<Level, Checked>,
+    checked: Option<Checked>,
+    // What the autosave file holds, or the pack as opened if there is none,
+    // and when it was last brought up to date.
+    autosaved: Vec<u8>,
+    autosaved_at: Instant,
+    // The work found in the autosave file on opening, until it is taken
+    // back or given up.
+    recovered: Vec<u8>,
+    dialog: Option<Dialog<Ask>>,
+    // The level last copied, in case the system clipboard cannot be used.
+    copied: Vec<u8>,
+}
+
+/// What the editor's Yes/No question is about.
+#[derive(Clone, Copy)]
+enum Ask {
+    /// Taking back the autosaved work.
+    Recover,
+    /// Deleting the level being edited.
+    Delete,
+}
+
+type Checked = (
+    [u8; TILES_WIDE * TILES_HIGH],
+    Result<Option<usize>, Vec<Problem>>,
+);
+
+impl Editor {
+    /// Edits the pack at `path`, which held `data`. A pack with no levels
+    /// gets an empty one to start on. If the editor last closed without
+    /// leaving, whatever it autosaved is offered back.
+    pub fn open(path: PathBuf, data: Vec<u8>) -> Editor {
+        let recovered = std::fs::read(storage::autosave(&path))
+            .ok()
+            .filter(|autosaved| autosaved != &data)
+            .unwrap_or_default();
+        let dialog = (!recovered.is_empty()).then(|| {
+            let question = format!("Recover unsaved changes to {}?", path.display());
+            Dialog::new(question, Ask::Recover)
+        });
+        let saved = data.clone();
+        let data = if pack::parse(&data).is_empty() {
+            pack::push_level(&data, &blank())
+        } else {
+            data
+        };
+        let level = pack::parse(&data).swap_remove(0);
+        let autosaved = data.clone();
+        let editor = Editor {
+            path,
+            data,
+            saved,
+            index: 0,
+            brush: 1,
+            mirror: Mirror::Off,
+            laying: None,
+            template: Board::new(level.clone()),
+            play: Board::new(level.clone()),
+            leaving: false,
+            testing: None,
+            tested: None,
+            checker: Worker::start(|level: Level| (*level.template(), validate::check(&level))),
+            checked: None,
+            autosaved,
+            autosaved_at: Instant::now(),
+            recovered,
+            dialog,
+            copied: Vec::new(),
+        };
+        editor.checker.send(level);
+        editor
+    }
+
+    /// Paints, plays and handles the editor's keys. Returns false once the
+    /// editor is left.
+    pub fn interact(&mut self, input: &mut UnblockInput, window: &impl Bounds) -> bool {
+        if let Some(dialog) = &mut self.dialog {
+            let answer = dialog.interact(input, window.width(), window.height());
+            let action = dialog.action();
+            match answer {
+                Answer::Pending => return true,
+                Answer::Yes(Ask::Recover) => {
+                    self.data = std::mem::take(&mut self.recovered);
+                    if pack::parse(&self.data).is_empty() {
+                        self.data = pack::push_level(&self.data, &blank());
+                    }
+                    self.switch_to(0);
+                    toast::show("Recovered - Ctrl+S saves it");
+                }
+                Answer::Yes(Ask::Delete) => self.delete(),
+                Answer::No => {
+                    if let Ask::Recover = action {
+                        self.recovered.clear();
+                        self.forget_autosave();
+                    }
+                }
+            }
+            self.dialog = None;
+            return true;
+        }
+        if let Some(board) = &mut self.testing {
+            board.interact(input, InputScheme::Drag);
+            if board.level.solved || input.was_key_released(KeyCode::Escape) {
+                self.tested = Some((board.level.solved, board.level.moves.len()));
+                self.testing = None;
+            }
+            return true;
+        }
+        let clicked = input
+            .left_clicks()
+            .iter()
+            .any(|&click| contains(&button(window.width()), click));
+        if clicked || input.was_key_released(KeyCode::T) {
+            self.test();
+            return true;
+        }
+        if input.was_key_released(KeyCode::Escape) {
+            if self.data == self.saved || self.leaving {
+                self.forget_autosave();
+                return false;
+            }
+            self.leaving = true;
+            toast::show("Unsaved changes: Ctrl+S saves them, Escape again leaves without");
+        }
+        for (i, (key, _, name)) in BRUSHES.iter().enumerate() {
+            if input.was_key_released(*key) {
+                self.brush = i;
+                toast::show(format!("Brush: {}", name));
+            }
+        }
+        if input.is_ctrl_pressed() && input.was_key_released(KeyCode::S) {
+            self.save();
+        }
+        let count = pack::parse(&self.data).len();
+        let ctrl = input.is_ctrl_pressed();
+        if input.was_key_released(KeyCode::PageDown) {
+            let next = (self.index + 1) % count;
+            if ctrl {
+                self.swap(next);
+            } else {
+                self.switch_to(next);
+                self.autosave();
+            }
+        }
+        if input.was_key_released(KeyCode::PageUp) {
+            let previous = (self.index + count - 1) % count;
+            if ctrl {
+                self.swap(previous);
+            } else {
+                self.switch_to(previous);
+                self.autosave();
+            }
+        }
+        if ctrl && input.was_key_released(KeyCode::D) {
+            let mut order: Vec<usize> = (0..count).collect();
+            order.insert(self.index, self.index);
+            self.rearrange(&order, self.index + 1);
+        }
+        if input.was_key_released(KeyCode::Delete) {
+            if count == 1 {
+                toast::show("A pack needs at least one level");
+            } else {
+                let question = format!("Delete level {}?", self.index + 1);
+                self.dialog = Some(Dialog::new(question, Ask::Delete));
+            }
+        }
+        if ctrl && input.was_key_released(KeyCode::C) {
+            self.copy();
+        }
+        if ctrl && input.was_key_released(KeyCode::V) {
+            self.paste();
+        }
+        if input.was_key_released(KeyCode::R) {
+            self.restart();
+        }
+        if input.was_key_released(KeyCode::M) {
+            self.mirror = self.mirror.next();
+            toast::show(format!("Mirror: {}", self.mirror.name()));
+        }
+        if input.was_key_released(KeyCode::F) {
+            self.set_grid(self.template.level.mirrored().serialize());
+        }
+        if input.was_key_released(KeyCode::O) {
+            self.set_grid(self.template.level.rotated().serialize());
+        }
+        let cursor = input.cursor_position();
+        match (BRUSHES[self.brush].1, input.is_mouse_pressed()) {
+            (Brush::Cell(ch), true) => {
+                if let Some(cell) = self.template.sxy_to_xy(cursor.x, cursor.y) {
+                    self.paint(ch, cell);
+                }
+            }
+            (_, true) => {
+                let (x, y) = self.template.sxy_to_xy_clamped(cursor.x, cursor.y);
+                let inner = (x.clamp(1, TILES_WIDE - 2), y.clamp(1, TILES_HIGH - 2));
+                self.laying = match self.laying {
+                    Some((start, _)) => Some((start, inner)),
+                    None => self
+                        .template
+                        .sxy_to_xy(cursor.x, cursor.y)
+                        .filter(|&cell| cell == inner)
+                        .map(|cell| (cell, cell)),
+                };
+            }
+            (brush, false) => {
+                if let Some((start, end)) = self.laying.take() {
+                    self.lay(brush, start, end);
+                }
+            }
+        }
+        self.play.interact(input, InputScheme::Drag);
+        if self.play.level.solved {
+            toast::show(format!("Solved in {} moves", self.play.level.moves.len()));
+            self.restart();
+        }
+        true
+    }
+
+    pub fn update(&mut self, window: &impl Bounds, sizing: Sizing) {
+        if self.autosaved_at.elapsed() >= AUTOSAVE {
+            self.autosave();
+        }
+        if let Some(checked) = self.checker.poll() {
+            self.checked = Some(checked);
+        }
+        if let Some(board) = &mut self.testing {
+            board.update(Viewport::window(window, sizing));
+            return;
+        }
+        self.template.update(Viewport::half(window, 0, sizing));
+        self.play.update(Viewport::half(window, 1, sizing));
+    }
+
+    pub fn draw(&mut self, renderer: &mut impl Renderer<Sprite = Sprite>, theme: &Theme) {
+        if let Some(board) = &mut self.testing {
+            board.draw(renderer, theme);
+            renderer.draw_text(
+                &format!(
+                    "Testing level {} - {} moves - Escape goes back to the editor",
+                    self.index + 1,
+                    board.level.moves.len()
+                ),
+                Place::At(Point::new(10.0, 10.0)),
+                text::SMALL,
+                Color::WHITE,
+            );
+            return;
+        }
+        self.template.draw(renderer, theme);
+        self.play.draw(renderer, theme);
+        self.draw_problems(renderer);
+        if let Some((start, end)) = self.laying {
+            let (x, y) = (start.0.min(end.0), start.1.min(end.1));
+            let size = (start.0.abs_diff(end.0) + 1, start.1.abs_diff(end.1) + 1);
+            let clear = Color {
+                a: 0.0,
+                ..Color::BLACK
+            };
+            renderer.draw_rect(
+                self.template.viewport.cells((x, y), size),
+                clear,
+                Some((Color::WHITE, 2)),
+            );
+        }
+        let count = pack::parse(&self.data).len();
+        let status = format!(
+            "{} - level {}/{} - brush: {}{}{}",
+            self.path.display(),
+            self.index + 1,
+            count,
+            BRUSHES[self.brush].2,
+            if self.mirror == Mirror::Off {
+                String::new()
+            } else {
+                format!(" - mirror {}", self.mirror.name())
+            },
+            if self.data == self.saved {
+                ""
+            } else {
+                " - unsaved"
+            }
+        );
+        renderer.draw_text(
+            &status,
+            Place::At(Point::new(10.0, 10.0)),
+            text::SMALL,
+            Color::WHITE,
+        );
+        let half = renderer.width() / 2.0;
+        renderer.draw_text(
+            "Play",
+            Place::At(Point::new(half + 10.0, 30.0)),
+            text::SMALL,
+            Color::WHITE,
+        );
+        let area = button(renderer.width());
+        renderer.draw_rect(area, theme.background, Some((Color::WHITE, 1)));
+        renderer.draw_text("Test", Place::Centered(area), text::SMALL, Color::WHITE);
+        if let Some(report) = self.report() {
+            renderer.draw_text(
+                &report,
+                Place::At(Point::new(10.0, renderer.height() - 50.0)),
+                text::SMALL,
+                Color::WHITE,
+            );
+        }
+        renderer.draw_text(
+            "1-9 brush  M mirror  F flip  O turn  PageUp/PageDown level  Ctrl+D/C/V duplicate/copy/paste  Delete  R restart  T test  Ctrl+S save  Escape leave",
+            Place::At(Point::new(10.0, renderer.height() - 30.0)),
+            text::SMALL,
+            Color::WHITE,
+        );
+        if let Some(dialog) = &self.dialog {
+            dialog.draw(renderer);
+        }
+    }
+
+    /// Marks what is wrong with the level on it, with a line above saying
+    /// what, or how few moves it takes if nothing is.
+    fn draw_problems(&self, renderer: &mut impl Renderer) {
+        let viewport = &self.template.viewport;
+        let (line, color) = match self.verdict() {
+            None => (String::from("Checking..."), Color::WHITE),
+            Some(Ok(Some(fewest))) => (format!("Fewest moves: {}", fewest), Color::WHITE),
+            Some(Ok(None)) => (String::from("Playable"), Color::WHITE),
+            Some(Err(problems)) => {
+                for problem in problems {
+                    let areas = match *problem {
+                        Problem::UnknownCharacter { x, y, .. } | Problem::ShortBlock { x, y } => {
+                            vec![viewport.cells((x, y), (1, 1))]
+                        }
+                        Problem::Players(players) if players > 1 => {
+                            (0..self.template.level.blocks.len())
+                                .filter(|&i| {
+                                    self.template.level.blocks[i].r#type == BlockType::Player
+                                })
+                                .map(|i| self.template.area(i))
+                                .collect()
+                        }
+                        _ => Vec::new(),
+                    };
+                    for area in areas {
+                        let clear = Color {
+                            a: 0.0,
+                            ..Color::BLACK
+                        };
+                        renderer.draw_rect(area, clear, Some((RED, 3)));
+                    }
+                }
+                let problems: Vec<String> = problems.iter().map(|p| p.to_string()).collect();
+                (problems.join(", "), RED)
+            }
+        };
+        let (_, origin_y) = viewport.origin();
+        renderer.draw_text(
+            &line,
+            Place::At(Point::new(viewport.x as f32 + 10.0, origin_y - 22.0)),
+            text::SMALL,
+            color,
+        );
+    }
+
+    /// What checking the level as it is drawn now found, once it is back.
+    fn verdict(&self) -> Option<&Result<Option<usize>, Vec<Problem>>> {
+        self.checked
+            .as_ref()
+            .filter(|(grid, _)| grid == self.template.level.template())
+            .map(|(_, verdict)| verdict)
+    }
+
+    /// Plays the level as it is drawn now on the whole window.
+    fn test(&mut self) {
+        let level = pack::parse(&self.data).swap_remove(self.index);
+        self.tested = None;
+        self.testing = Some(Board::new(level));
+    }
+
+    /// "Last test: solved in 12 moves - fewest 9", once there is a test to
+    /// report on.
+    fn report(&self) -> Option<String> {
+        let (solved, moves) = self.tested?;
+        let played = if solved {
+            format!("solved in {} moves", moves)
+        } else {
+            format!("left unsolved after {} moves", moves)
+        };
+        let fewest = match self.verdict() {
+            None => String::from("solving..."),
+            Some(Ok(Some(fewest))) => format!("fewest {}", fewest),
+            Some(Ok(None)) => String::from("fewest not known"),
+            Some(Err(_)) => String::from("cannot be played as it is"),
+        };
+        Some(format!("Last test: {} - {}", played, fewest))
+    }
+
+    /// Paints `brush` onto `(x, y)` of the level being edited, and onto
+    /// its reflections in the mirror. Blocks only go inside the outer ring,
+    /// where walls and exits belong.
+    fn paint(&mut self, brush: u8, (x, y): (usize, usize)) {
+        let inside = 0 < x && x < TILES_WIDE - 1 && 0 < y && y < TILES_HIGH - 1;
+        if !inside && !matches!(brush, FLOOR | WALL | EXIT) {
+            return;
+        }
+        let mut grid = *self.template.level.template();
+        for (x, y) in self.mirror.cells((x, y)) {
+            grid[xy_to_pos(x, y)] = brush;
+        }
+        self.set_grid(grid);
+    }
+
+    /// Lays the player or a block from `start` to `end`, which must share
+    /// a row or a column; the piece slides along it. Whatever was under it
+    /// is cleared, as is the player's old place, and a block is laid at
+    /// each reflection in the mirror too.
+    fn lay(&mut self, brush: Brush, start: (usize, usize), end: (usize, usize)) {
+        let vertical = start.0 == end.0;
+        let len = if vertical {
+            start.1.abs_diff(end.1)
+        } else {
+            start.0.abs_diff(end.0)
+        } + 1;
+        if (start.0 != end.0 && start.1 != end.1) || len < 2 {
+            toast::show("Drag along a row or column to lay a piece");
+            return;
+        }
+        let mut grid = *self.template.level.template();
+        let ends = match brush {
+            Brush::Player => {
+                for cell in grid.iter_mut().filter(|cell| **cell == PLAYER) {
+                    *cell = FLOOR;
+                }
+                vec![(start, end)]
+            }
+            _ => self
+                .mirror
+                .cells(start)
+                .into_iter()
+                .zip(self.mirror.cells(end))
+                .collect(),
+        };
+        for (start, end) in ends {
+            let (x, y) = (start.0.min(end.0), start.1.min(end.1));
+            for i in 0..len {
+                let (x, y) = if vertical { (x, y + i) } else { (x + i, y) };
+                grid[xy_to_pos(x, y)] = FLOOR;
+            }
+            if brush == Brush::Player {
+                for i in 0..len {
+                    let (x, y) = if vertical { (x, y + i) } else { (x + i, y) };
+                    grid[xy_to_pos(x, y)] = PLAYER;
+                }
+            } else if !generate::place(&mut grid, vertical, len, x, y) {
+                toast::show("No room for the block there");
+                return;
+            }
+        }
+        self.set_grid(grid);
+    }
+
+    /// Makes `grid` the level being edited, if it is any different.
+    fn set_grid(&mut self, grid: [u8; TILES_WIDE * TILES_HIGH]) {
+        if &grid == self.template.level.template() {
+            return;
+        }
+        self.data = pack::set_grid(&self.data, self.index, &grid);
+        self.leaving = false;
+        self.switch_to(self.index);
+    }
+
+    fn switch_to(&mut self, index: usize) {
+        self.index = index;
+        let level = pack::parse(&self.data).swap_remove(index);
+        self.checker.send(level.clone());
+        self.template = Board::new(level);
+        self.tested = None;
+        self.restart();
+    }
+
+    /// Starts playing the level as it is drawn now.
+    fn restart(&mut self) {
+        self.play = Board::new(pack::parse(&self.data).swap_remove(self.index));
+    }
+
+    /// Writes the pack, unless one of its levels cannot be played.
+    fn save(&mut self) {
+        let levels = pack::parse(&self.data);
+        let verdicts = pack::analyze(&levels, validate::check, |_| {});
+        let broken = verdicts
+            .iter()
+            .enumerate()
+            .find_map(|(index, verdict)| Some((index, verdict.as_ref().err()?)));
+        if let Some((index, problems)) = broken {
+            let problems: Vec<String> = problems.iter().map(|p| p.to_string()).collect();
+            toast::show(format!(
+                "Not saved: level {} {}",
+                index + 1,
+                problems.join(", ")
+            ));
+            return;
+        }
+        match storage::save_atomic(&self.path, &self.data) {
+            Ok(()) => {
+                self.saved = self.data.clone();
+                self.leaving = false;
+                self.forget_autosave();
+                toast::show(format!("Saved {}", self.path.display()));
+            }
+            Err(err) => toast::show(format!("Could not save {}: {}", self.path.display(), err)),
+        }
+    }
+
+    /// Swaps the level being edited with the one at `other`, and carries on
+    /// editing it there.
+    fn swap(&mut self, other: usize) {
+        let count = pack::parse(&self.data).len();
+        let mut order: Vec<usize> = (0..count).collect();
+        order.swap(self.index, other);
+        self.rearrange(&order, other);
+    }
+
+    fn delete(&mut self) {
+        let count = pack::parse(&self.data).len();
+        let order: Vec<usize> = (0..count).filter(|&i| i != self.index).collect();
+        self.rearrange(&order, self.index.min(count - 2));
+        toast::show("Level deleted");
+    }
+
+    /// Rewrites the pack with its levels in `order` (see `pack::rewrite`)
+    /// and goes to the level at `index` of the result.
+    fn rearrange(&mut self, order: &[usize], index: usize) {
+        self.data = pack::rewrite(&self.data, &pack::info(&self.data), order);
+        self.leaving = false;
+        self.switch_to(index);
+        self.autosave();
+    }
+
+    /// Copies the level being edited, with its comments, as pack text.
+    fn copy(&mut self) {
+        self.copied = pack::rewrite(&self.data, &pack::Info::default(), &[self.index]);
+        if clipboard::write(&String::from_utf8_lossy(&self.copied)) {
+            toast::show(format!("Copied level {}", self.index + 1));
+        } else {
+            toast::show(format!(
+                "Copied level {} for pasting here - no clipboard tool found",
+                self.index + 1
+            ));
+        }
+    }
+
+    /// Puts the levels on the clipboard, or the one last copied if there
+    /// are none there, in after the level being edited.
+    fn paste(&mut self) {
+        let text = clipboard::read()
+            .map(String::into_bytes)
+            .filter(|text| !pack::parse(text).is_empty())
+            .unwrap_or_else(|| self.copied.clone());
+        let added = pack::parse(&text).len();
+        if added == 0 {
+            toast::show("Nothing to paste: copy a level, or levels as pack text");
+            return;
+        }
+        self.data = pack::insert(&self.data, self.index + 1, &text);
+        self.leaving = false;
+        self.switch_to(self.index + 1);
+        self.autosave();
+        toast::show(format!("Pasted {} level(s)", added));
+    }
+
+    /// Brings the autosave file up to date: the pack as edited if that is
+    /// not what was saved, and no file if it is.
+    fn autosave(&mut self) {
+        self.autosaved_at = Instant::now();
+        if self.data == self.autosaved {
+            return;
+        }
+        let path = storage::autosave(&self.path);
+        let written = if self.data == self.saved {
+            remove(&path)
+        } else {
+            storage::save_atomic(&path, &self.data)
+        };
+        match written {
+            Ok(()) => self.autosaved = self.data.clone(),
+            Err(err) => toast::show(format!("Could not autosave {}: {}", path.display(), err)),
+        }
+    }
+
+    /// Removes the autosave file, once what it held is saved or given up.
+    fn forget_autosave(&mut self) {
+        let path = storage::autosave(&self.path);
+        match remove(&path) {
+            Ok(()) => self.autosaved = self.saved.clone(),
+            Err(err) => toast::show(format!("Could not remove {}: {}", path.display(), err)),
+        }
+    }
+}
+
+/// Removes the file at `path` and its backup, if they are there.
+fn remove(path: &Path) -> io::Result<()> {
+    for path in [path.to_path_buf(), storage::backup(path)] {
+        match std::fs::remove_file(path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Where the Test button is in a window `width` wide.
+fn button(width: f32) -> Rectangle<f32> {
+    Rectangle {
+        x: width - BUTTON_WIDTH - 10.0,
+        y: 6.0,
+        width: BUTTON_WIDTH,
+        height: BUTTON_HEIGHT,
+    }
+}
+
+/// A walled board with an exit on the third row, where the player goes.
+fn blank() -> [u8; TILES_WIDE * TILES_HIGH] {
+    let mut grid = [FLOOR; TILES_WIDE * TILES_HIGH];
+    for i in 0..TILES_WIDE {
+        grid[xy_to_pos(i, 0)] = WALL;
+        grid[xy_to_pos(i, TILES_HIGH - 1)] = WALL;
+    }
+    for i in 0..TILES_HIGH {
+        grid[xy_to_pos(0, i)] = WALL;
+        grid[xy_to_pos(TILES_WIDE - 1, i)] = WALL;
+    }
+    grid[xy_to_pos(TILES_WIDE - 1, 3)] = EXIT;
+    grid
+}
@@ -0,0 +1,35 @@
+//! Pictures of boards as PNG files, drawn in a theme the way the game draws
+//! them.
+
+use crate::canvas::Canvas;
+use crate::theme::Theme;
+use crate::{cli, Board};
+use std::fs;
+use std::path::Path;
+use unblock::level::Level;
+
+/// `level` with its blocks where they are now, as a PNG file. A level
+/// straight from `pack::parse` shows how it starts.
+pub fn png(level: &Level, theme: &Theme) -> Result<Vec<u8>, String> {
+    let (width, height) = Board::picture_size();
+    let mut canvas = Canvas::new(width, height);
+    Board::picture(level, theme, &mut canvas);
+    canvas.png()
+}
+
+/// Writes a picture of how each level starts into `dir`, as
+/// `level-001.png` and so on, exiting if it cannot.
+pub fn gallery(levels: &[Level], theme: &Theme, dir: &str) {
+    if let Err(err) = fs::create_dir_all(dir) {
+        cli::fail(&format!("Could not create {}: {}", dir, err));
+    }
+    for (index, level) in levels.iter().enumerate() {
+        let path = Path::new(dir).join(format!("level-{:03}.png", index + 1));
+        if let Err(err) =
+            png(level, theme).and_then(|png| fs::write(&path, png).map_err(|err| err.to_string()))
+        {
+            cli::fail(&format!("Could not write {}: {}", path.display(), err));
+        }
+    }
+    eprintln!("Wrote {} pictures to {}", levels.len(), dir);
+}
@@ -0,0 +1,173 @@
+use crate::level::{
+    xy_to_pos, Block, BlockDir, BlockType, Level, EXIT, FLOOR, LEFTRIGHT1, LEFTRIGHT2, PLAYER,
+    TILES_HIGH, TILES_WIDE, UPDOWN1, UPDOWN2, WALL,
+};
+use crate::rng::Rng;
+use crate::solver::{self, SolverConfig};
+
+// Changes to try before settling for the closest level to the difficulty
+// asked for.
+const ATTEMPTS: usize = 300;
+
+/// How crowded the boards the generator makes are.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Preset {
+    /// As crowded as the levels of the pack.
+    Standard,
+    /// Only a few blocks besides the player, for young children, who can
+    /// see every block and what is in its way.
+    Kids,
+}
+
+impl Preset {
+    /// Fewest and most blocks besides the player a board starts with. The
+    /// most is also as many as climbing can leave.
+    fn blocks(self) -> (usize, usize) {
+        match self {
+            Preset::Standard => (8, 13),
+            Preset::Kids => (1, 3),
+        }
+    }
+}
+
+/// Generates a solvable level that takes as close to `moves` moves as it can
+/// find, and sets its par. The same generator state always gives the same
+/// level.
+pub fn level(rng: &mut Rng, moves: usize) -> Level {
+    preset(rng, Preset::Standard, moves)
+}
+
+/// Like `level`, with boards as crowded as `preset` says.
+///
+/// Starts from a random solvable board and climbs: each attempt swaps one
+/// block for another random one and keeps the result if it is still
+/// solvable and no further from the target.
+pub fn preset(rng: &mut Rng, preset: Preset, moves: usize) -> Level {
+    let mut best = loop {
+        let level = Level::from(&mut random(rng, preset).iter().copied());
+        if let Some(solution) = solver::solve(&level, SolverConfig::default()) {
+            break (level, solution.len());
+        }
+    };
+    let distance = |par: usize| (par as isize - moves as isize).abs();
+    for _ in 0..ATTEMPTS {
+        if best.1 >= moves {
+            break;
+        }
+        let level = Level::from(&mut mutate(rng, preset, &best.0).iter().copied());
+        if let Some(solution) = solver::solve(&level, SolverConfig::default()) {
+            if distance(solution.len()) <= distance(best.1) {
+                best = (level, solution.len());
+            }
+        }
+    }
+    let (mut level, par) = best;
+    level.par = Some(par);
+    level
+}
+
+/// Takes a random block other than the player off the board and puts down
+/// a new one somewhere. A board with as many blocks as `preset` allows
+/// always loses one first.
+fn mutate(rng: &mut Rng, preset: Preset, level: &Level) -> [u8; TILES_WIDE * TILES_HIGH] {
+    let mut data = level.serialize();
+    let movable: Vec<&Block> = level
+        .blocks
+        .iter()
+        .filter(|block| block.dir != BlockDir::Static && block.r#type != BlockType::Player)
+        .collect();
+    if !movable.is_empty() && (rng.below(3) != 0 || movable.len() >= preset.blocks().1) {
+        let block = movable[rng.below(movable.len())];
+        for x in block.x1..block.x2 + 1 {
+            for y in block.y1..block.y2 + 1 {
+                data[xy_to_pos(x, y)] = FLOOR;
+            }
+        }
+    }
+    for _ in 0..20 {
+        if add_random(rng, &mut data) {
+            break;
+        }
+    }
+    data
+}
+
+/// A walled board with the player and the exit on row 3 and a random
+/// assortment of as many blocks as `preset` says, which may or may not be
+/// solvable.
+fn random(rng: &mut Rng, preset: Preset) -> [u8; TILES_WIDE * TILES_HIGH] {
+    let mut data = [FLOOR; TILES_WIDE * TILES_HIGH];
+    for i in 0..TILES_WIDE {
+        data[xy_to_pos(i, 0)] = WALL;
+        data[xy_to_pos(i, TILES_HIGH - 1)] = WALL;
+    }
+    for i in 0..TILES_HIGH {
+        data[xy_to_pos(0, i)] = WALL;
+        data[xy_to_pos(TILES_WIDE - 1, i)] = WALL;
+    }
+    data[xy_to_pos(TILES_WIDE - 1, 3)] = EXIT;
+    let player = 1 + rng.below(3);
+    data[xy_to_pos(player, 3)] = PLAYER;
+    data[xy_to_pos(player + 1, 3)] = PLAYER;
+    let (fewest, most) = preset.blocks();
+    let blocks = fewest + rng.below(most - fewest + 1);
+    let mut placed = 0;
+    for _ in 0..blocks * 4 {
+        if placed == blocks {
+            break;
+        }
+        if add_random(rng, &mut data) {
+            placed += 1;
+        }
+    }
+    data
+}
+
+/// Tries to add a block of random size and place.
+fn add_random(rng: &mut Rng, data: &mut [u8; 64]) -> bool {
+    let vertical = rng.below(2) == 0;
+    let len = 2 + rng.below(2);
+    let (x, y) = (1 + rng.below(6), 1 + rng.below(6));
+    // Nothing horizontal can get out of the player's way.
+    if !vertical && y == 3 {
+        return false;
+    }
+    place(data, vertical, len, x, y)
+}
+
+/// Adds a `len` cell block from `(x, y)` going right, or down if
+/// `vertical`, if it fits on the floor inside the outer ring. It is drawn
+/// with whichever of its two characters keeps it from running into a
+/// neighbour of the same kind. Returns false if it did not fit.
+pub fn place(data: &mut [u8; 64], vertical: bool, len: usize, x: usize, y: usize) -> bool {
+    let cells: Vec<(usize, usize)> = (0..len)
+        .map(|i| if vertical { (x, y + i) } else { (x + i, y) })
+        .collect();
+    if cells.iter().any(|&(cx, cy)| {
+        cx >= TILES_WIDE - 1 || cy >= TILES_HIGH - 1 || data[xy_to_pos(cx, cy)] != FLOOR
+    }) {
+        return false;
+    }
+    let (before, after, first, second) = if vertical {
+        (xy_to_pos(x, y - 1), xy_to_pos(x, y + len), UPDOWN1, UPDOWN2)
+    } else {
+        (
+            xy_to_pos(x - 1, y),
+            xy_to_pos(x + len, y),
+            LEFTRIGHT1,
+            LEFTRIGHT2,
+        )
+    };
+    let free = |ch| data[before] != ch && data[after] != ch;
+    let ch = if free(first) {
+        first
+    } else if free(second) {
+        second
+    } else {
+        return false;
+    };
+    for (cx, cy) in cells {
+        data[xy_to_pos(cx, cy)] = ch;
+    }
+    true
+}
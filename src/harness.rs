@@ -0,0 +1,221 @@
+//! Scripted mouse and keyboard input played to the game without a window,
+//! frame by frame as the game loop would, for checking how it takes drags,
+//! clicks and keys, edge cases included.
+//!
+//! A script is a file of lines, each one step; `#` starts a comment:
+//!
+//! - `board` and the eight lines after it: a level of the pack to play, as
+//!   in a pack. The boards come first; the game starts on the first of them
+//!   at the step after
+//! - `size W H`: the pixels of the window, 400 by 400 unless given
+//! - `mirrored`: draw boards flipped left to right
+//! - `click-to-move`: move blocks by clicking rather than dragging
+//! - `move X Y`: the cursor goes to the screen position, which may be off
+//!   the board or the window
+//! - `press` / `release`: the left button goes down or up
+//! - `key K`: the key named, a letter, is pressed and let go
+//! - `frame`: the game takes the input so far, then updates
+//! - `expect` and the eight lines after it: the board being played as it
+//!   should stand
+//! - `moves N`: how many moves should have been made on it
+//! - `level N`: which level of the pack should be played, counting from 1
+//!
+//! The game starts with nothing saved, the tutorial done and animations
+//! left out, and saves nothing.
+
+use crate::audio::Music;
+use crate::input::UnblockInput;
+use crate::save::Save;
+use crate::{Bounds, InputScheme, LevelSet, Options, OPTIONS};
+use coffee::input::keyboard::{self, KeyCode};
+use coffee::input::mouse::{self, Button};
+use coffee::input::{ButtonState, Event, Input};
+use std::mem;
+use unblock::level::{Level, TILES_HIGH};
+use unblock::pack;
+
+/// Keys a script can press, by name.
+const KEYS: [(&str, KeyCode); 26] = [
+    ("A", KeyCode::A),
+    ("B", KeyCode::B),
+    ("C", KeyCode::C),
+    ("D", KeyCode::D),
+    ("E", KeyCode::E),
+    ("F", KeyCode::F),
+    ("G", KeyCode::G),
+    ("H", KeyCode::H),
+    ("I", KeyCode::I),
+    ("J", KeyCode::J),
+    ("K", KeyCode::K),
+    ("L", KeyCode::L),
+    ("M", KeyCode::M),
+    ("N", KeyCode::N),
+    ("O", KeyCode::O),
+    ("P", KeyCode::P),
+    ("Q", KeyCode::Q),
+    ("R", KeyCode::R),
+    ("S", KeyCode::S),
+    ("T", KeyCode::T),
+    ("U", KeyCode::U),
+    ("V", KeyCode::V),
+    ("W", KeyCode::W),
+    ("X", KeyCode::X),
+    ("Y", KeyCode::Y),
+    ("Z", KeyCode::Z),
+];
+
+/// Stands in for the window the game would be drawn in.
+struct Headless {
+    width: f32,
+    height: f32,
+}
+
+impl Bounds for Headless {
+    fn width(&self) -> f32 {
+        self.width
+    }
+
+    fn height(&self) -> f32 {
+        self.height
+    }
+}
+
+/// Plays `script`, returning how many expectations it checked, or what went
+/// wrong: a line that cannot be read, or the first expectation not met.
+pub fn run(script: &str) -> Result<usize, String> {
+    let mut lines = script.lines().enumerate().map(|(index, line)| {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        (index + 1, line)
+    });
+    let mut pack = Vec::new();
+    let mut game: Option<LevelSet> = None;
+    let mut input = UnblockInput::new();
+    let mut window = Headless {
+        width: 400.0,
+        height: 400.0,
+    };
+    let mut checked = 0;
+    while let Some((number, line)) = lines.next() {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let fail = |problem: &str| format!("line {}: {}", number, problem);
+        let number_at = |index: usize| -> Result<f32, String> {
+            words
+                .get(index)
+                .and_then(|word| word.parse().ok())
+                .ok_or_else(|| fail(&format!("{} takes numbers", words[0])))
+        };
+        match words.as_slice() {
+            [] => continue,
+            ["board"] if game.is_none() => {
+                let mut level = pack::parse(grid(&mut lines, number)?.as_bytes());
+                if level.is_empty() {
+                    return Err(fail("the board is not a level"));
+                }
+                pack.push(level.swap_remove(0));
+                continue;
+            }
+            ["board"] => return Err(fail("boards come before the other steps")),
+            _ if game.is_none() && pack.is_empty() => return Err(fail("no board yet")),
+            _ => {}
+        }
+        let game = game.get_or_insert_with(|| start(mem::take(&mut pack), &window));
+        match words.as_slice() {
+            ["size", ..] => {
+                window.width = number_at(1)?;
+                window.height = number_at(2)?;
+            }
+            ["mirrored"] => game.save.settings.mirror = Some(true),
+            ["click-to-move"] => game.scheme = InputScheme::Click,
+            ["move", ..] => input.update(Event::Mouse(mouse::Event::CursorMoved {
+                x: number_at(1)?,
+                y: number_at(2)?,
+            })),
+            ["press"] => input.update(button(ButtonState::Pressed)),
+            ["release"] => input.update(button(ButtonState::Released)),
+            ["key", name] => {
+                let key_code = KEYS
+                    .iter()
+                    .find(|(known, _)| known.eq_ignore_ascii_case(name))
+                    .map(|(_, key_code)| *key_code)
+                    .ok_or_else(|| fail(&format!("no key {}", name)))?;
+                for state in [ButtonState::Pressed, ButtonState::Released] {
+                    input.update(Event::Keyboard(keyboard::Event::Input { key_code, state }));
+                }
+            }
+            ["frame"] => {
+                game.respond(&mut input, &window);
+                game.tick(&window);
+                input.clear();
+            }
+            ["expect"] => {
+                let expected = grid(&mut lines, number)?;
+                let level = &game.levels[game.current].level;
+                let actual = crate::cli::board(&level.serialize());
+                if actual != expected {
+                    return Err(fail(&format!(
+                        "expected\n{}but the board is\n{}",
+                        expected, actual
+                    )));
+                }
+                checked += 1;
+            }
+            ["moves", _] => {
+                let expected = number_at(1)? as usize;
+                let actual = game.levels[game.current].level.moves.len();
+                if actual != expected {
+                    return Err(fail(&format!(
+                        "expected {} moves but {} were made",
+                        expected, actual
+                    )));
+                }
+                checked += 1;
+            }
+            ["level", _] => {
+                let expected = number_at(1)? as usize;
+                let actual = game.current + 1;
+                if actual != expected {
+                    return Err(fail(&format!(
+                        "expected level {} but level {} is played",
+                        expected, actual
+                    )));
+                }
+                checked += 1;
+            }
+            _ => return Err(fail(&format!("cannot read {:?}", line))),
+        }
+        game.tick(&window);
+    }
+    Ok(checked)
+}
+
+/// The game as a script starts it, on the first of `pack`.
+fn start(pack: Vec<Level>, window: &Headless) -> LevelSet {
+    OPTIONS.get_or_init(Options::default);
+    let mut save = Save::blank();
+    save.tutorial_done = true;
+    save.settings.reduced_motion = true;
+    save.settings.mirror = Some(false);
+    let mut game = LevelSet::load(pack, save, Music::silent(), None);
+    game.tick(window);
+    game
+}
+
+fn button(state: ButtonState) -> Event {
+    Event::Mouse(mouse::Event::Input {
+        button: Button::Left,
+        state,
+    })
+}
+
+/// The eight lines of a board after the line `number`, each ending in a
+/// newline.
+fn grid<'a>(
+    lines: &mut impl Iterator<Item = (usize, &'a str)>,
+    number: usize,
+) -> Result<String, String> {
+    let rows: Vec<&str> = lines.take(TILES_HIGH).map(|(_, row)| row).collect();
+    if rows.len() < TILES_HIGH {
+        return Err(format!("line {}: a board takes eight lines", number));
+    }
+    Ok(rows.iter().map(|row| format!("{}\n", row)).collect())
+}
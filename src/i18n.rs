@@ -0,0 +1,86 @@
+//! The words the game shows, in the player's language. Each language is a
+//! file in `src_assets/lang` of `key = text` lines, built into the game;
+//! each `{}` in a text is filled in with what the game passes, in order.
+//! A text a language lacks comes from English, and one English lacks shows
+//! its key, so a missing translation is easy to spot.
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+
+/// The languages there are, by code, English first.
+const LANGUAGES: [(&str, &str); 2] = [
+    ("en", include_str!("../src_assets/lang/en.txt")),
+    ("de", include_str!("../src_assets/lang/de.txt")),
+];
+
+// Global like the toasts, so that anything can put words on the screen
+// without being handed the settings.
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static TABLES: OnceLock<Vec<HashMap<&'static str, &'static str>>> = OnceLock::new();
+
+/// The codes of the languages there are.
+pub fn codes() -> Vec<&'static str> {
+    LANGUAGES.iter().map(|(code, _)| *code).collect()
+}
+
+/// Switches to the language with `code`. Returns false, keeping the
+/// language as it was, if there is none.
+pub fn set(code: &str) -> bool {
+    match LANGUAGES.iter().position(|(known, _)| *known == code) {
+        Some(index) => {
+            CURRENT.store(index, Ordering::Relaxed);
+            true
+        }
+        None => false,
+    }
+}
+
+/// The text for `key`.
+pub fn text(key: &str) -> String {
+    fill(key, &[])
+}
+
+/// The text for `key` with its `{}`s filled in with `args`.
+pub fn fill(key: &str, args: &[&dyn Display]) -> String {
+    let tables = TABLES.get_or_init(|| LANGUAGES.iter().map(|(_, text)| parse(text)).collect());
+    let current = &tables[CURRENT.load(Ordering::Relaxed)];
+    let template = match current.get(key).or_else(|| tables[0].get(key)) {
+        Some(template) => *template,
+        None => return key.to_string(),
+    };
+    let mut out = String::new();
+    let mut pieces = template.split("{}");
+    out += pieces.next().unwrap_or_default();
+    let mut args = args.iter();
+    for piece in pieces {
+        if let Some(arg) = args.next() {
+            out += &arg.to_string();
+        }
+        out += piece;
+    }
+    out
+}
+
+/// Whether the language is written right to left, so that lines of text
+/// start at the right and the layout is mirrored.
+pub fn right_to_left() -> bool {
+    text("direction") == "rtl"
+}
+
+/// The text for `count` of something: `key.one` for one, `key.other` for
+/// any other number, with the number filled in.
+pub fn count(key: &str, count: usize) -> String {
+    let form = if count == 1 { "one" } else { "other" };
+    fill(&format!("{}.{}", key, form), &[&count])
+}
+
+fn parse(text: &'static str) -> HashMap<&'static str, &'static str> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, text)| (key.trim(), text.trim()))
+        .collect()
+}
@@ -0,0 +1,237 @@
+//! Levels read from pictures of boards, for digitizing puzzle books.
+//!
+//! A picture has to be an exact grid of 8x8 cells, or 6x6 for boards drawn
+//! without their walls, each cell one flat colour (lines between cells are
+//! fine, as only the middle of each cell is looked at):
+//!
+//! * black - a wall
+//! * white or light grey - floor
+//! * yellow - the exit
+//! * red - the player
+//! * any other colour - part of a block: the cells next to each other in
+//!   that colour, which have to make a straight line at least two long
+//!
+//! Blocks side by side need different colours. A 6x6 board gets walls all
+//! round and the exit in line with the player on the right (or bottom, for
+//! a vertical player), where the classic puzzles have it.
+
+use crate::level::{
+    xy_to_pos, Level, EXIT, FLOOR, LEFTRIGHT1, LEFTRIGHT2, PLAYER, TILES_HIGH, TILES_WIDE, UPDOWN1,
+    UPDOWN2, WALL,
+};
+
+/// How far apart two colours can be, adding up the differences of their
+/// channels, and still count as the same. Allows for compression and
+/// anti-aliasing without mixing up colours a person would tell apart.
+const TOLERANCE: usize = 60;
+
+/// A picture as rows of RGB pixels, top row first.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<[u8; 3]>,
+}
+
+/// What a cell of the picture shows.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Cell {
+    Wall,
+    Floor,
+    Exit,
+    Player,
+    Block([u8; 3]),
+}
+
+/// Reads the board in `image`. Errors say what is wrong with the picture.
+pub fn level(image: &Image) -> Result<Level, String> {
+    let (size, colours) = [TILES_WIDE, 6]
+        .iter()
+        .find_map(|&size| grid(image, size).map(|colours| (size, colours)))
+        .ok_or_else(|| String::from("is not an 8x8 or 6x6 grid of flat colours"))?;
+    // A 6x6 board sits inside a wall.
+    let offset = (TILES_WIDE - size) / 2;
+    let mut cells = [Cell::Wall; TILES_WIDE * TILES_HIGH];
+    for (i, &colour) in colours.iter().enumerate() {
+        let (x, y) = (offset + i % size, offset + i / size);
+        cells[xy_to_pos(x, y)] = classify(colour);
+    }
+    let mut data = [FLOOR; TILES_WIDE * TILES_HIGH];
+    let mut seen = [false; TILES_WIDE * TILES_HIGH];
+    let mut player = None;
+    for pos in 0..cells.len() {
+        let (x, y) = (pos % TILES_WIDE, pos / TILES_WIDE);
+        match cells[pos] {
+            Cell::Wall => data[pos] = WALL,
+            Cell::Floor => {}
+            Cell::Exit => data[pos] = EXIT,
+            _ if seen[pos] => {}
+            cell => {
+                let (x2, y2) = line(&cells, &mut seen, pos).ok_or_else(|| {
+                    format!(
+                        "has a block at column {}, row {} that is not a straight line",
+                        x + 1 - offset,
+                        y + 1 - offset
+                    )
+                })?;
+                let horizontal = y2 == y;
+                let ch = match cell {
+                    Cell::Player if player.is_some() => {
+                        return Err(String::from("has more than one player"))
+                    }
+                    Cell::Player => {
+                        player = Some((x2, y2, horizontal));
+                        PLAYER
+                    }
+                    // Blocks of the same character that touch end to end
+                    // would read as one.
+                    _ if horizontal && x > 0 && data[pos - 1] == LEFTRIGHT1 => LEFTRIGHT2,
+                    _ if horizontal => LEFTRIGHT1,
+                    _ if y > 0 && data[pos - TILES_WIDE] == UPDOWN1 => UPDOWN2,
+                    _ => UPDOWN1,
+                };
+                for x in x..=x2 {
+                    for y in y..=y2 {
+                        data[xy_to_pos(x, y)] = ch;
+                    }
+                }
+            }
+        }
+    }
+    let (x2, y2, horizontal) = player.ok_or_else(|| String::from("has no player"))?;
+    if size < TILES_WIDE {
+        let exit = if horizontal {
+            xy_to_pos(TILES_WIDE - 1, y2)
+        } else {
+            xy_to_pos(x2, TILES_HIGH - 1)
+        };
+        data[exit] = EXIT;
+    }
+    Ok(Level::from(&mut data.iter().copied()))
+}
+
+/// The colour of each cell if `image` is a `size` by `size` grid with every
+/// cell one colour, row by row.
+fn grid(image: &Image, size: usize) -> Option<Vec<[u8; 3]>> {
+    if image.width < size || image.height < size {
+        return None;
+    }
+    let (width, height) = (
+        image.width as f32 / size as f32,
+        image.height as f32 / size as f32,
+    );
+    let pixel = |x: f32, y: f32| image.pixels[y as usize * image.width + x as usize];
+    let mut colours = Vec::with_capacity(size * size);
+    for row in 0..size {
+        for column in 0..size {
+            let (left, top) = (column as f32 * width, row as f32 * height);
+            let middle = pixel(left + width / 2.0, top + height / 2.0);
+            let flat = [0.25, 0.5, 0.75].iter().all(|&dx| {
+                [0.25, 0.5, 0.75]
+                    .iter()
+                    .all(|&dy| same(pixel(left + width * dx, top + height * dy), middle))
+            });
+            if !flat {
+                return None;
+            }
+            colours.push(middle);
+        }
+    }
+    Some(colours)
+}
+
+fn classify([r, g, b]: [u8; 3]) -> Cell {
+    let (high, low) = (192, 96);
+    if r.max(g).max(b) < 64 {
+        Cell::Wall
+    } else if r.min(g).min(b) > high {
+        Cell::Floor
+    } else if r > high && g > high && b < low {
+        Cell::Exit
+    } else if r > high && g < low && b < low {
+        Cell::Player
+    } else {
+        Cell::Block([r, g, b])
+    }
+}
+
+fn same(a: [u8; 3], b: [u8; 3]) -> bool {
+    let difference: usize = a
+        .iter()
+        .zip(b.iter())
+        .map(|(&a, &b)| (a as isize - b as isize).unsigned_abs())
+        .sum();
+    difference <= TOLERANCE
+}
+
+/// Marks the cells of the block starting at `pos`, its top left cell, as
+/// seen, and returns its bottom right cell. None unless it is a straight
+/// line at least two long with nothing else of its colour touching it.
+fn line(
+    cells: &[Cell; TILES_WIDE * TILES_HIGH],
+    seen: &mut [bool; TILES_WIDE * TILES_HIGH],
+    pos: usize,
+) -> Option<(usize, usize)> {
+    let matches = |a: Cell, b: Cell| match (a, b) {
+        (Cell::Block(a), Cell::Block(b)) => same(a, b),
+        (a, b) => a == b,
+    };
+    // Every cell of the colour connected to this one.
+    let mut region = vec![pos];
+    seen[pos] = true;
+    let mut next = 0;
+    while next < region.len() {
+        let (x, y) = (region[next] % TILES_WIDE, region[next] / TILES_WIDE);
+        next += 1;
+        let neighbours = [
+            (x > 0).then(|| xy_to_pos(x - 1, y)),
+            (x + 1 < TILES_WIDE).then(|| xy_to_pos(x + 1, y)),
+            (y > 0).then(|| xy_to_pos(x, y - 1)),
+            (y + 1 < TILES_HIGH).then(|| xy_to_pos(x, y + 1)),
+        ];
+        for neighbour in neighbours.iter().flatten() {
+            if !seen[*neighbour] && matches(cells[*neighbour], cells[pos]) {
+                seen[*neighbour] = true;
+                region.push(*neighbour);
+            }
+        }
+    }
+    let (x, y) = (pos % TILES_WIDE, pos / TILES_WIDE);
+    let last = *region.iter().max()?;
+    let (x2, y2) = (last % TILES_WIDE, last / TILES_WIDE);
+    let straight = (x2 == x || y2 == y) && region.len() == x2 - x + y2 - y + 1;
+    if straight && region.len() > 1 {
+        Some((x2, y2))
+    } else {
+        None
+    }
+}
+
+/// Decodes a PNG file into an image.
+#[cfg(feature = "import")]
+pub fn decode_png(bytes: &[u8]) -> Result<Image, String> {
+    use png::ColorType;
+
+    let decoder = png::Decoder::new(bytes);
+    let (info, mut reader) = decoder.read_info().map_err(|e| e.to_string())?;
+    let mut buffer = vec![0; info.buffer_size()];
+    reader.next_frame(&mut buffer).map_err(|e| e.to_string())?;
+    // Palettes and high bit depths come out as plain 8 bit samples.
+    let (colour, _) = reader.output_color_type();
+    let samples = colour.samples();
+    let (width, height) = (info.width as usize, info.height as usize);
+    let mut pixels = Vec::with_capacity(width * height);
+    for row in buffer.chunks(info.line_size).take(height) {
+        for pixel in row.chunks(samples).take(width) {
+            pixels.push(match colour {
+                ColorType::Grayscale | ColorType::GrayscaleAlpha => [pixel[0]; 3],
+                _ => [pixel[0], pixel[1], pixel[2]],
+            });
+        }
+    }
+    Ok(Image {
+        width,
+        height,
+        pixels,
+    })
+}
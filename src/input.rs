@@ -0,0 +1,282 @@
+use coffee::graphics::Point;
+use coffee::input::mouse::{self, Button};
+use coffee::input::{keyboard, keyboard::KeyCode, ButtonState, Event, Input};
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+// Copy of KeyboardAndMouse in order to get access to mouse_pressed
+pub struct UnblockInput {
+    cursor_position: Point,
+    is_cursor_taken: bool,
+    pressed_buttons: HashSet<Button>,
+    clicks: HashMap<Button, Vec<Point>>,
+    double_clicks: HashMap<Button, Vec<Point>>,
+    double_click_interval: Duration,
+    // The last click that could still become the first half of a double click.
+    last_click: Option<(Button, Point, Instant)>,
+    // When each currently held key went down.
+    pressed_keys: HashMap<KeyCode, Instant>,
+    just_pressed_keys: HashSet<KeyCode>,
+    released_keys: HashSet<KeyCode>,
+    key_repeat: KeyRepeat,
+    // When each held key fires its next repeat.
+    next_repeats: HashMap<KeyCode, Instant>,
+    // Lines the wheel scrolled down by during the last interaction.
+    scrolled: f32,
+    // Characters typed during the last interaction.
+    typed: String,
+    // When the mouse or keyboard was last touched.
+    last_touched: Instant,
+}
+
+/// How held keys repeat for `was_key_repeated`.
+#[derive(Clone, Copy)]
+pub struct KeyRepeat {
+    /// Time between the first press and the first repeat.
+    pub delay: Duration,
+    /// Time between repeats after that.
+    pub interval: Duration,
+}
+
+// How far the cursor may move between the two clicks of a double click.
+const DOUBLE_CLICK_DISTANCE: f32 = 5.0;
+
+impl Default for KeyRepeat {
+    fn default() -> Self {
+        KeyRepeat {
+            delay: Duration::from_millis(400),
+            interval: Duration::from_millis(100),
+        }
+    }
+}
+
+impl UnblockInput {
+    /// Returns the current cursor position.
+    pub fn cursor_position(&self) -> Point {
+        self.cursor_position
+    }
+
+    /// Returns true if the left mouse button is currently held down.
+    pub fn is_mouse_pressed(&self) -> bool {
+        self.is_button_pressed(Button::Left)
+    }
+
+    /// Returns true if the given mouse button is currently held down.
+    pub fn is_button_pressed(&self, button: Button) -> bool {
+        self.pressed_buttons.contains(&button)
+    }
+
+    /// Returns the positions of the mouse clicks during the last interaction.
+    ///
+    /// Clicks performed while the mouse cursor is not available are
+    /// automatically ignored.
+    pub fn left_clicks(&self) -> &[Point] {
+        self.clicks(Button::Left)
+    }
+
+    /// Returns the positions of the clicks of the given button during the
+    /// last interaction.
+    pub fn clicks(&self, button: Button) -> &[Point] {
+        self.clicks.get(&button).map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns the positions of the double clicks of the given button during
+    /// the last interaction. The second click of a double click is also
+    /// reported by `clicks`.
+    pub fn double_clicks(&self, button: Button) -> &[Point] {
+        self.double_clicks.get(&button).map_or(&[], Vec::as_slice)
+    }
+
+    fn click(&mut self, button: Button) {
+        let position = self.cursor_position;
+        let now = Instant::now();
+        self.clicks.entry(button).or_default().push(position);
+        match self.last_click {
+            Some((last_button, last_position, at))
+                if last_button == button
+                    && now.duration_since(at) <= self.double_click_interval
+                    && (position.x - last_position.x).abs() <= DOUBLE_CLICK_DISTANCE
+                    && (position.y - last_position.y).abs() <= DOUBLE_CLICK_DISTANCE =>
+            {
+                self.double_clicks.entry(button).or_default().push(position);
+                // A third click starts over rather than making another pair.
+                self.last_click = None;
+            }
+            _ => self.last_click = Some((button, position, now)),
+        }
+    }
+
+    /// Returns true if the given key is currently pressed.
+    pub fn is_key_pressed(&self, key_code: KeyCode) -> bool {
+        self.pressed_keys.contains_key(&key_code)
+    }
+
+    /// Returns how long the given key has been held down, if it is pressed.
+    pub fn key_held_for(&self, key_code: KeyCode) -> Option<Duration> {
+        self.pressed_keys
+            .get(&key_code)
+            .map(|pressed| pressed.elapsed())
+    }
+
+    /// Returns true if the given key went down during the last interaction.
+    /// Auto-repeat from the operating system is not counted.
+    pub fn was_key_pressed(&self, key_code: KeyCode) -> bool {
+        self.just_pressed_keys.contains(&key_code)
+    }
+
+    /// Returns true if the given key went down during the last interaction
+    /// or has been held long enough to repeat, as configured by
+    /// `set_key_repeat`.
+    pub fn was_key_repeated(&self, key_code: KeyCode) -> bool {
+        self.was_key_pressed(key_code)
+            || self
+                .next_repeats
+                .get(&key_code)
+                .is_some_and(|next| Instant::now() >= *next)
+    }
+
+    /// Returns true if the given key was released during the last interaction.
+    pub fn was_key_released(&self, key_code: KeyCode) -> bool {
+        self.released_keys.contains(&key_code)
+    }
+
+    /// Returns how many lines the mouse wheel was scrolled by during the
+    /// last interaction: positive away from the user, negative towards.
+    pub fn wheel_scrolled(&self) -> f32 {
+        self.scrolled
+    }
+
+    /// Returns the characters typed during the last interaction, leaving
+    /// out Backspace, Enter and the like.
+    pub fn text_entered(&self) -> &str {
+        &self.typed
+    }
+
+    /// When the mouse was last moved, clicked or scrolled or a key last
+    /// went down or up.
+    pub fn last_touched(&self) -> Instant {
+        self.last_touched
+    }
+
+    pub fn is_ctrl_pressed(&self) -> bool {
+        self.is_key_pressed(KeyCode::LControl) || self.is_key_pressed(KeyCode::RControl)
+    }
+}
+
+impl Input for UnblockInput {
+    fn new() -> UnblockInput {
+        UnblockInput {
+            cursor_position: Point::new(0.0, 0.0),
+            is_cursor_taken: false,
+            pressed_buttons: HashSet::new(),
+            clicks: HashMap::new(),
+            double_clicks: HashMap::new(),
+            double_click_interval: Duration::from_millis(400),
+            last_click: None,
+            pressed_keys: HashMap::new(),
+            just_pressed_keys: HashSet::new(),
+            released_keys: HashSet::new(),
+            key_repeat: KeyRepeat::default(),
+            next_repeats: HashMap::new(),
+            scrolled: 0.0,
+            typed: String::new(),
+            last_touched: Instant::now(),
+        }
+    }
+
+    fn update(&mut self, event: Event) {
+        if matches!(event, Event::Mouse(_) | Event::Keyboard(_)) {
+            self.last_touched = Instant::now();
+        }
+        match event {
+            Event::Mouse(mouse_event) => match mouse_event {
+                mouse::Event::CursorMoved { x, y } => {
+                    self.cursor_position = Point::new(x, y);
+                }
+                mouse::Event::CursorTaken => {
+                    self.is_cursor_taken = true;
+                }
+                mouse::Event::CursorReturned => {
+                    self.is_cursor_taken = false;
+                }
+                mouse::Event::Input { button, state } => match state {
+                    ButtonState::Pressed => {
+                        if !self.is_cursor_taken {
+                            let _ = self.pressed_buttons.insert(button);
+                        }
+                    }
+                    ButtonState::Released => {
+                        if self.pressed_buttons.remove(&button) && !self.is_cursor_taken {
+                            self.click(button);
+                        }
+                    }
+                },
+                mouse::Event::CursorEntered => {
+                    // TODO: Track it!
+                }
+                mouse::Event::CursorLeft => {
+                    // TODO: Track it!
+                }
+                mouse::Event::WheelScrolled { delta_y, .. } => {
+                    if !self.is_cursor_taken {
+                        self.scrolled += delta_y;
+                    }
+                }
+            },
+            Event::Keyboard(keyboard_event) => match keyboard_event {
+                keyboard::Event::Input { key_code, state } => {
+                    match state {
+                        ButtonState::Pressed => {
+                            // Auto-repeat sends more presses; keep the first.
+                            if let Entry::Vacant(entry) = self.pressed_keys.entry(key_code) {
+                                let now = Instant::now();
+                                let _ = entry.insert(now);
+                                let _ = self.just_pressed_keys.insert(key_code);
+                                let _ = self
+                                    .next_repeats
+                                    .insert(key_code, now + self.key_repeat.delay);
+                            }
+                        }
+                        ButtonState::Released => {
+                            let _ = self.pressed_keys.remove(&key_code);
+                            let _ = self.next_repeats.remove(&key_code);
+                            let _ = self.released_keys.insert(key_code);
+                        }
+                    };
+                }
+                keyboard::Event::TextEntered { character } => {
+                    if !character.is_control() {
+                        self.typed.push(character);
+                    }
+                }
+            },
+            Event::Gamepad { .. } => {
+                // Ignore gamepad events...
+            }
+            Event::Window(_) => {
+                // Ignore window events...
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        for clicks in self.clicks.values_mut() {
+            clicks.clear();
+        }
+        for double_clicks in self.double_clicks.values_mut() {
+            double_clicks.clear();
+        }
+        self.just_pressed_keys.clear();
+        self.released_keys.clear();
+        self.scrolled = 0.0;
+        self.typed.clear();
+        let now = Instant::now();
+        let interval = self.key_repeat.interval;
+        for next in self.next_repeats.values_mut() {
+            if now >= *next {
+                *next = now + interval;
+            }
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use crate::input::UnblockInput;
+use crate::render::{Color, Place, Point, Rectangle, Renderer};
+use crate::text;
+use crate::Board;
+use coffee::input::keyboard::KeyCode;
+use unblock::level::{BlockDir, BlockType};
+use unblock::solver::{self, SolverConfig, Stats};
+
+const LINE: f32 = 17.0;
+const COLUMN: f32 = 250.0;
+
+/// Developer panel over the board, toggled with F12: the blocks, the data
+/// grid, the drag in progress and what solving the position costs.
+///
+/// While it is open PageUp / PageDown pick a block and the arrow keys move
+/// it a cell in any direction, ignoring the rules, onto any free cells.
+/// Each edit is a recorded move, so U takes it back.
+#[derive(Default)]
+pub struct Inspector {
+    open: bool,
+    picked: usize,
+    // Solving the position with the given Zobrist hash, redone when it
+    // changes.
+    solved: Option<(u64, Option<usize>, Stats)>,
+}
+
+impl Inspector {
+    pub fn new() -> Inspector {
+        Inspector::default()
+    }
+
+    /// Whether the panel is showing, and so has the arrow keys.
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Returns true if the board was edited and needs redrawing.
+    pub fn interact(&mut self, input: &UnblockInput, board: &mut Board) -> bool {
+        if input.was_key_released(KeyCode::F12) {
+            self.open = !self.open;
+        }
+        if !self.open {
+            return false;
+        }
+        let count = board.level.blocks.len();
+        if count == 0 {
+            return false;
+        }
+        if input.was_key_released(KeyCode::PageDown) {
+            self.picked = (self.picked + 1) % count;
+        }
+        if input.was_key_released(KeyCode::PageUp) {
+            self.picked = (self.picked + count - 1) % count;
+        }
+        self.picked = self.picked.min(count - 1);
+        let (dx, dy) = if input.was_key_released(KeyCode::Left) {
+            (-1, 0)
+        } else if input.was_key_released(KeyCode::Right) {
+            (1, 0)
+        } else if input.was_key_released(KeyCode::Up) {
+            (0, -1)
+        } else if input.was_key_released(KeyCode::Down) {
+            (0, 1)
+        } else {
+            return false;
+        };
+        let block = &board.level.blocks[self.picked];
+        if block.dir == BlockDir::Static {
+            return false;
+        }
+        let (x, y) = (block.x1 as isize + dx, block.y1 as isize + dy);
+        if x < 0 || y < 0 {
+            return false;
+        }
+        let target = (x as usize, y as usize);
+        board.level.fits(self.picked, target) && board.level.move_block(self.picked, target)
+    }
+
+    pub fn draw(&mut self, board: &Board, renderer: &mut impl Renderer) {
+        if !self.open {
+            return;
+        }
+        let level = &board.level;
+        let hash = level.bitboard().zobrist();
+        if self.solved.map(|(solved, _, _)| solved) != Some(hash) {
+            let (solution, stats) = solver::solve_with(level, SolverConfig::default());
+            self.solved = Some((hash, solution.map(|s| s.len()), stats));
+        }
+        let (_, moves, stats) = self.solved.expect("Solved above");
+        renderer.draw_rect(
+            Rectangle {
+                x: 0.0,
+                y: 0.0,
+                width: renderer.width(),
+                height: renderer.height(),
+            },
+            Color {
+                a: 0.8,
+                ..Color::BLACK
+            },
+            None,
+        );
+
+        let mut left = vec![
+            String::from("Inspector (F12)"),
+            format!("Level {:016x}", level.id()),
+            format!(
+                "{} moves made{}",
+                level.moves.len(),
+                if level.solved { ", solved" } else { "" }
+            ),
+            match &board.drag {
+                Some(drag) => format!("Drag {} {:?} -> {:?}", drag.block, drag.origin, drag.target),
+                None => String::from("No drag"),
+            },
+            format!("Mouse {:.0}, {:.0}", board.mouse_pos.0, board.mouse_pos.1),
+            match moves {
+                Some(moves) => format!("Solver: {} moves left", moves),
+                None => String::from("Solver: unsolvable"),
+            },
+            format!("  {} searched, {} stored", stats.nodes, stats.stored),
+            String::new(),
+            String::from("Data"),
+        ];
+        left.extend(level.cells_pretty().lines().map(|row| format!("  {}", row)));
+
+        let mut right = vec![String::from("Blocks (PgUp/PgDn, arrows)")];
+        right.extend(level.blocks.iter().enumerate().map(|(index, block)| {
+            let kind = match block.r#type {
+                BlockType::Player => '=',
+                BlockType::Other(id) => id as char,
+                BlockType::Wall => '&',
+                BlockType::Exit => '^',
+            };
+            let dir = match block.dir {
+                BlockDir::LeftRight => "lr",
+                BlockDir::UpDown => "ud",
+                BlockDir::Static => "--",
+            };
+            format!(
+                "{}{:2} {} {} {},{}-{},{}",
+                if index == self.picked { ">" } else { " " },
+                index,
+                kind,
+                dir,
+                block.x1,
+                block.y1,
+                block.x2,
+                block.y2
+            )
+        }));
+
+        for (column, lines) in [left, right].iter().enumerate() {
+            let x = 10.0 + column as f32 * COLUMN;
+            for (row, line) in lines.iter().enumerate() {
+                renderer.draw_text(
+                    line,
+                    Place::At(Point::new(x, 10.0 + row as f32 * LINE)),
+                    text::SMALL,
+                    Color::WHITE,
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+
+/// A background thread for one kind of job, so slow work (solving,
+/// generating puzzles, writing the save file, connecting to a server) never
+/// holds up the update loop. Requests go in with `send`, and results come
+/// back in order through `poll`, which `Game::update` calls every tick.
+///
+/// Requests that queue up while the worker is busy are collapsed into the
+/// newest. That suits every job the game has: only the position now on the
+/// board needs solving, and only the latest progress needs saving.
+pub struct Worker<In, Out> {
+    // None once finished.
+    requests: Option<Sender<In>>,
+    results: Receiver<Out>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl<In: Send + 'static, Out: Send + 'static> Worker<In, Out> {
+    pub fn start<F: FnMut(In) -> Out + Send + 'static>(mut job: F) -> Worker<In, Out> {
+        let (requests, inbox) = mpsc::channel::<In>();
+        let (outbox, results) = mpsc::channel();
+        let thread = thread::spawn(move || {
+            // Ends once the worker is dropped and no more requests can come.
+            while let Ok(mut request) = inbox.recv() {
+                while let Ok(newer) = inbox.try_recv() {
+                    request = newer;
+                }
+                if outbox.send(job(request)).is_err() {
+                    break;
+                }
+            }
+        });
+        Worker {
+            requests: Some(requests),
+            results,
+            thread: Some(thread),
+        }
+    }
+
+    pub fn send(&self, request: In) {
+        // The thread only stops once this side is dropped.
+        if let Some(requests) = &self.requests {
+            let _ = requests.send(request);
+        }
+    }
+
+    /// Waits for the requests already sent to be done and stops the
+    /// thread, for work that must not be cut off by the game exiting.
+    /// Anything sent after is ignored.
+    pub fn finish(&mut self) {
+        self.requests = None;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+
+    /// The oldest result not yet collected, if any has come back.
+    pub fn poll(&self) -> Option<Out> {
+        self.results.try_recv().ok()
+    }
+}
@@ -0,0 +1,237 @@
+//! A running record of a session, for getting it back after a crash and for
+//! looking over afterwards: one JSON object a line, appended as things
+//! happen, each with the seconds `t` since the journal was opened and its
+//! `event`. Only pack levels are recorded, as generated ones cannot be
+//! made again.
+
+use serde_json::{json, Value};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use unblock::level::{BlockDir, Level};
+use unblock::replay::Replay;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event {
+    /// Playing the pack level at `index`, which has the given `Level::id`
+    /// and `moves` made on it so far.
+    Level {
+        index: usize,
+        id: u64,
+        moves: Replay,
+    },
+    Move {
+        block: usize,
+        x: usize,
+        y: usize,
+    },
+    Undo,
+    Redo,
+    Reset,
+    /// Going to another line of the level's variations.
+    Goto {
+        variation: Option<usize>,
+    },
+    /// The level was solved, and put back to its start.
+    Solve,
+}
+
+pub struct Journal {
+    file: File,
+    opened: Instant,
+}
+
+impl Journal {
+    /// Opens the journal at `path`, adding to what is already there.
+    pub fn open(path: &Path) -> io::Result<Journal> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Journal {
+            file,
+            opened: Instant::now(),
+        })
+    }
+
+    /// Adds `event` as a line of its own. Each line is written in one go, so
+    /// a crash loses at most the event being written.
+    pub fn write(&mut self, event: &Event) -> io::Result<()> {
+        let mut line = match event {
+            Event::Level { index, id, moves } => json!({
+                "event": "level",
+                "index": index,
+                "id": format!("{:016x}", id),
+                "moves": moves.encode(),
+            }),
+            Event::Move { block, x, y } => json!({
+                "event": "move",
+                "block": block,
+                "x": x,
+                "y": y,
+            }),
+            Event::Undo => json!({ "event": "undo" }),
+            Event::Redo => json!({ "event": "redo" }),
+            Event::Reset => json!({ "event": "reset" }),
+            Event::Goto { variation } => json!({ "event": "goto", "variation": variation }),
+            Event::Solve => json!({ "event": "solve" }),
+        };
+        line["t"] = json!(self.opened.elapsed().as_secs_f64());
+        self.file.write_all(format!("{}\n", line).as_bytes())
+    }
+}
+
+/// The events of the journal at `path`, each with its `t`, or what is wrong
+/// with it. A last line cut short by a crash is left out.
+pub fn read(path: &Path) -> Result<Vec<(Duration, Event)>, String> {
+    let text = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    let lines: Vec<&str> = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+    let mut events = Vec::new();
+    for (number, line) in lines.iter().enumerate() {
+        match parse(line) {
+            Ok(event) => events.push(event),
+            Err(_) if number + 1 == lines.len() && !text.ends_with('\n') => break,
+            Err(problem) => return Err(format!("line {}: {}", number + 1, problem)),
+        }
+    }
+    Ok(events)
+}
+
+fn parse(line: &str) -> Result<(Duration, Event), String> {
+    let value: Value = serde_json::from_str(line).map_err(|err| err.to_string())?;
+    let t = value["t"]
+        .as_f64()
+        .filter(|t| *t >= 0.0)
+        .map(Duration::from_secs_f64)
+        .ok_or("no t")?;
+    let number = |key: &str| {
+        value[key]
+            .as_u64()
+            .map(|n| n as usize)
+            .ok_or(format!("no {}", key))
+    };
+    let event = match value["event"].as_str() {
+        Some("level") => Event::Level {
+            index: number("index")?,
+            id: value["id"]
+                .as_str()
+                .and_then(|id| u64::from_str_radix(id, 16).ok())
+                .ok_or("no id")?,
+            moves: value["moves"]
+                .as_str()
+                .and_then(Replay::decode)
+                .ok_or("no moves")?,
+        },
+        Some("move") => Event::Move {
+            block: number("block")?,
+            x: number("x")?,
+            y: number("y")?,
+        },
+        Some("undo") => Event::Undo,
+        Some("redo") => Event::Redo,
+        Some("reset") => Event::Reset,
+        Some("goto") => Event::Goto {
+            variation: value["variation"].as_u64().map(|n| n as usize),
+        },
+        Some("solve") => Event::Solve,
+        Some(other) => return Err(format!("unknown event {}", other)),
+        None => return Err(String::from("no event")),
+    };
+    Ok((t, event))
+}
+
+/// What playing the levels of a pack came to, over one or more journals.
+#[derive(Default)]
+pub struct Summary {
+    /// By level, in pack order.
+    pub levels: Vec<Played>,
+    /// From the first event to the last of each session, added up.
+    pub time: Duration,
+    /// Times a journal was opened: its `t` starts again from 0 each time.
+    pub sessions: usize,
+    /// Events about levels that are not in the pack, which are left out.
+    pub skipped: usize,
+}
+
+#[derive(Clone, Default)]
+pub struct Played {
+    /// The moves each solve took.
+    pub solves: Vec<usize>,
+    pub resets: usize,
+    /// Time between an event on the level and the next event.
+    pub time: Duration,
+}
+
+/// Plays `journals` back onto `levels` to see what happened on each level.
+pub fn summarize(levels: &[Level], journals: &[Vec<(Duration, Event)>]) -> Summary {
+    let mut summary = Summary {
+        levels: vec![Played::default(); levels.len()],
+        ..Summary::default()
+    };
+    for journal in journals {
+        let mut playing: Option<(usize, Level)> = None;
+        let mut last: Option<Duration> = None;
+        for (t, event) in journal {
+            match last {
+                Some(last) if *t >= last => {
+                    summary.time += *t - last;
+                    if let Some((index, _)) = &playing {
+                        summary.levels[*index].time += *t - last;
+                    }
+                }
+                _ => summary.sessions += 1,
+            }
+            last = Some(*t);
+            if let Event::Level { index, id, moves } = event {
+                // By position if the pack is unchanged, else by id.
+                let found = Some(*index)
+                    .filter(|&index| levels.get(index).is_some_and(|level| level.id() == *id))
+                    .or_else(|| levels.iter().position(|level| level.id() == *id));
+                playing = found.map(|index| {
+                    let mut level = levels[index].clone();
+                    level.resume(moves);
+                    (index, level)
+                });
+                continue;
+            }
+            let (index, level) = match &mut playing {
+                Some(playing) => playing,
+                None => {
+                    summary.skipped += 1;
+                    continue;
+                }
+            };
+            match event {
+                Event::Move { block, x, y } => {
+                    let movable = level
+                        .blocks
+                        .get(*block)
+                        .is_some_and(|block| block.dir != BlockDir::Static);
+                    if movable && level.can_move(*block, (*x, *y)) {
+                        level.move_block(*block, (*x, *y));
+                    }
+                }
+                Event::Undo => {
+                    level.undo();
+                }
+                Event::Redo => {
+                    level.redo();
+                }
+                Event::Goto { variation } => {
+                    level.goto(*variation);
+                }
+                Event::Reset => {
+                    summary.levels[*index].resets += 1;
+                    level.reset();
+                }
+                Event::Solve => {
+                    summary.levels[*index].solves.push(level.moves.len());
+                    level.reset();
+                }
+                Event::Level { .. } => {}
+            }
+        }
+    }
+    summary
+}
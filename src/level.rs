@@ -0,0 +1,1200 @@
+use crate::bitboard::Bitboard;
+use crate::prompt::Prompt;
+use crate::replay::Replay;
+use crate::win::WinCondition;
+use std::fmt;
+use std::time::Duration;
+
+pub const TILES_WIDE: usize = 8;
+pub const TILES_HIGH: usize = 8;
+
+pub const FLOOR: u8 = b'*';
+pub const WALL: u8 = b'&';
+pub const LEFTRIGHT1: u8 = b'-';
+pub const LEFTRIGHT2: u8 = b'_';
+pub const UPDOWN1: u8 = b'|';
+pub const UPDOWN2: u8 = b'(';
+pub const PLAYER: u8 = b'=';
+pub const EXIT: u8 = b'^';
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BlockDir {
+    LeftRight,
+    UpDown,
+    Static,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BlockType {
+    Player,
+    Other(u8),
+    Wall,
+    Exit,
+}
+
+/// Which of a level's blocks is meant: its index in `Level::blocks`. Moves
+/// renumber the blocks only when they bring in a new phase.
+pub type BlockId = usize;
+
+/// What is on a cell of the board as it stands. Levels are read from and
+/// written as characters; this is how they are kept in between.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Cell {
+    Floor,
+    /// A wall, or any character that is not a tile, which is in the way
+    /// all the same.
+    Wall,
+    /// An exit nothing is on.
+    Exit,
+    /// Covered by a movable block.
+    Block(BlockId),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Block {
+    pub dir: BlockDir,
+    pub r#type: BlockType,
+    pub x1: usize,
+    pub y1: usize,
+    pub x2: usize,
+    pub y2: usize,
+}
+
+impl Block {
+    pub fn new(
+        r#type: BlockType,
+        dir: BlockDir,
+        x1: usize,
+        y1: usize,
+        x2: usize,
+        y2: usize,
+    ) -> Block {
+        Block {
+            r#type,
+            dir,
+            x1,
+            y1,
+            x2,
+            y2,
+        }
+    }
+
+    /// Whether the block covers grid cell `(x, y)`.
+    pub fn covers(&self, x: usize, y: usize) -> bool {
+        (self.x1..=self.x2).contains(&x) && (self.y1..=self.y2).contains(&y)
+    }
+}
+
+/// The next 64 level characters, skipping line breaks and spaces.
+fn read_frame<I: Iterator<Item = u8>>(data: &mut I) -> [u8; TILES_WIDE * TILES_HIGH] {
+    let mut frame = [FLOOR; TILES_WIDE * TILES_HIGH];
+    let mut cells = data.filter(|&b| b != b' ' && b != b'\r' && b != b'\n');
+    for cell in frame.iter_mut() {
+        *cell = match cells.next() {
+            Some(byte) => byte,
+            None => panic!("Not enough level data"),
+        };
+    }
+    frame
+}
+
+pub fn pos_to_xy(pos: usize) -> (usize, usize) {
+    let x = pos % TILES_WIDE;
+    let y = pos / TILES_WIDE;
+    (x, y)
+}
+
+pub fn xy_to_pos(x: usize, y: usize) -> usize {
+    x + y * 8
+}
+
+/// Maps a cell through one of the eight symmetries of the (square) board:
+/// `symmetry % 4` quarter turns clockwise, then a left-right mirror if
+/// `symmetry >= 4`.
+fn transform(symmetry: usize, x: usize, y: usize) -> (usize, usize) {
+    let last = TILES_WIDE - 1;
+    let (mut x, mut y) = (x, y);
+    for _ in 0..symmetry % 4 {
+        (x, y) = (last - y, x);
+    }
+    if symmetry >= 4 {
+        x = last - x;
+    }
+    (x, y)
+}
+
+/// FNV-1a, which unlike the standard library's hasher is the same on every
+/// run and platform.
+fn fnv(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for &byte in bytes {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+/// A recorded move: the block that moved, from where (all undo needs) and
+/// to where (all redo needs), as the top left cells, which move of the
+/// attempt it is counting from 0, and when it was made by `Level::clock`.
+/// With gravity on, `fell` holds the blocks that fell afterwards, in the
+/// order they fell.
+#[derive(Clone, Debug)]
+pub struct Move {
+    pub block: usize,
+    pub from: (usize, usize),
+    pub to: (usize, usize),
+    pub index: usize,
+    pub at: Duration,
+    pub fell: Vec<Move>,
+}
+
+impl Move {
+    /// How many cells the block travelled.
+    pub fn distance(&self) -> usize {
+        let ((x1, y1), (x2, y2)) = (self.from, self.to);
+        (x1 as isize - x2 as isize).unsigned_abs() + (y1 as isize - y2 as isize).unsigned_abs()
+    }
+}
+
+/// A move in the tree of every line tried in an attempt: undoing and then
+/// making a different move starts a branch beside the old one. `parent` is
+/// the move it was made after, None for a first move.
+#[derive(Clone, Debug)]
+pub struct Variation {
+    pub made: Move,
+    pub parent: Option<usize>,
+    // The branch after this one that redo follows: the last one played.
+    next: Option<usize>,
+}
+
+/// The rules of the game: an 8x8 board of blocks and the moves made on it.
+#[derive(Clone)]
+pub struct Level {
+    template: [u8; TILES_WIDE * TILES_HIGH],
+    pub data: [Cell; TILES_WIDE * TILES_HIGH],
+    pub blocks: Vec<Block>,
+    pub solved: bool,
+    pub moves: Vec<Move>,
+    /// How long the attempt has gone on, kept up to date by whatever is
+    /// timing it. Moves are stamped with it.
+    pub clock: Duration,
+    /// The fewest moves the level takes, when the pack says.
+    pub par: Option<usize>,
+    /// The most moves a solve can take for three stars and for two, when
+    /// the pack says; any other solve gets one.
+    pub stars: Option<(usize, usize)>,
+    /// What the pack calls the level, if anything.
+    pub name: Option<String>,
+    /// A solution the pack gives, with notes on how it goes, if any.
+    pub walkthrough: Option<Replay>,
+    /// What the pack says to the player as the level is played (see
+    /// `prompt::current`).
+    pub prompts: Vec<Prompt>,
+    /// What solves the level, getting out unless the pack says otherwise.
+    pub win: WinCondition,
+    /// Which phase is being played. Getting out of one phase brings in the
+    /// walls and exit of the next, and only the last has to be solved.
+    pub phase: usize,
+    // The walls and exits of the phases after the first, as they were read,
+    // and how many moves had been made when the current phase began, which
+    // undo cannot go back past.
+    phases: Vec<[u8; TILES_WIDE * TILES_HIGH]>,
+    phase_start: usize,
+    placed: Vec<(usize, (usize, usize))>,
+    // Every move tried this attempt, of which `moves` is the line from the
+    // start to `at`, and the first move redo makes from the start.
+    variations: Vec<Variation>,
+    at: Option<usize>,
+    first: Option<usize>,
+    gravity: bool,
+    // The same position again, kept in step with `blocks` for fast
+    // legality checks.
+    bits: Bitboard,
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bytes = self.serialize();
+        write!(f, "{}", String::from_utf8_lossy(&bytes))
+    }
+}
+
+/// Shows the board as a grid, which is what you want to see when a test fails.
+impl fmt::Debug for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "\n{}", self.to_string_pretty())
+    }
+}
+
+impl Default for Level {
+    fn default() -> Self {
+        Level::new()
+    }
+}
+
+impl Level {
+    pub fn new() -> Level {
+        Level {
+            template: [FLOOR; TILES_WIDE * TILES_HIGH],
+            data: [Cell::Floor; TILES_WIDE * TILES_HIGH],
+            blocks: Vec::new(),
+            solved: false,
+            moves: Vec::new(),
+            clock: Duration::from_secs(0),
+            par: None,
+            stars: None,
+            name: None,
+            walkthrough: None,
+            prompts: Vec::new(),
+            win: WinCondition::Exit,
+            phase: 0,
+            phases: Vec::new(),
+            phase_start: 0,
+            placed: Vec::new(),
+            variations: Vec::new(),
+            at: None,
+            first: None,
+            gravity: false,
+            bits: Bitboard::new(&[Cell::Floor; TILES_WIDE * TILES_HIGH], &[], false),
+        }
+    }
+
+    pub fn from<I: Iterator<Item = u8> + Sized>(data: &mut I) -> Level {
+        let mut level = Level::new();
+        level.parse(data);
+        level
+    }
+
+    /// Puts every block back where the level started.
+    pub fn reset(&mut self) {
+        self.solved = false;
+        self.phase = 0;
+        self.phase_start = 0;
+        self.placed.clear();
+        self.moves.clear();
+        self.variations.clear();
+        self.at = None;
+        self.first = None;
+        self.clock = Duration::from_secs(0);
+        self.blocks = Vec::new();
+        let template = self.template;
+        self.parse(&mut template.iter().copied());
+        // The goal's block is numbered as at the start again.
+        let start = match self.win {
+            WinCondition::Reach { from: (x, y), .. } => self.block_at(x, y),
+            _ => None,
+        };
+        if let (Some(index), WinCondition::Reach { block, .. }) = (start, &mut self.win) {
+            *block = index;
+        }
+    }
+
+    pub fn parse<'a, I: Iterator<Item = u8> + Sized>(&mut self, data: &'a mut I) -> &'a mut I {
+        self.template = read_frame(data);
+        let frame = self.template;
+        self.data = [Cell::Floor; TILES_WIDE * TILES_HIGH];
+        for pos in 0..frame.len() {
+            // Cells already taken by a block found further up or left.
+            if let Cell::Block(_) = self.data[pos] {
+                continue;
+            }
+            let (x, y) = pos_to_xy(pos);
+            let index = self.blocks.len();
+            let unclaimed = |data: &[Cell], pos: usize, ch: u8| {
+                frame[pos] == ch && !matches!(data[pos], Cell::Block(_))
+            };
+            match frame[pos] {
+                WALL => {
+                    self.blocks
+                        .push(Block::new(BlockType::Wall, BlockDir::Static, x, y, x, y));
+                    self.data[pos] = Cell::Wall;
+                }
+                ch @ LEFTRIGHT1 | ch @ LEFTRIGHT2 => {
+                    // Blocks stop at the edge of the board, whatever is
+                    // beyond it, so that any bytes make some level.
+                    let mut pos2 = pos;
+                    while pos2 < xy_to_pos(0, y + 1) && unclaimed(&self.data, pos2, ch) {
+                        self.data[pos2] = Cell::Block(index);
+                        pos2 += 1;
+                    }
+                    let (x2, y2) = pos_to_xy(pos2 - 1);
+                    self.blocks.push(Block::new(
+                        BlockType::Other(ch),
+                        BlockDir::LeftRight,
+                        x,
+                        y,
+                        x2,
+                        y2,
+                    ));
+                }
+                EXIT => {
+                    self.blocks
+                        .push(Block::new(BlockType::Exit, BlockDir::Static, x, y, x, y));
+                    self.data[pos] = Cell::Exit;
+                }
+                PLAYER => {
+                    // The player lies along whichever axis it continues on.
+                    let (step, dir) =
+                        if x + 1 < TILES_WIDE && unclaimed(&self.data, pos + 1, PLAYER) {
+                            (1, BlockDir::LeftRight)
+                        } else {
+                            (TILES_WIDE, BlockDir::UpDown)
+                        };
+                    let mut pos2 = pos;
+                    let end = match dir {
+                        BlockDir::LeftRight => xy_to_pos(0, y + 1),
+                        _ => frame.len(),
+                    };
+                    while pos2 < end && unclaimed(&self.data, pos2, PLAYER) {
+                        self.data[pos2] = Cell::Block(index);
+                        pos2 += step;
+                    }
+                    let (x2, y2) = pos_to_xy(pos2 - step);
+                    self.blocks
+                        .push(Block::new(BlockType::Player, dir, x, y, x2, y2));
+                }
+                ch @ UPDOWN1 | ch @ UPDOWN2 => {
+                    let mut pos2 = pos;
+                    while pos2 < frame.len() && unclaimed(&self.data, pos2, ch) {
+                        self.data[pos2] = Cell::Block(index);
+                        pos2 += TILES_WIDE;
+                    }
+                    let (x2, y2) = pos_to_xy(pos2 - 8);
+                    self.blocks.push(Block::new(
+                        BlockType::Other(ch),
+                        BlockDir::UpDown,
+                        x,
+                        y,
+                        x2,
+                        y2,
+                    ));
+                }
+                FLOOR => {}
+                // Anything else is in the way, though not a block.
+                _ => self.data[pos] = Cell::Wall,
+            };
+        }
+        self.bits = Bitboard::new(&self.data, &self.blocks, self.gravity);
+        self.assert_invariants();
+        data
+    }
+
+    /// Reads another 8x8 frame as the level's next phase. Only its walls
+    /// and exit are used; the blocks carry over from the phase before.
+    pub fn parse_phase<'a, I: Iterator<Item = u8> + Sized>(
+        &mut self,
+        data: &'a mut I,
+    ) -> &'a mut I {
+        self.phases.push(read_frame(data));
+        data
+    }
+
+    /// Turns gravity on or off: with it on, vertical blocks fall as far as
+    /// they go after every move, and the solver knows it.
+    pub fn set_gravity(&mut self, gravity: bool) {
+        self.gravity = gravity;
+        self.bits = Bitboard::new(&self.data, &self.blocks, gravity);
+    }
+
+    pub fn gravity(&self) -> bool {
+        self.gravity
+    }
+
+    /// How many phases the level has, 1 for most.
+    pub fn phases(&self) -> usize {
+        1 + self.phases.len()
+    }
+
+    /// The walls and exit of the phase being played, as read.
+    fn frame(&self) -> &[u8; TILES_WIDE * TILES_HIGH] {
+        match self.phase {
+            0 => &self.template,
+            phase => &self.phases[phase - 1],
+        }
+    }
+
+    pub fn serialize(&self) -> [u8; 64] {
+        self.serialize_at(&self.bits)
+    }
+
+    /// `serialize` for another position of this level, as the solver
+    /// reaches it.
+    pub fn serialize_at(&self, bits: &Bitboard) -> [u8; 64] {
+        let mut level = [b'*'; 64];
+        for (index, block) in self.blocks.iter().enumerate() {
+            let (x1, y1, x2, y2) = bits.rect(index);
+            for x in x1..x2 + 1 {
+                for y in y1..y2 + 1 {
+                    level[xy_to_pos(x, y)] = match block.r#type {
+                        BlockType::Other(ch) => ch,
+                        BlockType::Exit => b'^',
+                        BlockType::Player => b'=',
+                        BlockType::Wall => b'&',
+                    }
+                }
+            }
+        }
+        level
+    }
+
+    /// The current position, in the form the solver works with.
+    pub fn bitboard(&self) -> &Bitboard {
+        &self.bits
+    }
+
+    /// The level as it was read, before any moves.
+    pub fn template(&self) -> &[u8; TILES_WIDE * TILES_HIGH] {
+        &self.template
+    }
+
+    pub fn to_string_pretty(&self) -> String {
+        let bytes = self.serialize();
+        let mut string = String::new();
+        for (pos, b) in bytes.iter().enumerate() {
+            string = format!("{}{}", string, *b as char);
+            if pos % 8 == 7 {
+                string = format!("{}\n", string);
+            }
+        }
+        string
+    }
+
+    /// The grid of cells as it stands, a row a line: floor, walls and exits
+    /// as the characters they are read from, and blocks by their number in
+    /// base 36, so each takes one character.
+    pub fn cells_pretty(&self) -> String {
+        let mut string = String::new();
+        for (pos, cell) in self.data.iter().enumerate() {
+            string.push(match *cell {
+                Cell::Floor => FLOOR as char,
+                Cell::Wall => WALL as char,
+                Cell::Exit => EXIT as char,
+                Cell::Block(index) => std::char::from_digit(index as u32 % 36, 36).unwrap(),
+            });
+            if pos % TILES_WIDE == TILES_WIDE - 1 {
+                string.push('\n');
+            }
+        }
+        string
+    }
+
+    /// The puzzle flipped left to right, exit and all.
+    pub fn mirrored(&self) -> Level {
+        self.transformed(4)
+    }
+
+    /// The puzzle turned a quarter turn clockwise. Blocks that slid left and
+    /// right now slide up and down, and the other way round.
+    pub fn rotated(&self) -> Level {
+        self.transformed(1)
+    }
+
+    /// The current position mapped through one of the board's symmetries
+    /// (see `transform`), as a fresh level with no moves.
+    fn transformed(&self, symmetry: usize) -> Level {
+        let quarter = symmetry % 2 == 1;
+        let mut data = [FLOOR; TILES_WIDE * TILES_HIGH];
+        for (pos, &ch) in self.serialize().iter().enumerate() {
+            let (x, y) = pos_to_xy(pos);
+            let (tx, ty) = transform(symmetry, x, y);
+            data[xy_to_pos(tx, ty)] = match ch {
+                LEFTRIGHT1 if quarter => UPDOWN1,
+                LEFTRIGHT2 if quarter => UPDOWN2,
+                UPDOWN1 if quarter => LEFTRIGHT1,
+                UPDOWN2 if quarter => LEFTRIGHT2,
+                ch => ch,
+            };
+        }
+        Level::from(&mut data.iter().copied())
+    }
+
+    /// Identifies the puzzle regardless of how it is drawn: the same blocks
+    /// reflected or rotated, or using the other character for a block, hash
+    /// the same. Gravity and the phases still to come are part of the
+    /// puzzle. Stable across runs and platforms (FNV-1a), so it can be
+    /// stored.
+    pub fn canonical_hash(&self) -> u64 {
+        self.canonical_hash_of(&self.bits)
+    }
+
+    /// `canonical_hash` of another position of this level, as the solver
+    /// reaches it.
+    pub fn canonical_hash_of(&self, bits: &Bitboard) -> u64 {
+        let mut bytes = vec![self.gravity as u8];
+        for (kind, x1, y1, x2, y2) in self.canonical_form(bits) {
+            bytes.extend_from_slice(&[kind, x1 as u8, y1 as u8, x2 as u8, y2 as u8]);
+        }
+        fnv(&bytes)
+    }
+
+    /// Identifies this exact starting position, unlike `canonical_hash`,
+    /// for things like replays that depend on where blocks are.
+    pub fn id(&self) -> u64 {
+        let bytes: Vec<u8> = self
+            .template
+            .iter()
+            .chain(self.phases.iter().flatten())
+            .copied()
+            .collect();
+        fnv(&bytes)
+    }
+
+    /// The letter naming the block at `index` in text: A for the player,
+    /// then B, C and so on for the other movable blocks in the order they
+    /// are read off the board, left to right and top to bottom. Blocks keep
+    /// their letter as they move; a new phase letters its blocks afresh.
+    /// Walls, exits and any block past Z have none.
+    pub fn label(&self, index: usize) -> Option<char> {
+        match self.blocks.get(index)?.r#type {
+            BlockType::Player => Some('A'),
+            BlockType::Other(_) => {
+                let before = self.blocks[..index]
+                    .iter()
+                    .filter(|block| matches!(block.r#type, BlockType::Other(_)))
+                    .count();
+                char::from_u32('B' as u32 + before as u32).filter(|&letter| letter <= 'Z')
+            }
+            BlockType::Wall | BlockType::Exit => None,
+        }
+    }
+
+    /// The blocks as sorted (kind, x1, y1, x2, y2) rectangles, then the
+    /// walls and exits of each phase still to come, under whichever of the
+    /// board's eight symmetries sorts first. With gravity only the left to
+    /// right mirror keeps down where it was, so only it and the board as
+    /// drawn are tried.
+    fn canonical_form(&self, bits: &Bitboard) -> Vec<(u8, usize, usize, usize, usize)> {
+        let symmetries: &[usize] = if self.gravity {
+            &[0, 4]
+        } else {
+            &[0, 1, 2, 3, 4, 5, 6, 7]
+        };
+        symmetries
+            .iter()
+            .map(|&symmetry| {
+                let mut shapes: Vec<_> = self
+                    .blocks
+                    .iter()
+                    .enumerate()
+                    .map(|(index, block)| {
+                        let (x1, y1, x2, y2) = bits.rect(index);
+                        let (ax, ay) = transform(symmetry, x1, y1);
+                        let (bx, by) = transform(symmetry, x2, y2);
+                        let kind = match block.r#type {
+                            BlockType::Wall => 0,
+                            BlockType::Exit => 1,
+                            BlockType::Player => 2,
+                            BlockType::Other(_) => 3,
+                        };
+                        (kind, ax.min(bx), ay.min(by), ax.max(bx), ay.max(by))
+                    })
+                    .collect();
+                shapes.sort_unstable();
+                for (phase, frame) in self.phases[self.phase..].iter().enumerate() {
+                    let mut cells: Vec<_> = frame
+                        .iter()
+                        .enumerate()
+                        .filter_map(|(pos, &ch)| {
+                            let kind = match ch {
+                                EXIT => 1,
+                                FLOOR | LEFTRIGHT1 | LEFTRIGHT2 | UPDOWN1 | UPDOWN2 | PLAYER => {
+                                    return None
+                                }
+                                _ => 0,
+                            };
+                            let (x, y) = pos_to_xy(pos);
+                            let (x, y) = transform(symmetry, x, y);
+                            Some((kind, x, y, x, y))
+                        })
+                        .collect();
+                    cells.sort_unstable();
+                    // Marks where each phase starts.
+                    shapes.push((4 + phase as u8, 0, 0, 0, 0));
+                    shapes.extend(cells);
+                }
+                shapes
+            })
+            .min()
+            .unwrap()
+    }
+
+    /// Where the block at `index` ends up when slid `delta` cells along its
+    /// axis (negative is left / up), stopping early at the first obstacle.
+    pub fn slide_limit(&self, index: usize, delta: isize) -> (usize, usize) {
+        let block = &self.blocks[index];
+        if block.dir == BlockDir::Static {
+            panic!(
+                "Not a valid direction for a draggable block: {:#?}",
+                block.r#type
+            );
+        }
+        pos_to_xy(self.bits.slide_limit(index, delta))
+    }
+
+    /// Like `can_move`, but for moves read from a file, which may name a
+    /// block that is not there or cannot move at all.
+    pub fn is_legal(&self, index: usize, target: (usize, usize)) -> bool {
+        let movable = self
+            .blocks
+            .get(index)
+            .is_some_and(|block| block.dir != BlockDir::Static);
+        movable && self.can_move(index, target)
+    }
+
+    /// True if the block at `index` can slide to `target` (its new top left
+    /// cell) in a single move.
+    pub fn can_move(&self, index: usize, target: (usize, usize)) -> bool {
+        let block = &self.blocks[index];
+        let delta = match block.dir {
+            BlockDir::LeftRight if target.1 == block.y1 => target.0 as isize - block.x1 as isize,
+            BlockDir::UpDown if target.0 == block.x1 => target.1 as isize - block.y1 as isize,
+            _ => return false,
+        };
+        self.slide_limit(index, delta) == target
+    }
+
+    /// True if the block at `index` would lie on the board covering
+    /// nothing but floor, the exit or itself with its top left cell at
+    /// `target`, however far away that is.
+    pub fn fits(&self, index: usize, target: (usize, usize)) -> bool {
+        let block = &self.blocks[index];
+        let (x, y) = target;
+        let (width, height) = (block.x2 - block.x1, block.y2 - block.y1);
+        if x + width >= TILES_WIDE || y + height >= TILES_HIGH {
+            return false;
+        }
+        (y..=y + height).all(|y| {
+            (x..=x + width).all(|x| match self.data[xy_to_pos(x, y)] {
+                Cell::Floor | Cell::Exit => true,
+                Cell::Block(other) => other == index,
+                Cell::Wall => false,
+            })
+        })
+    }
+
+    /// The furthest the block at `index` can slide back (left / up) and
+    /// forward in a single move, as top left cells.
+    fn sweep(&self, index: usize) -> ((usize, usize), (usize, usize)) {
+        let max = TILES_WIDE.max(TILES_HIGH) as isize;
+        (self.slide_limit(index, -max), self.slide_limit(index, max))
+    }
+
+    /// The cells the block at `index` could move onto in a single move:
+    /// everything it would cover anywhere along its sweep, less the cells
+    /// it covers now. Empty for walls and the exit.
+    pub fn reachable_cells(&self, index: usize) -> Vec<(usize, usize)> {
+        let block = &self.blocks[index];
+        if block.dir == BlockDir::Static {
+            return Vec::new();
+        }
+        let (back, forward) = self.sweep(index);
+        let (width, height) = (block.x2 - block.x1, block.y2 - block.y1);
+        (back.1..=forward.1 + height)
+            .flat_map(|y| (back.0..=forward.0 + width).map(move |x| (x, y)))
+            .filter(|&(x, y)| !block.covers(x, y))
+            .collect()
+    }
+
+    /// The floor cells between the player and the exit when nothing else
+    /// is in the way, so the next move can win. Empty when something is,
+    /// and once solved.
+    pub fn clear_lane(&self) -> Vec<(usize, usize)> {
+        let player = self
+            .blocks
+            .iter()
+            .position(|block| block.r#type == BlockType::Player);
+        let player = match player {
+            Some(player) if !self.solved => player,
+            _ => return Vec::new(),
+        };
+        let block = &self.blocks[player];
+        let reachable = self.reachable_cells(player);
+        let exits: Vec<(usize, usize)> = reachable
+            .iter()
+            .copied()
+            .filter(|&(x, y)| self.data[xy_to_pos(x, y)] == Cell::Exit)
+            .collect();
+        let between = |(x, y): (usize, usize), (ex, ey): (usize, usize)| match block.dir {
+            BlockDir::LeftRight => (block.x2 < x && x < ex) || (ex < x && x < block.x1),
+            _ => (block.y2 < y && y < ey) || (ey < y && y < block.y1),
+        };
+        reachable
+            .into_iter()
+            .filter(|&(x, y)| self.data[xy_to_pos(x, y)] == Cell::Floor)
+            .filter(|&cell| exits.iter().any(|&exit| between(cell, exit)))
+            .collect()
+    }
+
+    /// Puts the block at `index` with its top left cell at `target`, if it
+    /// fits there, without making a move: nothing is recorded to undo or
+    /// counted, for exploring positions the rules would not reach. Returns
+    /// false if the block does not fit.
+    pub fn set_block(&mut self, index: usize, target: (usize, usize)) -> bool {
+        let block = &self.blocks[index];
+        if block.dir == BlockDir::Static || !self.fits(index, target) {
+            return false;
+        }
+        self.placed.clear();
+        self.place(index, target);
+        self.assert_invariants();
+        true
+    }
+
+    /// Slides the block at `index` as far as it goes, in whichever direction
+    /// has more room. Returns false if it cannot move at all.
+    pub fn auto_slide(&mut self, index: usize) -> bool {
+        let block = &self.blocks[index];
+        let (x, y) = (block.x1, block.y1);
+        let (back, forward) = self.sweep(index);
+        let distance =
+            |(tx, ty): (usize, usize)| (tx as isize - x as isize + ty as isize - y as isize).abs();
+        let target = if distance(forward) >= distance(back) {
+            forward
+        } else {
+            back
+        };
+        self.move_block(index, target)
+    }
+
+    /// The movable block covering grid cell `(x, y)`, if any.
+    pub fn block_at(&self, x: usize, y: usize) -> Option<usize> {
+        self.blocks
+            .iter()
+            .position(|block| block.dir != BlockDir::Static && block.covers(x, y))
+    }
+
+    /// The block covering grid cell `(x, y)`, walls included: None for
+    /// floor, an exit nothing is on and cells off the board.
+    pub fn occupant(&self, x: usize, y: usize) -> Option<BlockId> {
+        self.blocks
+            .iter()
+            .position(|block| block.r#type != BlockType::Exit && block.covers(x, y))
+    }
+
+    pub fn block(&self, id: BlockId) -> &Block {
+        &self.blocks[id]
+    }
+
+    /// Moves a block to `target` as a single recorded move, along with any
+    /// blocks that fall because of it. The caller is responsible for
+    /// checking the move is legal. Returns false, recording nothing, if the
+    /// block is already there.
+    pub fn move_block(&mut self, index: usize, target: (usize, usize)) -> bool {
+        let block = &self.blocks[index];
+        if (block.x1, block.y1) == target {
+            return false;
+        }
+        self.moves.push(Move {
+            block: index,
+            from: (block.x1, block.y1),
+            to: target,
+            index: self.moves.len(),
+            at: self.clock,
+            fell: Vec::new(),
+        });
+        self.placed.clear();
+        self.place(index, target);
+        let phase = self.phase;
+        for (index, cell) in self.bits.falls() {
+            // Falls are worked out for the phase they started in.
+            if self.phase != phase {
+                break;
+            }
+            let block = &self.blocks[index];
+            if let Some(last) = self.moves.last_mut() {
+                last.fell.push(Move {
+                    block: index,
+                    from: (block.x1, block.y1),
+                    to: pos_to_xy(cell),
+                    index: last.index,
+                    at: last.at,
+                    fell: Vec::new(),
+                });
+            }
+            self.place(index, pos_to_xy(cell));
+        }
+        self.branch();
+        self.assert_invariants();
+        true
+    }
+
+    /// Adds the move just made to the tree of variations, as a new branch
+    /// unless the same move was tried from here before.
+    fn branch(&mut self) {
+        let made = self.moves.last().expect("A move was just made").clone();
+        let tried = self.branches(self.at).into_iter().find(|&node| {
+            let old = &self.variations[node].made;
+            (old.block, old.from, old.to) == (made.block, made.from, made.to)
+        });
+        let node = match tried {
+            Some(node) => {
+                self.variations[node].made = made;
+                node
+            }
+            None => {
+                self.variations.push(Variation {
+                    made,
+                    parent: self.at,
+                    next: None,
+                });
+                self.variations.len() - 1
+            }
+        };
+        self.follow(node);
+        self.at = Some(node);
+    }
+
+    /// Makes `node` the branch redo takes from the move before it.
+    fn follow(&mut self, node: usize) {
+        match self.variations[node].parent {
+            Some(parent) => self.variations[parent].next = Some(node),
+            None => self.first = Some(node),
+        }
+    }
+
+    /// Every move tried this attempt, as a tree: see `Variation`.
+    pub fn variations(&self) -> &[Variation] {
+        &self.variations
+    }
+
+    /// The variation the position is at, None at the start.
+    pub fn variation(&self) -> Option<usize> {
+        self.at
+    }
+
+    /// The moves tried straight after `node` (None for the start), oldest
+    /// first.
+    pub fn branches(&self, node: Option<usize>) -> Vec<usize> {
+        (0..self.variations.len())
+            .filter(|&i| self.variations[i].parent == node)
+            .collect()
+    }
+
+    /// The variations from the start to `node`, in the order they are made.
+    pub fn line(&self, node: Option<usize>) -> Vec<usize> {
+        let mut line: Vec<usize> =
+            std::iter::successors(node, |&i| self.variations[i].parent).collect();
+        line.reverse();
+        line
+    }
+
+    /// Goes to the position after `node` (None for the start), undoing back
+    /// to where its line leaves the current one and making its moves from
+    /// there as of `clock`. `last_placed` then holds every block placed on
+    /// the way. Returns false, doing nothing, if that means going back past
+    /// the start of the phase.
+    pub fn goto(&mut self, node: Option<usize>) -> bool {
+        let current = self.line(self.at);
+        let wanted = self.line(node);
+        let common = current
+            .iter()
+            .zip(&wanted)
+            .take_while(|(a, b)| a == b)
+            .count();
+        if common < self.phase_start {
+            return false;
+        }
+        let mut placed = Vec::new();
+        while self.moves.len() > common && self.undo() {
+            placed.extend_from_slice(&self.placed);
+        }
+        for &node in &wanted[common..] {
+            self.follow(node);
+            self.redo();
+            placed.extend_from_slice(&self.placed);
+        }
+        self.placed = placed;
+        true
+    }
+
+    /// How many cells the moves so far took blocks in all, which some
+    /// variants score by instead of the number of moves. Blocks falling
+    /// under gravity do not count.
+    pub fn cells_moved(&self) -> usize {
+        self.moves.iter().map(Move::distance).sum()
+    }
+
+    /// Takes back the last move. Returns false if there was nothing to undo,
+    /// which is also the case at the start of a phase.
+    pub fn undo(&mut self) -> bool {
+        if self.moves.len() == self.phase_start {
+            return false;
+        }
+        match self.moves.pop() {
+            Some(undo) => {
+                self.placed.clear();
+                for fell in undo.fell.iter().rev() {
+                    self.place(fell.block, fell.from);
+                }
+                self.place(undo.block, undo.from);
+                self.at = self.at.and_then(|node| self.variations[node].parent);
+                self.assert_invariants();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Makes the last move undone again, blocks that fell and all, as of
+    /// `clock`: the branch last played from here. Returns false if there
+    /// was nothing undone, or a move was made since.
+    pub fn redo(&mut self) -> bool {
+        let next = match self.at {
+            Some(node) => self.variations[node].next,
+            None => self.first,
+        };
+        let node = match next {
+            Some(node) => node,
+            None => return false,
+        };
+        let mut redo = self.variations[node].made.clone();
+        redo.at = self.clock;
+        for fell in redo.fell.iter_mut() {
+            fell.at = self.clock;
+        }
+        let (block, to) = (redo.block, redo.to);
+        let fell: Vec<(usize, (usize, usize))> =
+            redo.fell.iter().map(|fell| (fell.block, fell.to)).collect();
+        self.moves.push(redo);
+        self.at = Some(node);
+        self.placed.clear();
+        self.place(block, to);
+        for (block, to) in fell {
+            self.place(block, to);
+        }
+        self.assert_invariants();
+        true
+    }
+
+    /// The moves made so far: where each moved block went and when, for
+    /// picking the level up again with `resume`. Blocks that fell are left
+    /// out, as they fall again.
+    pub fn history(&self) -> Replay {
+        let mut history = Replay::new();
+        for m in &self.moves {
+            history.record(m.at, m.block, m.to);
+        }
+        history
+    }
+
+    /// Starts the level over and makes the moves in `history` again at
+    /// their times, so they can be undone as before. Stops at a move that
+    /// does not fit the level, as one from a damaged save file would not.
+    pub fn resume(&mut self, history: &Replay) {
+        self.reset();
+        for (at, step) in &history.steps {
+            if !self.is_legal(step.block, (step.x, step.y)) {
+                break;
+            }
+            self.clock = *at;
+            self.move_block(step.block, (step.x, step.y));
+        }
+    }
+
+    /// Checks the level is in a state the rules can get it into: every
+    /// block on the board and in a straight line along the way it moves,
+    /// no two movable blocks on the same cell, the grid of cells agreeing
+    /// with the blocks and the solver's copy of the position with both.
+    /// Returns what is wrong if anything is.
+    ///
+    /// How many players there are is up to the pack, so that is left to
+    /// `validate`.
+    pub fn check_invariants(&self) -> Result<(), String> {
+        for (index, block) in self.blocks.iter().enumerate() {
+            if block.x1 > block.x2 || block.y1 > block.y2 {
+                return Err(format!("block {} is inside out: {:?}", index, block));
+            }
+            if block.x2 >= TILES_WIDE || block.y2 >= TILES_HIGH {
+                return Err(format!("block {} is off the board: {:?}", index, block));
+            }
+            let straight = match block.dir {
+                BlockDir::LeftRight => block.y1 == block.y2,
+                BlockDir::UpDown => block.x1 == block.x2,
+                BlockDir::Static => block.x1 == block.x2 && block.y1 == block.y2,
+            };
+            if !straight {
+                return Err(format!(
+                    "block {} is not a straight line: {:?}",
+                    index, block
+                ));
+            }
+            if self.bits.position(index) != (block.x1, block.y1) {
+                return Err(format!(
+                    "block {} is at {:?} but the solver has it at {:?}",
+                    index,
+                    (block.x1, block.y1),
+                    self.bits.position(index)
+                ));
+            }
+            for x in block.x1..=block.x2 {
+                for y in block.y1..=block.y2 {
+                    let cell = self.data[xy_to_pos(x, y)];
+                    let expected = match (block.r#type, cell) {
+                        (_, Cell::Block(other)) if block.dir != BlockDir::Static => other == index,
+                        (BlockType::Wall, Cell::Wall) => true,
+                        (BlockType::Exit, Cell::Exit | Cell::Block(_)) => true,
+                        _ => false,
+                    };
+                    if !expected {
+                        return Err(format!(
+                            "cell {:?} of block {} holds {:?}",
+                            (x, y),
+                            index,
+                            cell
+                        ));
+                    }
+                }
+            }
+        }
+        for (pos, cell) in self.data.iter().enumerate() {
+            if let Cell::Block(index) = *cell {
+                let (x, y) = pos_to_xy(pos);
+                if !self
+                    .blocks
+                    .get(index)
+                    .is_some_and(|block| block.covers(x, y))
+                {
+                    return Err(format!(
+                        "cell {:?} holds block {}, which is not on it",
+                        (x, y),
+                        index
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Panics if `check_invariants` finds something wrong, in debug builds
+    /// and release builds with the `invariants` feature.
+    fn assert_invariants(&self) {
+        if cfg!(any(debug_assertions, feature = "invariants")) {
+            if let Err(problem) = self.check_invariants() {
+                panic!("Level is broken, {}:{:?}", problem, self);
+            }
+        }
+    }
+
+    /// Update block and data to reflect a move.
+    fn place(&mut self, index: usize, target: (usize, usize)) {
+        let frame = *self.frame();
+        let block = &mut self.blocks[index];
+        let width = block.x2 - block.x1;
+        let height = block.y2 - block.y1;
+        for x in block.x1..block.x2 + 1 {
+            for y in block.y1..block.y2 + 1 {
+                // The exit stays where it is when the player leaves it.
+                self.data[xy_to_pos(x, y)] = match frame[xy_to_pos(x, y)] {
+                    EXIT => Cell::Exit,
+                    _ => Cell::Floor,
+                };
+            }
+        }
+        self.bits.play(index, xy_to_pos(target.0, target.1));
+        block.x1 = target.0;
+        block.y1 = target.1;
+        block.x2 = block.x1 + width;
+        block.y2 = block.y1 + height;
+        for x in block.x1..block.x2 + 1 {
+            for y in block.y1..block.y2 + 1 {
+                self.data[xy_to_pos(x, y)] = Cell::Block(index);
+            }
+        }
+        self.placed.push((index, target));
+        self.check_solved();
+    }
+
+    /// The blocks the last move or undo placed and where, in order and
+    /// numbered as they were then: a move that brings in a new phase
+    /// renumbers the blocks. More than one when blocks fell.
+    pub fn last_placed(&self) -> &[(usize, (usize, usize))] {
+        &self.placed
+    }
+
+    /// Getting out of a phase before the last brings in the next one, and
+    /// the win condition decides when the last is solved.
+    fn check_solved(&mut self) {
+        if self.phase + 1 < self.phases() {
+            if self.bits.is_solved() {
+                self.next_phase();
+                self.check_solved();
+            }
+        } else {
+            self.solved = self.win.is_met(self);
+        }
+    }
+
+    /// Swaps the walls and exit for those of the next phase, leaving the
+    /// movable blocks where they are. Blocks are numbered afresh, in the
+    /// order `parse` would give them, and a movable block wins any cell it
+    /// shares with a new wall. A goal naming a block follows it.
+    fn next_phase(&mut self) {
+        self.phase += 1;
+        self.phase_start = self.moves.len();
+        let goal = match self.win {
+            WinCondition::Reach { block, .. } => {
+                Some(xy_to_pos(self.blocks[block].x1, self.blocks[block].y1))
+            }
+            _ => None,
+        };
+        let frame = *self.frame();
+        let mut covered = [false; TILES_WIDE * TILES_HIGH];
+        let mut placed: Vec<(usize, Block)> = Vec::new();
+        for block in self.blocks.iter().filter(|b| b.dir != BlockDir::Static) {
+            for x in block.x1..=block.x2 {
+                for y in block.y1..=block.y2 {
+                    covered[xy_to_pos(x, y)] = true;
+                }
+            }
+            placed.push((xy_to_pos(block.x1, block.y1), block.clone()));
+        }
+        let mut data = [Cell::Floor; TILES_WIDE * TILES_HIGH];
+        for (pos, &ch) in frame.iter().enumerate() {
+            if covered[pos] {
+                continue;
+            }
+            let (x, y) = pos_to_xy(pos);
+            data[pos] = match ch {
+                WALL => {
+                    placed.push((
+                        pos,
+                        Block::new(BlockType::Wall, BlockDir::Static, x, y, x, y),
+                    ));
+                    Cell::Wall
+                }
+                EXIT => {
+                    placed.push((
+                        pos,
+                        Block::new(BlockType::Exit, BlockDir::Static, x, y, x, y),
+                    ));
+                    Cell::Exit
+                }
+                FLOOR | LEFTRIGHT1 | LEFTRIGHT2 | UPDOWN1 | UPDOWN2 | PLAYER => Cell::Floor,
+                _ => Cell::Wall,
+            };
+        }
+        placed.sort_by_key(|(pos, _)| *pos);
+        if let WinCondition::Reach { block, .. } = &mut self.win {
+            if let Some(index) = placed
+                .iter()
+                .position(|(pos, b)| Some(*pos) == goal && b.dir != BlockDir::Static)
+            {
+                *block = index;
+            }
+        }
+        for (index, (_, block)) in placed.iter().enumerate() {
+            if block.dir == BlockDir::Static {
+                continue;
+            }
+            for x in block.x1..=block.x2 {
+                for y in block.y1..=block.y2 {
+                    data[xy_to_pos(x, y)] = Cell::Block(index);
+                }
+            }
+        }
+        self.data = data;
+        self.blocks = placed.into_iter().map(|(_, block)| block).collect();
+        self.bits = Bitboard::new(&self.data, &self.blocks, self.gravity);
+    }
+}
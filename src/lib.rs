@@ -0,0 +1,32 @@
+//! The rules of Unblock Me, free of any rendering so they can be driven
+//! headlessly: parsing level packs, legal moves and a solver.
+//!
+//! Depend on the crate with `default-features = false` to leave out the
+//! game window and its dependencies. Then solving a level is:
+//!
+//! ```
+//! use unblock::pack;
+//! use unblock::solver::{self, SolverConfig};
+//!
+//! let level = &pack::parse(pack::BUILTIN)[0];
+//! let solution = solver::solve(level, SolverConfig::default()).unwrap();
+//! assert_eq!(Some(solution.len()), level.par);
+//! ```
+
+pub mod bitboard;
+pub mod blocking;
+pub mod generate;
+pub mod import;
+pub mod level;
+pub mod limits;
+#[cfg(feature = "net")]
+pub mod net;
+pub mod pack;
+pub mod prompt;
+pub mod replay;
+pub mod rng;
+#[cfg(feature = "script")]
+pub mod script;
+pub mod solver;
+pub mod validate;
+pub mod win;
@@ -0,0 +1,76 @@
+//! How much a pack from outside the game is allowed to ask for, so that a
+//! broken or malicious one is turned away with a reason instead of hanging
+//! the game or running it out of memory. Boards are always 8x8, so a level
+//! only grows by its phases.
+
+use crate::level::Level;
+use crate::pack;
+use std::fmt;
+
+/// Most bytes a pack may take up: thousands of levels with room to spare.
+pub const MAX_BYTES: usize = 1024 * 1024;
+/// Most levels a pack may have.
+pub const MAX_LEVELS: usize = 2000;
+/// Most phases a level may have.
+pub const MAX_PHASES: usize = 16;
+/// Most positions the game's solver explores in a level before giving up
+/// on it, well above what any hand made level reaches.
+pub const SOLVER_POSITIONS: usize = 1_000_000;
+
+/// What a pack asked for that it may not have.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Exceeded {
+    Bytes(usize),
+    Levels(usize),
+    /// The level at `level`, counting from 0, has too many phases.
+    Phases {
+        level: usize,
+        phases: usize,
+    },
+}
+
+impl fmt::Display for Exceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Exceeded::Bytes(bytes) => write!(
+                f,
+                "{} KB, more than the {} KB a pack may take up",
+                bytes.div_ceil(1024),
+                MAX_BYTES / 1024
+            ),
+            Exceeded::Levels(levels) => write!(
+                f,
+                "{} levels, more than the {} a pack may have",
+                levels, MAX_LEVELS
+            ),
+            Exceeded::Phases { level, phases } => write!(
+                f,
+                "level {} has {} phases, more than the {} a level may have",
+                level + 1,
+                phases,
+                MAX_PHASES
+            ),
+        }
+    }
+}
+
+/// `pack::parse` for a pack from outside the game, or why it is too big.
+pub fn parse(data: &[u8]) -> Result<Vec<Level>, Exceeded> {
+    if data.len() > MAX_BYTES {
+        return Err(Exceeded::Bytes(data.len()));
+    }
+    let entries = pack::entries(data);
+    if entries.len() > MAX_LEVELS {
+        return Err(Exceeded::Levels(entries.len()));
+    }
+    for (level, entry) in entries.iter().enumerate() {
+        let phases = entry
+            .metadata("phases")
+            .and_then(|(_, value)| value.parse().ok())
+            .unwrap_or(1);
+        if phases > MAX_PHASES {
+            return Err(Exceeded::Phases { level, phases });
+        }
+    }
+    Ok(entries.into_iter().map(|entry| entry.level).collect())
+}
@@ -2,775 +2,3799 @@
 Add undo: Build stack of moves
 */
 
+#[cfg(not(any(feature = "vulkan", feature = "wasm")))]
+compile_error!("The game needs a graphics backend: enable the vulkan or wasm feature");
+
+mod analysis;
+mod attract;
+mod audio;
+#[cfg(feature = "browse")]
+mod browser;
+#[cfg(feature = "export")]
+mod canvas;
+mod celebration;
+mod cli;
+mod clipboard;
+mod dialog;
+mod editor;
+#[cfg(feature = "export")]
+mod export;
+mod harness;
+mod i18n;
+mod input;
+#[cfg(feature = "dev")]
+mod inspector;
+mod jobs;
+#[cfg(feature = "journal")]
+mod journal;
+mod minimap;
+mod mods;
+mod order;
+mod overlay;
+#[cfg(feature = "dev")]
+mod profile;
+mod render;
+#[cfg(feature = "journal")]
+mod report;
+mod save;
+mod storage;
+mod svg;
+#[cfg(feature = "sync")]
+mod sync;
+mod text;
+mod theme;
+mod timing;
+mod toast;
+mod tutorial;
+mod variations;
+
+use analysis::Analysis;
+use audio::Music;
+use clap::{App, Arg, SubCommand};
 use coffee::{
-    graphics::{Color, Frame, Mesh, Point, Rectangle, Shape, Window, WindowSettings},
-    input::{keyboard, keyboard::KeyCode, mouse, ButtonState, Event, Input, KeyboardAndMouse},
+    graphics::{Frame, Window, WindowSettings},
+    input::{keyboard::KeyCode, mouse::Button},
     load::Task,
     Game, Result, Timer,
 };
-use itertools::put_back;
-use std::collections::HashSet;
-
-const YELLOW: Color = Color {
-    r: 1.0,
-    g: 1.0,
-    b: 0.0,
-    a: 1.0,
+use dialog::{Answer, Dialog};
+use input::UnblockInput;
+use jobs::Worker;
+use order::Order;
+use render::{
+    contains, leading, rounded, Color, Place, Point, Rectangle, Renderer, Screen, Sketch, Sprite,
 };
+use save::Save;
+use std::f32::consts::PI;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+use text::Writer;
+use theme::{Theme, TileSize};
+use timing::{Animation, Stopwatch};
+use tutorial::Tutorial;
+use unblock::blocking;
+use unblock::generate::{self, Preset};
+use unblock::level::{Block, BlockDir, BlockType, Level, TILES_HIGH, TILES_WIDE};
+use unblock::limits;
+#[cfg(feature = "net")]
+use unblock::net;
+use unblock::pack;
+use unblock::replay::Replay;
+use unblock::rng::Rng;
+#[cfg(feature = "script")]
+use unblock::script::{Effects, Script};
+use unblock::win::WinCondition;
 
-const RED: Color = Color {
-    r: 1.0,
-    g: 0.0,
-    b: 0.0,
-    a: 1.0,
-};
+// Pixels a side of a cell, unless the settings or theme say otherwise,
+// and the least a fitted one shrinks to.
+const TILE: usize = 50;
+const MIN_TILE: usize = 10;
 
-const BLUE: Color = Color {
-    r: 0.0,
-    g: 0.0,
-    b: 1.0,
-    a: 1.0,
-};
+// How far Ctrl+scroll can zoom the board, and how much each line of the
+// wheel zooms it.
+const MIN_ZOOM: f32 = 0.5;
+const MAX_ZOOM: f32 = 3.0;
+const ZOOM_STEP: f32 = 1.1;
+// Pixels an arrow key scrolls the board by, and again while held.
+const PAN_STEP: f32 = 25.0;
+// How round a movable block's corners are, as a share of a tile, and how
+// far its shadow falls. A dragged block is lifted up and left by `LIFT`.
+const CORNER: f32 = 0.2;
+const SHADOW: f32 = 3.0;
+const LIFT: f32 = 2.0;
 
-const GREEN: Color = Color {
-    r: 0.0,
-    g: 1.0,
-    b: 0.0,
-    a: 1.0,
-};
+/// Startup configuration parsed from the command line.
+#[derive(Default)]
+struct Options {
+    seed: u64,
+    zen: bool,
+    click_to_move: bool,
+    count_cells: bool,
+    ghost: bool,
+    skip_solved: bool,
+    labels: bool,
+    reduced_motion: bool,
+    // A new size for cells and margin round the board, to remember.
+    tile_size: Option<TileSize>,
+    margin: Option<usize>,
+    // A new language to speak and way to draw boards, to remember; None
+    // inside for following the language.
+    language: Option<String>,
+    mirror: Option<Option<bool>>,
+    overlay: bool,
+    levels: Vec<Level>,
+    // Index of the level to start on.
+    level: usize,
+    endless: bool,
+    kids: bool,
+    time_attack: bool,
+    race: bool,
+    // A pack to open in the editor, and what it held.
+    edit: Option<(PathBuf, Vec<u8>)>,
+    #[cfg(feature = "net")]
+    versus: Option<String>,
+    // What the pack being played is called and the file it came from, if
+    // not built in, and the installed mods with more packs and themes.
+    pack: String,
+    pack_file: Option<PathBuf>,
+    mods: Vec<mods::Mod>,
+    // Where to keep a copy of the save file.
+    remote: Option<save::Remote>,
+    // A new catalog for the pack browser and key for its signatures, to
+    // remember.
+    #[cfg(feature = "browse")]
+    catalog: Option<String>,
+    #[cfg(feature = "browse")]
+    catalog_key: Option<String>,
+    // Where to record the session, and a recording to pick one up from.
+    #[cfg(feature = "journal")]
+    journal: Option<PathBuf>,
+    #[cfg(feature = "journal")]
+    replay_journal: Option<PathBuf>,
+}
 
-const TILES_WIDE: usize = 8;
-const TILES_HIGH: usize = 8;
-const TILE_WIDTH: usize = 50;
-const TILE_HEIGHT: usize = 50;
-
-const FLOOR: u8 = b'*';
-const WALL: u8 = b'&';
-const LEFTRIGHT1: u8 = b'-';
-const LEFTRIGHT2: u8 = b'_';
-const UPDOWN1: u8 = b'|';
-const UPDOWN2: u8 = b'(';
-const PLAYER: u8 = b'=';
-const EXIT: u8 = b'^';
-
-#[derive(Debug, PartialEq)]
-enum BlockDir {
-    LeftRight,
-    UpDown,
-    Static,
-}
-
-#[derive(Debug, PartialEq)]
-enum BlockType {
-    Player,
-    Other(u8),
-    Wall,
-    Exit,
-}
-
-struct Block {
-    dir: BlockDir,
-    r#type: BlockType,
-    x1: usize,
-    y1: usize,
-    x2: usize,
-    y2: usize,
-    drag: bool,
-    target_x: usize,
-    target_y: usize,
-}
-
-impl Block {
-    fn new(r#type: BlockType, dir: BlockDir, x1: usize, y1: usize, x2: usize, y2: usize) -> Block {
-        Block {
-            r#type,
-            dir,
-            x1,
-            y1,
-            x2,
-            y2,
-            ..Default::default()
-        }
-    }
-}
-
-impl Default for Block {
-    fn default() -> Self {
-        Block {
-            r#type: BlockType::Wall,
-            dir: BlockDir::Static,
-            x1: 0,
-            y1: 0,
-            x2: 0,
-            y2: 0,
-            drag: false,
-            target_x: 0,
-            target_y: 0,
-        }
-    }
-}
-
-fn pos_to_xy(pos: usize) -> (usize, usize) {
-    let x = pos % TILES_WIDE;
-    let y = pos / TILES_WIDE;
-    (x, y)
-}
-
-fn xy_to_pos(x: usize, y: usize) -> usize {
-    x + y * 8
-}
-
-fn color(block: &Block) -> Color {
-    match block.r#type {
-        BlockType::Player => RED,
-        BlockType::Wall => Color::WHITE,
-        BlockType::Exit => YELLOW,
-        BlockType::Other(_) => match block.dir {
-            BlockDir::LeftRight => BLUE,
-            BlockDir::UpDown => GREEN,
-            _ => panic!("No Static + Other blocks exist"),
-        },
+/// The `--sync` location, reporting a bad one and exiting.
+#[cfg(feature = "sync")]
+fn remote(matches: &clap::ArgMatches<'_>) -> Option<save::Remote> {
+    let location = matches.value_of("sync")?;
+    match save::Remote::parse(location) {
+        Ok(remote) => Some(remote),
+        Err(message) => cli::fail(&message),
+    }
+}
+
+#[cfg(not(feature = "sync"))]
+fn remote(_matches: &clap::ArgMatches<'_>) -> Option<save::Remote> {
+    None
+}
+
+/// A fresh copy of `board`'s level for the second racer, who starts with the
+/// player block picked.
+fn rival(board: &Board) -> Board {
+    let mut level = board.level.clone();
+    level.reset();
+    let mut rival = Board::new(level);
+    rival.selected = rival
+        .level
+        .blocks
+        .iter()
+        .position(|block| block.r#type == BlockType::Player);
+    rival
+}
+
+/// Boards for the levels of a pack.
+fn pack_boards(levels: &[Level]) -> Vec<Board> {
+    levels.iter().cloned().map(Board::new).collect()
+}
+
+/// The script shipped beside the pack at `pack`, if there is one. One that
+/// cannot be loaded is reported and the pack is played without it.
+#[cfg(feature = "script")]
+fn pack_script(pack: Option<&Path>) -> Option<Script> {
+    let path = pack?.with_extension("rhai");
+    let loaded = match fs::read_to_string(&path) {
+        Ok(source) => Script::compile(&source),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return None,
+        Err(e) => Err(e.to_string()),
+    };
+    match loaded {
+        Ok(script) => Some(script),
+        Err(e) => {
+            toast::show(format!("{}: {}", path.display(), e));
+            None
+        }
+    }
+}
+
+// coffee constructs the game through Game::load, which takes no arguments.
+static OPTIONS: OnceLock<Options> = OnceLock::new();
+
+fn options() -> &'static Options {
+    OPTIONS.get().expect("Options not initialized")
+}
+
+// Holding R this long resets without asking.
+const RESET_HOLD: Duration = Duration::from_secs(1);
+
+const FADE: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Copy)]
+enum Fade {
+    Out(Animation),
+    In(Animation),
+}
+
+/// How long switching pack levels takes, the old board sliding out of the
+/// window as the new one slides in.
+const SLIDE: Duration = Duration::from_millis(300);
+
+/// The board being left as it slides out: to the left when going forward
+/// through the pack, the new board coming in from the right, and the other
+/// way going back.
+struct Transition {
+    leaving: Board,
+    animation: Animation,
+    forward: bool,
+}
+
+/// How the player moves blocks with the mouse.
+#[derive(Clone, Copy, PartialEq)]
+enum InputScheme {
+    /// Press on a block and drag it.
+    Drag,
+    /// Click a block, then click where it should go.
+    Click,
+}
+
+/// What the score of an attempt counts. Both are shown either way; this
+/// decides which comes first.
+#[derive(Clone, Copy, PartialEq)]
+enum Scoring {
+    Moves,
+    /// Cells travelled, as some variants count.
+    Cells,
+}
+
+// Endless mode starts this hard, in solver moves, and each cleared puzzle
+// adds a move up to the most the generator reliably reaches.
+const ENDLESS_START: usize = 4;
+const ENDLESS_MAX: usize = 15;
+
+// Kids mode does the same, with puzzles of a move or two and never many.
+const KIDS_START: usize = 1;
+const KIDS_MAX: usize = 4;
+
+// Time attack lasts this long, with easy puzzles about this many moves long.
+const TIME_ATTACK: Duration = Duration::from_secs(5 * 60);
+const TIME_ATTACK_MOVES: usize = 5;
+
+/// What is being played.
+enum Mode {
+    /// The levels of the pack.
+    Pack,
+    /// A fresh generated puzzle every time one is finished, each a little
+    /// harder than the last.
+    Endless {
+        rng: Rng,
+        cleared: usize,
+        preset: Preset,
+    },
+    /// As many easy generated puzzles as can be solved before the clock
+    /// runs out.
+    TimeAttack {
+        rng: Rng,
+        clock: Animation,
+        solved: usize,
+        moves: usize,
+        over: bool,
+    },
+    /// Two players on the same pack level side by side: the first uses the
+    /// mouse on the current board, the second the keyboard on `rival`.
+    Race {
+        rival: Box<Board>,
+        wins: [usize; 2],
+        over: bool,
+    },
+    /// Racing someone else over the network on generated puzzles.
+    #[cfg(feature = "net")]
+    Versus(Box<Versus>),
+}
+
+/// State of a networked race. Puzzles come from the server as seeds.
+#[cfg(feature = "net")]
+struct Versus {
+    // Connecting happens in the background; the client is there once it
+    // succeeds.
+    client: Option<net::Client>,
+    connecting: Worker<String, std::io::Result<net::Client>>,
+    started: bool,
+    over: bool,
+    // Moves made and whether solved, for the opponent and as last sent
+    // for this player.
+    opponent: (usize, bool),
+    sent: (usize, bool),
+}
+
+impl Mode {
+    fn time_attack(rng: Rng) -> Mode {
+        Mode::TimeAttack {
+            rng,
+            clock: Animation::start(TIME_ATTACK),
+            solved: 0,
+            moves: 0,
+            over: false,
+        }
+    }
+
+    /// What to generate next, for the modes that generate puzzles: a
+    /// generator of its own, so the work can happen on another thread, how
+    /// crowded the board should be and how many moves the puzzle should
+    /// take.
+    fn puzzle(&mut self) -> Option<(Rng, Preset, usize)> {
+        let (rng, preset, moves) = match self {
+            Mode::Pack | Mode::Race { .. } => return None,
+            #[cfg(feature = "net")]
+            Mode::Versus(_) => return None,
+            Mode::Endless {
+                rng,
+                cleared,
+                preset: Preset::Standard,
+            } => (
+                rng,
+                Preset::Standard,
+                (ENDLESS_START + *cleared).min(ENDLESS_MAX),
+            ),
+            Mode::Endless {
+                rng,
+                cleared,
+                preset: Preset::Kids,
+            } => (rng, Preset::Kids, (KIDS_START + *cleared).min(KIDS_MAX)),
+            Mode::TimeAttack { rng, .. } => (rng, Preset::Standard, TIME_ATTACK_MOVES),
+        };
+        Some((rng.fork(), preset, moves))
+    }
+
+    /// Generated puzzles finished so far.
+    fn cleared(&self) -> usize {
+        match self {
+            Mode::Pack | Mode::Race { .. } => 0,
+            #[cfg(feature = "net")]
+            Mode::Versus(_) => 0,
+            Mode::Endless { cleared, .. } => *cleared,
+            Mode::TimeAttack { solved, .. } => *solved,
+        }
     }
 }
 
+/// Destructive actions that need to be confirmed first.
+#[derive(Clone, Copy, PartialEq)]
+enum Confirm {
+    Reset,
+    Quit,
+    /// Another round of time attack; No goes back to the pack.
+    PlayAgain,
+    /// Race again on the next level; No goes back to playing alone.
+    NextRace,
+    /// Another networked race; No disconnects and goes back to the pack.
+    #[cfg(feature = "net")]
+    Rematch,
+    /// Playing the pack at this index although it failed its check; No
+    /// skips it.
+    #[cfg(feature = "browse")]
+    Unverified(usize),
+    /// Going back to the position played before the sandbox; No stays in
+    /// it.
+    LeaveSandbox,
+}
+
 struct LevelSet {
-    levels: Vec<Level>,
+    levels: Vec<Board>,
     current: usize,
+    completed: Vec<bool>,
+    order: Order,
+    rng: Rng,
+    // Zen mode: no distractions, solved levels fade into the next one.
+    zen: bool,
+    fade: Option<Fade>,
+    transition: Option<Transition>,
+    music: Music,
+    // Lent to the screen while a frame is drawn.
+    writer: Option<Writer>,
+    save: Save,
+    // The themes and packs of the enabled mods after the classic theme and
+    // the pack the game started with, and which of each is in use. A pack
+    // is its name and where to read it from, None being the built-in one.
+    themes: Vec<Theme>,
+    theme: usize,
+    packs: Vec<(String, Option<PathBuf>)>,
+    pack: usize,
+    pack_levels: Vec<Level>,
+    // The pack's custom mechanics, if it ships a script.
+    #[cfg(feature = "script")]
+    script: Option<Script>,
+    tutorial: Tutorial,
+    #[cfg(feature = "dev")]
+    inspector: inspector::Inspector,
+    #[cfg(feature = "dev")]
+    profile: profile::Profile,
+    dialog: Option<Dialog<Confirm>>,
+    editor: Option<editor::Editor>,
+    #[cfg(feature = "browse")]
+    browser: Option<browser::Browser>,
+    // Where the session is being recorded, and the position in the pack
+    // and id of the level last noted in it.
+    #[cfg(feature = "journal")]
+    journal: Option<journal::Journal>,
+    #[cfg(feature = "journal")]
+    journaled: Option<(usize, u64)>,
+    // How far along holding R is, from 0 to 1, and whether the hold already
+    // reset the level so releasing the key should do nothing.
+    reset_hold: f32,
+    reset_fired: bool,
+    scheme: InputScheme,
+    scoring: Scoring,
+    mode: Mode,
+    // Whether pack levels play back a ghost of their fastest solve.
+    ghosts: bool,
+    // Whether solving a pack level moves on to the next unsolved one, and
+    // the screen shown once none are left.
+    skip_solved: bool,
+    celebration: Option<celebration::Celebration>,
+    attract: Option<attract::Attract>,
+    // Whether each movable block shows the letter that names it in text.
+    labels: bool,
+    // Whether animations and transitions are left out.
+    reduced_motion: bool,
+    // Whether the tree of lines tried on the level is shown, and the note
+    // being written on a move under it.
+    variations: bool,
+    note: Option<variations::NoteEditor>,
+    // Whether blocks are tinted by how often a shortest solution from the
+    // current position moves them.
+    heatmap: bool,
+    // Whether arrows show which blocks are in the way of which.
+    blocking: bool,
+    overlay: bool,
+    analysis: Analysis,
+    // Generated puzzles are made off the update loop; while one is on its
+    // way the finished board waits and ignores input.
+    puzzles: Worker<(Rng, Preset, usize), Level>,
+    generating: bool,
 }
 
 impl LevelSet {
-    fn load() -> LevelSet {
-        let data = include_bytes!("../levels.dat");
-        //fs::File::open(path.join("levels.dat"))?.read_to_end(&mut data)?;
-        let mut levels = Vec::new();
-        let mut data = put_back(data.into_iter().map(|b| *b));
-        'outer: loop {
-            let mut b = match data.next() {
-                Some(byte) => byte,
-                None => break,
+    /// A game of the pack `pack_levels` picked up from `save`, drawn with
+    /// `writer` once there is a window to draw in.
+    fn load(pack_levels: Vec<Level>, save: Save, music: Music, writer: Option<Writer>) -> LevelSet {
+        let mut rng = Rng::new(options().seed);
+        let mut mode = if options().endless || options().kids {
+            Mode::Endless {
+                rng: rng.fork(),
+                cleared: 0,
+                preset: if options().kids {
+                    Preset::Kids
+                } else {
+                    Preset::Standard
+                },
+            }
+        } else if options().time_attack {
+            Mode::time_attack(rng.fork())
+        } else {
+            Mode::Pack
+        };
+        let levels = match mode.puzzle() {
+            Some((mut rng, preset, moves)) => {
+                vec![Board::new(generate::preset(&mut rng, preset, moves))]
+            }
+            None => pack_boards(&pack_levels),
+        };
+        #[cfg(feature = "net")]
+        if let Some(address) = &options().versus {
+            let connecting = Worker::start(|address: String| net::Client::connect(&address));
+            connecting.send(address.clone());
+            mode = Mode::Versus(Box::new(Versus {
+                client: None,
+                connecting,
+                started: false,
+                over: false,
+                opponent: (0, false),
+                sent: (0, false),
+            }));
+        }
+        if options().race {
+            mode = Mode::Race {
+                rival: Box::new(rival(&levels[options().level])),
+                wins: [0, 0],
+                over: false,
             };
-            // Allow comment lines before levels.
-            if b == b'#' {
-                while b != b'\n' {
-                    b = match data.next() {
-                        Some(byte) => byte,
-                        None => break 'outer,
-                    };
-                }
-                continue;
+        }
+        let settings = save.settings.clone();
+        let enabled: Vec<&mods::Mod> = options()
+            .mods
+            .iter()
+            .filter(|m| !settings.disabled_mods.contains(&m.name))
+            .collect();
+        let mut themes = vec![Theme::classic()];
+        themes.extend(enabled.iter().filter_map(|m| m.theme.clone()));
+        let mut packs = vec![(options().pack.clone(), options().pack_file.clone())];
+        packs.extend(
+            enabled
+                .iter()
+                .flat_map(|m| m.packs.iter())
+                .filter(|(name, _)| *name != options().pack)
+                .map(|(name, path)| (name.clone(), Some(path.clone()))),
+        );
+        let mut set = LevelSet {
+            completed: vec![false; levels.len()],
+            levels,
+            current: match mode {
+                Mode::Pack | Mode::Race { .. } => options().level,
+                _ => 0,
+            },
+            order: Order::Sequential,
+            rng,
+            zen: options().zen || settings.zen,
+            fade: None,
+            transition: None,
+            music,
+            writer,
+            tutorial: Tutorial::new(save.tutorial_done || !matches!(mode, Mode::Pack)),
+            #[cfg(feature = "dev")]
+            inspector: inspector::Inspector::new(),
+            #[cfg(feature = "dev")]
+            profile: profile::Profile::new(),
+            save,
+            theme: themes
+                .iter()
+                .position(|theme| theme.name == settings.theme)
+                .unwrap_or(0),
+            themes,
+            packs,
+            pack: 0,
+            pack_levels,
+            #[cfg(feature = "script")]
+            script: pack_script(options().pack_file.as_deref()),
+            dialog: None,
+            editor: options()
+                .edit
+                .clone()
+                .map(|(path, data)| editor::Editor::open(path, data)),
+            #[cfg(feature = "browse")]
+            browser: None,
+            #[cfg(feature = "journal")]
+            journal: None,
+            #[cfg(feature = "journal")]
+            journaled: None,
+            reset_hold: 0.0,
+            reset_fired: false,
+            scheme: if options().click_to_move || settings.click_to_move {
+                InputScheme::Click
+            } else {
+                InputScheme::Drag
+            },
+            scoring: if options().count_cells || settings.count_cells {
+                Scoring::Cells
+            } else {
+                Scoring::Moves
+            },
+            mode,
+            ghosts: options().ghost || settings.ghosts,
+            skip_solved: options().skip_solved || settings.skip_solved,
+            celebration: None,
+            attract: None,
+            labels: options().labels || settings.labels,
+            reduced_motion: options().reduced_motion || settings.reduced_motion,
+            heatmap: false,
+            blocking: false,
+            variations: false,
+            note: None,
+            overlay: options().overlay,
+            analysis: Analysis::start(),
+            puzzles: Worker::start(|(mut rng, preset, moves): (Rng, Preset, usize)| {
+                generate::preset(&mut rng, preset, moves)
+            }),
+            generating: false,
+        };
+        if let Some(tile) = options().tile_size {
+            set.save.settings.tile = Some(tile);
+        }
+        if let Some(margin) = options().margin {
+            set.save.settings.margin = Some(margin);
+        }
+        if let Some(language) = &options().language {
+            set.save.settings.language = language.clone();
+        }
+        if let Some(mirror) = options().mirror {
+            set.save.settings.mirror = mirror;
+        }
+        if options().tile_size.is_some()
+            || options().margin.is_some()
+            || options().language.is_some()
+            || options().mirror.is_some()
+        {
+            set.save.store();
+        }
+        // Checked when given, so a code saved by a later version that knows
+        // more languages just leaves the game in English.
+        if !set.save.settings.language.is_empty() {
+            i18n::set(&set.save.settings.language);
+        }
+        #[cfg(feature = "browse")]
+        {
+            if let Some(catalog) = &options().catalog {
+                set.save.settings.catalog = catalog.clone();
             }
-            // Skip lines with just whitespace.
-            if b == b' ' || b == b'\r' || b == b'\n' {
-                while b == b' ' || b == b'\r' || b == b'\n' {
-                    b = match data.next() {
-                        Some(byte) => byte,
-                        None => break 'outer,
-                    };
-                }
-                data.put_back(b);
-                continue;
+            if let Some(key) = &options().catalog_key {
+                set.save.settings.catalog_key = key.clone();
+            }
+            if options().catalog.is_some() || options().catalog_key.is_some() {
+                set.save.store();
             }
-            data.put_back(b);
-            let (lower, _upper) = data.size_hint();
-            if lower < 64 {
-                break;
+            // Asked for by name, so played anyway.
+            if let Some(path) = &options().pack_file {
+                if let Err(problem) = browser::check(path, &set.save.settings.catalog_key) {
+                    toast::show(problem);
+                }
             }
-            // Load level data.
-            levels.push(Level::from(&mut data));
         }
-        LevelSet { levels, current: 0 }
+        if let Mode::Pack = set.mode {
+            set.resume_unfinished();
+            #[cfg(feature = "journal")]
+            set.start_journal();
+        }
+        set.show_ghosts();
+        // So a session that went wrong can be played again with --seed.
+        #[cfg(feature = "dev")]
+        eprintln!("seed: {}", set.rng.seed());
+        set.current().timer.start();
+        if set.zen {
+            set.music.play();
+        }
+        set
     }
 
-    fn current(&mut self) -> &mut Level {
+    fn current(&mut self) -> &mut Board {
         &mut self.levels[self.current]
     }
 
     fn next(&mut self) {
-        if self.current + 1 < self.levels.len() {
-            self.current += 1;
+        if let Some(request) = self.mode.puzzle() {
+            self.puzzles.send(request);
+            self.generating = true;
+            return;
+        }
+        if self.skip_solved && !self.order.is_shuffled() && matches!(self.mode, Mode::Pack) {
+            let count = self.levels.len();
+            match (1..count)
+                .map(|step| (self.current + step) % count)
+                .find(|&index| !self.is_solved(index))
+            {
+                Some(next) => self.switch_to(next),
+                None if self.is_solved(self.current) => self.celebrate(),
+                None => toast::show(i18n::text("skip_solved.none")),
+            }
+            return;
+        }
+        if let Some(next) = self.order.next(self.current, &mut self.completed) {
+            self.switch_to(next);
         }
     }
 
-    fn previous(&mut self) {
-        if self.current > 0 {
-            self.current -= 1;
-        }
+    /// Whether the pack level at `index` has been solved, now or in an
+    /// earlier run.
+    fn is_solved(&self, index: usize) -> bool {
+        self.completed[index]
+            || self
+                .save
+                .fewest
+                .contains_key(&self.levels[index].level.id())
     }
-}
 
-struct Move {
-    block: usize,
-    x: usize,
-    y: usize,
-}
+    /// Shows the pack complete screen, with the fewest moves each level
+    /// has been solved in added up.
+    fn celebrate(&mut self) {
+        let moves: usize = self
+            .levels
+            .iter()
+            .filter_map(|board| self.save.fewest.get(&board.level.id()))
+            .sum();
+        let theme = &self.themes[self.theme];
+        self.celebration = Some(celebration::Celebration::new(
+            i18n::fill("celebration.summary", &[&self.levels.len(), &moves]),
+            if self.reduced_motion {
+                Vec::new()
+            } else {
+                vec![theme.player, theme.exit, theme.horizontal, theme.vertical]
+            },
+        ));
+    }
 
-struct Level {
-    template: [u8; TILES_WIDE * TILES_HIGH],
-    data: [u8; TILES_WIDE * TILES_HIGH],
-    blocks: Vec<Block>,
-    // UI state
-    mouse_pos: (usize, usize),
-    drag_origin: Option<(usize, usize)>,
-    drag_target: Option<usize>,
-    solved: bool,
-    width: usize,
-    height: usize,
-    moves: Vec<Move>,
-}
+    fn previous(&mut self) {
+        if let Some(previous) = self.order.previous(self.current) {
+            self.switch_to(previous);
+        }
+    }
 
-fn xy_to_sxy(width: usize, height: usize, x: usize, y: usize) -> (usize, usize) {
-    let margin_x = (width - TILE_WIDTH * TILES_WIDE) / 2;
-    let margin_y = (height - TILE_HEIGHT * TILES_HIGH) / 2;
-    (x * TILE_WIDTH + margin_x, y * TILE_HEIGHT + margin_y)
-}
+    fn switch_to(&mut self, index: usize) {
+        // Zen mode fades between levels instead.
+        if !self.zen && !self.reduced_motion && matches!(self.mode, Mode::Pack) {
+            self.transition = Some(Transition {
+                leaving: Board::new(self.current().level.clone()),
+                animation: Animation::start(SLIDE),
+                forward: index > self.current,
+            });
+        }
+        self.current().timer.pause();
+        self.current = index;
+        self.current().timer.start();
+        toast::show(i18n::fill("level", &[&(self.current + 1)]));
+    }
 
-impl Level {
-    fn new() -> Level {
-        Level {
-            template: [FLOOR; TILES_WIDE * TILES_HIGH],
-            data: [FLOOR; TILES_WIDE * TILES_HIGH],
-            blocks: Vec::new(),
-            mouse_pos: (0, 0),
-            drag_origin: None,
-            drag_target: None,
-            solved: false,
-            width: 500,
-            height: 500,
-            moves: Vec::new(),
+    /// Puts a puzzle that finished generating on the board.
+    fn install(&mut self, level: Level) {
+        if !self.generating {
+            return;
         }
+        self.generating = false;
+        self.current = 0;
+        self.levels[0] = Board::new(level);
+        self.levels[0].timer.start();
+        #[cfg(feature = "net")]
+        if let Mode::Versus(versus) = &mut self.mode {
+            versus.started = true;
+            versus.over = false;
+            versus.opponent = (0, false);
+            versus.sent = (0, false);
+            toast::show(i18n::text("versus.go"));
+            return;
+        }
+        toast::show(i18n::fill("endless.puzzle", &[&(self.mode.cleared() + 1)]));
     }
 
-    fn from<I: Iterator<Item = u8> + Sized>(data: &mut I) -> Level {
-        let mut level = Level::new();
-        level.parse(data);
-        level
+    /// Lets the tutorial react to what the player did on the first level.
+    fn observe(&mut self, action: Action) {
+        if self.current != 0 || !self.tutorial.is_active() {
+            return;
+        }
+        if self.tutorial.observe(&action, &self.levels[0].level) {
+            self.save.tutorial_done = true;
+            self.save.store();
+        }
     }
 
-    fn sxy_to_xy(&self, sx: usize, sy: usize) -> (usize, usize) {
-        let margin_x = (self.width - TILE_WIDTH * TILES_WIDE) / 2;
-        let margin_y = (self.height - TILE_HEIGHT * TILES_HIGH) / 2;
-        ((sx - margin_x) / TILE_WIDTH, (sy - margin_y) / TILE_HEIGHT)
+    /// True if leaving the current level now would throw away moves.
+    fn has_progress(&mut self) -> bool {
+        let level = &self.current().level;
+        !level.moves.is_empty() && !level.solved
     }
 
     fn reset(&mut self) {
-        self.solved = false;
-        self.blocks = Vec::new();
-        self.parse(&mut self.template.clone().into_iter().map(|b| *b));
+        self.current().reset();
+        self.store_unfinished();
+        #[cfg(feature = "journal")]
+        self.journal_action(&Action::Reset);
+        self.observe(Action::Reset);
     }
 
-    fn parse<'a, I: Iterator<Item = u8> + Sized>(&mut self, data: &'a mut I) -> &'a mut I {
-        let mut pos = 0;
-        loop {
-            let b = match data.next() {
-                Some(byte) => byte,
-                None => panic!("Not enough level data"),
-            };
-            if b != b' ' && b != b'\r' && b != b'\n' {
-                self.template[pos] = b;
-                pos += 1;
-            }
-            if pos == 64 {
-                break;
-            }
-        }
-        self.data = self.template.clone();
-        let mut id = 1;
-        assert!(pos == 64, "Corrupt data passed to parse: {}", pos);
-        assert!(self.data.len() == 64, "Too many chars: {}", self.data.len());
-        for pos in 0..self.data.len() {
-            let (x, y) = pos_to_xy(pos);
-            match self.data[pos] {
-                WALL => {
-                    self.blocks
-                        .push(Block::new(BlockType::Wall, BlockDir::Static, x, y, x, y));
+    /// Replays the journal asked for, if any, then starts recording to the
+    /// one asked for, which may be the same file.
+    #[cfg(feature = "journal")]
+    fn start_journal(&mut self) {
+        if let Some(path) = &options().replay_journal {
+            let replayed = journal::read(path).and_then(|events| {
+                let events: Vec<journal::Event> =
+                    events.into_iter().map(|(_, event)| event).collect();
+                self.replay_journal(&events)?;
+                Ok(events.len())
+            });
+            toast::show(match replayed {
+                Ok(count) => i18n::fill("journal.replayed", &[&count, &path.display()]),
+                Err(problem) => i18n::fill("journal.replay_failed", &[&path.display(), &problem]),
+            });
+        }
+        if let Some(path) = &options().journal {
+            match journal::Journal::open(path) {
+                Ok(journal) => self.journal = Some(journal),
+                Err(err) => {
+                    toast::show(i18n::fill("journal.open_failed", &[&path.display(), &err]))
                 }
-                ch @ LEFTRIGHT1 | ch @ LEFTRIGHT2 => {
-                    let mut pos2 = pos.clone();
-                    while self.data[pos2] == ch {
-                        self.data[pos2] = id;
-                        pos2 += 1;
+            }
+        }
+    }
+
+    /// Plays the events of a journal back onto the pack, leaving each level
+    /// as the session left it, and remembers the moves on them as if they
+    /// had just been made. Stops at the first event that does not fit the
+    /// pack, saying which.
+    #[cfg(feature = "journal")]
+    fn replay_journal(&mut self, events: &[journal::Event]) -> std::result::Result<(), String> {
+        use journal::Event;
+        for (number, event) in events.iter().enumerate() {
+            let fail = |problem: &str| Err(format!("event {} is {}", number + 1, problem));
+            let board = &mut self.levels[self.current];
+            board.level.clock = board.timer.elapsed();
+            match event {
+                Event::Level { index, id, moves } => {
+                    if self.levels.get(*index).map(|board| board.level.id()) != Some(*id) {
+                        return fail("for a level not in this pack");
                     }
-                    id += 1;
-                    let (x2, y2) = pos_to_xy(pos2 - 1);
-                    self.blocks.push(Block::new(
-                        BlockType::Other(ch),
-                        BlockDir::LeftRight,
-                        x,
-                        y,
-                        x2,
-                        y2,
-                    ));
-                }
-                EXIT => {
-                    self.blocks
-                        .push(Block::new(BlockType::Exit, BlockDir::Static, x, y, x, y));
+                    self.levels[*index].resume(moves);
+                    self.switch_to(*index);
                 }
-                PLAYER => {
-                    let mut pos2 = pos;
-                    while self.data[pos2] == PLAYER {
-                        self.data[pos2] = id;
-                        pos2 += 1;
+                Event::Move { block, x, y } => {
+                    if !board.level.is_legal(*block, (*x, *y)) {
+                        return fail("a move that cannot be made");
                     }
-                    id += 1;
-                    let (x2, y2) = pos_to_xy(pos2 - 1);
-                    self.blocks.push(Block::new(
-                        BlockType::Player,
-                        BlockDir::LeftRight,
-                        x,
-                        y,
-                        x2,
-                        y2,
-                    ));
+                    board.level.move_block(*block, (*x, *y));
+                    board.record();
                 }
-                ch @ UPDOWN1 | ch @ UPDOWN2 => {
-                    let mut pos2 = pos;
-                    while self.data[pos2] == ch {
-                        self.data[pos2] = id;
-                        pos2 += TILES_WIDE;
+                Event::Undo | Event::Redo => {
+                    let done = match event {
+                        Event::Undo => board.level.undo(),
+                        _ => board.level.redo(),
+                    };
+                    if !done {
+                        return fail("an undo or redo with nothing to take back or make again");
                     }
-                    id += 1;
-                    let (x2, y2) = pos_to_xy(pos2 - 8);
-                    self.blocks.push(Block::new(
-                        BlockType::Other(ch),
-                        BlockDir::UpDown,
-                        x,
-                        y,
-                        x2,
-                        y2,
-                    ));
+                    board.record();
                 }
-                FLOOR => {}
-                _ => {}
-            };
-        }
-        data
-    }
-
-    fn serialize(&self) -> [u8; 64] {
-        let mut level = [b'*'; 64];
-        for block in &self.blocks {
-            for x in block.x1..block.x2 + 1 {
-                for y in block.y1..block.y2 + 1 {
-                    level[xy_to_pos(x, y)] = match block.r#type {
-                        BlockType::Other(ch) => ch,
-                        BlockType::Exit => b'^',
-                        BlockType::Player => b'=',
-                        BlockType::Wall => b'&',
+                Event::Reset => board.reset(),
+                Event::Goto { variation } => {
+                    if !board.goto(*variation) {
+                        return fail("a line that cannot be gone to");
                     }
                 }
+                Event::Solve => {
+                    self.completed[self.current] = true;
+                    board.reset();
+                }
+            }
+            self.current().invalidate();
+        }
+        for board in &self.levels {
+            let (level, id) = (&board.level, board.level.id());
+            if level.moves.is_empty() || level.solved {
+                self.save.unfinished.remove(&id);
+            } else {
+                self.save.unfinished.insert(id, level.history());
             }
         }
-        level
+        self.save.store();
+        Ok(())
     }
 
-    fn to_string(&self) -> String {
-        let bytes = self.serialize();
-        String::from_utf8(bytes.to_vec()).expect("Unable to convert")
+    /// Adds what was just done on the current level to the journal.
+    #[cfg(feature = "journal")]
+    fn journal_action(&mut self, action: &Action) {
+        let event = match action {
+            Action::Moved(_) => match self.levels[self.current].level.moves.last() {
+                Some(made) => journal::Event::Move {
+                    block: made.block,
+                    x: made.to.0,
+                    y: made.to.1,
+                },
+                None => return,
+            },
+            Action::Undo => journal::Event::Undo,
+            Action::Redo => journal::Event::Redo,
+            Action::Reset => journal::Event::Reset,
+            Action::Solved => journal::Event::Solve,
+        };
+        self.journal(event);
     }
 
-    fn to_string_pretty(&self) -> String {
-        let bytes = self.serialize();
-        let mut string = String::new();
-        for pos in 0..64 {
-            string = format!("{}{}", string, bytes[pos] as char);
-            if pos % 8 == 7 {
-                string = format!("{}\n", string);
-            }
-        }
-        string
+    /// Writes `event` to the journal, after noting the level it happened
+    /// on if that is not the one last noted.
+    #[cfg(feature = "journal")]
+    fn journal(&mut self, event: journal::Event) {
+        self.note_level();
+        self.write_journal(&event);
     }
 
-    fn drag_to(&mut self, mx: usize, my: usize) {
-        let drag_target = match self.drag_target {
-            Some(dt) => dt,
-            None => return,
+    /// Notes a switch to another pack level in the journal, with the moves
+    /// made on it so far.
+    #[cfg(feature = "journal")]
+    fn note_level(&mut self) {
+        let level = &self.levels[self.current].level;
+        let noting = Some((self.current, level.id()));
+        if self.journal.is_none() || !matches!(self.mode, Mode::Pack) || self.journaled == noting {
+            return;
+        }
+        self.journaled = noting;
+        let event = journal::Event::Level {
+            index: self.current,
+            id: level.id(),
+            moves: level.history(),
         };
-        let (bx, by) = self.sxy_to_xy(mx, my);
-        let (ox, oy) = self.drag_origin.unwrap();
-        let (dx, dy): (isize, isize) = (bx as isize - ox as isize, by as isize - oy as isize);
-        let mut block = &mut self.blocks[drag_target];
-        block.target_x = block.x1;
-        block.target_y = block.y1;
-        let (x, y) = (block.x1, block.y1);
-        match block.dir {
-            BlockDir::LeftRight => {
-                let blocks_wide = block.x2 - block.x1;
-                // see if this is a valid move
-                let range: Vec<usize> = if dx > 0 {
-                    (block.x1..block.x1 + dx as usize + 1).collect()
-                } else {
-                    (block.x1 - dx.abs() as usize..block.x1).rev().collect()
-                };
-                for px in range {
-                    if (self.data[xy_to_pos(px, y)] == FLOOR
-                        || self.data[xy_to_pos(px, y)] == EXIT
-                        || self.data[xy_to_pos(px, y)] == self.data[xy_to_pos(x, y)])
-                        && (self.data[xy_to_pos(px + blocks_wide, y)] == FLOOR
-                            || self.data[xy_to_pos(px + blocks_wide, y)] == EXIT
-                            || self.data[xy_to_pos(px + blocks_wide, y)]
-                                == self.data[xy_to_pos(x, y)])
-                    {
-                        block.target_x = px;
-                    } else {
-                        break;
+        self.write_journal(&event);
+    }
+
+    /// Writes `event` to the journal, if one is being kept and a pack level
+    /// is being played. A journal that cannot be written to is given up on.
+    #[cfg(feature = "journal")]
+    fn write_journal(&mut self, event: &journal::Event) {
+        if !matches!(self.mode, Mode::Pack) {
+            return;
+        }
+        let written = self.journal.as_mut().map(|journal| journal.write(event));
+        if let Some(Err(err)) = written {
+            self.journal = None;
+            toast::show(i18n::fill("journal.stopped", &[&err]));
+        }
+    }
+
+    fn confirm(&mut self, confirm: Confirm) {
+        match confirm {
+            Confirm::Reset => self.reset(),
+            Confirm::Quit => {
+                self.save.finish();
+                std::process::exit(0)
+            }
+            Confirm::LeaveSandbox => {
+                self.current().leave_sandbox();
+                toast::show(i18n::text("sandbox.left"));
+            }
+            #[cfg(feature = "browse")]
+            Confirm::Unverified(index) => self.open_pack(index),
+            Confirm::PlayAgain => {
+                self.mode = Mode::time_attack(self.rng.fork());
+                self.next();
+            }
+            #[cfg(feature = "net")]
+            Confirm::Rematch => {
+                if let Mode::Versus(versus) = &mut self.mode {
+                    versus.started = false;
+                    let sent = versus
+                        .client
+                        .as_mut()
+                        .map(|client| client.send(&net::Message::Ready));
+                    if !matches!(sent, Some(Ok(()))) {
+                        toast::show(i18n::text("versus.disconnected"));
+                        self.play_pack();
                     }
                 }
             }
-            BlockDir::UpDown => {
-                let blocks_high = block.y2 - block.y1;
-                // see if this is a valid move
-                let range: Vec<usize> = if dy > 0 {
-                    (block.y1..block.y1 + dy as usize + 1).collect()
-                } else {
-                    (block.y1 - dy.abs() as usize..block.y1).rev().collect()
-                };
-                for py in range {
-                    if (self.data[xy_to_pos(x, py)] == FLOOR
-                        || self.data[xy_to_pos(x, py)] == self.data[xy_to_pos(x, y)])
-                        && (self.data[xy_to_pos(x, py + blocks_high)] == FLOOR
-                            || self.data[xy_to_pos(x, py + blocks_high)]
-                                == self.data[xy_to_pos(x, y)])
-                    {
-                        block.target_y = py;
-                    } else {
-                        break;
-                    }
+            Confirm::NextRace => {
+                self.current().reset();
+                self.next();
+                if let Mode::Race { rival: r, over, .. } = &mut self.mode {
+                    **r = rival(&self.levels[self.current]);
+                    *over = false;
                 }
             }
-            _ => panic!(
-                "Not a valid direction for a draggable block: {:#?}",
-                block.r#type
-            ),
         }
     }
 
-    fn begin_drag(&mut self, mx: usize, my: usize) {
-        let (x, y) = self.sxy_to_xy(mx, my);
-        self.drag_origin = Some((x, y));
-        let width = self.width;
-        let height = self.height;
-        for (i, block) in self
-            .blocks
-            .iter_mut()
-            .enumerate()
-            .filter(|(_i, b)| b.dir != BlockDir::Static)
-        {
-            if (block.x1 <= x) && (x <= block.x2) && (block.y1 <= y) && (y <= block.y2) {
-                block.drag = true;
-                self.drag_target = Some(i);
-                return;
+    /// Leaves a generated mode for the levels of the pack.
+    fn play_pack(&mut self) {
+        self.mode = Mode::Pack;
+        self.generating = false;
+        self.levels = pack_boards(&self.pack_levels);
+        self.completed = vec![false; self.levels.len()];
+        self.current = if self.pack == 0 { options().level } else { 0 };
+        self.resume_unfinished();
+        self.current().timer.start();
+        self.show_ghosts();
+    }
+
+    /// Picks each pack level up where it was left, in this run or an
+    /// earlier one.
+    fn resume_unfinished(&mut self) {
+        for board in &mut self.levels {
+            if let Some(history) = self.save.unfinished.get(&board.level.id()) {
+                board.resume(history);
             }
         }
+    }
 
-        // Look for less than perfect hits to attempt touch support
-        for (i, block) in self
-            .blocks
-            .iter_mut()
-            .enumerate()
-            .filter(|(_i, b)| b.dir != BlockDir::Static)
-        {
-            let (sx1, sy1) = xy_to_sxy(width, height, block.x1, block.y1);
-            let (sx2, sy2) = xy_to_sxy(width, height, block.x2 + 1, block.y2 + 1);
-            if (sx1 - 10 <= mx) && (mx <= sx2 + 10) && (sy1 - 10 <= my) && (my <= sy2 + 10) {
-                block.drag = true;
-                self.drag_target = Some(i);
+    /// Remembers the moves made on the current pack level, so that leaving
+    /// it, or the game, does not lose them.
+    fn store_unfinished(&mut self) {
+        if !matches!(self.mode, Mode::Pack) {
+            return;
+        }
+        let level = &self.levels[self.current].level;
+        let id = level.id();
+        if level.moves.is_empty() || level.solved {
+            if self.save.unfinished.remove(&id).is_none() {
                 return;
             }
+        } else {
+            self.save.unfinished.insert(id, level.history());
         }
+        self.save.store();
     }
 
-    fn end_drag(&mut self) {
-        for (i, block) in self.blocks.iter_mut().enumerate() {
-            if block.drag {
-                if self.drag_target.is_some() {
-                    self.moves.push(Move {
-                        block: i,
-                        x: block.x1,
-                        y: block.y1,
-                    })
-                }
-                // Update block and data to reflect move.
-                let id = self.data[xy_to_pos(block.x1, block.y1)];
-                let width = block.x2 - block.x1;
-                let height = block.y2 - block.y1;
-                for x in block.x1..block.x2 + 1 {
-                    for y in block.y1..block.y2 + 1 {
-                        self.data[xy_to_pos(x, y)] = FLOOR;
-                    }
-                }
-                block.x1 = block.target_x;
-                block.y1 = block.target_y;
-                block.target_x = 0;
-                block.target_y = 0;
-                block.x2 = block.x1 + width;
-                block.y2 = block.y1 + height;
-                for x in block.x1..block.x2 + 1 {
-                    for y in block.y1..block.y2 + 1 {
-                        if self.data[xy_to_pos(x, y)] == EXIT {
-                            self.solved = true;
-                        }
-                        self.data[xy_to_pos(x, y)] = id;
-                    }
-                }
-            }
-            block.drag = false;
+    /// Gives each pack level its fastest solve as a ghost, or takes them
+    /// away if ghosts are off.
+    fn show_ghosts(&mut self) {
+        let pack = matches!(self.mode, Mode::Pack);
+        for board in &mut self.levels {
+            let ghost = match self.save.best.get(&board.level.id()) {
+                Some(best) if pack && self.ghosts => Some(best.clone()),
+                _ => None,
+            };
+            board.set_ghost(ghost);
         }
-        self.drag_target = None;
-        self.drag_origin = None;
     }
 
-    fn update(&mut self, window: &Window) {
-        self.width = window.width() as usize;
-        self.height = window.height() as usize;
-        if self.drag_origin.is_some() {
-            // Convert mouse pos to block pos, subtract from original pos to get delta pos.
-            let (mx, my) = self.mouse_pos;
-            self.drag_to(mx, my);
-        }
+    fn toggle_skip_solved(&mut self) {
+        self.skip_solved = !self.skip_solved;
+        self.save.settings.skip_solved = self.skip_solved;
+        self.save.store();
+        toast::show(if self.skip_solved {
+            i18n::text("skip_solved.on")
+        } else {
+            i18n::text("skip_solved.off")
+        });
     }
 
-    fn interact(&mut self, input: &mut UnblockInput, _window: &mut Window) {
-        if input.is_mouse_pressed {
-            let (mx, my) = self.mouse_pos;
-            let (gx, gy) = self.sxy_to_xy(
-                input.cursor_position().coords.x as usize,
-                input.cursor_position().coords.y as usize,
-            );
-            println!("mouse: {} {}; grid: {} {}", mx, my, gx, gy);
-            if self.drag_target.is_none() {
-                let (mx, my) = self.mouse_pos;
-                println!("mouse down: {} {}", mx, my);
-                self.begin_drag(mx, my);
-            }
-        }
-        let mouse_pos = input.cursor_position();
-        //mouse_pos.coords.y = 500 - mouse_pos.coords.y;
-        //println!("mouse pos: {} {}", mouse_pos.0, mouse_pos.1);
-        // TODO: Stop using usize to for mouse_pos...
-        let margin_x = (500 - TILE_WIDTH * TILES_WIDE) / 2;
-        let margin_y = (500 - TILE_HEIGHT * TILES_HIGH) / 2;
-        if mouse_pos.coords.x > margin_x as f32 && mouse_pos.coords.y > margin_y as f32 {
-            self.mouse_pos = (mouse_pos.coords.x as usize, mouse_pos.coords.y as usize);
+    /// Enters the sandbox, or asks whether to leave it, as leaving gives up
+    /// the position explored. Not while racing or against the clock.
+    fn toggle_sandbox(&mut self) {
+        if self.current().sandbox.is_some() {
+            self.dialog = Some(Dialog::new(
+                i18n::text("sandbox.leave"),
+                Confirm::LeaveSandbox,
+            ));
+        } else if !matches!(self.mode, Mode::Pack | Mode::Endless { .. }) {
+            toast::show(i18n::text("sandbox.unavailable"));
+        } else {
+            self.current().enter_sandbox();
+            toast::show(i18n::text("sandbox.on"));
         }
-        if input.was_key_released(KeyCode::U) {
-            let move_to_undo = self.moves.pop();
-            if move_to_undo.is_some() {
-                let undo = move_to_undo.unwrap();
-                self.blocks[undo.block].target_x = undo.x;
-                self.blocks[undo.block].target_y = undo.y;
-                self.blocks[undo.block].drag = true;
-                self.end_drag();
+    }
+
+    /// Starts writing a note on the move just made, kept with the replay of
+    /// the attempt.
+    fn write_note(&mut self) {
+        let replay = &self.levels[self.current].replay;
+        match replay.steps.len().checked_sub(1) {
+            Some(step) => {
+                let text = replay.notes.get(&step).map_or("", String::as_str);
+                self.note = Some(variations::NoteEditor::new(step, text));
             }
+            None => toast::show(i18n::text("note.no_move")),
         }
+    }
 
-        if !input.is_mouse_pressed && self.drag_target.is_some() {
-            println!("mouse up");
-            self.end_drag();
+    /// Puts `text` on the step at `step` of the attempt's replay, and on
+    /// the fastest solve too if this attempt is it.
+    fn annotate(&mut self, step: usize, text: &str) {
+        let board = &mut self.levels[self.current];
+        board.replay.annotate(step, text);
+        let id = board.level.id();
+        if let Some(best) = self.save.best.get_mut(&id) {
+            if best.steps == board.replay.steps {
+                best.notes = board.replay.notes.clone();
+                self.save.store();
+                self.show_ghosts();
+            }
         }
     }
 
-    fn draw(&mut self, frame: &mut Frame<'_>, _timer: &Timer) {
-        let mut mesh = Mesh::new();
-        for block in { self.blocks.iter_mut().rev() } {
-            let (mut x, mut y) = (block.x1, block.y1);
-            if block.drag && block.target_x != 0 && block.target_y != 0 {
-                x = block.target_x;
-                y = block.target_y;
-            }
-            let (sx, sy) = xy_to_sxy(self.width, self.height, x, y);
-            let width = (1 + block.x2 - block.x1) * TILE_WIDTH;
-            let height = (1 + block.y2 - block.y1) * TILE_HEIGHT;
-            mesh.fill(
-                Shape::Rectangle(Rectangle {
-                    x: sx as f32,
-                    y: sy as f32,
-                    width: width as f32,
-                    height: height as f32,
-                }),
-                color(block),
-            );
-            mesh.stroke(
-                Shape::Rectangle(Rectangle {
-                    x: sx as f32,
-                    y: sy as f32,
-                    width: width as f32,
-                    height: height as f32,
-                }),
-                Color::BLACK,
-                1,
-            );
+    /// Jumps to the variation clicked in the tree, if one was.
+    fn pick_variation(&mut self, input: &UnblockInput, width: f32) {
+        let clicked = input
+            .left_clicks()
+            .iter()
+            .find_map(|&click| variations::hit(&self.current().level, width, click));
+        if let Some(node) = clicked {
+            if self.current().sandbox.is_some() {
+                toast::show(i18n::text("variations.sandbox"));
+            } else if self.current().goto(node) {
+                self.store_unfinished();
+                #[cfg(feature = "journal")]
+                self.journal(journal::Event::Goto { variation: node });
+            } else {
+                toast::show(i18n::text("variations.earlier_phase"));
+            }
         }
-        mesh.draw(&mut frame.as_target());
     }
-}
-
-// Copy of KeyboardAndMouse in order to get access to mouse_pressed
-struct UnblockInput {
-    cursor_position: Point,
-    is_cursor_taken: bool,
-    is_mouse_pressed: bool,
-    left_clicks: Vec<Point>,
-    pressed_keys: HashSet<keyboard::KeyCode>,
-    released_keys: HashSet<keyboard::KeyCode>,
-}
 
-impl UnblockInput {
-    /// Returns the current cursor position.
-    pub fn cursor_position(&self) -> Point {
-        self.cursor_position
+    /// How big boards are drawn: as the settings say, or else the theme.
+    fn sizing(&self) -> Sizing {
+        let theme = &self.themes[self.theme];
+        let settings = &self.save.settings;
+        let default = Sizing::default();
+        Sizing {
+            tile: settings.tile.or(theme.tile).unwrap_or(default.tile),
+            margin: settings.margin.or(theme.margin).unwrap_or(default.margin),
+        }
     }
 
-    /// Returns true if the cursor is currently not available.
-    ///
-    /// This mostly happens when the cursor is currently over a
-    /// [`UserInterface`].
-    ///
-    /// [`UserInterface`]: ../ui/trait.UserInterface.html
-    pub fn is_cursor_taken(&self) -> bool {
-        self.is_cursor_taken
+    /// Whether boards are drawn flipped left to right: as the settings say,
+    /// or else when the language is written right to left.
+    fn mirrored(&self) -> bool {
+        self.save
+            .settings
+            .mirror
+            .unwrap_or_else(i18n::right_to_left)
     }
 
-    /// Returns the positions of the mouse clicks during the last interaction.
-    ///
-    /// Clicks performed while the mouse cursor is not available are
-    /// automatically ignored.
-    pub fn left_clicks(&self) -> &[Point] {
-        &self.left_clicks
+    fn toggle_motion(&mut self) {
+        self.reduced_motion = !self.reduced_motion;
+        self.save.settings.reduced_motion = self.reduced_motion;
+        self.save.store();
+        toast::show(if self.reduced_motion {
+            i18n::text("motion.reduced")
+        } else {
+            i18n::text("motion.on")
+        });
     }
 
-    /// Returns true if the given key is currently pressed.
-    pub fn is_key_pressed(&self, key_code: keyboard::KeyCode) -> bool {
-        self.pressed_keys.contains(&key_code)
+    fn toggle_labels(&mut self) {
+        self.labels = !self.labels;
+        self.save.settings.labels = self.labels;
+        self.save.store();
+        toast::show(if self.labels {
+            i18n::text("labels.on")
+        } else {
+            i18n::text("labels.off")
+        });
     }
 
-    /// Returns true if the given key was released during the last interaction.
-    pub fn was_key_released(&self, key_code: keyboard::KeyCode) -> bool {
-        self.released_keys.contains(&key_code)
+    fn toggle_ghosts(&mut self) {
+        self.ghosts = !self.ghosts;
+        self.save.settings.ghosts = self.ghosts;
+        self.save.store();
+        self.show_ghosts();
+        toast::show(i18n::text(if self.ghosts {
+            "ghost.on"
+        } else {
+            "ghost.off"
+        }));
     }
-}
 
-impl Input for UnblockInput {
-    fn new() -> UnblockInput {
-        UnblockInput {
-            cursor_position: Point::new(0.0, 0.0),
-            is_cursor_taken: false,
-            is_mouse_pressed: false,
-            left_clicks: Vec::new(),
-            pressed_keys: HashSet::new(),
-            released_keys: HashSet::new(),
+    /// Counts the pack level just solved, and keeps its replay if it is the
+    /// fastest yet.
+    fn record_solve(&mut self) {
+        let board = &self.levels[self.current];
+        let id = board.level.id();
+        let moves = board.level.moves.len();
+        let fewest = self.save.fewest.entry(id).or_insert(moves);
+        *fewest = moves.min(*fewest);
+        let cells = board.level.cells_moved();
+        let fewest = self.save.fewest_cells.entry(id).or_insert(cells);
+        *fewest = cells.min(*fewest);
+        self.save.solves += 1;
+        self.save.unfinished.remove(&id);
+        let faster = match self.save.best.get(&id) {
+            Some(best) => board.replay.duration() < best.duration(),
+            None => true,
+        };
+        // Stars for the moves made, by the thresholds playtesting gave.
+        let mut news: Vec<String> = board
+            .level
+            .stars
+            .map(|(three, two)| match moves {
+                _ if moves <= three => i18n::count("stars", 3),
+                _ if moves <= two => i18n::count("stars", 2),
+                _ => i18n::count("stars", 1),
+            })
+            .into_iter()
+            .collect();
+        if faster {
+            if self.save.best.contains_key(&id) {
+                news.push(i18n::text("best_time.new"));
+            }
+            self.save.best.insert(id, board.replay.clone());
+        }
+        if !news.is_empty() {
+            toast::show(news.join(" - "));
+        }
+        self.save.store();
+        if faster {
+            self.show_ghosts();
         }
     }
 
-    fn update(&mut self, event: Event) {
-        match event {
-            Event::Mouse(mouse_event) => match mouse_event {
-                mouse::Event::CursorMoved { x, y } => {
-                    self.cursor_position = Point::new(x, y);
-                }
-                mouse::Event::CursorTaken => {
-                    self.is_cursor_taken = true;
-                }
-                mouse::Event::CursorReturned => {
-                    self.is_cursor_taken = false;
-                }
-                mouse::Event::Input {
-                    button: mouse::Button::Left,
-                    state,
-                } => match state {
-                    ButtonState::Pressed => {
-                        self.is_mouse_pressed = !self.is_cursor_taken;
-                    }
-                    ButtonState::Released => {
-                        if !self.is_cursor_taken && self.is_mouse_pressed {
-                            self.left_clicks.push(self.cursor_position);
-                        }
-
-                        self.is_mouse_pressed = false;
-                    }
-                },
-                mouse::Event::Input { .. } => {
-                    // TODO: Track other buttons!
-                }
-                mouse::Event::CursorEntered => {
-                    // TODO: Track it!
-                }
-                mouse::Event::CursorLeft => {
-                    // TODO: Track it!
+    /// Handles messages from the lobby server, reports progress to it and
+    /// ends the race once either player gets out.
+    #[cfg(feature = "net")]
+    fn check_versus(&mut self) {
+        let versus = match &mut self.mode {
+            Mode::Versus(versus) => versus,
+            _ => return,
+        };
+        if versus.client.is_none() {
+            match versus.connecting.poll() {
+                Some(Ok(client)) => versus.client = Some(client),
+                Some(Err(err)) => {
+                    let address = options().versus.as_deref().unwrap_or_default();
+                    toast::show(i18n::fill("versus.connect_failed", &[&address, &err]));
+                    self.play_pack();
+                    return;
                 }
-                mouse::Event::WheelScrolled { .. } => {
-                    // TODO: Track it!
+                None => return,
+            }
+        }
+        let client = versus.client.as_mut().unwrap();
+        while let Some(message) = client.poll() {
+            match message {
+                net::Message::Wait => toast::show(i18n::text("versus.waiting")),
+                net::Message::Start { seed, moves } => {
+                    self.puzzles.send((Rng::new(seed), Preset::Standard, moves));
+                    self.generating = true;
                 }
-            },
-            Event::Keyboard(keyboard_event) => match keyboard_event {
-                keyboard::Event::Input { key_code, state } => {
-                    match state {
-                        ButtonState::Pressed => {
-                            let _ = self.pressed_keys.insert(key_code);
-                        }
-                        ButtonState::Released => {
-                            let _ = self.pressed_keys.remove(&key_code);
-                            let _ = self.released_keys.insert(key_code);
-                        }
-                    };
+                net::Message::Opponent { moves, solved } => versus.opponent = (moves, solved),
+                net::Message::Left => {
+                    toast::show(i18n::text("versus.left"));
+                    self.dialog = None;
+                    self.play_pack();
+                    return;
                 }
-                keyboard::Event::TextEntered { .. } => {}
-            },
-            Event::Gamepad { .. } => {
-                // Ignore gamepad events...
-            }
-            Event::Window(_) => {
-                // Ignore window events...
+                _ => {}
             }
         }
+        if !versus.started || versus.over {
+            return;
+        }
+        let level = &self.levels[self.current].level;
+        let progress = (level.moves.len(), level.solved);
+        if progress != versus.sent {
+            versus.sent = progress;
+            let (moves, solved) = progress;
+            let _ = client.send(&net::Message::Progress { moves, solved });
+        }
+        let outcome = if progress.1 {
+            "versus.won"
+        } else if versus.opponent.1 {
+            "versus.lost"
+        } else {
+            return;
+        };
+        versus.over = true;
+        self.dialog = Some(Dialog::new(
+            i18n::fill("versus.again", &[&i18n::text(outcome)]),
+            Confirm::Rematch,
+        ));
     }
 
-    fn clear(&mut self) {
-        self.left_clicks.clear();
-        self.released_keys.clear();
+    /// Ends the race once either player gets out.
+    fn check_race(&mut self) {
+        let winner = match &mut self.mode {
+            Mode::Race { rival, wins, over } if !*over => {
+                let winner = if self.levels[self.current].level.solved {
+                    0
+                } else if rival.level.solved {
+                    1
+                } else {
+                    return;
+                };
+                *over = true;
+                wins[winner] += 1;
+                winner
+            }
+            _ => return,
+        };
+        self.dialog = Some(Dialog::new(
+            i18n::fill("race.won", &[&(winner + 1)]),
+            Confirm::NextRace,
+        ));
     }
-}
-
-impl Game for LevelSet {
-    type Input = UnblockInput;
-    type LoadingScreen = ();
-    const TICKS_PER_SECOND: u16 = 20;
 
-    fn load(_window: &Window) -> Task<LevelSet> {
-        Task::new(|| LevelSet::load())
+    /// Ends time attack once the clock runs out and shows the results.
+    fn check_time(&mut self) {
+        let (solved, moves) = match &mut self.mode {
+            Mode::TimeAttack {
+                clock,
+                solved,
+                moves,
+                over,
+                ..
+            } if !*over && clock.is_done() => {
+                *over = true;
+                (*solved, *moves)
+            }
+            _ => return,
+        };
+        self.current().timer.pause();
+        let best = if solved > self.save.time_attack_best {
+            self.save.time_attack_best = solved;
+            self.save.store();
+            i18n::text("time_attack.new_best")
+        } else {
+            i18n::fill("time_attack.best", &[&self.save.time_attack_best])
+        };
+        self.dialog = Some(Dialog::new(
+            i18n::fill("time_attack.over", &[&solved, &moves, &best]),
+            Confirm::PlayAgain,
+        ));
     }
 
-    fn draw(&mut self, frame: &mut Frame<'_>, timer: &Timer) {
-        frame.clear(Color::BLACK);
-        self.current().draw(frame, timer);
+    fn advance(&mut self) {
+        #[cfg(feature = "journal")]
+        self.journal_action(&Action::Solved);
+        self.observe(Action::Solved);
+        self.completed[self.current] = true;
+        let made = self.current().level.moves.len();
+        if let Mode::Pack = self.mode {
+            self.record_solve();
+        }
+        match &mut self.mode {
+            Mode::Pack | Mode::Race { .. } => {}
+            #[cfg(feature = "net")]
+            Mode::Versus(_) => {}
+            Mode::Endless { cleared, .. } => *cleared += 1,
+            Mode::TimeAttack { solved, moves, .. } => {
+                *solved += 1;
+                *moves += made;
+            }
+        }
+        // Reset once it is switched away from, so it slides out solved.
+        let solved = self.current;
+        self.next();
+        self.levels[solved].reset();
     }
 
-    fn interact(&mut self, input: &mut Self::Input, _window: &mut Window) {
-        if input.was_key_released(KeyCode::N) {
-            self.next();
-        }
-        if input.was_key_released(KeyCode::P) {
-            self.previous();
+    fn toggle_zen(&mut self) {
+        self.zen = !self.zen;
+        self.save.settings.zen = self.zen;
+        self.save.store();
+        if self.zen {
+            self.music.play();
+        } else {
+            self.music.stop();
         }
-        if input.was_key_released(KeyCode::R) {
-            self.current().reset();
+        toast::show(i18n::text(if self.zen { "zen.on" } else { "zen.off" }));
+    }
+
+    /// "Level 7/40 - 12 moves (par 15), 30 cells - 1:05". This belongs in the window title, but coffee
+    /// has no way to change the title (or the icon) once the window exists.
+    /// What is being played, and the time to show for it.
+    fn progress(&self) -> (String, String) {
+        let board = &self.levels[self.current];
+        match &self.mode {
+            Mode::Pack => (
+                i18n::fill("hud.level", &[&(self.current + 1), &self.levels.len()]),
+                timing::format(board.timer.elapsed()),
+            ),
+            Mode::Endless {
+                cleared,
+                preset: Preset::Kids,
+                ..
+            } => (
+                i18n::fill("hud.kids", &[cleared]),
+                timing::format(board.timer.elapsed()),
+            ),
+            Mode::Endless { cleared, .. } => (
+                i18n::fill("hud.endless", &[cleared]),
+                timing::format(board.timer.elapsed()),
+            ),
+            Mode::TimeAttack { solved, clock, .. } => (
+                i18n::fill("hud.time_attack", &[solved]),
+                i18n::fill("hud.time_left", &[&timing::format(clock.remaining())]),
+            ),
+            Mode::Race { wins, .. } => (
+                i18n::fill("hud.race", &[&(self.current + 1), &wins[0], &wins[1]]),
+                timing::format(board.timer.elapsed()),
+            ),
+            #[cfg(feature = "net")]
+            Mode::Versus(versus) => (
+                match (versus.started, versus.opponent) {
+                    (false, _) if versus.client.is_none() => i18n::text("hud.versus.connecting"),
+                    (false, _) => i18n::text("hud.versus.waiting"),
+                    (true, (_, true)) => i18n::text("hud.versus.out"),
+                    (true, (moves, false)) => i18n::fill("hud.versus.moves", &[&moves]),
+                },
+                timing::format(board.timer.elapsed()),
+            ),
         }
-        self.current().interact(input, _window);
     }
 
-    fn update(&mut self, _window: &Window) {
-        self.current().update(_window);
-        if self.current().solved {
-            self.current().reset();
-            self.next();
+    fn status(&self) -> String {
+        let level = &self.levels[self.current].level;
+        let moves = level.moves.len();
+        // The solver only knows how to get out.
+        let remaining = if level.win.needs_exit() {
+            self.analysis.remaining(level)
+        } else {
+            None
+        };
+        // How the moves so far plus the fewest still needed compare to par.
+        let par = match (level.par, remaining) {
+            (_, Some(None)) => format!(" ({})", i18n::text("hud.unsolvable")),
+            (Some(par), Some(Some(left))) => {
+                let diff = format!("{:+}", (moves + left) as isize - par as isize);
+                format!(" ({})", i18n::fill("hud.par_diff", &[&par, &diff]))
+            }
+            (Some(par), None) => format!(" ({})", i18n::fill("hud.par", &[&par])),
+            (None, Some(Some(left))) => format!(" ({})", i18n::fill("hud.to_go", &[&left])),
+            (None, None) => String::new(),
+        };
+        let mut phase = if level.phases() > 1 {
+            let phase = i18n::fill("hud.phase", &[&(level.phase + 1), &level.phases()]);
+            format!(" - {}", phase)
+        } else {
+            String::new()
+        };
+        if level.gravity() {
+            phase += &format!(" - {}", i18n::text("hud.gravity"));
+        }
+        if self.levels[self.current].sandbox.is_some() {
+            phase += &format!(" - {}", i18n::text("hud.sandbox"));
         }
+        let moves = format!("{}{}", i18n::count("hud.moves", moves), par);
+        let cells = i18n::count("hud.cells", level.cells_moved());
+        let score = match self.scoring {
+            Scoring::Moves => format!("{}, {}", moves, cells),
+            Scoring::Cells => format!("{}, {}", cells, moves),
+        };
+        let (level, time) = self.progress();
+        format!("{}{} - {} - {}", level, phase, score, time)
     }
-}
+
+    /// Draws a frame: the boards, then everything laid over them.
+    fn render(&mut self, renderer: &mut impl Renderer<Sprite = Sprite>) {
+        let theme = &self.themes[self.theme];
+        renderer.clear(theme.background);
+        if let Some(editor) = &mut self.editor {
+            editor.draw(renderer, theme);
+            toast::draw(renderer);
+            return;
+        }
+        if let Some(attract) = &mut self.attract {
+            attract.draw(renderer, theme);
+            toast::draw(renderer);
+            return;
+        }
+        if let Some(transition) = &mut self.transition {
+            transition.leaving.draw(renderer, theme);
+        }
+        self.levels[self.current].draw(renderer, theme);
+        if self.heatmap {
+            let board = &self.levels[self.current];
+            let heat = Some(&board.level)
+                .filter(|level| level.win.needs_exit())
+                .and_then(|level| self.analysis.heat(level));
+            if let Some(heat) = heat {
+                board.draw_heat(renderer, &heat);
+            }
+        }
+        if self.blocking {
+            self.levels[self.current].draw_blocking(renderer);
+        }
+        if self.labels {
+            self.levels[self.current].draw_labels(renderer);
+        }
+        if let Mode::Race { rival, .. } = &mut self.mode {
+            rival.draw(renderer, theme);
+            if self.labels {
+                rival.draw_labels(renderer);
+            }
+            for (side, label) in ["race.player1", "race.player2"].iter().enumerate() {
+                let half = renderer.width() / 2.0;
+                let x = side as f32 * half;
+                renderer.draw_text(
+                    &i18n::text(label),
+                    leading(x + 10.0, x + half - 10.0, 10.0),
+                    text::SMALL,
+                    Color::WHITE,
+                );
+            }
+        }
+        minimap::draw(&self.levels[self.current], theme, renderer);
+        if self.current == 0 && self.tutorial.is_active() {
+            self.tutorial.draw(&self.levels[0], renderer);
+        } else {
+            tutorial::draw_prompt(&self.levels[self.current].level, renderer);
+        }
+        if self.overlay {
+            let (title, _) = self.progress();
+            let board = &self.levels[self.current];
+            let remaining = Some(&board.level)
+                .filter(|level| level.win.needs_exit())
+                .and_then(|level| self.analysis.remaining(level));
+            overlay::draw(&title, board, remaining, renderer);
+        } else if !self.zen {
+            self.draw_hud(renderer);
+        }
+        if self.variations {
+            let board = &self.levels[self.current];
+            variations::draw(&board.level, renderer);
+            if let Some(note) = &self.note {
+                note.draw(renderer);
+            } else if let Some(note) = board.replay.note(board.replay.steps.len()) {
+                variations::draw_note(note, renderer);
+            }
+        }
+        self.draw_reset_hold(renderer);
+        #[cfg(feature = "dev")]
+        self.inspector.draw(&self.levels[self.current], renderer);
+        toast::draw(renderer);
+        if let Some(dialog) = &self.dialog {
+            dialog.draw(renderer);
+        }
+        if let Some(celebration) = &self.celebration {
+            celebration.draw(renderer);
+        }
+        #[cfg(feature = "browse")]
+        if let Some(browser) = &self.browser {
+            browser.draw(renderer);
+        }
+        self.draw_fade(renderer);
+        renderer.present();
+    }
+
+    fn draw_hud(&mut self, renderer: &mut impl Renderer) {
+        let board = &self.levels[self.current];
+        if let Some(note) = board
+            .ghost
+            .as_ref()
+            .and_then(|ghost| ghost.note(ghost.steps_at(board.timer.elapsed())))
+        {
+            renderer.draw_text(
+                note,
+                leading(10.0, renderer.width() - 10.0, renderer.height() - 70.0),
+                text::SMALL,
+                Color::WHITE,
+            );
+        }
+        let win = &self.levels[self.current].level.win;
+        if *win != WinCondition::Exit {
+            renderer.draw_text(
+                &i18n::fill("hud.goal", &[win]),
+                leading(10.0, renderer.width() - 10.0, renderer.height() - 50.0),
+                text::SMALL,
+                Color::WHITE,
+            );
+        }
+        let status = self.status();
+        renderer.draw_text(
+            &status,
+            leading(10.0, renderer.width() - 10.0, renderer.height() - 30.0),
+            text::SMALL,
+            Color::WHITE,
+        );
+    }
+
+    /// Radial indicator that fills up while R is held.
+    fn draw_reset_hold(&self, renderer: &mut impl Renderer) {
+        if self.reset_hold <= 0.0 || self.reset_fired {
+            return;
+        }
+        // At the end of the status line.
+        let x = if i18n::right_to_left() {
+            30.0
+        } else {
+            renderer.width() - 30.0
+        };
+        let center = Point::new(x, renderer.height() - 25.0);
+        let radius = 12.0;
+        let segments = 32;
+        let arc = |sweep: f32| {
+            (0..=segments)
+                .map(|i| {
+                    let angle = -PI / 2.0 + sweep * i as f32 / segments as f32;
+                    Point::new(
+                        center.x + radius * angle.cos(),
+                        center.y + radius * angle.sin(),
+                    )
+                })
+                .collect()
+        };
+        renderer.draw_line(
+            arc(2.0 * PI),
+            Color {
+                a: 0.3,
+                ..Color::WHITE
+            },
+            4,
+        );
+        renderer.draw_line(arc(2.0 * PI * self.reset_hold.min(1.0)), Color::WHITE, 4);
+    }
+
+    fn draw_fade(&self, renderer: &mut impl Renderer) {
+        let alpha = match self.fade {
+            Some(Fade::Out(fade)) => fade.progress(),
+            Some(Fade::In(fade)) => 1.0 - fade.progress(),
+            None => return,
+        };
+        renderer.draw_rect(
+            Rectangle {
+                x: 0.0,
+                y: 0.0,
+                width: renderer.width(),
+                height: renderer.height(),
+            },
+            Color {
+                a: alpha,
+                ..Color::BLACK
+            },
+            None,
+        );
+    }
+
+    fn toggle_scheme(&mut self) {
+        self.scheme = match self.scheme {
+            InputScheme::Drag => InputScheme::Click,
+            InputScheme::Click => InputScheme::Drag,
+        };
+        self.save.settings.click_to_move = self.scheme == InputScheme::Click;
+        self.save.store();
+        self.current().selected = None;
+        self.current().invalidate();
+        toast::show(i18n::text(match self.scheme {
+            InputScheme::Drag => "scheme.drag",
+            InputScheme::Click => "scheme.click",
+        }));
+    }
+
+    fn toggle_scoring(&mut self) {
+        self.scoring = match self.scoring {
+            Scoring::Moves => Scoring::Cells,
+            Scoring::Cells => Scoring::Moves,
+        };
+        self.save.settings.count_cells = self.scoring == Scoring::Cells;
+        self.save.store();
+        toast::show(i18n::text(match self.scoring {
+            Scoring::Moves => "scoring.moves",
+            Scoring::Cells => "scoring.cells",
+        }));
+    }
+
+    /// Saves a picture of the board as it stands to the current folder.
+    #[cfg(feature = "export")]
+    fn export_picture(&mut self) {
+        let path = format!("unblock-level-{}.png", self.current + 1);
+        let theme = &self.themes[self.theme];
+        let saved = export::png(&self.levels[self.current].level, theme)
+            .and_then(|png| fs::write(&path, png).map_err(|e| e.to_string()));
+        toast::show(match saved {
+            Ok(()) => i18n::fill("file.saved", &[&path]),
+            Err(e) => i18n::fill("file.save_failed", &[&path, &e]),
+        });
+    }
+
+    /// Writes a bug report on the level being played to the current folder.
+    #[cfg(feature = "journal")]
+    fn write_report(&self) {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        let path = format!("unblock-report-{}.json", now);
+        let context = report::Context {
+            pack: &self.packs[self.pack].0,
+            index: self.current,
+            settings: &self.save.settings.lines(),
+            journal: options().journal.as_deref(),
+        };
+        let written = report::write(Path::new(&path), &self.levels[self.current].level, &context);
+        toast::show(match written {
+            Ok(()) => i18n::fill("report.saved", &[&path]),
+            Err(e) => i18n::fill("file.save_failed", &[&path, &e]),
+        });
+    }
+
+    fn next_theme(&mut self) {
+        self.theme = (self.theme + 1) % self.themes.len();
+        let theme = &self.themes[self.theme];
+        self.save.settings.theme = if self.theme == 0 {
+            String::new()
+        } else {
+            theme.name.clone()
+        };
+        self.save.store();
+        toast::show(i18n::fill("theme", &[&theme.name]));
+        for board in &mut self.levels {
+            board.redraw();
+        }
+        if let Mode::Race { rival, .. } = &mut self.mode {
+            rival.redraw();
+        }
+    }
+
+    /// Plays the next pack: the one the game started with, then those of
+    /// the enabled mods.
+    fn next_pack(&mut self) {
+        if self.packs.len() < 2 {
+            toast::show(i18n::text("pack.no_others"));
+            return;
+        }
+        let next = (self.pack + 1) % self.packs.len();
+        #[cfg(feature = "browse")]
+        if let (name, Some(path)) = &self.packs[next] {
+            if let Err(problem) = browser::check(path, &self.save.settings.catalog_key) {
+                toast::show(problem);
+                self.dialog = Some(Dialog::new(
+                    i18n::fill("pack.anyway", &[name]),
+                    Confirm::Unverified(next),
+                ));
+                return;
+            }
+        }
+        self.open_pack(next);
+    }
+
+    /// Plays the pack at `index` in `packs`, or stays on the current one
+    /// if it has no levels.
+    fn open_pack(&mut self, index: usize) {
+        self.pack = index;
+        let (name, path) = &self.packs[self.pack];
+        let levels = match path {
+            Some(path) => fs::read(path)
+                .map_err(|e| i18n::fill("pack.unreadable", &[name, &e]))
+                .and_then(|data| {
+                    limits::parse(&data).map_err(|e| i18n::fill("pack.too_big", &[name, &e]))
+                }),
+            None => Ok(options().levels.clone()),
+        };
+        match levels {
+            Ok(levels) if !levels.is_empty() => {
+                toast::show(i18n::fill("pack.levels", &[name, &levels.len()]));
+                self.pack_levels = levels;
+                #[cfg(feature = "script")]
+                {
+                    self.script = pack_script(path.as_deref());
+                }
+                self.order = Order::Sequential;
+                self.fade = None;
+                self.play_pack();
+            }
+            Ok(_) => toast::show(i18n::fill("pack.empty", &[name])),
+            Err(problem) => toast::show(problem),
+        }
+    }
+
+    /// Opens the pack browser on the saved catalog.
+    #[cfg(feature = "browse")]
+    fn open_browser(&mut self) {
+        let catalog = &self.save.settings.catalog;
+        if catalog.is_empty() {
+            toast::show(i18n::text("catalog.none"));
+            return;
+        }
+        self.browser = Some(browser::Browser::open(
+            catalog,
+            &self.save.settings.catalog_key,
+        ));
+    }
+
+    /// Offers a pack the browser finished downloading after the others.
+    #[cfg(feature = "browse")]
+    fn poll_browser(&mut self) {
+        match self.browser.as_mut().and_then(|browser| browser.poll()) {
+            Some(Ok((name, path))) => {
+                if !self.packs.iter().any(|(pack, _)| *pack == name) {
+                    self.packs.push((name.clone(), Some(path)));
+                }
+                toast::show(i18n::fill("catalog.installed", &[&name]));
+            }
+            Some(Err(e)) => toast::show(e),
+            None => {}
+        }
+    }
+
+    /// Lets the pack's script react on the current level, showing what it
+    /// prints. A script that fails is reported and switched off.
+    #[cfg(feature = "script")]
+    fn run_script(
+        &mut self,
+        call: impl FnOnce(&Script, &mut Level, usize) -> std::result::Result<Effects, String>,
+    ) {
+        let script = match (&self.mode, &self.script) {
+            (Mode::Pack, Some(script)) => script,
+            _ => return,
+        };
+        let board = &mut self.levels[self.current];
+        board.level.clock = board.timer.elapsed();
+        match call(script, &mut board.level, self.current + 1) {
+            Ok(effects) => {
+                if !effects.moved.is_empty() {
+                    board.invalidate();
+                }
+                let at = board.timer.elapsed();
+                for (block, to) in effects.moved {
+                    board.replay.record(at, block, to);
+                }
+                for message in effects.printed {
+                    toast::show(message);
+                }
+            }
+            Err(e) => {
+                toast::show(i18n::fill("script.stopped", &[&e]));
+                self.script = None;
+            }
+        }
+    }
+
+    fn toggle_shuffle(&mut self) {
+        self.order = if self.order.is_shuffled() {
+            Order::Sequential
+        } else {
+            Order::shuffled(self.rng.fork())
+        };
+        toast::show(i18n::text(if self.order.is_shuffled() {
+            "shuffle.on"
+        } else {
+            "shuffle.off"
+        }));
+    }
+
+    /// Everything `update` does, timed for the profile around it.
+    fn tick(&mut self, window: &impl Bounds) {
+        if let Some(level) = self.puzzles.poll() {
+            self.install(level);
+        }
+        self.save.poll();
+        let sizing = self.sizing();
+        if let Some(editor) = &mut self.editor {
+            editor.update(window, sizing);
+            return;
+        }
+        let mirrored = self.mirrored();
+        if let Some(attract) = &mut self.attract {
+            attract.update(Viewport {
+                mirrored,
+                ..Viewport::window(window, sizing)
+            });
+            return;
+        }
+        #[cfg(feature = "browse")]
+        self.poll_browser();
+        #[cfg(feature = "journal")]
+        self.note_level();
+        let _solves = self.analysis.update(&self.levels[self.current].level);
+        #[cfg(feature = "dev")]
+        for took in _solves {
+            self.profile.solve(took);
+        }
+        if let Mode::Race { rival, .. } = &mut self.mode {
+            self.levels[self.current].update(Viewport {
+                mirrored,
+                ..Viewport::half(window, 0, sizing)
+            });
+            rival.update(Viewport {
+                mirrored,
+                ..Viewport::half(window, 1, sizing)
+            });
+            self.check_race();
+            return;
+        }
+        let mut viewport = Viewport {
+            mirrored,
+            ..Viewport::window(window, sizing)
+        };
+        if self.overlay {
+            viewport.width = viewport.width.saturating_sub(overlay::WIDTH as usize);
+        }
+        self.transition = self
+            .transition
+            .take()
+            .filter(|transition| !transition.animation.is_done());
+        if let Some(transition) = &mut self.transition {
+            let progress = transition.animation.progress();
+            let eased = 1.0 - (1.0 - progress) * (1.0 - progress);
+            let width = viewport.width as f32 * if transition.forward { 1.0 } else { -1.0 };
+            transition.leaving.update(Viewport {
+                slide: -eased * width,
+                ..viewport
+            });
+            viewport.slide = (1.0 - eased) * width;
+        }
+        self.current().update(viewport);
+        #[cfg(feature = "net")]
+        if let Mode::Versus(_) = self.mode {
+            self.check_versus();
+            return;
+        }
+        self.check_time();
+        self.fade = match self.fade {
+            Some(Fade::Out(fade)) if fade.is_done() => {
+                self.advance();
+                Some(Fade::In(Animation::start(FADE)))
+            }
+            Some(Fade::In(fade)) if fade.is_done() => None,
+            fade => fade,
+        };
+        #[cfg(feature = "script")]
+        if self.fade.is_none() && self.current().sandbox.is_none() {
+            if !self.current().level.solved {
+                let elapsed = self.current().timer.elapsed();
+                self.run_script(|script, level, number| script.on_tick(level, number, elapsed));
+            }
+            if self.current().level.solved {
+                self.run_script(|script, level, number| script.on_solve(level, number));
+            }
+        }
+        // A sandbox position is not a solve.
+        if self.current().level.solved && self.fade.is_none() && self.current().sandbox.is_none() {
+            if self.zen && !self.reduced_motion {
+                self.fade = Some(Fade::Out(Animation::start(FADE)));
+            } else {
+                self.advance();
+            }
+        }
+    }
+
+    /// Everything `interact` does, for any window size.
+    fn respond(&mut self, input: &mut UnblockInput, window: &impl Bounds) {
+        #[cfg(feature = "dev")]
+        if input.was_key_released(KeyCode::F3) {
+            self.profile.toggle();
+        }
+        if let Some(dialog) = &mut self.dialog {
+            match dialog.interact(input, window.width(), window.height()) {
+                Answer::Pending => {}
+                Answer::No => {
+                    match dialog.action() {
+                        Confirm::PlayAgain => self.play_pack(),
+                        Confirm::NextRace => self.mode = Mode::Pack,
+                        #[cfg(feature = "net")]
+                        Confirm::Rematch => self.play_pack(),
+                        #[cfg(feature = "browse")]
+                        Confirm::Unverified(index) => self.pack = index,
+                        _ => {}
+                    }
+                    self.dialog = None;
+                }
+                Answer::Yes(confirm) => {
+                    self.dialog = None;
+                    self.confirm(confirm);
+                }
+            }
+            return;
+        }
+        if let Some(editor) = &mut self.editor {
+            if !editor.interact(input, window) {
+                self.editor = None;
+            }
+            return;
+        }
+        if let Some(note) = &mut self.note {
+            match note.interact(input) {
+                Answer::Pending => {}
+                Answer::No => self.note = None,
+                Answer::Yes((step, text)) => {
+                    self.note = None;
+                    self.annotate(step, &text);
+                }
+            }
+            return;
+        }
+        if let Some(celebration) = &self.celebration {
+            if !celebration.interact(input) {
+                self.celebration = None;
+            }
+            return;
+        }
+        #[cfg(feature = "browse")]
+        if let Some(browser) = &mut self.browser {
+            if !browser.interact(input) {
+                self.browser = None;
+            }
+            return;
+        }
+        if self.generating {
+            return;
+        }
+        // Nothing but the board until the server starts the race.
+        #[cfg(feature = "net")]
+        if let Mode::Versus(versus) = &self.mode {
+            if versus.started && !versus.over {
+                let scheme = self.scheme;
+                self.levels[self.current].interact(input, scheme);
+            }
+            return;
+        }
+        // Racing players share the keyboard, so nothing else listens to it.
+        if let Mode::Race { rival, over, .. } = &mut self.mode {
+            if !*over {
+                rival.keyboard(input);
+                let scheme = self.scheme;
+                self.levels[self.current].interact(input, scheme);
+            }
+            return;
+        }
+        if let Some(attract) = &mut self.attract {
+            if !attract.interact(input) {
+                self.attract = None;
+                self.current().timer.start();
+            }
+            return;
+        }
+        // Only a pack level not yet started is left for the demo, so no
+        // clock runs and nothing played is hidden behind it.
+        if input.last_touched().elapsed() >= attract::IDLE
+            && matches!(self.mode, Mode::Pack)
+            && self.current().level.moves.is_empty()
+            && self.transition.is_none()
+        {
+            let levels = self
+                .levels
+                .iter()
+                .map(|board| {
+                    let mut level = board.level.clone();
+                    level.reset();
+                    level
+                })
+                .collect();
+            self.current().timer.pause();
+            self.attract = Some(attract::Attract::start(levels, self.current));
+            return;
+        }
+        #[cfg(feature = "dev")]
+        if self
+            .inspector
+            .interact(input, &mut self.levels[self.current])
+        {
+            self.levels[self.current].invalidate();
+        }
+        #[cfg(feature = "dev")]
+        let arrows = !self.inspector.is_open();
+        #[cfg(not(feature = "dev"))]
+        let arrows = true;
+        self.levels[self.current].look(input, arrows);
+        if input.was_key_released(KeyCode::N) {
+            self.next();
+        }
+        if input.was_key_released(KeyCode::P) {
+            self.previous();
+        }
+        self.reset_hold = match input.key_held_for(KeyCode::R) {
+            Some(held) => held.as_secs_f32() / RESET_HOLD.as_secs_f32(),
+            None => 0.0,
+        };
+        if self.reset_hold >= 1.0 && !self.reset_fired {
+            self.reset_fired = true;
+            self.reset();
+            toast::show(i18n::text("reset.done"));
+        }
+        if input.was_key_released(KeyCode::R) {
+            if self.reset_fired {
+                self.reset_fired = false;
+            } else if self.has_progress() {
+                self.dialog = Some(Dialog::new(i18n::text("reset.confirm"), Confirm::Reset));
+            } else {
+                self.reset();
+            }
+        }
+        if input.was_key_released(KeyCode::S) {
+            self.toggle_shuffle();
+        }
+        if input.was_key_released(KeyCode::Z) {
+            self.toggle_zen();
+        }
+        if input.was_key_released(KeyCode::C) {
+            self.toggle_scheme();
+        }
+        if input.was_key_released(KeyCode::G) {
+            self.toggle_ghosts();
+        }
+        if input.was_key_released(KeyCode::O) {
+            self.toggle_skip_solved();
+        }
+        if input.was_key_released(KeyCode::L) {
+            self.toggle_labels();
+        }
+        if input.was_key_released(KeyCode::A) {
+            self.toggle_motion();
+        }
+        if input.was_key_released(KeyCode::H) {
+            self.heatmap = !self.heatmap;
+            toast::show(i18n::text(if self.heatmap {
+                "heatmap.on"
+            } else {
+                "heatmap.off"
+            }));
+        }
+        if input.was_key_released(KeyCode::I) {
+            self.blocking = !self.blocking;
+            toast::show(i18n::text(if self.blocking {
+                "blocking.on"
+            } else {
+                "blocking.off"
+            }));
+        }
+        if input.was_key_released(KeyCode::F) {
+            self.toggle_sandbox();
+        }
+        if input.was_key_released(KeyCode::V) {
+            self.variations = !self.variations;
+        }
+        if self.variations {
+            self.pick_variation(input, window.width());
+            if input.was_key_released(KeyCode::Return) {
+                self.write_note();
+            }
+        }
+        if input.was_key_released(KeyCode::M) {
+            self.toggle_scoring();
+        }
+        if input.was_key_released(KeyCode::T) {
+            self.next_theme();
+        }
+        if input.was_key_released(KeyCode::K) {
+            self.next_pack();
+        }
+        #[cfg(feature = "export")]
+        if input.was_key_released(KeyCode::X) {
+            self.export_picture();
+        }
+        #[cfg(feature = "browse")]
+        if input.was_key_released(KeyCode::B) {
+            self.open_browser();
+        }
+        #[cfg(feature = "journal")]
+        if input.was_key_released(KeyCode::F9) {
+            self.write_report();
+        }
+        if matches!(self.fade, Some(Fade::Out(_))) || self.transition.is_some() {
+            return;
+        }
+        let scheme = self.scheme;
+        if let Some(action) = self.current().interact(input, scheme) {
+            self.store_unfinished();
+            #[cfg(feature = "journal")]
+            self.journal_action(&action);
+            #[cfg(feature = "script")]
+            if let Action::Moved(block) = action {
+                self.run_script(|script, level, number| script.on_move(level, number, block));
+            }
+            self.observe(action);
+        }
+    }
+}
+
+/// Something the player did, reported to interested subsystems.
+enum Action {
+    Moved(usize),
+    Undo,
+    Redo,
+    Reset,
+    Solved,
+}
+
+/// A block being dragged, from the cell the press started on, and where the
+/// block would land if it was let go now.
+struct Drag {
+    block: usize,
+    origin: (usize, usize),
+    target: (usize, usize),
+}
+
+/// A level as it is played on screen: the rules plus mouse and drawing state.
+struct Board {
+    level: Level,
+    mouse_pos: (f32, f32),
+    // Whether the left button was down last interaction, and whether that
+    // press started off the board and should not drag anything.
+    mouse_down: bool,
+    press_off_board: bool,
+    drag: Option<Drag>,
+    // Block picked with the first click in the click to move scheme.
+    selected: Option<usize>,
+    viewport: Viewport,
+    timer: Stopwatch,
+    // Cached geometry; walls and the exit never move, so they are kept
+    // apart from the blocks, which are rebuilt whenever one changes.
+    static_sprite: Option<Sprite>,
+    block_sprite: Option<Sprite>,
+    // The phase of the level the sprites show; a new phase moves the walls.
+    phase: usize,
+    // Every move of this attempt, and an earlier attempt to play back
+    // translucently over it, with the sprite of the ghost's blocks after the
+    // given number of its steps.
+    replay: Replay,
+    ghost: Option<Replay>,
+    ghost_sprite: Option<(usize, Sprite)>,
+    // The position being played, kept while blocks are placed freely in the
+    // sandbox, to go back to when it is left.
+    sandbox: Option<Level>,
+    // How the board is looked at, kept here as the viewport is made afresh
+    // every update, and where the cursor was while the middle button pans.
+    camera: Camera,
+    panning: Option<Point>,
+}
+
+/// The size of what the game is drawn in: the window, or a stand-in for
+/// one when input is played to the game without a window.
+trait Bounds {
+    fn width(&self) -> f32;
+    fn height(&self) -> f32;
+}
+
+impl Bounds for Window {
+    fn width(&self) -> f32 {
+        Window::width(self)
+    }
+
+    fn height(&self) -> f32 {
+        Window::height(self)
+    }
+}
+
+/// The part of the window a board is drawn in.
+#[derive(Clone, Copy, PartialEq)]
+struct Viewport {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+    camera: Camera,
+    /// Pixels the board is pushed right by, or left if negative, as it
+    /// slides in or out between levels.
+    slide: f32,
+    sizing: Sizing,
+    /// The board is drawn flipped left to right, and input flipped back, so
+    /// the level and everything kept about it stay as they are.
+    mirrored: bool,
+}
+
+/// How big cells are drawn before zooming, and the pixels kept clear round
+/// a board fitted to its viewport.
+#[derive(Clone, Copy, PartialEq)]
+struct Sizing {
+    tile: TileSize,
+    margin: usize,
+}
+
+impl Default for Sizing {
+    fn default() -> Sizing {
+        Sizing {
+            tile: TileSize::Pixels(TILE),
+            margin: 0,
+        }
+    }
+}
+
+/// How a board is zoomed and scrolled within its viewport.
+#[derive(Clone, Copy, PartialEq)]
+struct Camera {
+    /// Size of a cell, as a multiple of the usual tile.
+    zoom: f32,
+    /// Pixels the board is scrolled by from its top left corner, along
+    /// whichever axes it is bigger than the viewport on.
+    pan: (f32, f32),
+}
+
+impl Default for Camera {
+    fn default() -> Camera {
+        Camera {
+            zoom: 1.0,
+            pan: (0.0, 0.0),
+        }
+    }
+}
+
+impl Viewport {
+    fn window(window: &impl Bounds, sizing: Sizing) -> Viewport {
+        Viewport {
+            x: 0,
+            y: 0,
+            width: window.width() as usize,
+            height: window.height() as usize,
+            camera: Camera::default(),
+            slide: 0.0,
+            sizing,
+            mirrored: false,
+        }
+    }
+
+    /// The left or right half of the window, for `side` 0 or 1.
+    fn half(window: &impl Bounds, side: usize, sizing: Sizing) -> Viewport {
+        let width = window.width() as usize / 2;
+        Viewport {
+            x: side * width,
+            width,
+            ..Viewport::window(window, sizing)
+        }
+    }
+
+    /// Size of a cell on screen, at the camera's zoom.
+    fn tile(&self) -> (f32, f32) {
+        let tile = match self.sizing.tile {
+            TileSize::Pixels(pixels) => pixels,
+            TileSize::Fit => {
+                let room = |pixels: usize| pixels.saturating_sub(2 * self.sizing.margin);
+                (room(self.width) / TILES_WIDE)
+                    .min(room(self.height) / TILES_HIGH)
+                    .max(MIN_TILE)
+            }
+        };
+        let tile = tile as f32 * self.camera.zoom;
+        (tile, tile)
+    }
+
+    /// How far the board can be scrolled along each axis: as much as it is
+    /// bigger than the viewport.
+    fn overflow(&self) -> (f32, f32) {
+        let (tile_width, tile_height) = self.tile();
+        (
+            (tile_width * TILES_WIDE as f32 - self.width as f32).max(0.0),
+            (tile_height * TILES_HIGH as f32 - self.height as f32).max(0.0),
+        )
+    }
+
+    /// `pan` kept to where some of the board is still in view.
+    fn clamp(&self, pan: (f32, f32)) -> (f32, f32) {
+        let (most_x, most_y) = self.overflow();
+        (pan.0.clamp(0.0, most_x), pan.1.clamp(0.0, most_y))
+    }
+
+    /// Screen position of the board's top left corner. The board is
+    /// centered along axes it fits on, and scrolled by the camera along the
+    /// others, from the top left corner of the viewport.
+    fn origin(&self) -> (f32, f32) {
+        let (tile_width, tile_height) = self.tile();
+        let (pan_x, pan_y) = self.clamp(self.camera.pan);
+        let room_x = self.width as f32 - tile_width * TILES_WIDE as f32;
+        let room_y = self.height as f32 - tile_height * TILES_HIGH as f32;
+        (
+            self.x as f32 + (room_x.max(0.0) / 2.0).floor() - pan_x + self.slide,
+            self.y as f32 + (room_y.max(0.0) / 2.0).floor() - pan_y,
+        )
+    }
+
+    /// Screen area of the `width` by `height` cells from `(x, y)`.
+    fn cells(&self, (x, y): (usize, usize), (width, height): (usize, usize)) -> Rectangle<f32> {
+        // Mirrored, the cells' right edge is the one drawn on the left.
+        let left = if self.mirrored { x + width } else { x };
+        let (sx, sy) = xy_to_sxy(*self, left, y);
+        let (tile_width, tile_height) = self.tile();
+        Rectangle {
+            x: sx,
+            y: sy,
+            width: width as f32 * tile_width,
+            height: height as f32 * tile_height,
+        }
+    }
+
+    /// Screen area of `block` with its top left corner at `(x, y)`.
+    fn block(&self, block: &Block, at: (usize, usize)) -> Rectangle<f32> {
+        self.cells(at, (1 + block.x2 - block.x1, 1 + block.y2 - block.y1))
+    }
+}
+
+/// Screen position of the corner between cells at the top left of `(x, y)`,
+/// or its top right on a mirrored board.
+fn xy_to_sxy(viewport: Viewport, x: usize, y: usize) -> (f32, f32) {
+    let (origin_x, origin_y) = viewport.origin();
+    let (tile_width, tile_height) = viewport.tile();
+    let x = if viewport.mirrored { TILES_WIDE - x } else { x };
+    (
+        x as f32 * tile_width + origin_x,
+        y as f32 * tile_height + origin_y,
+    )
+}
+
+impl Board {
+    fn new(level: Level) -> Board {
+        Board {
+            level,
+            mouse_pos: (0.0, 0.0),
+            mouse_down: false,
+            press_off_board: false,
+            drag: None,
+            selected: None,
+            viewport: Viewport {
+                x: 0,
+                y: 0,
+                width: 500,
+                height: 500,
+                camera: Camera::default(),
+                slide: 0.0,
+                sizing: Sizing::default(),
+                mirrored: false,
+            },
+            timer: Stopwatch::new(),
+            static_sprite: None,
+            block_sprite: None,
+            phase: 0,
+            replay: Replay::new(),
+            ghost: None,
+            ghost_sprite: None,
+            sandbox: None,
+            camera: Camera::default(),
+            panning: None,
+        }
+    }
+
+    /// Signed grid cell under screen position `(sx, sy)`, which is outside
+    /// the board when the position is.
+    fn sxy_to_signed_xy(&self, sx: f32, sy: f32) -> (isize, isize) {
+        let (origin_x, origin_y) = self.viewport.origin();
+        let (tile_width, tile_height) = self.viewport.tile();
+        let x = ((sx - origin_x) / tile_width).floor() as isize;
+        (
+            if self.viewport.mirrored {
+                TILES_WIDE as isize - 1 - x
+            } else {
+                x
+            },
+            ((sy - origin_y) / tile_height).floor() as isize,
+        )
+    }
+
+    /// Grid cell under screen position `(sx, sy)`, if it is on the board
+    /// and not under the minimap.
+    fn sxy_to_xy(&self, sx: f32, sy: f32) -> Option<(usize, usize)> {
+        if minimap::area(&self.viewport).is_some_and(|area| contains(&area, Point::new(sx, sy))) {
+            return None;
+        }
+        let (x, y) = self.sxy_to_signed_xy(sx, sy);
+        if 0 <= x && x < TILES_WIDE as isize && 0 <= y && y < TILES_HIGH as isize {
+            Some((x as usize, y as usize))
+        } else {
+            None
+        }
+    }
+
+    /// Grid cell closest to screen position `(sx, sy)`, so that dragging
+    /// past the edge of the board still slides blocks all the way.
+    fn sxy_to_xy_clamped(&self, sx: f32, sy: f32) -> (usize, usize) {
+        let (x, y) = self.sxy_to_signed_xy(sx, sy);
+        (
+            x.clamp(0, TILES_WIDE as isize - 1) as usize,
+            y.clamp(0, TILES_HIGH as isize - 1) as usize,
+        )
+    }
+
+    /// Forgets the cached sprites, so the board is drawn from scratch.
+    fn redraw(&mut self) {
+        self.static_sprite = None;
+        self.block_sprite = None;
+        self.ghost_sprite = None;
+    }
+
+    fn reset(&mut self) {
+        self.redraw();
+        self.replay = Replay::new();
+        self.timer.reset();
+        self.selected = None;
+        self.drag = None;
+        self.sandbox = None;
+        self.level.reset();
+    }
+
+    /// Goes to the position after `node` of the variations tried, as
+    /// `Level::goto`. Returns false if it could not.
+    fn goto(&mut self, node: Option<usize>) -> bool {
+        self.level.clock = self.timer.elapsed();
+        if self.sandbox.is_some() || !self.level.goto(node) {
+            return false;
+        }
+        self.record();
+        self.invalidate();
+        true
+    }
+
+    /// Starts placing blocks freely, keeping the position being played.
+    fn enter_sandbox(&mut self) {
+        self.sandbox = Some(self.level.clone());
+        self.selected = None;
+        self.drag = None;
+        self.invalidate();
+    }
+
+    /// Puts back the position that was being played before the sandbox.
+    fn leave_sandbox(&mut self) {
+        if let Some(level) = self.sandbox.take() {
+            self.level = level;
+            self.selected = None;
+            self.drag = None;
+            self.redraw();
+        }
+    }
+
+    fn drag_to(&mut self, mx: f32, my: f32) {
+        let (bx, by) = self.sxy_to_xy_clamped(mx, my);
+        let drag = match &self.drag {
+            Some(drag) => drag,
+            None => return,
+        };
+        let (ox, oy) = drag.origin;
+        let (dx, dy): (isize, isize) = (bx as isize - ox as isize, by as isize - oy as isize);
+        if self.sandbox.is_some() {
+            // Anywhere the block fits, in any direction.
+            let block = &self.level.blocks[drag.block];
+            let (x, y) = (block.x1 as isize + dx, block.y1 as isize + dy);
+            let target = (x.max(0) as usize, y.max(0) as usize);
+            if drag.target != target && self.level.fits(drag.block, target) {
+                self.drag.as_mut().unwrap().target = target;
+                self.invalidate();
+            }
+            return;
+        }
+        let delta = match self.level.blocks[drag.block].dir {
+            BlockDir::LeftRight => dx,
+            _ => dy,
+        };
+        let target = self.level.slide_limit(drag.block, delta);
+        if drag.target != target {
+            self.drag.as_mut().unwrap().target = target;
+            self.invalidate();
+        }
+    }
+
+    fn begin_drag(&mut self, mx: f32, my: f32) {
+        let (x, y) = match self.sxy_to_xy(mx, my) {
+            Some(xy) => xy,
+            None => return,
+        };
+        let viewport = self.viewport;
+        // Look for less than perfect hits to attempt touch support
+        let block = self.level.block_at(x, y).or_else(|| {
+            self.level.blocks.iter().position(|block| {
+                let area = viewport.block(block, (block.x1, block.y1));
+                block.dir != BlockDir::Static
+                    && (area.x - 10.0 <= mx)
+                    && (mx <= area.x + area.width + 10.0)
+                    && (area.y - 10.0 <= my)
+                    && (my <= area.y + area.height + 10.0)
+            })
+        });
+        if let Some(i) = block {
+            let block = &self.level.blocks[i];
+            self.drag = Some(Drag {
+                block: i,
+                origin: (x, y),
+                target: (block.x1, block.y1),
+            });
+            self.invalidate();
+        }
+    }
+
+    /// Drops the dragged block where it was dragged to. Returns the block if
+    /// that moved it.
+    fn end_drag(&mut self) -> Option<usize> {
+        let drag = self.drag.take()?;
+        self.invalidate();
+        if self.level.move_block(drag.block, drag.target) {
+            Some(drag.block)
+        } else {
+            None
+        }
+    }
+
+    fn update(&mut self, viewport: Viewport) {
+        let viewport = Viewport {
+            camera: self.camera,
+            ..viewport
+        };
+        if viewport != self.viewport {
+            self.viewport = viewport;
+            self.redraw();
+        }
+        if self.level.phase != self.phase {
+            // Blocks are numbered afresh in a new phase.
+            self.phase = self.level.phase;
+            self.selected = None;
+            self.drag = None;
+            self.redraw();
+        }
+        if self.drag.is_some() {
+            // Convert mouse pos to block pos, subtract from original pos to get delta pos.
+            let (mx, my) = self.mouse_pos;
+            self.drag_to(mx, my);
+        }
+    }
+
+    fn interact(&mut self, input: &mut UnblockInput, scheme: InputScheme) -> Option<Action> {
+        if self.sandbox.is_some() {
+            self.explore(input);
+            return None;
+        }
+        let mut action = None;
+        self.level.clock = self.timer.elapsed();
+        let cursor = input.cursor_position();
+        self.mouse_pos = (cursor.x, cursor.y);
+        let pressed = input.is_mouse_pressed();
+        if pressed && !self.mouse_down {
+            self.press_off_board = self.sxy_to_xy(cursor.x, cursor.y).is_none();
+        }
+        self.mouse_down = pressed;
+        if scheme == InputScheme::Drag && pressed && !self.press_off_board && self.drag.is_none() {
+            self.begin_drag(cursor.x, cursor.y);
+        }
+        if input.was_key_released(KeyCode::U) {
+            if self.level.undo() {
+                self.record();
+                self.invalidate();
+                action = Some(Action::Undo);
+                toast::show(i18n::text("undo.done"));
+            } else {
+                toast::show(i18n::text("undo.none"));
+            }
+        }
+        if input.was_key_released(KeyCode::Y) {
+            if self.level.redo() {
+                self.record();
+                self.invalidate();
+                action = Some(Action::Redo);
+                toast::show(i18n::text("redo.done"));
+            } else {
+                toast::show(i18n::text("redo.none"));
+            }
+        }
+
+        if !input.is_mouse_pressed() && self.drag.is_some() {
+            if let Some(moved) = self.end_drag() {
+                self.record();
+                action = Some(Action::Moved(moved));
+            }
+        }
+        match scheme {
+            InputScheme::Drag => {
+                for click in input.double_clicks(Button::Left) {
+                    let target = self
+                        .sxy_to_xy(click.x, click.y)
+                        .and_then(|(x, y)| self.level.block_at(x, y));
+                    if let Some(target) = target {
+                        if self.level.auto_slide(target) {
+                            self.record();
+                            self.invalidate();
+                            action = Some(Action::Moved(target));
+                        }
+                    }
+                }
+            }
+            InputScheme::Click => {
+                for click in input.left_clicks() {
+                    let moved = self
+                        .sxy_to_xy(click.x, click.y)
+                        .and_then(|(x, y)| self.click(x, y));
+                    if let Some(moved) = moved {
+                        self.record();
+                        action = Some(Action::Moved(moved));
+                    }
+                }
+            }
+        }
+        action
+    }
+
+    /// Camera controls: Ctrl+scroll zooms about the cursor, and dragging
+    /// with the middle button, holding the arrow keys when `arrows` or
+    /// pressing on the minimap scrolls a board bigger than its viewport.
+    fn look(&mut self, input: &UnblockInput, arrows: bool) {
+        let cursor = input.cursor_position();
+        let mut camera = self.camera;
+        let scrolled = input.wheel_scrolled();
+        if input.is_ctrl_pressed() && scrolled != 0.0 {
+            let zoom = (camera.zoom * ZOOM_STEP.powf(scrolled)).clamp(MIN_ZOOM, MAX_ZOOM);
+            // Keep the cell under the cursor where it is.
+            let (origin_x, origin_y) = self.viewport.origin();
+            let scale = zoom / camera.zoom;
+            camera.pan = (
+                self.viewport.x as f32 - cursor.x + (cursor.x - origin_x) * scale,
+                self.viewport.y as f32 - cursor.y + (cursor.y - origin_y) * scale,
+            );
+            camera.zoom = zoom;
+        }
+        if input.is_button_pressed(Button::Middle) {
+            if let Some(last) = self.panning {
+                camera.pan.0 += last.x - cursor.x;
+                camera.pan.1 += last.y - cursor.y;
+            }
+            self.panning = Some(cursor);
+        } else {
+            self.panning = None;
+        }
+        if arrows {
+            for (key, (dx, dy)) in [
+                (KeyCode::Left, (-1.0, 0.0)),
+                (KeyCode::Right, (1.0, 0.0)),
+                (KeyCode::Up, (0.0, -1.0)),
+                (KeyCode::Down, (0.0, 1.0)),
+            ] {
+                if input.was_key_repeated(key) {
+                    camera.pan.0 += dx * PAN_STEP;
+                    camera.pan.1 += dy * PAN_STEP;
+                }
+            }
+        }
+        let viewport = Viewport {
+            camera,
+            ..self.viewport
+        };
+        camera.pan = viewport.clamp(camera.pan);
+        if let Some(area) = minimap::area(&viewport) {
+            if input.is_mouse_pressed() && self.drag.is_none() && contains(&area, cursor) {
+                camera.pan = minimap::pan_to(&viewport, area, (cursor.x, cursor.y));
+            }
+        }
+        self.camera = camera;
+    }
+
+    /// Sandbox play: blocks are dragged onto any cells they fit, whatever
+    /// the control scheme, and nothing counts as a move.
+    fn explore(&mut self, input: &UnblockInput) {
+        let cursor = input.cursor_position();
+        self.mouse_pos = (cursor.x, cursor.y);
+        let pressed = input.is_mouse_pressed();
+        if pressed && !self.mouse_down {
+            self.press_off_board = self.sxy_to_xy(cursor.x, cursor.y).is_none();
+        }
+        self.mouse_down = pressed;
+        if pressed && !self.press_off_board && self.drag.is_none() {
+            self.begin_drag(cursor.x, cursor.y);
+        }
+        if !pressed {
+            if let Some(drag) = self.drag.take() {
+                self.level.set_block(drag.block, drag.target);
+                self.invalidate();
+            }
+        }
+    }
+
+    /// Keyboard play: Q / E pick the previous / next block and W / A / S / D
+    /// slide it one cell. Returns the block if it moved.
+    fn keyboard(&mut self, input: &UnblockInput) -> Option<usize> {
+        let movable: Vec<usize> = (0..self.level.blocks.len())
+            .filter(|&i| self.level.blocks[i].dir != BlockDir::Static)
+            .collect();
+        let count = movable.len();
+        let picked = self
+            .selected
+            .and_then(|selected| movable.iter().position(|&i| i == selected));
+        let mut pick = picked;
+        if input.was_key_released(KeyCode::E) {
+            pick = Some(picked.map_or(0, |p| (p + 1) % count));
+        }
+        if input.was_key_released(KeyCode::Q) {
+            pick = Some(picked.map_or(0, |p| (p + count - 1) % count));
+        }
+        if pick != picked {
+            self.selected = pick.map(|p| movable[p]);
+            self.invalidate();
+        }
+        let selected = self.selected?;
+        let delta = match self.level.blocks[selected].dir {
+            // A and D go left and right on screen, whichever way the board
+            // is drawn.
+            BlockDir::LeftRight if input.was_key_released(KeyCode::A) => {
+                if self.viewport.mirrored {
+                    1
+                } else {
+                    -1
+                }
+            }
+            BlockDir::LeftRight if input.was_key_released(KeyCode::D) => {
+                if self.viewport.mirrored {
+                    -1
+                } else {
+                    1
+                }
+            }
+            BlockDir::UpDown if input.was_key_released(KeyCode::W) => -1,
+            BlockDir::UpDown if input.was_key_released(KeyCode::S) => 1,
+            _ => return None,
+        };
+        let target = self.level.slide_limit(selected, delta);
+        if !self.level.move_block(selected, target) {
+            return None;
+        }
+        self.record();
+        self.invalidate();
+        Some(selected)
+    }
+
+    fn set_ghost(&mut self, ghost: Option<Replay>) {
+        self.ghost = ghost;
+        self.ghost_sprite = None;
+    }
+
+    /// Adds the move, undo or redo just made to the replay of this attempt.
+    /// Picks the level up where `history` left it, after the time it had
+    /// taken, with the moves recorded for the ghost as if made in one go.
+    fn resume(&mut self, history: &Replay) {
+        self.reset();
+        self.level.resume(history);
+        for m in &self.level.moves {
+            self.replay.record(m.at, m.block, m.to);
+            for fell in &m.fell {
+                self.replay.record(m.at, fell.block, fell.to);
+            }
+        }
+        self.timer.set(history.duration());
+    }
+
+    fn record(&mut self) {
+        let at = self.timer.elapsed();
+        for &(block, to) in self.level.last_placed() {
+            self.replay.record(at, block, to);
+        }
+    }
+
+    /// Click to move: the first click selects a block, the second picks the
+    /// cell it should move to. Returns the block if it moved.
+    fn click(&mut self, x: usize, y: usize) -> Option<usize> {
+        self.invalidate();
+        let clicked = self.level.block_at(x, y);
+        let selected = match self.selected {
+            Some(selected) => selected,
+            None => {
+                self.selected = clicked;
+                return None;
+            }
+        };
+        if clicked.is_some() {
+            // Clicking the selected block again lets go of it.
+            self.selected = if clicked == Some(selected) {
+                None
+            } else {
+                clicked
+            };
+            return None;
+        }
+        // Move so that the block covers the clicked cell.
+        let block = &self.level.blocks[selected];
+        let target = match block.dir {
+            BlockDir::LeftRight if y == block.y1 && x > block.x2 => (x - (block.x2 - block.x1), y),
+            BlockDir::LeftRight if y == block.y1 && x < block.x1 => (x, y),
+            BlockDir::UpDown if x == block.x1 && y > block.y2 => (x, y - (block.y2 - block.y1)),
+            BlockDir::UpDown if x == block.x1 && y < block.y1 => (x, y),
+            _ => {
+                toast::show(i18n::text("move.along"));
+                return None;
+            }
+        };
+        if !self.level.can_move(selected, target) {
+            toast::show(i18n::text("move.blocked"));
+            return None;
+        }
+        self.level.move_block(selected, target);
+        self.selected = None;
+        Some(selected)
+    }
+
+    fn draw(&mut self, renderer: &mut impl Renderer<Sprite = Sprite>, theme: &Theme) {
+        if self.static_sprite.is_none() {
+            self.static_sprite = Some(renderer.sprite(&self.static_sketch(theme)));
+        }
+        if self.block_sprite.is_none() {
+            self.block_sprite = Some(renderer.sprite(&self.block_sketch(theme)));
+        }
+        if let Some(ghost) = &self.ghost {
+            let steps = ghost.steps_at(self.timer.elapsed());
+            if self.ghost_sprite.as_ref().map(|(built, _)| *built) != Some(steps) {
+                let level = ghost.play(&self.level, steps);
+                let sketch = self.ghost_sketch(theme, &level);
+                self.ghost_sprite = Some((steps, renderer.sprite(&sketch)));
+            }
+        }
+        for sprite in self.static_sprite.iter().chain(self.block_sprite.iter()) {
+            renderer.draw_sprite(sprite);
+        }
+        if let Some((_, sprite)) = &self.ghost_sprite {
+            renderer.draw_sprite(sprite);
+        }
+    }
+
+    /// Writes each movable block's letter in its middle, following a block
+    /// being dragged.
+    fn draw_labels(&self, renderer: &mut impl Renderer) {
+        for i in 0..self.level.blocks.len() {
+            if let Some(label) = self.level.label(i) {
+                renderer.draw_text(
+                    &label.to_string(),
+                    Place::Centered(self.area(i)),
+                    text::MEDIUM,
+                    Color::WHITE,
+                );
+            }
+        }
+    }
+
+    /// Tints each movable block by `heat`, how often it moves in a shortest
+    /// solution: the busiest red, the rest shading to yellow, and the ones
+    /// that never move dimmed. The count is written in the corner.
+    fn draw_heat(&self, renderer: &mut impl Renderer, heat: &[usize]) {
+        let hottest = heat.iter().copied().max().unwrap_or(0).max(1) as f32;
+        for (i, block) in self.level.blocks.iter().enumerate() {
+            if block.dir == BlockDir::Static {
+                continue;
+            }
+            let area = self.area(i);
+            let moves = heat.get(i).copied().unwrap_or(0);
+            let tint = if moves == 0 {
+                Color {
+                    a: 0.6,
+                    ..Color::BLACK
+                }
+            } else {
+                Color {
+                    r: 1.0,
+                    g: 1.0 - moves as f32 / hottest,
+                    b: 0.0,
+                    a: 0.6,
+                }
+            };
+            renderer.draw_rect(area, tint, None);
+            renderer.draw_text(
+                &moves.to_string(),
+                Place::At(Point::new(area.x + 4.0, area.y + 2.0)),
+                text::SMALL,
+                Color::WHITE,
+            );
+        }
+    }
+
+    /// Draws the blocking graph: an arrow from the player to each block in
+    /// its way, from those to each block in theirs, and so on.
+    fn draw_blocking(&self, renderer: &mut impl Renderer) {
+        let center = |index: usize| {
+            let area = self.area(index);
+            Point::new(area.x + area.width / 2.0, area.y + area.height / 2.0)
+        };
+        for (from, to) in blocking::graph(&self.level) {
+            let (start, end) = (center(from), center(to));
+            let (dx, dy) = (end.x - start.x, end.y - start.y);
+            let length = (dx * dx + dy * dy).sqrt().max(1.0);
+            let (ux, uy) = (dx / length, dy / length);
+            let head = |side: f32| {
+                Point::new(
+                    end.x - 10.0 * ux + side * 6.0 * uy,
+                    end.y - 10.0 * uy - side * 6.0 * ux,
+                )
+            };
+            renderer.draw_line(vec![start, end], Color::WHITE, 3);
+            renderer.draw_line(vec![head(1.0), end, head(-1.0)], Color::WHITE, 3);
+        }
+    }
+
+    /// Where the block at `index` is drawn, following it while dragged.
+    fn area(&self, index: usize) -> Rectangle<f32> {
+        let block = &self.level.blocks[index];
+        let (x, y) = match &self.drag {
+            Some(drag) if drag.block == index => drag.target,
+            _ => (block.x1, block.y1),
+        };
+        self.viewport.block(block, (x, y))
+    }
+
+    /// How big `picture` draws a board.
+    fn picture_size() -> (usize, usize) {
+        (TILES_WIDE * TILE, TILES_HIGH * TILE)
+    }
+
+    /// Draws `level` as it stands on the whole of `renderer`, for pictures
+    /// made without a window.
+    fn picture(level: &Level, theme: &Theme, renderer: &mut impl Renderer) {
+        let (width, height) = Board::picture_size();
+        let mut board = Board::new(level.clone());
+        board.viewport = Viewport {
+            x: 0,
+            y: 0,
+            width,
+            height,
+            camera: Camera::default(),
+            slide: 0.0,
+            sizing: Sizing::default(),
+            mirrored: false,
+        };
+        renderer.clear(theme.background);
+        for sketch in &[board.static_sketch(theme), board.block_sketch(theme)] {
+            let sprite = renderer.sprite(sketch);
+            renderer.draw_sprite(&sprite);
+        }
+    }
+
+    /// The walls and exit, and where the goal wants a block moved to.
+    fn static_sketch(&self, theme: &Theme) -> Sketch {
+        let mut sketch = self.sketch(theme, |block| block.dir == BlockDir::Static);
+        if let WinCondition::Reach { block, target, .. } = self.level.win {
+            let block = &self.level.blocks[block];
+            sketch.stroke(self.viewport.block(block, target), theme.exit, 2);
+        }
+        sketch
+    }
+
+    /// The movable blocks, with the selected one outlined and the cells the
+    /// held or selected block can reach this move shaded beneath them. The
+    /// way from the player to the exit is tinted once it is clear.
+    fn block_sketch(&self, theme: &Theme) -> Sketch {
+        let mut sketch = Sketch::new();
+        if self.level.win.needs_exit() {
+            for (x, y) in self.level.clear_lane() {
+                sketch.fill(
+                    self.viewport.cells((x, y), (1, 1)),
+                    Color {
+                        a: 0.2,
+                        ..theme.exit
+                    },
+                );
+            }
+        }
+        let held = self.drag.as_ref().map(|drag| drag.block).or(self.selected);
+        for (x, y) in held.map_or_else(Vec::new, |held| self.level.reachable_cells(held)) {
+            sketch.fill(
+                self.viewport.cells((x, y), (1, 1)),
+                Color {
+                    a: 0.25,
+                    ..Color::WHITE
+                },
+            );
+        }
+        self.sketch_into(&mut sketch, theme, |block| block.dir != BlockDir::Static);
+        if let Some(selected) = self.selected {
+            let block = &self.level.blocks[selected];
+            let area = self.viewport.block(block, (block.x1, block.y1));
+            let (tile_width, tile_height) = self.viewport.tile();
+            let inner = Rectangle {
+                x: area.x + 2.0,
+                y: area.y + 2.0,
+                width: area.width - 4.0,
+                height: area.height - 4.0,
+            };
+            sketch.line(
+                rounded(inner, tile_width.min(tile_height) * CORNER - 2.0),
+                Color::WHITE,
+                4,
+            );
+        }
+        sketch
+    }
+
+    /// The movable blocks of `level`, see-through so the board shows under
+    /// them.
+    fn ghost_sketch(&self, theme: &Theme, level: &Level) -> Sketch {
+        let mut sketch = Sketch::new();
+        for (i, block) in level.blocks.iter().enumerate() {
+            if block.dir == BlockDir::Static {
+                continue;
+            }
+            let area = self.viewport.block(block, (block.x1, block.y1));
+            sketch.fill(
+                area,
+                Color {
+                    a: 0.3,
+                    ..theme.block_color(block, i)
+                },
+            );
+            sketch.stroke(
+                area,
+                Color {
+                    a: 0.6,
+                    ..Color::WHITE
+                },
+                2,
+            );
+        }
+        sketch
+    }
+
+    /// Marks the movable blocks as needing to be redrawn.
+    fn invalidate(&mut self) {
+        self.block_sprite = None;
+    }
+
+    fn sketch<F: Fn(&Block) -> bool>(&self, theme: &Theme, include: F) -> Sketch {
+        let mut sketch = Sketch::new();
+        self.sketch_into(&mut sketch, theme, include);
+        sketch
+    }
+
+    /// Adds the blocks `include` picks to `sketch`, over what it has. The
+    /// walls and exit are square so they join up; movable blocks have
+    /// rounded corners and cast a shadow, the one being dragged lifted off
+    /// the board with a longer one.
+    fn sketch_into<F: Fn(&Block) -> bool>(&self, sketch: &mut Sketch, theme: &Theme, include: F) {
+        let blocks: Vec<(usize, &Block, Rectangle<f32>, bool)> = self
+            .level
+            .blocks
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|(_i, block)| include(block))
+            .map(|(i, block)| {
+                let dragged = self.drag.as_ref().filter(|drag| drag.block == i);
+                let at = dragged.map_or((block.x1, block.y1), |drag| drag.target);
+                let mut area = self.viewport.block(block, at);
+                if dragged.is_some() {
+                    area.x -= LIFT;
+                    area.y -= LIFT;
+                }
+                (i, block, area, dragged.is_some())
+            })
+            .collect();
+        let (tile_width, tile_height) = self.viewport.tile();
+        let radius = tile_width.min(tile_height) * CORNER;
+        // Shadows first, so none falls on a block.
+        for &(_, block, area, lifted) in &blocks {
+            if block.dir != BlockDir::Static {
+                let offset = if lifted { SHADOW + LIFT * 2.0 } else { SHADOW };
+                let shadow = Rectangle {
+                    x: area.x + offset,
+                    y: area.y + offset,
+                    ..area
+                };
+                sketch.fill_polygon(
+                    rounded(shadow, radius),
+                    Color {
+                        a: 0.4,
+                        ..Color::BLACK
+                    },
+                );
+            }
+        }
+        for &(i, block, area, _) in &blocks {
+            if block.dir == BlockDir::Static {
+                sketch.fill(area, theme.block_color(block, i));
+                sketch.stroke(area, Color::BLACK, 1);
+            } else {
+                let outline = rounded(area, radius);
+                sketch.fill_polygon(outline.clone(), theme.block_color(block, i));
+                sketch.line(outline, Color::BLACK, 1);
+            }
+        }
+    }
+}
+
+impl Game for LevelSet {
+    type Input = UnblockInput;
+    type LoadingScreen = ();
+    const TICKS_PER_SECOND: u16 = 20;
+
+    fn load(_window: &Window) -> Task<LevelSet> {
+        Task::using_gpu(|gpu| {
+            Ok(LevelSet::load(
+                options().levels.clone(),
+                Save::load(options().remote.clone()),
+                Music::new(),
+                Some(Writer::load(gpu)?),
+            ))
+        })
+    }
+
+    fn draw(&mut self, frame: &mut Frame<'_>, _timer: &Timer) {
+        let writer = self
+            .writer
+            .take()
+            .expect("Writer is only lent while drawing");
+        let mut screen = Screen::new(frame, writer);
+        self.render(&mut screen);
+        #[cfg(feature = "dev")]
+        {
+            self.profile.frame();
+            self.profile.draw(&mut screen);
+        }
+        let (writer, _draws) = screen.finish();
+        #[cfg(feature = "dev")]
+        self.profile.draws(_draws);
+        self.writer = Some(writer);
+    }
+
+    fn interact(&mut self, input: &mut Self::Input, window: &mut Window) {
+        self.respond(input, window);
+    }
+
+    fn update(&mut self, window: &Window) {
+        #[cfg(feature = "dev")]
+        let started = std::time::Instant::now();
+        self.tick(window);
+        #[cfg(feature = "dev")]
+        self.profile.update(started.elapsed());
+    }
+
+    fn on_close_request(&mut self) -> bool {
+        // Closing again while being asked means yes.
+        let asked = matches!(&self.dialog, Some(dialog) if dialog.action() == Confirm::Quit);
+        if !asked && !matches!(self.mode, Mode::Pack) && self.has_progress() {
+            self.dialog = Some(Dialog::new(i18n::text("quit.confirm"), Confirm::Quit));
+            return false;
+        }
+        // Saves still being written are not cut off.
+        self.save.finish();
+        // Pack levels are picked up where they were left.
+        if let Mode::Pack = self.mode {
+            if let Err(e) = self.save.store_now() {
+                println!("Unable to save progress: {}", e);
+            }
+        }
+        true
+    }
+}
 
 fn main() -> Result<()> {
+    let app = App::new("Unblock Me!")
+        .arg(
+            Arg::with_name("seed")
+                .long("seed")
+                .takes_value(true)
+                .help("Seed for everything random, to make a session reproducible"),
+        )
+        .arg(
+            Arg::with_name("daily")
+                .long("daily")
+                .conflicts_with("seed")
+                .help("Use today's seed, shared by everyone playing today"),
+        )
+        .arg(
+            Arg::with_name("zen")
+                .long("zen")
+                .help("Start in zen mode: soft music and levels that flow into each other"),
+        )
+        .arg(
+            Arg::with_name("click-to-move")
+                .long("click-to-move")
+                .help("Move blocks by clicking them and then their destination"),
+        )
+        .arg(
+            Arg::with_name("count-cells")
+                .long("count-cells")
+                .help("Score by cells travelled rather than moves made"),
+        )
+        .arg(
+            Arg::with_name("ghost")
+                .long("ghost")
+                .help("Start with a ghost of your fastest solve playing along"),
+        )
+        .arg(
+            Arg::with_name("skip-solved")
+                .long("skip-solved")
+                .help("After solving a level, go on to the next one not solved yet"),
+        )
+        .arg(
+            Arg::with_name("labels")
+                .long("labels")
+                .help("Letter each block the way --solve names it"),
+        )
+        .arg(
+            Arg::with_name("reduced-motion")
+                .long("reduced-motion")
+                .help("Leave out animations and transitions"),
+        )
+        .arg(
+            Arg::with_name("tile-size")
+                .long("tile-size")
+                .takes_value(true)
+                .value_name("PIXELS")
+                .help("Size of a cell in pixels, or fit to fill the window, remembered from then on"),
+        )
+        .arg(
+            Arg::with_name("margin")
+                .long("margin")
+                .takes_value(true)
+                .value_name("PIXELS")
+                .help("Room kept round a board fitted to the window, remembered from then on"),
+        )
+        .arg(
+            Arg::with_name("language")
+                .long("language")
+                .takes_value(true)
+                .value_name("CODE")
+                .help("Language to show the game in, such as en or de, remembered from then on"),
+        )
+        .arg(
+            Arg::with_name("mirror")
+                .long("mirror")
+                .takes_value(true)
+                .possible_values(&["auto", "on", "off"])
+                .help("Draw boards flipped left to right, or auto to as right-to-left languages do, remembered from then on"),
+        )
+        .arg(
+            Arg::with_name("overlay")
+                .long("overlay")
+                .conflicts_with("race")
+                .help("Streaming layout: large move count, timer and optimal moves left beside the board"),
+        )
+        .arg(
+            Arg::with_name("pack")
+                .long("pack")
+                .takes_value(true)
+                .value_name("PACK")
+                .help("Play the levels in the file PACK, or in an installed mod's pack named like mod/pack, instead of the built-in ones"),
+        )
+        .arg(
+            Arg::with_name("level")
+                .long("level")
+                .takes_value(true)
+                .value_name("N")
+                .help("Start on level N (counting from 1)"),
+        )
+        .arg(
+            Arg::with_name("size")
+                .long("size")
+                .takes_value(true)
+                .value_name("WxH")
+                .help("Window size in pixels, e.g. 800x600"),
+        )
+        .arg(
+            Arg::with_name("fullscreen")
+                .long("fullscreen")
+                .help("Start in fullscreen"),
+        )
+        .arg(
+            Arg::with_name("endless")
+                .long("endless")
+                .help("Play an endless stream of generated puzzles that slowly get harder"),
+        )
+        .arg(
+            Arg::with_name("kids")
+                .long("kids")
+                .conflicts_with("endless")
+                .help("Play an endless stream of generated puzzles with few blocks and a move or two, for young children"),
+        )
+        .arg(
+            Arg::with_name("time-attack")
+                .long("time-attack")
+                .conflicts_with_all(&["endless", "kids"])
+                .help("Solve as many easy generated puzzles as you can in five minutes"),
+        )
+        .arg(
+            Arg::with_name("race")
+                .long("race")
+                .conflicts_with_all(&["endless", "kids", "time-attack"])
+                .help("Two players race on the same level: one with the mouse, one with the keyboard"),
+        )
+        .arg(
+            Arg::with_name("edit")
+                .long("edit")
+                .takes_value(true)
+                .value_name("FILE")
+                .help("Open a pack in the level editor, with a copy of the level to play beside it. The file is made when first saved"),
+        )
+        .arg(
+            Arg::with_name("solve")
+                .long("solve")
+                .help("Print the shortest solution of each level (or just --level) and exit"),
+        )
+        .arg(
+            Arg::with_name("strategy")
+                .long("strategy")
+                .takes_value(true)
+                .value_name("NAME")
+                .possible_values(&["bfs", "ida"])
+                .default_value("bfs")
+                .help("How --solve searches: bfs (breadth first) or ida (IDA*, which needs little memory)"),
+        )
+        .arg(
+            Arg::with_name("proof")
+                .long("proof")
+                .takes_value(true)
+                .value_name("FILE")
+                .requires("solve")
+                .help("With --solve, also write each solution to FILE as the board after every move"),
+        )
+        .arg(
+            Arg::with_name("trace")
+                .long("trace")
+                .requires("proof")
+                .help("With --proof, also write every position the search explored, showing no shorter solution exists"),
+        )
+        .arg(
+            Arg::with_name("validate")
+                .long("validate")
+                .conflicts_with("solve")
+                .help("Check every level can be played and print its fewest moves, without opening a window"),
+        )
+        .subcommand(
+            SubCommand::with_name("annotate")
+                .about("Writes each level's fewest moves into the pack as a '# par: N' comment")
+                .arg(
+                    Arg::with_name("FILE")
+                        .required(true)
+                        .help("Pack to annotate in place"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("input-test")
+                .about("Plays scripts of mouse and keyboard input to the game without a window and checks the boards they leave")
+                .arg(
+                    Arg::with_name("SCRIPT")
+                        .required(true)
+                        .multiple(true)
+                        .help("Input scripts to play"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("gallery")
+                .about("Writes a web page picturing every level of a pack, with its fewest moves")
+                .arg(
+                    Arg::with_name("FILE")
+                        .required(true)
+                        .help("Pack to picture"),
+                )
+                .arg(
+                    Arg::with_name("out")
+                        .long("out")
+                        .takes_value(true)
+                        .value_name("PAGE")
+                        .help("File to write the page to, instead of printing it"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("meta")
+                .about("Shows or changes a pack's title, author, description and level order")
+                .arg(
+                    Arg::with_name("FILE")
+                        .required(true)
+                        .help("Pack to show or change in place"),
+                )
+                .arg(
+                    Arg::with_name("title")
+                        .long("title")
+                        .takes_value(true)
+                        .help("What the pack is called"),
+                )
+                .arg(
+                    Arg::with_name("author")
+                        .long("author")
+                        .takes_value(true)
+                        .help("Who made the pack"),
+                )
+                .arg(
+                    Arg::with_name("description")
+                        .long("description")
+                        .takes_value(true)
+                        .help("A line about the pack"),
+                )
+                .arg(
+                    Arg::with_name("order")
+                        .long("order")
+                        .takes_value(true)
+                        .value_name("N,N,...")
+                        .help("Every level's number, in the order to play them"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("mods")
+                .about("Lists the installed mods, or enables or disables one")
+                .arg(
+                    Arg::with_name("enable")
+                        .long("enable")
+                        .takes_value(true)
+                        .value_name("NAME")
+                        .conflicts_with("disable")
+                        .help("Use the mod's packs and theme again"),
+                )
+                .arg(
+                    Arg::with_name("disable")
+                        .long("disable")
+                        .takes_value(true)
+                        .value_name("NAME")
+                        .help("Leave the mod's packs and theme out"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("progress")
+                .about("Copies your progress, stats and settings to or from another machine")
+                .arg(
+                    Arg::with_name("export")
+                        .long("export")
+                        .takes_value(true)
+                        .value_name("FILE")
+                        .conflicts_with("import")
+                        .required_unless("import")
+                        .help("Write everything saved to FILE"),
+                )
+                .arg(
+                    Arg::with_name("import")
+                        .long("import")
+                        .takes_value(true)
+                        .value_name("FILE")
+                        .help("Add what FILE has, keeping the best of each score"),
+                ),
+        );
+    #[cfg(feature = "net")]
+    let app = app
+        .arg(
+            Arg::with_name("versus")
+                .long("versus")
+                .takes_value(true)
+                .value_name("HOST:PORT")
+                .conflicts_with_all(&["endless", "kids", "time-attack", "race"])
+                .help("Race someone over the network through the lobby server at HOST:PORT"),
+        )
+        .subcommand(
+            SubCommand::with_name("serve")
+                .about("Runs a lobby server that pairs up --versus players")
+                .arg(
+                    Arg::with_name("port")
+                        .long("port")
+                        .takes_value(true)
+                        .default_value("7878")
+                        .help("Port to listen on"),
+                ),
+        );
+    #[cfg(feature = "export")]
+    let app = app.arg(
+        Arg::with_name("gallery")
+            .long("gallery")
+            .takes_value(true)
+            .value_name("DIR")
+            .requires("validate")
+            .help("With --validate, also save a picture of each level to DIR, in your theme"),
+    );
+    #[cfg(feature = "journal")]
+    let app = app.subcommand(
+        SubCommand::with_name("report")
+            .about(
+                "Sums up playtesters' journals of a pack: moves over par, resets and time by level",
+            )
+            .arg(
+                Arg::with_name("JOURNAL")
+                    .required(true)
+                    .multiple(true)
+                    .help("Journals written with --journal"),
+            )
+            .arg(
+                Arg::with_name("pack")
+                    .long("pack")
+                    .takes_value(true)
+                    .value_name("FILE")
+                    .help("Pack the journals were played on, instead of the built-in one"),
+            ),
+    )
+    .subcommand(
+        SubCommand::with_name("calibrate")
+            .about("Writes star thresholds into a pack from the solves in playtesters' journals")
+            .arg(
+                Arg::with_name("FILE")
+                    .required(true)
+                    .help("Pack to calibrate in place"),
+            )
+            .arg(
+                Arg::with_name("JOURNAL")
+                    .required(true)
+                    .multiple(true)
+                    .help("Journals written with --journal while playing the pack"),
+            )
+            .arg(
+                Arg::with_name("min-solves")
+                    .long("min-solves")
+                    .takes_value(true)
+                    .value_name("N")
+                    .default_value("3")
+                    .help("Leave levels solved fewer times than this alone"),
+            ),
+    );
+    #[cfg(feature = "import")]
+    let app = app.subcommand(
+        SubCommand::with_name("import")
+            .about("Reads levels from PNG pictures of boards into a pack")
+            .arg(
+                Arg::with_name("DIR")
+                    .required(true)
+                    .help("Folder of pictures: 8x8 or 6x6 grids of flat colours"),
+            )
+            .arg(
+                Arg::with_name("out")
+                    .long("out")
+                    .takes_value(true)
+                    .value_name("FILE")
+                    .help("Pack to write, instead of printing it"),
+            ),
+    );
+    #[cfg(feature = "browse")]
+    let app = app.arg(
+        Arg::with_name("catalog")
+            .long("catalog")
+            .takes_value(true)
+            .value_name("URL")
+            .help("Catalog of community packs for the pack browser (B) to list, remembered from then on"),
+    )
+    .arg(
+        Arg::with_name("catalog-key")
+            .long("catalog-key")
+            .takes_value(true)
+            .value_name("HEX")
+            .help("ed25519 public key that signs the catalog's packs, remembered from then on"),
+    );
+    #[cfg(feature = "journal")]
+    let app = app
+        .arg(
+            Arg::with_name("journal")
+                .long("journal")
+                .takes_value(true)
+                .value_name("FILE")
+                .help("Add every move, undo, reset and level played to a JSON lines file"),
+        )
+        .arg(
+            Arg::with_name("replay-journal")
+                .long("replay-journal")
+                .takes_value(true)
+                .value_name("FILE")
+                .help("Play a journal back at startup, to pick a session up where it ended"),
+        );
+    #[cfg(feature = "sync")]
+    let app = app.arg(
+        Arg::with_name("sync")
+            .long("sync")
+            .takes_value(true)
+            .value_name("URL")
+            .help("Keep a copy of your progress at a WebDAV URL or s3://bucket/key"),
+    );
+    let matches = app.get_matches();
+    #[cfg(feature = "net")]
+    {
+        if let Some(serve) = matches.subcommand_matches("serve") {
+            let address = format!("0.0.0.0:{}", serve.value_of("port").unwrap());
+            println!("Listening on {}", address);
+            if let Err(err) = net::serve(&address, Rng::new(Rng::random_seed())) {
+                cli::fail(&format!("Could not serve on {}: {}", address, err));
+            }
+            return Ok(());
+        }
+    }
+    if let Some(input_test) = matches.subcommand_matches("input-test") {
+        cli::input_test(&input_test.values_of("SCRIPT").unwrap().collect::<Vec<_>>());
+    }
+    if let Some(annotate) = matches.subcommand_matches("annotate") {
+        cli::annotate(annotate.value_of("FILE").unwrap());
+    }
+    if let Some(meta) = matches.subcommand_matches("meta") {
+        cli::meta(
+            meta.value_of("FILE").unwrap(),
+            meta.value_of("title"),
+            meta.value_of("author"),
+            meta.value_of("description"),
+            meta.value_of("order"),
+        );
+    }
+    #[cfg(feature = "journal")]
+    if let Some(report) = matches.subcommand_matches("report") {
+        let journals: Vec<&str> = report.values_of("JOURNAL").unwrap().collect();
+        cli::report(report.value_of("pack"), &journals);
+    }
+    #[cfg(feature = "journal")]
+    if let Some(calibrate) = matches.subcommand_matches("calibrate") {
+        let journals: Vec<&str> = calibrate.values_of("JOURNAL").unwrap().collect();
+        let least = match calibrate.value_of("min-solves").unwrap().parse() {
+            Ok(least) => least,
+            Err(_) => cli::fail("--min-solves takes a number"),
+        };
+        cli::calibrate(calibrate.value_of("FILE").unwrap(), &journals, least);
+    }
+    #[cfg(feature = "import")]
+    if let Some(import) = matches.subcommand_matches("import") {
+        cli::import(import.value_of("DIR").unwrap(), import.value_of("out"));
+    }
+    let mods = mods::scan();
+    if let Some(gallery) = matches.subcommand_matches("gallery") {
+        cli::gallery(
+            gallery.value_of("FILE").unwrap(),
+            gallery.value_of("out"),
+            &Theme::saved(&mods),
+        );
+    }
+    if let Some(command) = matches.subcommand_matches("progress") {
+        cli::progress(command.value_of("export"), command.value_of("import"));
+    }
+    if let Some(command) = matches.subcommand_matches("mods") {
+        cli::mods(
+            &mods,
+            command.value_of("enable"),
+            command.value_of("disable"),
+        );
+    }
+    let seed = if matches.is_present("daily") {
+        Rng::daily_seed()
+    } else {
+        match matches.value_of("seed") {
+            Some(seed) => seed
+                .parse()
+                .unwrap_or_else(|_| cli::fail("--seed must be a number")),
+            None => Rng::random_seed(),
+        }
+    };
+    let (levels, pack_file) = match matches.value_of("pack") {
+        Some(name) => {
+            // A file of that name comes before a mod's pack.
+            let path = match mods::find_pack(&mods, name) {
+                Some(found) if !Path::new(name).exists() => found,
+                _ => PathBuf::from(name),
+            };
+            match fs::read(&path) {
+                Ok(data) => match limits::parse(&data) {
+                    Ok(levels) => (levels, Some(path)),
+                    Err(exceeded) => cli::fail(&format!("{} is too big: {}", name, exceeded)),
+                },
+                Err(err) => cli::fail(&format!("Could not read {}: {}", name, err)),
+            }
+        }
+        None => (pack::parse(pack::BUILTIN), None),
+    };
+    if matches.is_present("validate") {
+        #[cfg(feature = "export")]
+        if let Some(dir) = matches.value_of("gallery") {
+            export::gallery(&levels, &Theme::saved(&mods), dir);
+        }
+        cli::validate(&levels);
+    }
+    for (first, duplicate) in pack::duplicates(&levels) {
+        eprintln!(
+            "warning: level {} is the same puzzle as level {}",
+            duplicate + 1,
+            first + 1
+        );
+    }
+    let level = match matches.value_of("level") {
+        Some(level) => match level.parse::<usize>() {
+            Ok(level) if 1 <= level && level <= levels.len() => Some(level - 1),
+            _ => cli::fail(&format!("--level must be between 1 and {}", levels.len())),
+        },
+        None => None,
+    };
+    if matches.is_present("solve") {
+        let config = cli::parse_strategy(matches.value_of("strategy").unwrap())
+            .unwrap_or_else(|| cli::fail("--strategy must be bfs or ida"));
+        cli::solve(
+            &levels,
+            level,
+            config,
+            matches.value_of("proof"),
+            matches.is_present("trace"),
+        );
+    }
+    let edit = matches.value_of("edit").map(|path| match fs::read(path) {
+        Ok(data) => (PathBuf::from(path), data),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => (PathBuf::from(path), Vec::new()),
+        Err(err) => cli::fail(&format!("Could not read {}: {}", path, err)),
+    });
+    let size = match matches.value_of("size") {
+        Some(size) => {
+            cli::parse_size(size).unwrap_or_else(|| cli::fail("--size must look like 800x600"))
+        }
+        // Room for two boards side by side.
+        None if matches.is_present("race") || edit.is_some() => (1000, 500),
+        None if matches.is_present("overlay") => (500 + overlay::WIDTH as u32, 500),
+        None => (500, 500),
+    };
+    let _ = OPTIONS.set(Options {
+        seed,
+        zen: matches.is_present("zen"),
+        click_to_move: matches.is_present("click-to-move"),
+        count_cells: matches.is_present("count-cells"),
+        ghost: matches.is_present("ghost"),
+        skip_solved: matches.is_present("skip-solved"),
+        labels: matches.is_present("labels"),
+        reduced_motion: matches.is_present("reduced-motion"),
+        tile_size: matches.value_of("tile-size").map(|tile| {
+            TileSize::parse(tile)
+                .unwrap_or_else(|| cli::fail("--tile-size takes fit or a number from 10 up"))
+        }),
+        margin: matches.value_of("margin").map(|margin| {
+            margin
+                .parse()
+                .unwrap_or_else(|_| cli::fail("--margin takes a number"))
+        }),
+        language: matches.value_of("language").map(|code| {
+            if !i18n::set(code) {
+                cli::fail(&format!(
+                    "--language takes one of {}",
+                    i18n::codes().join(", ")
+                ));
+            }
+            code.to_string()
+        }),
+        mirror: matches.value_of("mirror").map(|mirror| match mirror {
+            "on" => Some(true),
+            "off" => Some(false),
+            _ => None,
+        }),
+        overlay: matches.is_present("overlay"),
+        levels,
+        level: level.unwrap_or(0),
+        endless: matches.is_present("endless"),
+        kids: matches.is_present("kids"),
+        time_attack: matches.is_present("time-attack"),
+        race: matches.is_present("race"),
+        edit,
+        #[cfg(feature = "net")]
+        versus: matches.value_of("versus").map(String::from),
+        remote: remote(&matches),
+        #[cfg(feature = "browse")]
+        catalog: matches.value_of("catalog").map(String::from),
+        #[cfg(feature = "browse")]
+        catalog_key: matches.value_of("catalog-key").map(String::from),
+        #[cfg(feature = "journal")]
+        journal: matches.value_of("journal").map(PathBuf::from),
+        #[cfg(feature = "journal")]
+        replay_journal: matches.value_of("replay-journal").map(PathBuf::from),
+        pack: String::from(matches.value_of("pack").unwrap_or("built-in")),
+        pack_file,
+        mods,
+    });
     LevelSet::run(WindowSettings {
         title: String::from("Unblock Me!"),
-        size: (500, 500),
+        size,
         resizable: false,
-        fullscreen: false,
+        fullscreen: matches.is_present("fullscreen"),
     })
 }
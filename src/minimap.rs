@@ -0,0 +1,84 @@
+use crate::render::{Color, Rectangle, Renderer};
+use crate::theme::Theme;
+use crate::{Board, Viewport};
+use unblock::level::{TILES_HIGH, TILES_WIDE};
+
+// Size of a cell on the map, and its distance from the viewport's corner.
+const CELL: f32 = 12.0;
+const MARGIN: f32 = 10.0;
+
+/// Where the map goes: the bottom right corner of `viewport`, when the
+/// board is too big to see all of in it.
+pub fn area(viewport: &Viewport) -> Option<Rectangle<f32>> {
+    if viewport.overflow() == (0.0, 0.0) {
+        return None;
+    }
+    let (width, height) = (CELL * TILES_WIDE as f32, CELL * TILES_HIGH as f32);
+    Some(Rectangle {
+        x: (viewport.x + viewport.width) as f32 - MARGIN - width,
+        y: (viewport.y + viewport.height) as f32 - MARGIN - height,
+        width,
+        height,
+    })
+}
+
+/// The whole of `board` in small, with a frame round the part of it in
+/// view.
+pub fn draw(board: &Board, theme: &Theme, renderer: &mut impl Renderer) {
+    let area = match area(&board.viewport) {
+        Some(area) => area,
+        None => return,
+    };
+    renderer.draw_rect(area, theme.background, Some((Color::WHITE, 1)));
+    for (i, block) in board.level.blocks.iter().enumerate() {
+        let x = if board.viewport.mirrored {
+            TILES_WIDE - 1 - block.x2
+        } else {
+            block.x1
+        };
+        renderer.draw_rect(
+            Rectangle {
+                x: area.x + x as f32 * CELL,
+                y: area.y + block.y1 as f32 * CELL,
+                width: (1 + block.x2 - block.x1) as f32 * CELL,
+                height: (1 + block.y2 - block.y1) as f32 * CELL,
+            },
+            theme.block_color(block, i),
+            None,
+        );
+    }
+    let viewport = &board.viewport;
+    let (origin_x, origin_y) = viewport.origin();
+    let (tile_width, tile_height) = viewport.tile();
+    // The viewport in cells, cut to the board.
+    let left = ((viewport.x as f32 - origin_x) / tile_width).max(0.0);
+    let top = ((viewport.y as f32 - origin_y) / tile_height).max(0.0);
+    let right =
+        (((viewport.x + viewport.width) as f32 - origin_x) / tile_width).min(TILES_WIDE as f32);
+    let bottom =
+        (((viewport.y + viewport.height) as f32 - origin_y) / tile_height).min(TILES_HIGH as f32);
+    let clear = Color {
+        a: 0.0,
+        ..Color::BLACK
+    };
+    renderer.draw_rect(
+        Rectangle {
+            x: area.x + left * CELL,
+            y: area.y + top * CELL,
+            width: (right - left) * CELL,
+            height: (bottom - top) * CELL,
+        },
+        clear,
+        Some((Color::WHITE, 2)),
+    );
+}
+
+/// The camera pan that centers the view on the cell at `(x, y)` on the
+/// map at `area`.
+pub fn pan_to(viewport: &Viewport, area: Rectangle<f32>, (x, y): (f32, f32)) -> (f32, f32) {
+    let (tile_width, tile_height) = viewport.tile();
+    viewport.clamp((
+        (x - area.x) / CELL * tile_width - viewport.width as f32 / 2.0,
+        (y - area.y) / CELL * tile_height - viewport.height as f32 / 2.0,
+    ))
+}
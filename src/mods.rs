@@ -0,0 +1,76 @@
+use crate::save;
+use crate::theme::Theme;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A folder in the mods folder: any `.dat` files in it are level packs,
+/// and a `theme.txt` is a theme named after the folder.
+pub struct Mod {
+    pub name: String,
+    /// Each pack's name, "mod/file" without the extension, and its path.
+    pub packs: Vec<(String, PathBuf)>,
+    pub theme: Option<Theme>,
+    /// What could not be loaded, for `unblock mods` to report.
+    pub problems: Vec<String>,
+}
+
+/// Where mods are installed: `mods` beside the save file.
+pub fn dir() -> Option<PathBuf> {
+    save::dir().map(|dir| dir.join("mods"))
+}
+
+/// Every installed mod, in name order. A missing mods folder is no mods.
+pub fn scan() -> Vec<Mod> {
+    let entries = match dir().and_then(|dir| fs::read_dir(dir).ok()) {
+        Some(entries) => entries,
+        None => return Vec::new(),
+    };
+    let mut mods: Vec<Mod> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .filter_map(|path| load(&path))
+        .collect();
+    mods.sort_by(|a, b| a.name.cmp(&b.name));
+    mods
+}
+
+fn load(path: &Path) -> Option<Mod> {
+    let name = path.file_name()?.to_str()?.to_string();
+    let mut packs: Vec<(String, PathBuf)> = fs::read_dir(path)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|file| file.extension().is_some_and(|ext| ext == "dat"))
+        .filter_map(|file| {
+            let stem = file.file_stem()?.to_str()?.to_string();
+            Some((format!("{}/{}", name, stem), file))
+        })
+        .collect();
+    packs.sort();
+    let mut problems = Vec::new();
+    let theme = match fs::read_to_string(path.join("theme.txt")) {
+        Ok(text) => match Theme::parse(&name, &text) {
+            Ok(theme) => Some(theme),
+            Err(problem) => {
+                problems.push(format!("theme.txt: {}", problem));
+                None
+            }
+        },
+        Err(_) => None,
+    };
+    Some(Mod {
+        name,
+        packs,
+        theme,
+        problems,
+    })
+}
+
+/// The path of the pack called `name` ("mod/pack") in any installed mod.
+pub fn find_pack(mods: &[Mod], name: &str) -> Option<PathBuf> {
+    mods.iter()
+        .flat_map(|m| m.packs.iter())
+        .find(|(pack, _)| pack == name)
+        .map(|(_, path)| path.clone())
+}
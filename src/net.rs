@@ -0,0 +1,258 @@
+//! Networked versus: a tiny lobby server pairs up clients and has each pair
+//! race on the same generated puzzle.
+//!
+//! The protocol is one message per line over TCP:
+//!
+//! * client to server: `HELLO <version>` on connecting, `PROGRESS <moves>
+//!   <solved>` after every move (solved is 0 or 1) and `READY` to race again.
+//! * server to client: `WAIT` while there is no opponent, `START <seed>
+//!   <moves>` to race on `generate::level(&mut Rng::new(seed), moves)`,
+//!   `OPPONENT <moves> <solved>` with the other player's progress and `LEFT`
+//!   when they disconnect.
+//!
+//! The generator is deterministic, so both clients build the same puzzle
+//! from the seed and no level data goes over the wire. A line longer than
+//! `MAX_LINE` ends the connection.
+
+use crate::rng::Rng;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+pub const VERSION: u32 = 1;
+
+/// How hard versus puzzles are, in solver moves.
+pub const MOVES: usize = 8;
+
+/// Longest line, newline included, either end reads. Every message fits
+/// in far less.
+pub const MAX_LINE: usize = 256;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Message {
+    Hello(u32),
+    Progress { moves: usize, solved: bool },
+    Ready,
+    Wait,
+    Start { seed: u64, moves: usize },
+    Opponent { moves: usize, solved: bool },
+    Left,
+}
+
+impl Message {
+    pub fn parse(line: &str) -> Option<Message> {
+        let mut words = line.split_whitespace();
+        let message = match words.next()? {
+            "HELLO" => Message::Hello(words.next()?.parse().ok()?),
+            "PROGRESS" => Message::Progress {
+                moves: words.next()?.parse().ok()?,
+                solved: words.next()? == "1",
+            },
+            "READY" => Message::Ready,
+            "WAIT" => Message::Wait,
+            "START" => Message::Start {
+                seed: words.next()?.parse().ok()?,
+                moves: words.next()?.parse().ok()?,
+            },
+            "OPPONENT" => Message::Opponent {
+                moves: words.next()?.parse().ok()?,
+                solved: words.next()? == "1",
+            },
+            "LEFT" => Message::Left,
+            _ => return None,
+        };
+        Some(message)
+    }
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Message::Hello(version) => write!(f, "HELLO {}", version),
+            Message::Progress { moves, solved } => {
+                write!(f, "PROGRESS {} {}", moves, *solved as u8)
+            }
+            Message::Ready => write!(f, "READY"),
+            Message::Wait => write!(f, "WAIT"),
+            Message::Start { seed, moves } => write!(f, "START {} {}", seed, moves),
+            Message::Opponent { moves, solved } => {
+                write!(f, "OPPONENT {} {}", moves, *solved as u8)
+            }
+            Message::Left => write!(f, "LEFT"),
+        }
+    }
+}
+
+fn send(stream: &mut TcpStream, message: &Message) -> io::Result<()> {
+    writeln!(stream, "{}", message)
+}
+
+/// Reads messages off `stream` on a background thread, tagging each with
+/// `tag`. A disconnect arrives as `Message::Left`, and a line longer than
+/// `MAX_LINE` disconnects.
+fn listen<T: Copy + Send + 'static>(
+    stream: &TcpStream,
+    tag: T,
+    sender: Sender<(T, Message)>,
+) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    thread::spawn(move || {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            match (&mut reader).take(MAX_LINE as u64).read_line(&mut line) {
+                Ok(0) | Err(_) => break,
+                Ok(read) if read == MAX_LINE && !line.ends_with('\n') => {
+                    let _ = reader.get_ref().shutdown(Shutdown::Both);
+                    break;
+                }
+                Ok(_) => {}
+            }
+            if let Some(message) = Message::parse(&line) {
+                if sender.send((tag, message)).is_err() {
+                    return;
+                }
+            }
+        }
+        let _ = sender.send((tag, Message::Left));
+    });
+    Ok(())
+}
+
+/// A connection to a lobby server. Messages are read in the background and
+/// picked up with `poll`, so the game loop never waits on the network.
+pub struct Client {
+    stream: TcpStream,
+    messages: Receiver<((), Message)>,
+}
+
+impl Client {
+    pub fn connect(address: &str) -> io::Result<Client> {
+        let mut stream = TcpStream::connect(address)?;
+        send(&mut stream, &Message::Hello(VERSION))?;
+        let (sender, messages) = mpsc::channel();
+        listen(&stream, (), sender)?;
+        Ok(Client { stream, messages })
+    }
+
+    pub fn send(&mut self, message: &Message) -> io::Result<()> {
+        send(&mut self.stream, message)
+    }
+
+    /// The next message from the server, if one has arrived.
+    pub fn poll(&self) -> Option<Message> {
+        self.messages.try_recv().ok().map(|(_, message)| message)
+    }
+}
+
+/// The reading thread holds its own handle on the socket, so dropping the
+/// client has to close the connection explicitly for the server to notice.
+impl Drop for Client {
+    fn drop(&mut self) {
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+}
+
+/// Runs a lobby server on `address` forever, pairing clients in the order
+/// they connect. Seeds for the puzzles come from `rng`.
+pub fn serve(address: &str, rng: Rng) -> io::Result<()> {
+    serve_on(TcpListener::bind(address)?, rng)
+}
+
+/// Like `serve`, on a listener already bound, such as one on port 0 for
+/// the system to pick.
+pub fn serve_on(listener: TcpListener, mut rng: Rng) -> io::Result<()> {
+    let mut waiting: Option<TcpStream> = None;
+    for stream in listener.incoming() {
+        let mut stream = match stream {
+            Ok(stream) => stream,
+            Err(_) => continue,
+        };
+        // A player who gave up waiting is not one to race.
+        match waiting.take().filter(is_connected) {
+            Some(first) => {
+                let rng = rng.fork();
+                thread::spawn(move || race([first, stream], rng));
+            }
+            None => {
+                if send(&mut stream, &Message::Wait).is_ok() {
+                    waiting = Some(stream);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Whether the other end of `stream` is still there, found out without
+/// waiting or taking anything it sent.
+fn is_connected(stream: &TcpStream) -> bool {
+    if stream.set_nonblocking(true).is_err() {
+        return false;
+    }
+    let connected = match stream.peek(&mut [0]) {
+        Ok(0) => false,
+        Ok(_) => true,
+        Err(e) => e.kind() == io::ErrorKind::WouldBlock,
+    };
+    stream.set_nonblocking(false).is_ok() && connected
+}
+
+/// Referees one pair of players until either leaves, then disconnects
+/// both, as the threads reading from them hold the connections open.
+fn race(mut players: [TcpStream; 2], rng: Rng) {
+    referee(&mut players, rng);
+    for player in &players {
+        let _ = player.shutdown(Shutdown::Both);
+    }
+}
+
+fn referee(players: &mut [TcpStream; 2], mut rng: Rng) {
+    let (sender, messages) = mpsc::channel();
+    for (side, player) in players.iter().enumerate() {
+        if listen(player, side, sender.clone()).is_err() {
+            return;
+        }
+    }
+    let mut start = |players: &mut [TcpStream; 2]| {
+        let start = Message::Start {
+            seed: rng.next_u64(),
+            moves: MOVES,
+        };
+        players
+            .iter_mut()
+            .all(|player| send(player, &start).is_ok())
+    };
+    if !start(players) {
+        return;
+    }
+    let mut ready = [false; 2];
+    while let Ok((side, message)) = messages.recv() {
+        let other = 1 - side;
+        match message {
+            Message::Hello(version) if version != VERSION => {
+                let _ = send(&mut players[other], &Message::Left);
+                return;
+            }
+            Message::Progress { moves, solved } => {
+                let _ = send(&mut players[other], &Message::Opponent { moves, solved });
+            }
+            Message::Ready => {
+                ready[side] = true;
+                if ready == [true, true] {
+                    ready = [false, false];
+                    if !start(players) {
+                        return;
+                    }
+                }
+            }
+            Message::Left => {
+                let _ = send(&mut players[other], &Message::Left);
+                return;
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use crate::toast;
+use unblock::rng::Rng;
+
+/// Decides which level follows the current one.
+pub enum Order {
+    Sequential,
+    /// Plays the uncompleted levels in a random order. `queue` holds the
+    /// levels still to be played this round, `history` the ones already
+    /// visited so that previous can retrace them.
+    Shuffled {
+        rng: Rng,
+        queue: Vec<usize>,
+        history: Vec<usize>,
+    },
+}
+
+impl Order {
+    pub fn shuffled(rng: Rng) -> Order {
+        Order::Shuffled {
+            rng,
+            queue: Vec::new(),
+            history: Vec::new(),
+        }
+    }
+
+    pub fn is_shuffled(&self) -> bool {
+        match self {
+            Order::Sequential => false,
+            Order::Shuffled { .. } => true,
+        }
+    }
+
+    /// Picks the level to play after `current`. `completed` is updated when
+    /// a shuffled round exhausts the pack and a new one begins.
+    pub fn next(&mut self, current: usize, completed: &mut [bool]) -> Option<usize> {
+        match self {
+            Order::Sequential => {
+                if current + 1 < completed.len() {
+                    Some(current + 1)
+                } else {
+                    None
+                }
+            }
+            Order::Shuffled {
+                rng,
+                queue,
+                history,
+            } => loop {
+                while let Some(next) = queue.pop() {
+                    if !completed[next] {
+                        history.push(current);
+                        return Some(next);
+                    }
+                }
+                let mut remaining = unplayed(current, completed);
+                if remaining.is_empty() {
+                    toast::show("Pack complete! Starting a new round");
+                    for done in completed.iter_mut() {
+                        *done = false;
+                    }
+                    remaining = unplayed(current, completed);
+                    if remaining.is_empty() {
+                        return None;
+                    }
+                }
+                rng.shuffle(&mut remaining);
+                *queue = remaining;
+            },
+        }
+    }
+
+    pub fn previous(&mut self, current: usize) -> Option<usize> {
+        match self {
+            Order::Sequential => {
+                if current > 0 {
+                    Some(current - 1)
+                } else {
+                    None
+                }
+            }
+            Order::Shuffled { queue, history, .. } => {
+                let previous = history.pop()?;
+                queue.push(current);
+                Some(previous)
+            }
+        }
+    }
+}
+
+fn unplayed(current: usize, completed: &[bool]) -> Vec<usize> {
+    (0..completed.len())
+        .filter(|&i| i != current && !completed[i])
+        .collect()
+}
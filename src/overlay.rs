@@ -0,0 +1,62 @@
+use crate::render::{Color, Place, Point, Renderer};
+use crate::text;
+use crate::timing;
+use crate::Board;
+
+/// Width of the telemetry panel to the right of the board.
+pub const WIDTH: f32 = 320.0;
+
+const GREY: Color = Color {
+    r: 0.6,
+    g: 0.6,
+    b: 0.6,
+    a: 1.0,
+};
+
+/// Streaming layout: large, readable telemetry beside the board, including
+/// how many moves a perfect player would still need (`remaining`, None while
+/// that is being worked out).
+pub fn draw(
+    title: &str,
+    board: &Board,
+    remaining: Option<Option<usize>>,
+    renderer: &mut impl Renderer,
+) {
+    let x = renderer.width() - WIDTH + 20.0;
+    let rows = [
+        ("Moves", board.level.moves.len().to_string()),
+        ("Cells", board.level.cells_moved().to_string()),
+        (
+            "Par",
+            board
+                .level
+                .par
+                .map_or(String::from("-"), |par| par.to_string()),
+        ),
+        ("Time", timing::format(board.timer.elapsed())),
+        (
+            "Optimal left",
+            match remaining {
+                Some(Some(left)) => left.to_string(),
+                Some(None) => String::from("Unsolvable"),
+                None => String::from("..."),
+            },
+        ),
+    ];
+    renderer.draw_text(
+        title,
+        Place::At(Point::new(x, 30.0)),
+        text::MEDIUM,
+        Color::WHITE,
+    );
+    for (i, (label, value)) in rows.iter().enumerate() {
+        let y = 80.0 + i as f32 * 90.0;
+        renderer.draw_text(label, Place::At(Point::new(x, y)), text::SMALL, GREY);
+        renderer.draw_text(
+            value,
+            Place::At(Point::new(x, y + 22.0)),
+            text::LARGE,
+            Color::WHITE,
+        );
+    }
+}
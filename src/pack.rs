@@ -0,0 +1,366 @@
+use crate::level::{Level, TILES_HIGH, TILES_WIDE};
+use crate::prompt::Prompt;
+use crate::replay::Replay;
+use crate::win::WinCondition;
+use rayon::prelude::*;
+use std::collections::hash_map::{Entry as HashEntry, HashMap};
+use std::mem;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The levels that ship with the game.
+pub const BUILTIN: &[u8] = include_bytes!("../levels.dat");
+
+/// A `#` comment line, with the byte range it covers including its newline.
+pub struct Comment {
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+}
+
+/// A level as it appears in a pack file: the comments right before it and
+/// where its rows start and end.
+pub struct Entry {
+    pub level: Level,
+    pub start: usize,
+    pub end: usize,
+    pub comments: Vec<Comment>,
+}
+
+impl Entry {
+    /// The comment holding `key: value` metadata for `key`, if any.
+    pub fn metadata(&self, key: &str) -> Option<(&Comment, &str)> {
+        self.comments.iter().find_map(|comment| {
+            let (k, value) = comment.text.split_once(':')?;
+            if k.trim() == key {
+                Some((comment, value.trim()))
+            } else {
+                None
+            }
+        })
+    }
+}
+
+/// Reads every level in a pack: 8x8 grids of level characters, separated by
+/// blank lines and optionally preceded by `#` comment lines. A `# par: N`
+/// comment gives the fewest moves the level takes, `# stars: A B` the most
+/// moves for three stars and for two, `# name:` what it is called, and a
+/// `# goal:` comment what solves it (see `WinCondition`). A
+/// `# phases: N` comment makes the level N grids long, each after the first
+/// giving the walls and exit of the next phase, and `# gravity: on` makes
+/// vertical blocks fall after every move. A `# replay:` comment gives a
+/// walkthrough, a replay as save files keep them, notes and all, that the
+/// demo plays instead of the solver's solution (left out if any of its
+/// moves cannot be made), and each `# prompt:` comment something to tell
+/// the player (see `Prompt`). Comments before the first level can also
+/// describe the pack (see `info`).
+pub fn parse(data: &[u8]) -> Vec<Level> {
+    entries(data).into_iter().map(|entry| entry.level).collect()
+}
+
+/// Like `parse`, but keeps track of where everything is in the file.
+pub fn entries(data: &[u8]) -> Vec<Entry> {
+    let mut entries = Vec::new();
+    let mut comments = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        let b = data[pos];
+        // Allow comment lines before levels.
+        if b == b'#' {
+            let end = data[pos..]
+                .iter()
+                .position(|&b| b == b'\n')
+                .map_or(data.len(), |n| pos + n + 1);
+            comments.push(Comment {
+                start: pos,
+                end,
+                text: String::from_utf8_lossy(&data[pos + 1..end])
+                    .trim()
+                    .to_string(),
+            });
+            pos = end;
+            continue;
+        }
+        // Skip lines with just whitespace.
+        if b == b' ' || b == b'\r' || b == b'\n' {
+            pos += 1;
+            continue;
+        }
+        // A grid not finished by the end of the file is left out.
+        if !has_frame(&data[pos..]) {
+            break;
+        }
+        // Load level data.
+        let mut rest = data[pos..].iter().copied();
+        let mut entry = Entry {
+            level: Level::from(&mut rest),
+            start: pos,
+            end: pos,
+            comments: mem::take(&mut comments),
+        };
+        let phases = entry
+            .metadata("phases")
+            .and_then(|(_, value)| value.parse().ok())
+            .unwrap_or(1);
+        for _ in 1..phases {
+            if !has_frame(&data[data.len() - rest.len()..]) {
+                break;
+            }
+            entry.level.parse_phase(&mut rest);
+        }
+        entry.level.par = entry
+            .metadata("par")
+            .and_then(|(_, value)| value.parse().ok());
+        entry.level.stars = entry.metadata("stars").and_then(|(_, value)| {
+            let mut moves = value.split_whitespace().map(|n| n.parse().ok());
+            Some((moves.next()??, moves.next()??))
+        });
+        entry.level.name = entry.metadata("name").map(|(_, value)| value.to_string());
+        entry.level.prompts = entry
+            .comments
+            .iter()
+            .filter_map(|comment| {
+                let (key, value) = comment.text.split_once(':')?;
+                if key.trim() == "prompt" {
+                    Prompt::parse(value, &entry.level)
+                } else {
+                    None
+                }
+            })
+            .collect();
+        let walkthrough = entry
+            .metadata("replay")
+            .and_then(|(_, value)| Replay::decode(value))
+            .filter(|walkthrough| walkthrough.fits(&entry.level));
+        entry.level.walkthrough = walkthrough;
+        if let Some(win) = entry
+            .metadata("goal")
+            .and_then(|(_, value)| WinCondition::parse(value, &entry.level))
+        {
+            entry.level.win = win;
+        }
+        if entry.metadata("gravity").map(|(_, value)| value) == Some("on") {
+            entry.level.set_gravity(true);
+        }
+        pos = data.len() - rest.len();
+        entry.end = pos;
+        entries.push(entry);
+    }
+    entries
+}
+
+/// Whether `data` holds the 64 cells of another grid, not counting the
+/// spaces and line breaks between them.
+fn has_frame(data: &[u8]) -> bool {
+    data.iter()
+        .filter(|&&b| b != b' ' && b != b'\r' && b != b'\n')
+        .take(64)
+        .count()
+        == 64
+}
+
+/// Metadata keys that describe the whole pack rather than a level.
+const PACK_KEYS: [&str; 3] = ["title", "author", "description"];
+
+/// What a pack says about itself, in `# title:`, `# author:` and
+/// `# description:` comments before its first level.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Info {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub description: Option<String>,
+}
+
+pub fn info(data: &[u8]) -> Info {
+    let entries = entries(data);
+    let value = |key: &str| {
+        let (_, value) = entries.first()?.metadata(key)?;
+        Some(value.to_string())
+    };
+    Info {
+        title: value("title"),
+        author: value("author"),
+        description: value("description"),
+    }
+}
+
+/// Rewrites a pack with `info` at the top and its levels in `order`, which
+/// holds each level's index in the file. Levels keep their own comments,
+/// and anything between levels that is neither is dropped.
+pub fn rewrite(data: &[u8], info: &Info, order: &[usize]) -> Vec<u8> {
+    let newline: &[u8] = if data.windows(2).any(|w| w == b"\r\n") {
+        b"\r\n"
+    } else {
+        b"\n"
+    };
+    let entries = entries(data);
+    let mut out = Vec::with_capacity(data.len());
+    let fields = [
+        ("title", &info.title),
+        ("author", &info.author),
+        ("description", &info.description),
+    ];
+    for (key, value) in fields.iter() {
+        if let Some(value) = value {
+            out.extend_from_slice(format!("# {}: {}", key, value).as_bytes());
+            out.extend_from_slice(newline);
+        }
+    }
+    for &index in order {
+        let entry = match entries.get(index) {
+            Some(entry) => entry,
+            None => continue,
+        };
+        if !out.is_empty() {
+            out.extend_from_slice(newline);
+        }
+        for comment in &entry.comments {
+            // The pack's own metadata is written above.
+            let key = comment.text.split_once(':').map(|(key, _)| key.trim());
+            if index > 0 || !key.is_some_and(|key| PACK_KEYS.contains(&key)) {
+                out.extend_from_slice(data[comment.start..comment.end].trim_ascii_end());
+                out.extend_from_slice(newline);
+            }
+        }
+        out.extend_from_slice(data[entry.start..entry.end].trim_ascii_end());
+        out.extend_from_slice(newline);
+    }
+    out
+}
+
+/// Pairs of levels, by index, that are the same puzzle up to reflection,
+/// rotation and which characters the blocks are drawn with. The second of
+/// each pair duplicates the first.
+pub fn duplicates(levels: &[Level]) -> Vec<(usize, usize)> {
+    let mut seen = HashMap::new();
+    let mut duplicates = Vec::new();
+    for (index, level) in levels.iter().enumerate() {
+        match seen.entry(level.canonical_hash()) {
+            HashEntry::Occupied(first) => duplicates.push((*first.get(), index)),
+            HashEntry::Vacant(entry) => {
+                entry.insert(index);
+            }
+        }
+    }
+    duplicates
+}
+
+/// Runs `work` on every level across all cores, results in level order.
+/// `progress` hears how many levels are done after each one finishes, from
+/// whichever thread finished it.
+pub fn analyze<T, W, P>(levels: &[Level], work: W, progress: P) -> Vec<T>
+where
+    T: Send,
+    W: Fn(&Level) -> T + Sync,
+    P: Fn(usize) + Sync,
+{
+    let done = AtomicUsize::new(0);
+    levels
+        .par_iter()
+        .map(|level| {
+            let result = work(level);
+            progress(done.fetch_add(1, Ordering::Relaxed) + 1);
+            result
+        })
+        .collect()
+}
+
+/// Rewrites a pack with a `# par: N` comment before each level, `pars`
+/// holding N for each level in order. Existing par comments are updated,
+/// levels without a par are left alone, and every other byte is kept.
+pub fn annotate(data: &[u8], pars: &[Option<usize>]) -> Vec<u8> {
+    let values: Vec<Option<String>> = pars
+        .iter()
+        .map(|par| par.map(|par| par.to_string()))
+        .collect();
+    set_metadata(data, "par", &values)
+}
+
+/// Rewrites a pack with a `# stars: A B` comment before each level, like
+/// `annotate` does par comments.
+pub fn set_stars(data: &[u8], stars: &[Option<(usize, usize)>]) -> Vec<u8> {
+    let values: Vec<Option<String>> = stars
+        .iter()
+        .map(|stars| stars.map(|(three, two)| format!("{} {}", three, two)))
+        .collect();
+    set_metadata(data, "stars", &values)
+}
+
+/// Rewrites the first grid of the level at `index` as `grid`, cell for
+/// cell, keeping its layout and every other byte of the pack.
+pub fn set_grid(data: &[u8], index: usize, grid: &[u8; TILES_WIDE * TILES_HIGH]) -> Vec<u8> {
+    let mut out = data.to_vec();
+    if let Some(entry) = entries(data).get(index) {
+        let cells = (entry.start..entry.end)
+            .filter(|&pos| !matches!(data[pos], b' ' | b'\r' | b'\n'))
+            .take(grid.len());
+        for (pos, &cell) in cells.zip(grid) {
+            out[pos] = cell;
+        }
+    }
+    out
+}
+
+/// Adds `grid` to the end of a pack as a level of its own.
+pub fn push_level(data: &[u8], grid: &[u8; TILES_WIDE * TILES_HIGH]) -> Vec<u8> {
+    let newline: &[u8] = if data.windows(2).any(|w| w == b"\r\n") {
+        b"\r\n"
+    } else {
+        b"\n"
+    };
+    let mut out = data.trim_ascii_end().to_vec();
+    if !out.is_empty() {
+        out.extend_from_slice(newline);
+        out.extend_from_slice(newline);
+    }
+    for row in grid.chunks(TILES_WIDE) {
+        out.extend_from_slice(row);
+        out.extend_from_slice(newline);
+    }
+    out
+}
+
+/// Puts the levels of the pack `other` into `data` before the level at
+/// `index`, or at the end if there is no such level. Both packs are
+/// rewritten as `rewrite` does, keeping the title and author of `data`.
+pub fn insert(data: &[u8], index: usize, other: &[u8]) -> Vec<u8> {
+    let count = entries(data).len();
+    let added = entries(other).len();
+    let mut both = data.trim_ascii_end().to_vec();
+    both.extend_from_slice(b"\n\n");
+    both.extend_from_slice(other);
+    let index = index.min(count);
+    let order: Vec<usize> = (0..index)
+        .chain(count..count + added)
+        .chain(index..count)
+        .collect();
+    rewrite(&both, &info(data), &order)
+}
+
+/// Gives each level the `# key: value` comment in `values`, in level order,
+/// replacing the one it has or adding one just before it. Levels without a
+/// value are left alone, and every other byte is kept.
+fn set_metadata(data: &[u8], key: &str, values: &[Option<String>]) -> Vec<u8> {
+    let newline: &[u8] = if data.windows(2).any(|w| w == b"\r\n") {
+        b"\r\n"
+    } else {
+        b"\n"
+    };
+    let mut out = Vec::with_capacity(data.len());
+    let mut copied = 0;
+    for (entry, value) in entries(data).iter().zip(values) {
+        let value = match value {
+            Some(value) => value,
+            None => continue,
+        };
+        let (start, end) = match entry.metadata(key) {
+            Some((comment, _)) => (comment.start, comment.end),
+            None => (entry.start, entry.start),
+        };
+        out.extend_from_slice(&data[copied..start]);
+        out.extend_from_slice(format!("# {}: {}", key, value).as_bytes());
+        out.extend_from_slice(newline);
+        copied = end;
+    }
+    out.extend_from_slice(&data[copied..]);
+    out
+}
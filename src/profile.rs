@@ -0,0 +1,148 @@
+use crate::render::{Color, Place, Point, Rectangle, Renderer};
+use crate::text;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+// Frames kept, about two seconds' worth at 60 a second.
+const SAMPLES: usize = 120;
+const WIDTH: f32 = 260.0;
+const LINE: f32 = 18.0;
+const GRAPH: f32 = 50.0;
+// Frame time at the top of the graph, twice a 60th of a second.
+const SCALE: f32 = 1000.0 / 30.0;
+
+const PANEL: Color = Color {
+    r: 0.0,
+    g: 0.0,
+    b: 0.0,
+    a: 0.7,
+};
+const BAR: Color = Color {
+    r: 0.4,
+    g: 0.8,
+    b: 0.4,
+    a: 1.0,
+};
+const SLOW: Color = Color {
+    r: 0.9,
+    g: 0.3,
+    b: 0.3,
+    a: 1.0,
+};
+
+/// Recent timings, shown over the game with F3: the time between frames,
+/// spent updating and spent solving positions in the background, and how
+/// many draw calls a frame takes, with a graph of the frame times.
+#[derive(Default)]
+pub struct Profile {
+    open: bool,
+    last_frame: Option<Instant>,
+    frames: VecDeque<Duration>,
+    updates: VecDeque<Duration>,
+    solves: VecDeque<Duration>,
+    draws: usize,
+}
+
+impl Profile {
+    pub fn new() -> Profile {
+        Profile::default()
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    /// Notes a frame starting to be drawn.
+    pub fn frame(&mut self) {
+        let now = Instant::now();
+        if let Some(last) = self.last_frame {
+            push(&mut self.frames, now - last);
+        }
+        self.last_frame = Some(now);
+    }
+
+    pub fn update(&mut self, took: Duration) {
+        push(&mut self.updates, took);
+    }
+
+    pub fn solve(&mut self, took: Duration) {
+        push(&mut self.solves, took);
+    }
+
+    /// Notes the draw calls the last frame took.
+    pub fn draws(&mut self, draws: usize) {
+        self.draws = draws;
+    }
+
+    pub fn draw(&self, renderer: &mut impl Renderer) {
+        if !self.open {
+            return;
+        }
+        let lines = [
+            format!("frame  {}", summary(&self.frames)),
+            format!("update {}", summary(&self.updates)),
+            format!("solve  {}", summary(&self.solves)),
+            format!("draws  {} a frame", self.draws),
+        ];
+        let x = renderer.width() - WIDTH - 10.0;
+        let area = Rectangle {
+            x,
+            y: 10.0,
+            width: WIDTH,
+            height: lines.len() as f32 * LINE + GRAPH + 20.0,
+        };
+        renderer.draw_rect(area, PANEL, None);
+        for (row, line) in lines.iter().enumerate() {
+            renderer.draw_text(
+                line,
+                Place::At(Point::new(x + 5.0, 15.0 + row as f32 * LINE)),
+                text::SMALL,
+                Color::WHITE,
+            );
+        }
+        let bottom = area.y + area.height - 5.0;
+        let width = (WIDTH - 10.0) / SAMPLES as f32;
+        for (i, frame) in self.frames.iter().enumerate() {
+            let ms = frame.as_secs_f32() * 1000.0;
+            let height = (ms / SCALE).min(1.0) * GRAPH;
+            renderer.draw_rect(
+                Rectangle {
+                    x: x + 5.0 + i as f32 * width,
+                    y: bottom - height,
+                    width,
+                    height,
+                },
+                if ms > SCALE / 2.0 { SLOW } else { BAR },
+                None,
+            );
+        }
+        // A 60th of a second.
+        let y = bottom - GRAPH / 2.0;
+        renderer.draw_line(
+            vec![Point::new(x + 5.0, y), Point::new(x + WIDTH - 5.0, y)],
+            Color::WHITE,
+            1,
+        );
+    }
+}
+
+fn push(samples: &mut VecDeque<Duration>, sample: Duration) {
+    if samples.len() == SAMPLES {
+        samples.pop_front();
+    }
+    samples.push_back(sample);
+}
+
+/// "4.2 ms, 9.8 at worst", over the samples kept.
+fn summary(samples: &VecDeque<Duration>) -> String {
+    if samples.is_empty() {
+        return String::from("-");
+    }
+    let total: Duration = samples.iter().sum();
+    let average = total.as_secs_f32() * 1000.0 / samples.len() as f32;
+    let worst = samples
+        .iter()
+        .max()
+        .map_or(0.0, |w| w.as_secs_f32() * 1000.0);
+    format!("{:.1} ms, {:.1} at worst", average, worst)
+}
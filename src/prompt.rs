@@ -0,0 +1,59 @@
+use crate::level::{BlockId, Level, Move};
+use crate::win;
+
+/// Text a tutorial pack shows while a level is played, from a
+/// `# prompt:` comment: `Drag the red block` shows from the start, and
+/// `c2 to e5: Now it can get out` once the block covering c2 has its top
+/// left corner moved onto e5.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Prompt {
+    /// The block and the cell that set the prompt off, None for one shown
+    /// from the start.
+    pub trigger: Option<(BlockId, (usize, usize))>,
+    pub text: String,
+}
+
+impl Prompt {
+    /// Reads a prompt comment for `level`. None if its trigger makes no
+    /// sense, such as moving a block that is not there.
+    pub fn parse(text: &str, level: &Level) -> Option<Prompt> {
+        if let Some((trigger, rest)) = text.split_once(':') {
+            if let [from, "to", to] = trigger.split_whitespace().collect::<Vec<_>>()[..] {
+                let (x, y) = win::cell(from)?;
+                return Some(Prompt {
+                    trigger: Some((level.block_at(x, y)?, win::cell(to)?)),
+                    text: rest.trim().to_string(),
+                });
+            }
+        }
+        Some(Prompt {
+            trigger: None,
+            text: text.trim().to_string(),
+        })
+    }
+
+    fn set_off_by(&self, m: &Move) -> bool {
+        self.trigger == Some((m.block, m.to))
+    }
+}
+
+/// The prompt to show on `level` as it stands: the last one set off by
+/// the moves made to get there, blocks that fell included, or else the
+/// first one shown from the start. Undoing a move takes back what it set
+/// off.
+pub fn current(level: &Level) -> Option<&Prompt> {
+    let mut shown = level.prompts.iter().find(|prompt| prompt.trigger.is_none());
+    for m in &level.moves {
+        for m in Some(m).into_iter().chain(&m.fell) {
+            if let Some(prompt) = level
+                .prompts
+                .iter()
+                .rev()
+                .find(|prompt| prompt.set_off_by(m))
+            {
+                shown = Some(prompt);
+            }
+        }
+    }
+    shown
+}
@@ -0,0 +1,309 @@
+//! What the game needs from a graphics backend, and the coffee backend that
+//! provides it.
+//!
+//! Everything the game draws goes through `Renderer`, so a second backend
+//! only has to implement it and replace `Screen` below.
+
+use crate::i18n;
+use crate::text::Writer;
+pub use coffee::graphics::{Color, Point, Rectangle};
+use coffee::graphics::{Frame, Mesh, Shape};
+
+/// Where `Renderer::draw_text` puts its text.
+pub enum Place {
+    /// Top left corner at a point.
+    At(Point),
+    /// Top right corner at a point.
+    AtRight(Point),
+    /// Centered in an area, wrapped to its width.
+    Centered(Rectangle<f32>),
+}
+
+/// Shapes recorded once and drawn as a unit, like the blocks of a board.
+#[derive(Clone, Default)]
+pub struct Sketch {
+    shapes: Vec<Outline>,
+}
+
+#[derive(Clone)]
+enum Outline {
+    Fill(Rectangle<f32>, Color),
+    Stroke(Rectangle<f32>, Color, u16),
+    FillPolygon(Vec<Point>, Color),
+    Line(Vec<Point>, Color, u16),
+}
+
+impl Sketch {
+    pub fn new() -> Sketch {
+        Sketch::default()
+    }
+
+    pub fn fill(&mut self, area: Rectangle<f32>, color: Color) {
+        self.shapes.push(Outline::Fill(area, color));
+    }
+
+    pub fn stroke(&mut self, area: Rectangle<f32>, color: Color, width: u16) {
+        self.shapes.push(Outline::Stroke(area, color, width));
+    }
+
+    /// Fills the polygon with corners `points`, like `rounded` makes.
+    pub fn fill_polygon(&mut self, points: Vec<Point>, color: Color) {
+        self.shapes.push(Outline::FillPolygon(points, color));
+    }
+
+    /// Connected line segments through `points`.
+    pub fn line(&mut self, points: Vec<Point>, color: Color, width: u16) {
+        self.shapes.push(Outline::Line(points, color, width));
+    }
+
+    /// Draws the shapes one by one, for backends without anything better
+    /// to turn a sketch into.
+    pub fn replay(&self, renderer: &mut impl Renderer) {
+        for shape in &self.shapes {
+            match shape {
+                Outline::Fill(area, color) => renderer.draw_rect(*area, *color, None),
+                Outline::Stroke(area, color, width) => {
+                    renderer.draw_line(corners(*area), *color, *width)
+                }
+                Outline::FillPolygon(points, color) => {
+                    renderer.draw_polygon(points.clone(), *color)
+                }
+                Outline::Line(points, color, width) => {
+                    renderer.draw_line(points.clone(), *color, *width)
+                }
+            }
+        }
+    }
+}
+
+/// The way round the edge of `area`, back to where it starts.
+pub fn corners(area: Rectangle<f32>) -> Vec<Point> {
+    let Rectangle {
+        x,
+        y,
+        width,
+        height,
+    } = area;
+    vec![
+        Point::new(x, y),
+        Point::new(x + width, y),
+        Point::new(x + width, y + height),
+        Point::new(x, y + height),
+        Point::new(x, y),
+    ]
+}
+
+/// The way round the edge of `area` with its corners rounded off to
+/// `radius`, each corner a few straight segments, back to where it starts.
+pub fn rounded(area: Rectangle<f32>, radius: f32) -> Vec<Point> {
+    const SEGMENTS: usize = 4;
+    let radius = radius.min(area.width / 2.0).min(area.height / 2.0);
+    let (left, top) = (area.x + radius, area.y + radius);
+    let (right, bottom) = (area.x + area.width - radius, area.y + area.height - radius);
+    // Clockwise from the top left, each corner's centre and the angle its
+    // arc starts at.
+    let corners = [
+        (left, top, 180.0_f32),
+        (right, top, 270.0),
+        (right, bottom, 0.0),
+        (left, bottom, 90.0),
+    ];
+    let mut points = Vec::with_capacity(4 * (SEGMENTS + 1) + 1);
+    for (x, y, start) in corners {
+        for step in 0..=SEGMENTS {
+            let angle = (start + 90.0 * step as f32 / SEGMENTS as f32).to_radians();
+            points.push(Point::new(
+                x + radius * angle.cos(),
+                y + radius * angle.sin(),
+            ));
+        }
+    }
+    points.push(points[0]);
+    points
+}
+
+/// Where a line of text starting a row goes: at `left` for languages written
+/// left to right, and ending at `right` for those written right to left.
+pub fn leading(left: f32, right: f32, y: f32) -> Place {
+    if i18n::right_to_left() {
+        Place::AtRight(Point::new(right, y))
+    } else {
+        Place::At(Point::new(left, y))
+    }
+}
+
+/// Whether `point` is in `area`, edges included.
+pub fn contains(area: &Rectangle<f32>, point: Point) -> bool {
+    area.x <= point.x
+        && point.x <= area.x + area.width
+        && area.y <= point.y
+        && point.y <= area.y + area.height
+}
+
+/// A surface the game draws one frame on. Things are drawn in the order
+/// they are asked for, later ones on top, and are only guaranteed to be
+/// visible once `present` is called.
+pub trait Renderer {
+    /// A `Sketch` prepared for drawing, kept between frames.
+    type Sprite;
+
+    fn width(&self) -> f32;
+
+    fn height(&self) -> f32;
+
+    /// Fills the whole surface with `color`, discarding what was drawn.
+    fn clear(&mut self, color: Color);
+
+    /// A filled rectangle, with an outline `border` pixels wide if given.
+    fn draw_rect(&mut self, area: Rectangle<f32>, fill: Color, border: Option<(Color, u16)>);
+
+    /// Connected line segments through `points`.
+    fn draw_line(&mut self, points: Vec<Point>, color: Color, width: u16);
+
+    /// The inside of the polygon with corners `points`, filled.
+    fn draw_polygon(&mut self, points: Vec<Point>, fill: Color);
+
+    fn sprite(&mut self, sketch: &Sketch) -> Self::Sprite;
+
+    fn draw_sprite(&mut self, sprite: &Self::Sprite);
+
+    fn draw_text(&mut self, content: &str, place: Place, size: f32, color: Color);
+
+    /// Width and height `content` takes up at `size`.
+    fn measure_text(&mut self, content: &str, size: f32) -> (f32, f32);
+
+    /// Puts everything drawn so far on the surface.
+    fn present(&mut self);
+}
+
+/// The sprite type of the backend the game is built with.
+pub type Sprite = <Screen<'static, 'static> as Renderer>::Sprite;
+
+/// The coffee backend: shapes are batched into a mesh and text into the
+/// writer's font, and each batch is flushed when the other kind of thing
+/// is drawn so the order is kept.
+pub struct Screen<'a, 'f> {
+    frame: &'a mut Frame<'f>,
+    writer: Writer,
+    mesh: Mesh,
+    text: bool,
+    draws: usize,
+}
+
+impl<'a, 'f> Screen<'a, 'f> {
+    pub fn new(frame: &'a mut Frame<'f>, writer: Writer) -> Screen<'a, 'f> {
+        Screen {
+            frame,
+            writer,
+            mesh: Mesh::new(),
+            text: false,
+            draws: 0,
+        }
+    }
+
+    /// Presents the frame and hands back the writer for the next one, with
+    /// how many draw calls the frame took.
+    pub fn finish(mut self) -> (Writer, usize) {
+        self.present();
+        (self.writer, self.draws)
+    }
+
+    fn flush_text(&mut self) {
+        if self.text {
+            self.present();
+        }
+    }
+}
+
+impl Renderer for Screen<'_, '_> {
+    type Sprite = Mesh;
+
+    fn width(&self) -> f32 {
+        self.frame.width()
+    }
+
+    fn height(&self) -> f32 {
+        self.frame.height()
+    }
+
+    fn clear(&mut self, color: Color) {
+        self.present();
+        self.frame.clear(color);
+    }
+
+    fn draw_rect(&mut self, area: Rectangle<f32>, fill: Color, border: Option<(Color, u16)>) {
+        self.flush_text();
+        self.mesh.fill(Shape::Rectangle(area), fill);
+        if let Some((color, width)) = border {
+            self.mesh.stroke(Shape::Rectangle(area), color, width);
+        }
+    }
+
+    fn draw_line(&mut self, points: Vec<Point>, color: Color, width: u16) {
+        self.flush_text();
+        self.mesh.stroke(Shape::Polyline { points }, color, width);
+    }
+
+    fn draw_polygon(&mut self, points: Vec<Point>, fill: Color) {
+        self.flush_text();
+        self.mesh.fill(Shape::Polyline { points }, fill);
+    }
+
+    fn sprite(&mut self, sketch: &Sketch) -> Mesh {
+        let mut mesh = Mesh::new();
+        for shape in &sketch.shapes {
+            match shape {
+                Outline::Fill(area, color) => mesh.fill(Shape::Rectangle(*area), *color),
+                Outline::Stroke(area, color, width) => {
+                    mesh.stroke(Shape::Rectangle(*area), *color, *width)
+                }
+                Outline::FillPolygon(points, color) => mesh.fill(
+                    Shape::Polyline {
+                        points: points.clone(),
+                    },
+                    *color,
+                ),
+                Outline::Line(points, color, width) => mesh.stroke(
+                    Shape::Polyline {
+                        points: points.clone(),
+                    },
+                    *color,
+                    *width,
+                ),
+            }
+        }
+        mesh
+    }
+
+    fn draw_sprite(&mut self, sprite: &Mesh) {
+        self.present();
+        sprite.draw(&mut self.frame.as_target());
+        self.draws += 1;
+    }
+
+    fn draw_text(&mut self, content: &str, place: Place, size: f32, color: Color) {
+        match place {
+            Place::At(position) => self.writer.at(content, position, size, color),
+            Place::AtRight(position) => self.writer.at_right(content, position, size, color),
+            Place::Centered(area) => self.writer.centered(content, area, size, color),
+        }
+        self.text = true;
+    }
+
+    fn measure_text(&mut self, content: &str, size: f32) -> (f32, f32) {
+        self.writer.measure(content, size)
+    }
+
+    fn present(&mut self) {
+        if !self.mesh.is_empty() {
+            self.mesh.draw(&mut self.frame.as_target());
+            self.mesh = Mesh::new();
+            self.draws += 1;
+        }
+        if self.text {
+            self.writer.draw(self.frame);
+            self.text = false;
+            self.draws += 1;
+        }
+    }
+}
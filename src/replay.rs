@@ -0,0 +1,157 @@
+use crate::level::Level;
+use crate::solver::Step;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// The moves of one attempt at a level and when each was made, counted
+/// from the start of the attempt. Undos are recorded as the move they make,
+/// and blocks falling under gravity as moves of their own, so playing the
+/// steps back in order always ends where the attempt did.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Replay {
+    pub steps: Vec<(Duration, Step)>,
+    /// Commentary on the steps it is keyed by, shown once they are played,
+    /// to explain a solution.
+    pub notes: BTreeMap<usize, String>,
+}
+
+impl Replay {
+    pub fn new() -> Replay {
+        Replay::default()
+    }
+
+    /// Notes that `block` was just moved to `(x, y)`, numbered as it was
+    /// when it moved (see `Level::last_placed`).
+    pub fn record(&mut self, at: Duration, block: usize, (x, y): (usize, usize)) {
+        self.steps.push((at, Step { block, x, y }));
+    }
+
+    /// Notes `text` on the step at `index`, or takes its note away if
+    /// `text` is blank.
+    pub fn annotate(&mut self, index: usize, text: &str) {
+        let text = text.trim();
+        if text.is_empty() {
+            self.notes.remove(&index);
+        } else {
+            self.notes.insert(index, text.to_string());
+        }
+    }
+
+    /// The note to show once `count` steps have been played: the one on the
+    /// last step played, if it has one.
+    pub fn note(&self, count: usize) -> Option<&str> {
+        let index = count.checked_sub(1)?;
+        self.notes.get(&index).map(String::as_str)
+    }
+
+    /// How long the attempt took, up to its last move.
+    pub fn duration(&self) -> Duration {
+        self.steps
+            .last()
+            .map_or(Duration::from_secs(0), |(at, _)| *at)
+    }
+
+    /// How many steps had been made `at` into the attempt.
+    pub fn steps_at(&self, at: Duration) -> usize {
+        self.steps
+            .iter()
+            .take_while(|(time, _)| *time <= at)
+            .count()
+    }
+
+    /// `start` with the first `count` steps played on it.
+    pub fn play(&self, start: &Level, count: usize) -> Level {
+        let mut level = start.clone();
+        level.reset();
+        // Blocks that fell were recorded as steps of their own.
+        level.set_gravity(false);
+        for (_, step) in self.steps.iter().take(count) {
+            level.move_block(step.block, (step.x, step.y));
+        }
+        level
+    }
+
+    /// True if every step is a move the rules allow, played on `start` as
+    /// `play` plays them, so a replay read from a file cannot break the
+    /// level.
+    pub fn fits(&self, start: &Level) -> bool {
+        let mut level = start.clone();
+        level.reset();
+        level.set_gravity(false);
+        self.steps.iter().all(|(_, step)| {
+            let legal = level.is_legal(step.block, (step.x, step.y));
+            if legal {
+                level.move_block(step.block, (step.x, step.y));
+            }
+            legal
+        })
+    }
+
+    /// Compact text form for save files: `millis:block,x,y` steps separated
+    /// by `;`, a step with a note followed by `:note`, its `%`, `;` and line
+    /// breaks escaped.
+    pub fn encode(&self) -> String {
+        let steps: Vec<String> = self
+            .steps
+            .iter()
+            .enumerate()
+            .map(|(i, (at, step))| {
+                let mut text = format!("{}:{},{},{}", at.as_millis(), step.block, step.x, step.y);
+                if let Some(note) = self.notes.get(&i) {
+                    text += ":";
+                    text += &escape(note);
+                }
+                text
+            })
+            .collect();
+        steps.join(";")
+    }
+
+    pub fn decode(text: &str) -> Option<Replay> {
+        let mut replay = Replay::new();
+        for step in text.split(';').filter(|step| !step.is_empty()) {
+            let (at, step) = step.split_once(':')?;
+            let (step, note) = match step.split_once(':') {
+                Some((step, note)) => (step, Some(unescape(note)?)),
+                None => (step, None),
+            };
+            let mut numbers = step.split(',').map(|n| n.parse::<usize>().ok());
+            let (block, x, y) = (numbers.next()??, numbers.next()??, numbers.next()??);
+            if let Some(note) = note {
+                replay.notes.insert(replay.steps.len(), note);
+            }
+            replay.steps.push((
+                Duration::from_millis(at.parse().ok()?),
+                Step { block, x, y },
+            ));
+        }
+        Some(replay)
+    }
+}
+
+fn escape(note: &str) -> String {
+    let mut text = String::new();
+    for c in note.chars() {
+        match c {
+            '%' => text += "%25",
+            ';' => text += "%3B",
+            '\n' => text += "%0A",
+            '\r' => text += "%0D",
+            c => text.push(c),
+        }
+    }
+    text
+}
+
+fn unescape(text: &str) -> Option<String> {
+    let mut note = String::new();
+    let mut rest = text;
+    while let Some(at) = rest.find('%') {
+        note += &rest[..at];
+        let code = u8::from_str_radix(rest.get(at + 1..at + 3)?, 16).ok()?;
+        note.push(Some(code).filter(u8::is_ascii)? as char);
+        rest = &rest[at + 3..];
+    }
+    note += rest;
+    Some(note)
+}
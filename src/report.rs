@@ -0,0 +1,73 @@
+//! Bug reports: the level being played, how it got where it is, the
+//! settings and what the game said lately, in one JSON file to attach to
+//! an issue.
+
+use crate::toast;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::Path;
+use unblock::level::{Level, TILES_WIDE};
+
+// Journal lines kept, from the end: enough for the last few levels.
+const JOURNAL_LINES: usize = 200;
+
+/// What went into the report besides the level: where it is in which pack,
+/// the `settings.` lines of the save file and the journal being recorded,
+/// if any.
+pub struct Context<'a> {
+    pub pack: &'a str,
+    pub index: usize,
+    pub settings: &'a str,
+    pub journal: Option<&'a Path>,
+}
+
+/// The report on `level`, as JSON.
+pub fn build(level: &Level, context: &Context<'_>) -> Value {
+    let rows = |text: &str| -> Vec<String> { text.lines().map(String::from).collect() };
+    let template: String = level
+        .template()
+        .chunks(TILES_WIDE)
+        .map(|row| format!("{}\n", String::from_utf8_lossy(row)))
+        .collect();
+    let journal = context.journal.map(|path| match fs::read_to_string(path) {
+        Ok(text) => {
+            let lines: Vec<&str> = text.lines().collect();
+            let start = lines.len().saturating_sub(JOURNAL_LINES);
+            Value::Array(
+                lines[start..]
+                    .iter()
+                    .map(|line| serde_json::from_str(line).unwrap_or_else(|_| json!(line)))
+                    .collect(),
+            )
+        }
+        Err(e) => json!(format!("Could not read {}: {}", path.display(), e)),
+    });
+    json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "pack": context.pack,
+        "level": {
+            "index": context.index,
+            "id": format!("{:016x}", level.id()),
+            "name": level.name,
+            "phase": level.phase,
+            "solved": level.solved,
+            "template": rows(&template),
+            "position": rows(&level.to_string_pretty()),
+            "cells": rows(&level.cells_pretty()),
+            "moves": level.history().encode(),
+        },
+        "settings": rows(context.settings),
+        "messages": toast::recent()
+            .into_iter()
+            .map(|(ago, text)| json!({ "ago": ago, "text": text }))
+            .collect::<Vec<Value>>(),
+        "journal": journal,
+    })
+}
+
+/// Writes the report on `level` to `path`.
+pub fn write(path: &Path, level: &Level, context: &Context<'_>) -> Result<(), String> {
+    let report = build(level, context);
+    let text = serde_json::to_string_pretty(&report).map_err(|e| e.to_string())?;
+    fs::write(path, text).map_err(|e| e.to_string())
+}
@@ -0,0 +1,66 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A small seeded random number generator (SplitMix64).
+///
+/// The sequence produced for a given seed never changes, so anything driven
+/// by it (generated levels, shuffled packs, visual jitter) can be reproduced
+/// by passing the same seed on the command line.
+pub struct Rng {
+    seed: u64,
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng { seed, state: seed }
+    }
+
+    /// Seed shared by everyone playing on the same (UTC) day.
+    pub fn daily_seed() -> u64 {
+        Rng::now().as_secs() / (60 * 60 * 24)
+    }
+
+    /// Seed that differs from run to run.
+    pub fn random_seed() -> u64 {
+        let now = Rng::now();
+        now.as_secs() ^ u64::from(now.subsec_nanos()).rotate_left(32)
+    }
+
+    fn now() -> std::time::Duration {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("System clock is before 1970")
+    }
+
+    /// The seed this generator was created with.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number in `0..n`.
+    pub fn below(&mut self, n: usize) -> usize {
+        assert!(n > 0, "Empty range passed to below");
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// Fisher-Yates shuffle.
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i + 1));
+        }
+    }
+
+    /// Creates an independent generator for a subsystem, so that one
+    /// consumer drawing more numbers does not change what another sees.
+    pub fn fork(&mut self) -> Rng {
+        Rng::new(self.next_u64())
+    }
+}
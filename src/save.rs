@@ -0,0 +1,567 @@
+use crate::jobs::Worker;
+use crate::storage;
+#[cfg(feature = "sync")]
+pub use crate::sync::Remote;
+use crate::theme::TileSize;
+use crate::toast;
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+use unblock::replay::Replay;
+
+/// Version of the file `store` writes. Bump it, and add a step to
+/// `MIGRATIONS`, whenever the meaning of a key changes.
+const VERSION: usize = 2;
+
+/// Steps that bring a file's entries up one version: the first upgrades
+/// version 1 to 2, and so on. Files from before the version was written
+/// count as version 1.
+const MIGRATIONS: &[fn(Entries) -> Entries] = &[group_keys];
+
+/// The `key=value` pairs of a save file, in order.
+type Entries = Vec<(String, String)>;
+
+/// Nowhere: without the sync feature progress stays on this machine.
+#[cfg(not(feature = "sync"))]
+#[derive(Clone)]
+pub enum Remote {}
+
+#[cfg(not(feature = "sync"))]
+impl Remote {
+    fn pull(&self) -> Result<Option<String>, String> {
+        match *self {}
+    }
+
+    fn push(&self, _contents: &str) -> Result<(), String> {
+        match *self {}
+    }
+}
+
+#[cfg(not(feature = "sync"))]
+impl std::fmt::Display for Remote {
+    fn fmt(&self, _f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {}
+    }
+}
+
+/// Choices that are remembered between runs. The matching command line
+/// flags turn the toggles on regardless.
+#[derive(Clone, Default)]
+pub struct Settings {
+    pub zen: bool,
+    pub click_to_move: bool,
+    /// Score by cells travelled rather than moves made.
+    pub count_cells: bool,
+    pub ghosts: bool,
+    /// Solving a level moves on to the next one not solved yet.
+    pub skip_solved: bool,
+    /// Blocks show the letters `--solve` names them by.
+    pub labels: bool,
+    /// Animations and transitions are left out: boards change at once.
+    pub reduced_motion: bool,
+    /// How big cells are drawn and the room kept round the board, None to
+    /// leave it to the theme.
+    pub tile: Option<TileSize>,
+    pub margin: Option<usize>,
+    /// Code of the language the game speaks, empty for English.
+    pub language: String,
+    /// Whether boards are drawn flipped left to right, None to follow the
+    /// language's direction.
+    pub mirror: Option<bool>,
+    /// Name of the theme in use, empty for the classic one.
+    pub theme: String,
+    /// URL of the catalog the pack browser lists, empty for none.
+    pub catalog: String,
+    /// The ed25519 public key catalog packs are signed with, in hex, empty
+    /// for none.
+    pub catalog_key: String,
+    /// Mods whose packs and themes are left out.
+    pub disabled_mods: BTreeSet<String>,
+}
+
+impl Settings {
+    /// The settings as the lines of the save file that keep them.
+    pub fn lines(&self) -> String {
+        let mut lines = String::new();
+        lines += &format!("settings.zen={}\n", self.zen);
+        lines += &format!("settings.click_to_move={}\n", self.click_to_move);
+        lines += &format!("settings.count_cells={}\n", self.count_cells);
+        lines += &format!("settings.ghosts={}\n", self.ghosts);
+        lines += &format!("settings.skip_solved={}\n", self.skip_solved);
+        lines += &format!("settings.labels={}\n", self.labels);
+        lines += &format!("settings.reduced_motion={}\n", self.reduced_motion);
+        let tile = self.tile.map(|tile| tile.to_string());
+        lines += &format!("settings.tile={}\n", tile.unwrap_or_default());
+        let margin = self.margin.map(|margin| margin.to_string());
+        lines += &format!("settings.margin={}\n", margin.unwrap_or_default());
+        lines += &format!("settings.language={}\n", self.language);
+        let mirror = self.mirror.map(|mirror| mirror.to_string());
+        lines += &format!("settings.mirror={}\n", mirror.unwrap_or_default());
+        lines += &format!("settings.theme={}\n", self.theme);
+        lines += &format!("settings.catalog={}\n", self.catalog);
+        lines += &format!("settings.catalog_key={}\n", self.catalog_key);
+        for name in &self.disabled_mods {
+            lines += &format!("settings.mod.{}=false\n", name);
+        }
+        lines
+    }
+}
+
+/// Progress that survives between runs.
+///
+/// Stored as `key=value` lines under a `version=N` line and a `saved` time,
+/// so the file is easy to inspect and edit by hand. Older versions are
+/// migrated when read, and unknown keys are ignored. A file that cannot be
+/// read is moved aside and play goes back to the backup `storage` kept of
+/// the file before it was last written, or starts fresh. Written on a
+/// background thread, and copied to the remote if there is one.
+pub struct Save {
+    pub tutorial_done: bool,
+    /// Fewest moves each pack level has been solved in, keyed by
+    /// `Level::id`.
+    pub fewest: BTreeMap<u64, usize>,
+    /// Fewest cells each pack level has been solved in, which may take
+    /// more moves than the fewest.
+    pub fewest_cells: BTreeMap<u64, usize>,
+    /// Most puzzles solved in one round of time attack.
+    pub time_attack_best: usize,
+    /// Pack levels solved, counting repeats.
+    pub solves: usize,
+    pub settings: Settings,
+    /// Fastest solve of each pack level, keyed by `Level::id`, played back
+    /// as a ghost.
+    pub best: BTreeMap<u64, Replay>,
+    /// The moves made on pack levels left part way through, keyed by
+    /// `Level::id`, as `Level::history` gives them.
+    pub unfinished: BTreeMap<u64, Replay>,
+    writer: Worker<String, Result<(), String>>,
+}
+
+impl Save {
+    /// Progress starting from nothing that is never written anywhere, for
+    /// games driven without a window.
+    pub fn blank() -> Save {
+        Save {
+            tutorial_done: false,
+            fewest: BTreeMap::new(),
+            fewest_cells: BTreeMap::new(),
+            time_attack_best: 0,
+            solves: 0,
+            settings: Settings::default(),
+            best: BTreeMap::new(),
+            unfinished: BTreeMap::new(),
+            writer: Worker::start(|_: String| Ok(())),
+        }
+    }
+
+    /// The progress saved on this machine, or the copy on `remote` if that
+    /// was saved more recently.
+    pub fn load(remote: Option<Remote>) -> Save {
+        let path = path();
+        let local = match path.as_ref().map(fs::read) {
+            Some(Ok(contents)) => Some(contents),
+            Some(Err(e)) if e.kind() != ErrorKind::NotFound => {
+                report(format!("Unable to load progress: {}", e));
+                None
+            }
+            _ => None,
+        };
+        let pulled = remote
+            .as_ref()
+            .and_then(|remote| match remote.pull() {
+                Ok(pulled) => pulled,
+                Err(e) => {
+                    report(format!("Unable to sync progress: {}", e));
+                    None
+                }
+            })
+            .map(String::into_bytes);
+        let synced = pulled.as_deref().map_or(0, saved_at);
+        let mut save = Save {
+            tutorial_done: false,
+            fewest: BTreeMap::new(),
+            fewest_cells: BTreeMap::new(),
+            time_attack_best: 0,
+            solves: 0,
+            settings: Settings::default(),
+            best: BTreeMap::new(),
+            unfinished: BTreeMap::new(),
+            writer: Worker::start(writer(remote.clone(), synced)),
+        };
+        if let (Some(remote), Some(pulled)) = (&remote, &pulled) {
+            if saved_at(pulled) > local.as_deref().map_or(0, saved_at) {
+                match entries(pulled).and_then(|entries| save.apply(entries)) {
+                    Ok(()) => {
+                        // Kept here too, for playing offline.
+                        if let Err(e) = write(&String::from_utf8_lossy(pulled)) {
+                            report(format!("Unable to save progress: {}", e));
+                        }
+                        toast::show(format!("Progress synced from {}", remote));
+                        return save;
+                    }
+                    Err(problem) => {
+                        save.clear();
+                        report(format!(
+                            "The save file at {} {}, using this machine's",
+                            remote, problem
+                        ));
+                    }
+                }
+            }
+        }
+        let (path, local) = match (path, local) {
+            (Some(path), Some(local)) => (path, local),
+            _ => return save,
+        };
+        if let Err(problem) = entries(&local).and_then(|entries| save.apply(entries)) {
+            save.clear();
+            let moved = back_up(&path);
+            // The file as it was before the last save, which loses at most
+            // that save's progress.
+            let restored = fs::read(storage::backup(&path))
+                .map_err(|e| e.to_string())
+                .and_then(|previous| entries(&previous))
+                .and_then(|entries| save.apply(entries));
+            let then = match restored {
+                Ok(()) => "going back to the one before",
+                Err(_) => {
+                    save.clear();
+                    "starting fresh"
+                }
+            };
+            report(match moved {
+                Ok(backup) => format!(
+                    "Save file {}, {}. The old one is at {}",
+                    problem,
+                    then,
+                    backup.display()
+                ),
+                Err(e) => format!("Save file {}, {} ({})", problem, then, e),
+            });
+            if restored.is_ok() {
+                save.store();
+            }
+            return save;
+        }
+        if remote.is_some() && saved_at(&local) > synced {
+            save.store();
+        }
+        save
+    }
+
+    fn apply(&mut self, entries: Entries) -> Result<(), String> {
+        for (key, value) in entries {
+            let key = key.as_str();
+            match key {
+                "progress.tutorial_done" => self.tutorial_done = parse(key, &value)?,
+                "stats.time_attack_best" => self.time_attack_best = parse(key, &value)?,
+                "stats.solves" => self.solves = parse(key, &value)?,
+                "settings.zen" => self.settings.zen = parse(key, &value)?,
+                "settings.click_to_move" => self.settings.click_to_move = parse(key, &value)?,
+                "settings.count_cells" => self.settings.count_cells = parse(key, &value)?,
+                "settings.ghosts" => self.settings.ghosts = parse(key, &value)?,
+                "settings.skip_solved" => self.settings.skip_solved = parse(key, &value)?,
+                "settings.labels" => self.settings.labels = parse(key, &value)?,
+                "settings.reduced_motion" => self.settings.reduced_motion = parse(key, &value)?,
+                "settings.tile" => {
+                    self.settings.tile = match value.as_str() {
+                        "" => None,
+                        value => Some(TileSize::parse(value).ok_or_else(|| invalid(key))?),
+                    }
+                }
+                "settings.margin" => {
+                    self.settings.margin = match value.as_str() {
+                        "" => None,
+                        _ => Some(parse(key, &value)?),
+                    }
+                }
+                "settings.language" => self.settings.language = value,
+                "settings.mirror" => {
+                    self.settings.mirror = match value.as_str() {
+                        "" => None,
+                        _ => Some(parse(key, &value)?),
+                    }
+                }
+                "settings.theme" => self.settings.theme = value,
+                "settings.catalog" => self.settings.catalog = value,
+                "settings.catalog_key" => self.settings.catalog_key = value,
+                _ => {
+                    if let Some(name) = key.strip_prefix("settings.mod.") {
+                        if parse(key, &value)? {
+                            self.settings.disabled_mods.remove(name);
+                        } else {
+                            self.settings.disabled_mods.insert(name.to_string());
+                        }
+                    } else if let Some(id) = key.strip_prefix("progress.fewest.") {
+                        self.fewest.insert(id_of(key, id)?, parse(key, &value)?);
+                    } else if let Some(id) = key.strip_prefix("progress.fewest_cells.") {
+                        self.fewest_cells
+                            .insert(id_of(key, id)?, parse(key, &value)?);
+                    } else if let Some(id) = key.strip_prefix("progress.unfinished.") {
+                        let history = Replay::decode(&value).ok_or_else(|| invalid(key))?;
+                        self.unfinished.insert(id_of(key, id)?, history);
+                    } else if let Some(id) = key.strip_prefix("replay.") {
+                        let replay = Replay::decode(&value).ok_or_else(|| invalid(key))?;
+                        self.best.insert(id_of(key, id)?, replay);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Forgets everything read so far, after finding the file is damaged.
+    fn clear(&mut self) {
+        self.tutorial_done = false;
+        self.fewest.clear();
+        self.fewest_cells.clear();
+        self.time_attack_best = 0;
+        self.solves = 0;
+        self.settings = Settings::default();
+        self.best.clear();
+        self.unfinished.clear();
+    }
+
+    pub fn store(&self) {
+        self.writer.send(self.contents());
+    }
+
+    /// Waits for the saves already asked for to be written, before the
+    /// game exits. Saves asked for after are not made in the background.
+    pub fn finish(&mut self) {
+        self.writer.finish();
+        self.poll();
+    }
+
+    /// Writes the file here and now, for commands that exit straight
+    /// after. Syncing waits until the game next saves.
+    pub fn store_now(&self) -> Result<(), String> {
+        write(&self.contents())
+    }
+
+    /// Everything saved, as the save file has it, for carrying to another
+    /// machine and `merge`.
+    pub fn export(&self) -> String {
+        self.contents()
+    }
+
+    /// Brings in what `export` wrote on another machine, keeping the best
+    /// of both: the fewest moves and cells and the fastest solve of each
+    /// level, the better time attack round and the larger count of solves.
+    /// Settings come from the file; levels left part way through here stay
+    /// as they are. Nothing changes if the file cannot be read.
+    pub fn merge(&mut self, contents: &[u8]) -> Result<(), String> {
+        let entries = entries(contents)?;
+        let fewest = self.fewest.clone();
+        let fewest_cells = self.fewest_cells.clone();
+        let best = self.best.clone();
+        let unfinished = self.unfinished.clone();
+        let settings = self.settings.clone();
+        let (tutorial_done, time_attack_best, solves) =
+            (self.tutorial_done, self.time_attack_best, self.solves);
+        if let Err(problem) = self.apply(entries) {
+            self.fewest = fewest;
+            self.fewest_cells = fewest_cells;
+            self.best = best;
+            self.unfinished = unfinished;
+            self.settings = settings;
+            self.tutorial_done = tutorial_done;
+            self.time_attack_best = time_attack_best;
+            self.solves = solves;
+            return Err(problem);
+        }
+        for (id, moves) in fewest {
+            let fewest = self.fewest.entry(id).or_insert(moves);
+            *fewest = moves.min(*fewest);
+        }
+        for (id, cells) in fewest_cells {
+            let fewest = self.fewest_cells.entry(id).or_insert(cells);
+            *fewest = cells.min(*fewest);
+        }
+        for (id, replay) in best {
+            let faster = match self.best.get(&id) {
+                Some(theirs) => replay.duration() <= theirs.duration(),
+                None => true,
+            };
+            if faster {
+                self.best.insert(id, replay);
+            }
+        }
+        self.unfinished.extend(unfinished);
+        self.tutorial_done |= tutorial_done;
+        self.time_attack_best = self.time_attack_best.max(time_attack_best);
+        self.solves = self.solves.max(solves);
+        Ok(())
+    }
+
+    fn contents(&self) -> String {
+        let saved = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        let mut contents = format!("version={}\nsaved={}\n", VERSION, saved);
+        contents += &format!("progress.tutorial_done={}\n", self.tutorial_done);
+        for (id, moves) in &self.fewest {
+            contents += &format!("progress.fewest.{:016x}={}\n", id, moves);
+        }
+        for (id, cells) in &self.fewest_cells {
+            contents += &format!("progress.fewest_cells.{:016x}={}\n", id, cells);
+        }
+        contents += &format!("stats.time_attack_best={}\n", self.time_attack_best);
+        contents += &format!("stats.solves={}\n", self.solves);
+        contents += &self.settings.lines();
+        for (id, history) in &self.unfinished {
+            contents += &format!("progress.unfinished.{:016x}={}\n", id, history.encode());
+        }
+        for (id, replay) in &self.best {
+            contents += &format!("replay.{:016x}={}\n", id, replay.encode());
+        }
+        contents
+    }
+
+    /// Reports a failure to write the file, once the writer gets to it.
+    pub fn poll(&self) {
+        while let Some(result) = self.writer.poll() {
+            if let Err(e) = result {
+                report(format!("Unable to save progress: {}", e));
+            }
+        }
+    }
+}
+
+/// The `key=value` pairs of a save file, migrated to the current version.
+/// Errors say what is wrong with the file.
+fn entries(contents: &[u8]) -> Result<Entries, String> {
+    let contents = std::str::from_utf8(contents).map_err(|_| String::from("is not text"))?;
+    let mut version = 1;
+    let mut entries = Vec::new();
+    for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| format!("has a broken line \"{}\"", line))?;
+        let (key, value) = (key.trim(), value.trim());
+        if key == "version" {
+            version = parse(key, value)?;
+        } else if key != "saved" {
+            entries.push((key.to_string(), value.to_string()));
+        }
+    }
+    if version == 0 {
+        return Err(invalid("version"));
+    }
+    if version > VERSION {
+        return Err(format!("is version {}, newer than this game", version));
+    }
+    for migrate in &MIGRATIONS[version - 1..] {
+        entries = migrate(entries);
+    }
+    Ok(entries)
+}
+
+/// When the save file `contents` was written, in seconds since the epoch,
+/// or 0 if it does not say.
+fn saved_at(contents: &[u8]) -> u64 {
+    String::from_utf8_lossy(contents)
+        .lines()
+        .find_map(|line| line.strip_prefix("saved="))
+        .and_then(|saved| saved.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+fn parse<T: FromStr>(key: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| invalid(key))
+}
+
+fn id_of(key: &str, id: &str) -> Result<u64, String> {
+    u64::from_str_radix(id, 16).map_err(|_| invalid(key))
+}
+
+fn invalid(key: &str) -> String {
+    format!("has a bad {} value", key)
+}
+
+/// Version 2 grouped the keys by what they are for. Its new keys start out
+/// at their defaults.
+fn group_keys(mut entries: Entries) -> Entries {
+    for (key, _) in entries.iter_mut() {
+        *key = match key.as_str() {
+            "tutorial_done" => String::from("progress.tutorial_done"),
+            "time_attack_best" => String::from("stats.time_attack_best"),
+            _ => match key.strip_prefix("best_") {
+                Some(id) => format!("replay.{}", id),
+                None => continue,
+            },
+        };
+    }
+    entries
+}
+
+/// Moves a damaged save file out of the way, to a name that does not
+/// replace an earlier backup.
+fn back_up(path: &Path) -> Result<PathBuf, String> {
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let backup = path.with_extension(format!("{}.bak", stamp));
+    fs::rename(path, &backup)
+        .map(|_| backup)
+        .map_err(|e| format!("could not move it aside: {}", e))
+}
+
+/// The save file job: writes `contents` here, then to the remote unless
+/// another machine has pushed there since this one last synced. `synced`
+/// is when the copy there then was saved, so the newest progress wins.
+fn writer(remote: Option<Remote>, mut synced: u64) -> impl FnMut(String) -> Result<(), String> {
+    move |contents| {
+        write(&contents)?;
+        let remote = match &remote {
+            Some(remote) => remote,
+            None => return Ok(()),
+        };
+        let theirs = remote
+            .pull()?
+            .map_or(0, |theirs| saved_at(theirs.as_bytes()));
+        if theirs > synced {
+            return Err(format!(
+                "another machine has saved newer progress to {}, restart to pick it up",
+                remote
+            ));
+        }
+        remote.push(&contents)?;
+        synced = saved_at(contents.as_bytes());
+        Ok(())
+    }
+}
+
+fn write(contents: &str) -> Result<(), String> {
+    let path = match path() {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+    storage::save_atomic(&path, contents.as_bytes())
+        .map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn report(message: String) {
+    println!("{}", message);
+    toast::show(message);
+}
+
+fn path() -> Option<PathBuf> {
+    Some(dir()?.join("save.txt"))
+}
+
+/// Where the game keeps its files.
+pub fn dir() -> Option<PathBuf> {
+    let dir = match env::var_os("XDG_DATA_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => match env::var_os("APPDATA") {
+            Some(dir) => PathBuf::from(dir),
+            None => PathBuf::from(env::var_os("HOME")?).join(".local/share"),
+        },
+    };
+    Some(dir.join("unblock"))
+}
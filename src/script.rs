@@ -0,0 +1,260 @@
+//! Custom level mechanics written in Rhai and shipped with a pack.
+//!
+//! A script defines any of these functions, which the game calls with the
+//! level being played:
+//!
+//! * `on_move(level, block)` - after the player moves a block
+//! * `on_solve(level)` - when the player gets out
+//! * `on_tick(level, seconds)` - every frame, with the time spent on the
+//!   level so far
+//!
+//! The level offers `number`, `moves`, `blocks` and `solved`, and for the
+//! block at an index `x`, `y`, `width`, `height` and `kind` ("player",
+//! "wall", "exit", "horizontal" or "vertical"). `free(x, y)` says whether a
+//! cell is empty. `slide(block, cells)` moves a block along its length as
+//! the player could, and `teleport(block, x, y)` puts it anywhere it fits.
+//! Both are recorded as moves, so they can be undone, and return false if
+//! the block did not move. `print` shows a message.
+//!
+//! Scripts cannot touch files or load other scripts, and each call is
+//! stopped if it runs too long.
+
+use crate::level::{Block, BlockDir, BlockType, Level, TILES_HIGH, TILES_WIDE};
+use rhai::module_resolvers::DummyModuleResolver;
+use rhai::{Dynamic, Engine, EvalAltResult, FuncArgs, Scope, AST, INT};
+use std::cell::{Cell, RefCell};
+use std::convert::TryFrom;
+use std::mem;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+/// How long one call may run, and how much work it may do regardless of
+/// how fast the machine is.
+const TIME_LIMIT: Duration = Duration::from_millis(5);
+const MAX_OPERATIONS: u64 = 100_000;
+
+/// What the level's functions give scripts: a value, or an error that
+/// stops the script.
+type Checked<T> = Result<T, Box<EvalAltResult>>;
+
+/// A compiled pack script.
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+    printed: Rc<RefCell<Vec<String>>>,
+    deadline: Rc<Cell<Instant>>,
+}
+
+/// What a call did besides changing the level: the blocks it moved and
+/// where to, in order (see `Level::last_placed`), and what it printed.
+#[derive(Default, Debug)]
+pub struct Effects {
+    pub moved: Vec<(usize, (usize, usize))>,
+    pub printed: Vec<String>,
+}
+
+/// The level as scripts see it, shared with the script for the length of
+/// one call.
+#[derive(Clone)]
+struct Handle(Rc<RefCell<State>>);
+
+struct State {
+    level: Level,
+    number: usize,
+    moved: Vec<(usize, (usize, usize))>,
+}
+
+impl Script {
+    pub fn compile(source: &str) -> Result<Script, String> {
+        let printed = Rc::new(RefCell::new(Vec::new()));
+        let deadline = Rc::new(Cell::new(Instant::now()));
+        let mut engine = Engine::new();
+        engine
+            .set_module_resolver(DummyModuleResolver::new())
+            .set_max_operations(MAX_OPERATIONS)
+            .set_max_call_levels(32)
+            .set_max_expr_depths(64, 32)
+            .set_max_string_size(1_000)
+            .set_max_array_size(1_000)
+            .set_max_map_size(1_000);
+        engine.disable_symbol("eval");
+        let sink = Rc::clone(&printed);
+        engine.on_print(move |text| sink.borrow_mut().push(text.to_string()));
+        let until = Rc::clone(&deadline);
+        engine.on_progress(move |_| {
+            if Instant::now() > until.get() {
+                Some(Dynamic::from("out of time"))
+            } else {
+                None
+            }
+        });
+        register(&mut engine);
+        let ast = engine.compile(source).map_err(|e| e.to_string())?;
+        Ok(Script {
+            engine,
+            ast,
+            printed,
+            deadline,
+        })
+    }
+
+    /// Runs `on_move` after the player moved `block` on level `number`
+    /// (counting from 1).
+    pub fn on_move(
+        &self,
+        level: &mut Level,
+        number: usize,
+        block: usize,
+    ) -> Result<Effects, String> {
+        self.call("on_move", level, number, |handle| (handle, block as INT))
+    }
+
+    pub fn on_solve(&self, level: &mut Level, number: usize) -> Result<Effects, String> {
+        self.call("on_solve", level, number, |handle| (handle,))
+    }
+
+    pub fn on_tick(
+        &self,
+        level: &mut Level,
+        number: usize,
+        elapsed: Duration,
+    ) -> Result<Effects, String> {
+        self.call("on_tick", level, number, |handle| {
+            (handle, elapsed.as_secs_f64())
+        })
+    }
+
+    /// Calls the script's `name` function, if it has one, with the level
+    /// lent to it for the duration.
+    fn call<A: FuncArgs>(
+        &self,
+        name: &str,
+        level: &mut Level,
+        number: usize,
+        args: impl FnOnce(Handle) -> A,
+    ) -> Result<Effects, String> {
+        if !self.ast.iter_functions().any(|f| f.name == name) {
+            return Ok(Effects::default());
+        }
+        let handle = Handle(Rc::new(RefCell::new(State {
+            level: mem::take(level),
+            number,
+            moved: Vec::new(),
+        })));
+        self.deadline.set(Instant::now() + TIME_LIMIT);
+        let result = self.engine.call_fn::<Dynamic>(
+            &mut Scope::new(),
+            &self.ast,
+            name,
+            args(handle.clone()),
+        );
+        let mut state = handle.0.borrow_mut();
+        *level = mem::take(&mut state.level);
+        let effects = Effects {
+            moved: mem::take(&mut state.moved),
+            printed: mem::take(&mut *self.printed.borrow_mut()),
+        };
+        result
+            .map(|_| effects)
+            .map_err(|e| format!("{}: {}", name, e))
+    }
+}
+
+fn register(engine: &mut Engine) {
+    engine
+        .register_type_with_name::<Handle>("Level")
+        .register_get("number", |h: &mut Handle| h.0.borrow().number as INT)
+        .register_get("moves", |h: &mut Handle| {
+            h.0.borrow().level.moves.len() as INT
+        })
+        .register_get("blocks", |h: &mut Handle| {
+            h.0.borrow().level.blocks.len() as INT
+        })
+        .register_get("solved", |h: &mut Handle| h.0.borrow().level.solved)
+        .register_fn("x", |h: &mut Handle, i: INT| h.block(i, |b| b.x1 as INT))
+        .register_fn("y", |h: &mut Handle, i: INT| h.block(i, |b| b.y1 as INT))
+        .register_fn("width", |h: &mut Handle, i: INT| {
+            h.block(i, |b| (b.x2 - b.x1 + 1) as INT)
+        })
+        .register_fn("height", |h: &mut Handle, i: INT| {
+            h.block(i, |b| (b.y2 - b.y1 + 1) as INT)
+        })
+        .register_fn("kind", |h: &mut Handle, i: INT| {
+            h.block(i, |b| {
+                match (b.r#type, b.dir) {
+                    (BlockType::Player, _) => "player",
+                    (BlockType::Wall, _) => "wall",
+                    (BlockType::Exit, _) => "exit",
+                    (_, BlockDir::UpDown) => "vertical",
+                    _ => "horizontal",
+                }
+                .to_string()
+            })
+        })
+        .register_fn("free", |h: &mut Handle, x: INT, y: INT| {
+            let level = &h.0.borrow().level;
+            cell(x, y).is_some_and(|(x, y)| level.occupant(x, y).is_none())
+        })
+        .register_fn(
+            "slide",
+            |h: &mut Handle, i: INT, cells: INT| -> Checked<bool> {
+                let index = h.movable(i)?;
+                let mut state = h.0.borrow_mut();
+                let target = state.level.slide_limit(index, cells as isize);
+                Ok(state.move_block(index, target))
+            },
+        )
+        .register_fn(
+            "teleport",
+            |h: &mut Handle, i: INT, x: INT, y: INT| -> Checked<bool> {
+                let index = h.movable(i)?;
+                let mut state = h.0.borrow_mut();
+                Ok(match cell(x, y) {
+                    Some(target) if state.level.fits(index, target) => {
+                        state.move_block(index, target)
+                    }
+                    _ => false,
+                })
+            },
+        );
+}
+
+/// `(x, y)` if it is a cell of the board.
+fn cell(x: INT, y: INT) -> Option<(usize, usize)> {
+    if 0 <= x && x < TILES_WIDE as INT && 0 <= y && y < TILES_HIGH as INT {
+        Some((x as usize, y as usize))
+    } else {
+        None
+    }
+}
+
+impl Handle {
+    fn block<T>(&mut self, index: INT, read: impl FnOnce(&Block) -> T) -> Checked<T> {
+        let state = self.0.borrow();
+        match usize::try_from(index)
+            .ok()
+            .and_then(|i| state.level.blocks.get(i))
+        {
+            Some(block) => Ok(read(block)),
+            None => Err(format!("there is no block {}", index).into()),
+        }
+    }
+
+    /// `index` if it is a block that can move.
+    fn movable(&mut self, index: INT) -> Checked<usize> {
+        if self.block(index, |b| b.dir)? == BlockDir::Static {
+            return Err(format!("block {} cannot move", index).into());
+        }
+        Ok(index as usize)
+    }
+}
+
+impl State {
+    fn move_block(&mut self, index: usize, target: (usize, usize)) -> bool {
+        let moved = self.level.move_block(index, target);
+        if moved {
+            self.moved.extend_from_slice(self.level.last_placed());
+        }
+        moved
+    }
+}
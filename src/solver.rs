@@ -0,0 +1,442 @@
+use crate::bitboard::Bitboard;
+use crate::level::{pos_to_xy, Level};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// One move of a solution: slide `block` so its top left cell is `(x, y)`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Step {
+    pub block: usize,
+    pub x: usize,
+    pub y: usize,
+}
+
+/// A shortest sequence of moves that solves a level.
+#[derive(Clone, Debug)]
+pub struct Solution {
+    pub steps: Vec<Step>,
+}
+
+impl Solution {
+    /// The minimum number of moves the level takes.
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+}
+
+/// Every single move available from the current position. A move slides one
+/// block any distance along its axis, same as a drag.
+pub fn moves(level: &Level) -> Vec<Step> {
+    level
+        .bitboard()
+        .moves()
+        .into_iter()
+        .map(|(block, cell)| step(block, cell))
+        .collect()
+}
+
+fn step(block: usize, cell: usize) -> Step {
+    let (x, y) = pos_to_xy(cell);
+    Step { block, x, y }
+}
+
+/// The fewest moves that get the player onto the exit, starting from the
+/// level as it currently stands. Returns None if the level cannot be solved
+/// from here, or if the solver does not apply to it (see `applies`).
+/// `SolverConfig::default()` suits the standard 8x8 board.
+pub fn solve(level: &Level, config: SolverConfig) -> Option<Solution> {
+    solve_with(level, config).0
+}
+
+/// Whether the solver's answers hold for `level` as it stands. Positions
+/// only know the walls and exit of the phase being played, so before the
+/// last phase getting out would look like solving the level.
+pub fn applies(level: &Level) -> bool {
+    level.phase + 1 == level.phases()
+}
+
+/// How a search goes about finding the shortest solution.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Strategy {
+    /// Remembers every position it reaches: fast, but memory grows with
+    /// the number of positions the level has.
+    BreadthFirst,
+    /// Iterative deepening A* with the blocking car heuristic: searches
+    /// depth first again and again with a growing move limit, remembering
+    /// only the current line and a fixed size table of positions, so
+    /// memory stays flat however large the board is.
+    IdaStar,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SolverConfig {
+    pub strategy: Strategy,
+    /// Most positions to search from before giving up and finding no
+    /// solution, None for no limit.
+    pub budget: Option<usize>,
+}
+
+impl Default for SolverConfig {
+    fn default() -> SolverConfig {
+        SolverConfig {
+            strategy: Strategy::BreadthFirst,
+            budget: None,
+        }
+    }
+}
+
+/// What a search cost, for comparing strategies.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Stats {
+    /// Positions whose moves were generated, counting repeats.
+    pub nodes: usize,
+    /// The most positions held in memory at once.
+    pub stored: usize,
+}
+
+/// `solve` with a choice of strategy, also reporting what the search cost.
+pub fn solve_with(level: &Level, config: SolverConfig) -> (Option<Solution>, Stats) {
+    let mut stats = Stats::default();
+    if !applies(level) {
+        return (None, stats);
+    }
+    let solution = match config.strategy {
+        Strategy::BreadthFirst => search(level, None, config.budget, &mut stats),
+        Strategy::IdaStar => ida_star(level, config.budget, &mut stats),
+    };
+    (solution, stats)
+}
+
+/// Every position a search reached, by how many moves from the start it
+/// is. A breadth first search reaches every position a given number of
+/// moves away before any further one, so when a solution of N moves is
+/// found, the first N layers hold every position fewer than N moves away,
+/// none of them solved: the proof that no shorter solution exists.
+#[derive(Clone, Default)]
+pub struct Trace {
+    pub layers: Vec<Vec<Bitboard>>,
+}
+
+impl Trace {
+    /// How many positions were reached in total.
+    pub fn len(&self) -> usize {
+        self.layers.iter().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+}
+
+/// `solve` breadth first, also keeping every position the search reached.
+pub fn solve_traced(level: &Level) -> (Option<Solution>, Trace) {
+    let mut trace = Trace::default();
+    if !applies(level) {
+        return (None, trace);
+    }
+    let solution = search(level, Some(&mut trace), None, &mut Stats::default());
+    (solution, trace)
+}
+
+fn search(
+    level: &Level,
+    mut trace: Option<&mut Trace>,
+    budget: Option<usize>,
+    stats: &mut Stats,
+) -> Option<Solution> {
+    let start = level.bitboard().clone();
+    let mut note = |position: &Bitboard, depth: usize| {
+        if let Some(trace) = trace.as_mut() {
+            if trace.layers.len() <= depth {
+                trace.layers.push(Vec::new());
+            }
+            trace.layers[depth].push(position.clone());
+        }
+    };
+    note(&start, 0);
+    if level.solved {
+        return Some(Solution { steps: Vec::new() });
+    }
+    // Each visited position, and the position and step it was reached from.
+    let mut parents: HashMap<Bitboard, Option<(Bitboard, Step)>> = HashMap::new();
+    parents.insert(start.clone(), None);
+    stats.stored = 1;
+    let mut queue = VecDeque::new();
+    queue.push_back((start, 0));
+    while let Some((current, depth)) = queue.pop_front() {
+        if budget.is_some_and(|budget| stats.nodes >= budget) {
+            return None;
+        }
+        stats.nodes += 1;
+        for (block, cell) in current.moves() {
+            let mut next = current.clone();
+            next.play(block, cell);
+            next.settle();
+            if parents.contains_key(&next) {
+                continue;
+            }
+            stats.stored += 1;
+            note(&next, depth + 1);
+            parents.insert(next.clone(), Some((current.clone(), step(block, cell))));
+            if next.is_solved() {
+                return Some(Solution {
+                    steps: path(&parents, next),
+                });
+            }
+            queue.push_back((next, depth + 1));
+        }
+    }
+    None
+}
+
+// Slots in the IDA* table of positions already searched.
+const IDA_TABLE: usize = 1 << 16;
+
+/// A position the current IDA* round has searched from: its Zobrist hash,
+/// the moves it took to get there and the round.
+#[derive(Clone, Copy, Default)]
+struct Searched {
+    hash: u64,
+    moves: usize,
+    round: usize,
+}
+
+struct Ida {
+    table: Vec<Searched>,
+    budget: usize,
+    round: usize,
+    limit: usize,
+    line: Vec<Step>,
+}
+
+fn ida_star(level: &Level, budget: Option<usize>, stats: &mut Stats) -> Option<Solution> {
+    let start = level.bitboard().clone();
+    let mut ida = Ida {
+        table: vec![Searched::default(); IDA_TABLE],
+        budget: budget.unwrap_or(usize::MAX),
+        round: 0,
+        limit: start.lower_bound(),
+        line: Vec::new(),
+    };
+    loop {
+        ida.round += 1;
+        match ida.search(&start, None, stats) {
+            Ok(()) => return Some(Solution { steps: ida.line }),
+            Err(Some(limit)) => ida.limit = limit,
+            // Nothing went over the limit, so every position was searched,
+            // or the budget ran out.
+            Err(None) => return None,
+        }
+    }
+}
+
+impl Ida {
+    /// Depth first search below `position` for a solution within the
+    /// limit. On failure, returns the smallest estimate that went over the
+    /// limit, which is the limit for the next round.
+    fn search(
+        &mut self,
+        position: &Bitboard,
+        last: Option<usize>,
+        stats: &mut Stats,
+    ) -> Result<(), Option<usize>> {
+        // Over budget, the search stops as if there were nowhere left to go.
+        if stats.nodes >= self.budget {
+            return Err(None);
+        }
+        let moves = self.line.len();
+        let estimate = moves + position.lower_bound();
+        if estimate > self.limit {
+            return Err(Some(estimate));
+        }
+        if position.is_solved() {
+            return Ok(());
+        }
+        // Reached before this round in as few moves: whatever lies below
+        // was already searched, and its estimates already reported.
+        let slot = &mut self.table[position.zobrist() as usize % IDA_TABLE];
+        if slot.round == self.round && slot.hash == position.zobrist() && slot.moves <= moves {
+            return Err(None);
+        }
+        if slot.round == 0 {
+            stats.stored += 1;
+        }
+        *slot = Searched {
+            hash: position.zobrist(),
+            moves,
+            round: self.round,
+        };
+        stats.nodes += 1;
+        let mut over = None;
+        for (block, cell) in position.moves() {
+            // Two slides of one block in a row are never shorter than one.
+            if last == Some(block) {
+                continue;
+            }
+            let mut next = position.clone();
+            next.play(block, cell);
+            next.settle();
+            self.line.push(step(block, cell));
+            match self.search(&next, Some(block), stats) {
+                Ok(()) => return Ok(()),
+                Err(Some(estimate)) => {
+                    over = Some(over.map_or(estimate, |o: usize| o.min(estimate)))
+                }
+                Err(None) => {}
+            }
+            self.line.pop();
+        }
+        Err(over)
+    }
+}
+
+fn path(parents: &HashMap<Bitboard, Option<(Bitboard, Step)>>, end: Bitboard) -> Vec<Step> {
+    let mut steps = Vec::new();
+    let mut position = end;
+    while let Some(Some((parent, step))) = parents.get(&position) {
+        steps.push(*step);
+        position = parent.clone();
+    }
+    steps.reverse();
+    steps
+}
+
+/// Answers "how many moves are left" for positions of a level as it is
+/// played. The first question explores every position the level can reach
+/// and works out how far each is from solved, so the answer after any later
+/// move is a table lookup.
+///
+/// Keyed by `Level::canonical_hash`, as mirrored and rotated positions are
+/// the same distance from solved.
+#[derive(Clone, Default)]
+pub struct Solver {
+    table: HashMap<u64, Option<usize>>,
+    // Most positions a level may reach, and the levels, by `Level::id`,
+    // that reach more.
+    budget: Option<usize>,
+    too_big: HashSet<u64>,
+}
+
+impl Solver {
+    pub fn new() -> Solver {
+        Solver::default()
+    }
+
+    /// A solver that gives up on levels reaching more than `budget`
+    /// positions, rather than holding them all.
+    pub fn with_budget(budget: usize) -> Solver {
+        Solver {
+            budget: Some(budget),
+            ..Solver::default()
+        }
+    }
+
+    /// The fewest moves that solve `level` from where it stands, or None if
+    /// it cannot be solved, was given up on or the solver does not apply.
+    pub fn remaining(&mut self, level: &Level) -> Option<usize> {
+        if !applies(level) {
+            return None;
+        }
+        let key = level.canonical_hash();
+        if let Some(&known) = self.table.get(&key) {
+            return known;
+        }
+        if self.gave_up(level) || !self.learn(level) {
+            return None;
+        }
+        self.table[&key]
+    }
+
+    /// Whether `level` reaches more positions than the budget, so nothing
+    /// is known about it.
+    pub fn gave_up(&self, level: &Level) -> bool {
+        self.too_big.contains(&level.id())
+    }
+
+    /// A shortest solution from where `level` stands, read off the table by
+    /// always taking a move that leaves one fewer to go, or None if it
+    /// cannot be solved.
+    pub fn solution(&mut self, level: &Level) -> Option<Solution> {
+        let mut left = self.remaining(level)?;
+        let mut position = level.bitboard().clone();
+        let mut steps = Vec::new();
+        while left > 0 {
+            left -= 1;
+            let (next, block, cell) = position
+                .moves()
+                .into_iter()
+                .map(|(block, cell)| {
+                    let mut next = position.clone();
+                    next.play(block, cell);
+                    next.settle();
+                    (next, block, cell)
+                })
+                .find(|(next, _, _)| {
+                    self.table.get(&level.canonical_hash_of(next)) == Some(&Some(left))
+                })?;
+            steps.push(step(block, cell));
+            position = next;
+        }
+        Some(Solution { steps })
+    }
+
+    /// Fills the table in for every position reachable from `level`, unless
+    /// there are more than the budget. Returns whether it did.
+    fn learn(&mut self, level: &Level) -> bool {
+        let start = level.bitboard().clone();
+        let mut index = HashMap::new();
+        index.insert(start.clone(), 0);
+        let mut positions = vec![start];
+        // The positions each one is reached from in a move, for searching
+        // backwards: with gravity on, a move cannot always be undone, so
+        // the positions a move leads to are not the ones it comes from.
+        let mut before: Vec<Vec<usize>> = vec![Vec::new()];
+        let mut expanded = 0;
+        while expanded < positions.len() {
+            if self.budget.is_some_and(|budget| positions.len() > budget) {
+                self.too_big.insert(level.id());
+                return false;
+            }
+            let current = positions[expanded].clone();
+            for (block, cell) in current.moves() {
+                let mut next = current.clone();
+                next.play(block, cell);
+                next.settle();
+                let count = positions.len();
+                let i = *index.entry(next.clone()).or_insert(count);
+                if i == count {
+                    positions.push(next);
+                    before.push(Vec::new());
+                }
+                before[i].push(expanded);
+            }
+            expanded += 1;
+        }
+        // Searching backwards from the solved positions finds how far every
+        // position is from one.
+        let mut distances: Vec<Option<usize>> = positions
+            .iter()
+            .map(|position| if position.is_solved() { Some(0) } else { None })
+            .collect();
+        let mut queue: VecDeque<usize> = (0..positions.len())
+            .filter(|&i| positions[i].is_solved())
+            .collect();
+        while let Some(i) = queue.pop_front() {
+            let distance = distances[i].map(|d| d + 1);
+            for &previous in &before[i] {
+                if distances[previous].is_none() {
+                    distances[previous] = distance;
+                    queue.push_back(previous);
+                }
+            }
+        }
+        for (position, distance) in positions.iter().zip(distances) {
+            self.table
+                .insert(level.canonical_hash_of(position), distance);
+        }
+        true
+    }
+}
@@ -0,0 +1,72 @@
+//! Writing the files the game keeps (the save file, packs it rewrites or
+//! downloads) so that a crash part way through cannot leave one truncated.
+
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Numbers the temporary files of this process's writes.
+static WRITES: AtomicUsize = AtomicUsize::new(0);
+
+/// Replaces the file at `path` with `bytes`, creating its folder if need
+/// be. The bytes go to a temporary file beside it, which is flushed to disk
+/// and then renamed over the old file, so the file is always either all
+/// old or all new. Each write has a temporary file of its own, so two at
+/// once cannot mix, and it is deleted if the write fails. What was there
+/// before is kept as its `backup`.
+pub fn save_atomic(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty());
+    if let Some(dir) = dir {
+        fs::create_dir_all(dir)?;
+    }
+    let write = WRITES.fetch_add(1, Ordering::Relaxed);
+    let name = format!("{}-{}.partial", process::id(), write);
+    let partial = Partial(beside(path, &name));
+    let mut file = File::create(&partial.0)?;
+    file.write_all(bytes)?;
+    file.sync_all()?;
+    drop(file);
+    // Copied rather than moved, so there is never a moment with no file.
+    if path.exists() {
+        fs::copy(path, backup(path))?;
+    }
+    fs::rename(&partial.0, path)?;
+    // The rename itself only lasts once the folder is on disk too. Windows
+    // cannot open folders, and makes renames last by itself.
+    #[cfg(unix)]
+    File::open(dir.unwrap_or_else(|| Path::new(".")))?.sync_all()?;
+    Ok(())
+}
+
+/// A temporary file of `save_atomic`'s, deleted when dropped. After the
+/// rename there is nothing left to delete.
+struct Partial(PathBuf);
+
+impl Drop for Partial {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+/// Where `save_atomic` keeps what `path` held before it last wrote it:
+/// `save.txt` is backed up to `save.txt.bak`, replacing the older backup.
+pub fn backup(path: &Path) -> PathBuf {
+    beside(path, "bak")
+}
+
+/// Where the editor keeps its work on the pack at `path` between saves, so
+/// that it can be recovered after a crash: `pack.txt.autosave`.
+pub fn autosave(path: &Path) -> PathBuf {
+    beside(path, "autosave")
+}
+
+/// `path` with `.extension` added after its own.
+fn beside(path: &Path, extension: &str) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(".");
+    name.push(extension);
+    PathBuf::from(name)
+}
@@ -0,0 +1,146 @@
+//! A `Renderer` that writes SVG, for pictures of boards that stay sharp at
+//! any size, like the pack gallery's.
+
+use crate::render::{Color, Place, Point, Rectangle, Renderer, Sketch};
+
+pub struct Svg {
+    width: f32,
+    height: f32,
+    body: String,
+}
+
+impl Svg {
+    pub fn new(width: f32, height: f32) -> Svg {
+        Svg {
+            width,
+            height,
+            body: String::new(),
+        }
+    }
+
+    /// The finished picture, as an `<svg>` element that scales to fit
+    /// whatever it is put in.
+    pub fn finish(self) -> String {
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {} {}\">{}</svg>",
+            self.width, self.height, self.body
+        )
+    }
+}
+
+/// `fill="#rrggbb"` and the like for `color`, with its opacity if it is
+/// see-through.
+fn paint(attribute: &str, color: Color) -> String {
+    let [r, g, b, _] = color.to_rgba();
+    let mut paint = format!("{}=\"#{:02x}{:02x}{:02x}\"", attribute, r, g, b);
+    if color.a < 1.0 {
+        paint += &format!(" {}-opacity=\"{}\"", attribute, color.a);
+    }
+    paint
+}
+
+/// `text` with the characters that mean something in markup escaped.
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+impl Renderer for Svg {
+    type Sprite = Sketch;
+
+    fn width(&self) -> f32 {
+        self.width
+    }
+
+    fn height(&self) -> f32 {
+        self.height
+    }
+
+    fn clear(&mut self, color: Color) {
+        self.body = format!(
+            "<rect width=\"{}\" height=\"{}\" {}/>",
+            self.width,
+            self.height,
+            paint("fill", color)
+        );
+    }
+
+    fn draw_rect(&mut self, area: Rectangle<f32>, fill: Color, border: Option<(Color, u16)>) {
+        self.body += &format!(
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" {}",
+            area.x,
+            area.y,
+            area.width,
+            area.height,
+            paint("fill", fill)
+        );
+        if let Some((color, width)) = border {
+            self.body += &format!(" {} stroke-width=\"{}\"", paint("stroke", color), width);
+        }
+        self.body += "/>";
+    }
+
+    fn draw_line(&mut self, points: Vec<Point>, color: Color, width: u16) {
+        let points: Vec<String> = points
+            .iter()
+            .map(|point| format!("{},{}", point.x, point.y))
+            .collect();
+        self.body += &format!(
+            "<polyline points=\"{}\" fill=\"none\" {} stroke-width=\"{}\"/>",
+            points.join(" "),
+            paint("stroke", color),
+            width
+        );
+    }
+
+    fn draw_polygon(&mut self, points: Vec<Point>, fill: Color) {
+        let points: Vec<String> = points
+            .iter()
+            .map(|point| format!("{},{}", point.x, point.y))
+            .collect();
+        self.body += &format!(
+            "<polygon points=\"{}\" {}/>",
+            points.join(" "),
+            paint("fill", fill)
+        );
+    }
+
+    fn sprite(&mut self, sketch: &Sketch) -> Sketch {
+        sketch.clone()
+    }
+
+    fn draw_sprite(&mut self, sprite: &Sketch) {
+        sprite.replay(self);
+    }
+
+    fn draw_text(&mut self, content: &str, place: Place, size: f32, color: Color) {
+        let (x, y, anchor) = match place {
+            // SVG places text by its baseline.
+            Place::At(point) => (point.x, point.y + size, "start"),
+            Place::AtRight(point) => (point.x, point.y + size, "end"),
+            Place::Centered(area) => (
+                area.x + area.width / 2.0,
+                area.y + (area.height + size) / 2.0,
+                "middle",
+            ),
+        };
+        self.body += &format!(
+            "<text x=\"{}\" y=\"{}\" font-size=\"{}\" text-anchor=\"{}\" {}>{}</text>",
+            x,
+            y,
+            size,
+            anchor,
+            paint("fill", color),
+            escape(content)
+        );
+    }
+
+    /// A guess: the viewer picks the font.
+    fn measure_text(&mut self, content: &str, size: f32) -> (f32, f32) {
+        (content.chars().count() as f32 * size * 0.6, size)
+    }
+
+    fn present(&mut self) {}
+}
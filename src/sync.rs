@@ -0,0 +1,357 @@
+//! A copy of the save file on a server of the player's choosing, so
+//! progress follows them between machines.
+
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use std::env;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Where the remote copy lives.
+#[derive(Clone)]
+pub enum Remote {
+    /// A file on a WebDAV server (or anything that takes a plain PUT),
+    /// with the `Authorization` header to send, if any.
+    WebDav { url: String, auth: Option<String> },
+    /// An object in an S3 compatible bucket, addressed path style.
+    S3 {
+        endpoint: String,
+        region: String,
+        bucket: String,
+        key: String,
+        access_key: String,
+        secret_key: String,
+    },
+}
+
+impl Remote {
+    /// Reads the `--sync` location: an http(s) URL of a WebDAV file, with
+    /// the user and password from `UNBLOCK_SYNC_USER` and
+    /// `UNBLOCK_SYNC_PASSWORD`, or `s3://bucket/key`, with the usual
+    /// `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and `AWS_REGION` and
+    /// `UNBLOCK_S3_ENDPOINT` for servers other than Amazon's.
+    pub fn parse(location: &str) -> Result<Remote, String> {
+        if let Some(path) = location.strip_prefix("s3://") {
+            let (bucket, key) = match path.split_once('/') {
+                Some((bucket, key)) if !bucket.is_empty() && !key.is_empty() => (bucket, key),
+                _ => return Err(format!("{} should look like s3://bucket/key", location)),
+            };
+            let var = |name: &str| {
+                env::var(name).map_err(|_| format!("Syncing with S3 needs {} set", name))
+            };
+            let region = env::var("AWS_REGION").unwrap_or_else(|_| String::from("us-east-1"));
+            let endpoint = env::var("UNBLOCK_S3_ENDPOINT")
+                .unwrap_or_else(|_| format!("https://s3.{}.amazonaws.com", region));
+            return Ok(Remote::S3 {
+                endpoint: endpoint.trim_end_matches('/').to_string(),
+                region,
+                bucket: bucket.to_string(),
+                key: key.to_string(),
+                access_key: var("AWS_ACCESS_KEY_ID")?,
+                secret_key: var("AWS_SECRET_ACCESS_KEY")?,
+            });
+        }
+        if !location.starts_with("http://") && !location.starts_with("https://") {
+            return Err(format!(
+                "{} should be an http(s) URL or s3://bucket/key",
+                location
+            ));
+        }
+        let auth = env::var("UNBLOCK_SYNC_USER").ok().map(|user| {
+            let password = env::var("UNBLOCK_SYNC_PASSWORD").unwrap_or_default();
+            format!(
+                "Basic {}",
+                base64(format!("{}:{}", user, password).as_bytes())
+            )
+        });
+        Ok(Remote::WebDav {
+            url: location.to_string(),
+            auth,
+        })
+    }
+
+    /// The remote copy, or None if nothing has been pushed yet.
+    pub fn pull(&self) -> Result<Option<String>, String> {
+        match self.request("GET", "").call() {
+            Ok(response) => response
+                .into_string()
+                .map(Some)
+                .map_err(|e| format!("{}: {}", self, e)),
+            Err(ureq::Error::Status(404, _)) => Ok(None),
+            Err(e) => Err(format!("{}: {}", self, e)),
+        }
+    }
+
+    pub fn push(&self, contents: &str) -> Result<(), String> {
+        self.request("PUT", contents)
+            .send_string(contents)
+            .map(|_| ())
+            .map_err(|e| format!("{}: {}", self, e))
+    }
+
+    /// A request with whatever authentication the remote needs. S3 signs
+    /// the body, so it has to be known up front.
+    fn request(&self, method: &str, body: &str) -> ureq::Request {
+        match self {
+            Remote::WebDav { url, auth } => {
+                let request = ureq::request(method, url).timeout(TIMEOUT);
+                match auth {
+                    Some(auth) => request.set("Authorization", auth),
+                    None => request,
+                }
+            }
+            Remote::S3 { endpoint, .. } => {
+                let path = self.s3_path();
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |since| since.as_secs());
+                let (date, authorization, payload) = self.sign(method, &path, body, now);
+                ureq::request(method, &format!("{}{}", endpoint, path))
+                    .timeout(TIMEOUT)
+                    .set("x-amz-date", &date)
+                    .set("x-amz-content-sha256", &payload)
+                    .set("Authorization", &authorization)
+            }
+        }
+    }
+
+    fn s3_path(&self) -> String {
+        match self {
+            Remote::S3 { bucket, key, .. } => {
+                format!("/{}/{}", uri_encode(bucket), uri_encode(key))
+            }
+            Remote::WebDav { .. } => unreachable!("Only S3 objects have a bucket path"),
+        }
+    }
+
+    /// AWS signature version 4 of a request made at `now` (seconds since
+    /// the epoch): the `x-amz-date`, `Authorization` and
+    /// `x-amz-content-sha256` header values.
+    fn sign(&self, method: &str, path: &str, body: &str, now: u64) -> (String, String, String) {
+        let (endpoint, region, access_key, secret_key) = match self {
+            Remote::S3 {
+                endpoint,
+                region,
+                access_key,
+                secret_key,
+                ..
+            } => (endpoint, region, access_key, secret_key),
+            Remote::WebDav { .. } => unreachable!("Only S3 requests are signed"),
+        };
+        let host = endpoint.split("://").nth(1).unwrap_or(endpoint);
+        let date = timestamp(now);
+        let payload = hex(&Sha256::digest(body.as_bytes()));
+        let canonical = canonical_request(method, path, "", host, &payload, &date);
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            access_key,
+            scope(&date, region),
+            SIGNED_HEADERS,
+            signature(secret_key, region, &canonical, &date)
+        );
+        (date, authorization, payload)
+    }
+}
+
+// The headers signed, in the order the canonical request lists them.
+const SIGNED_HEADERS: &str = "host;x-amz-content-sha256;x-amz-date";
+
+/// The canonical form of an S3 request that signature version 4 signs.
+/// `query` is already encoded and sorted.
+fn canonical_request(
+    method: &str,
+    path: &str,
+    query: &str,
+    host: &str,
+    payload: &str,
+    date: &str,
+) -> String {
+    format!(
+        "{}\n{}\n{}\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+        method, path, query, host, payload, date, SIGNED_HEADERS, payload
+    )
+}
+
+/// The day, region and service a signature made on `date` is good for.
+fn scope(date: &str, region: &str) -> String {
+    format!("{}/{}/s3/aws4_request", &date[..8], region)
+}
+
+/// The key that signs requests to `service` in `region` on `day`, derived
+/// from the secret key.
+fn signing_key(secret_key: &str, day: &str, region: &str, service: &str) -> Vec<u8> {
+    let mut key = format!("AWS4{}", secret_key).into_bytes();
+    for part in &[day, region, service, "aws4_request"] {
+        key = hmac(&key, part);
+    }
+    key
+}
+
+/// The signature of `canonical`, a canonical request made on `date`, in
+/// hex.
+fn signature(secret_key: &str, region: &str, canonical: &str, date: &str) -> String {
+    let to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        date,
+        scope(date, region),
+        hex(&Sha256::digest(canonical.as_bytes()))
+    );
+    let key = signing_key(secret_key, &date[..8], region, "s3");
+    hex(&hmac(&key, &to_sign))
+}
+
+impl std::fmt::Display for Remote {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Remote::WebDav { url, .. } => write!(f, "{}", url),
+            Remote::S3 { bucket, key, .. } => write!(f, "s3://{}/{}", bucket, key),
+        }
+    }
+}
+
+fn hmac(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Percent encodes everything but unreserved characters and slashes, as S3
+/// expects of paths.
+fn uri_encode(text: &str) -> String {
+    text.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// `seconds` since the epoch as an ISO 8601 basic UTC timestamp, like
+/// "20130524T000000Z".
+fn timestamp(seconds: u64) -> String {
+    let days = (seconds / 86_400) as i64;
+    let time = seconds % 86_400;
+    // Howard Hinnant's days to civil date conversion.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        year,
+        month,
+        day,
+        time / 3_600,
+        time / 60 % 60,
+        time % 60
+    )
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut text = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &byte)| n | (byte as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                text.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                text.push('=');
+            }
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The examples in AWS's documentation of signature version 4.
+    const SECRET_KEY: &str = "wJalrXUtnFEMI/K7MDENG/bPxRfiCYEXAMPLEKEY";
+    const EMPTY: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+    #[test]
+    fn signing_keys_match_the_published_example() {
+        let key = signing_key(
+            "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            "20120215",
+            "us-east-1",
+            "iam",
+        );
+        assert_eq!(
+            hex(&key),
+            "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d"
+        );
+    }
+
+    #[test]
+    fn signatures_match_the_published_s3_examples() {
+        let date = "20130524T000000Z";
+        let host = "examplebucket.s3.amazonaws.com";
+        for (query, expected) in &[
+            (
+                "lifecycle=",
+                "fea454ca298b7da1c68078a5d1bdbfbbe0d65c699e0f91ac7a200a0136783543",
+            ),
+            (
+                "max-keys=2&prefix=J",
+                "34b48302e7b5fa45bde8084f4b7868a86f0a534bc59db6670ed5711ef69dc6f7",
+            ),
+        ] {
+            let canonical = canonical_request("GET", "/", query, host, EMPTY, date);
+            assert_eq!(
+                signature(SECRET_KEY, "us-east-1", &canonical, date),
+                *expected
+            );
+        }
+        assert_eq!(hex(&Sha256::digest(b"")), EMPTY);
+    }
+
+    #[test]
+    fn base64_matches_rfc_4648() {
+        let vectors = [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ];
+        for (text, encoded) in &vectors {
+            assert_eq!(base64(text.as_bytes()), *encoded);
+        }
+    }
+
+    #[test]
+    fn timestamps_get_leap_years_right() {
+        let times = [
+            (0, "19700101T000000Z"),
+            (951_868_799, "20000229T235959Z"),
+            (951_868_800, "20000301T000000Z"),
+            (1_369_353_600, "20130524T000000Z"),
+            (1_709_210_096, "20240229T123456Z"),
+            (4_107_542_399, "21000228T235959Z"),
+            (4_107_542_400, "21000301T000000Z"),
+        ];
+        for (seconds, expected) in &times {
+            assert_eq!(timestamp(*seconds), *expected);
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use coffee::graphics::{
+    Color, Font, Frame, Gpu, HorizontalAlignment, Point, Rectangle, Text, VerticalAlignment,
+};
+use coffee::Result;
+
+const FONT: &[u8] = include_bytes!("../src_assets/fonts/Inconsolata-Regular.ttf");
+
+pub const SMALL: f32 = 16.0;
+pub const MEDIUM: f32 = 20.0;
+pub const LARGE: f32 = 48.0;
+
+/// Queues text for the current frame and draws it in one go.
+///
+/// Text is only visible once `draw` is called, so call it after everything
+/// the text should appear on top of has been drawn.
+pub struct Writer {
+    font: Font,
+}
+
+impl Writer {
+    pub fn load(gpu: &mut Gpu) -> Result<Writer> {
+        Ok(Writer {
+            font: Font::from_bytes(gpu, FONT)?,
+        })
+    }
+
+    /// Text whose top left corner is at `position`.
+    pub fn at(&mut self, content: &str, position: Point, size: f32, color: Color) {
+        self.font.add(Text {
+            content,
+            position,
+            size,
+            color,
+            ..Text::default()
+        });
+    }
+
+    /// Text whose top right corner is at `position`.
+    pub fn at_right(&mut self, content: &str, position: Point, size: f32, color: Color) {
+        self.font.add(Text {
+            content,
+            position,
+            size,
+            color,
+            horizontal_alignment: HorizontalAlignment::Right,
+            ..Text::default()
+        });
+    }
+
+    /// Text centered in `area`, wrapped to its width.
+    pub fn centered(&mut self, content: &str, area: Rectangle<f32>, size: f32, color: Color) {
+        self.font.add(Text {
+            content,
+            position: Point::new(area.x + area.width / 2.0, area.y + area.height / 2.0),
+            bounds: (area.width, area.height),
+            size,
+            color,
+            horizontal_alignment: HorizontalAlignment::Center,
+            vertical_alignment: VerticalAlignment::Center,
+        });
+    }
+
+    /// Width and height `content` takes up at `size`.
+    pub fn measure(&mut self, content: &str, size: f32) -> (f32, f32) {
+        self.font.measure(Text {
+            content,
+            size,
+            ..Text::default()
+        })
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame<'_>) {
+        self.font.draw(&mut frame.as_target());
+    }
+}
@@ -0,0 +1,189 @@
+use crate::mods::Mod;
+use crate::render::Color;
+use crate::save::Save;
+use unblock::level::{Block, BlockDir, BlockType};
+
+/// The colors a board is drawn in.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Theme {
+    pub name: String,
+    pub background: Color,
+    pub player: Color,
+    pub wall: Color,
+    pub exit: Color,
+    pub horizontal: Color,
+    pub vertical: Color,
+    /// How big the theme draws cells and the room it keeps round the board
+    /// in pixels, if it cares.
+    pub tile: Option<TileSize>,
+    pub margin: Option<usize>,
+}
+
+/// How big a board's cells are drawn, before any zoom.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TileSize {
+    /// As big as lets the whole board fit where it is drawn, margin aside,
+    /// following the window as it is resized.
+    Fit,
+    /// This many pixels a side.
+    Pixels(usize),
+}
+
+impl TileSize {
+    /// `fit`, or a number of pixels from 10 up.
+    pub fn parse(text: &str) -> Option<TileSize> {
+        match text.trim() {
+            "fit" => Some(TileSize::Fit),
+            pixels => pixels
+                .parse()
+                .ok()
+                .filter(|&pixels| pixels >= 10)
+                .map(TileSize::Pixels),
+        }
+    }
+}
+
+impl std::fmt::Display for TileSize {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            TileSize::Fit => write!(f, "fit"),
+            TileSize::Pixels(pixels) => write!(f, "{}", pixels),
+        }
+    }
+}
+
+/// The game's own colors, as a theme file.
+const CLASSIC: &str = include_str!("../src_assets/themes/classic.txt");
+
+/// How far each movable block is lightened (above 0) or darkened (below)
+/// by its place in the level, so that blocks of a kind side by side can be
+/// told apart.
+const SHADES: [f32; 5] = [0.0, 0.35, -0.35, 0.2, -0.2];
+
+const fn rgb(r: f32, g: f32, b: f32) -> Color {
+    Color { r, g, b, a: 1.0 }
+}
+
+impl Theme {
+    /// The game's own colors, from `src_assets/themes/classic.txt`.
+    pub fn classic() -> Theme {
+        let blank = Theme {
+            name: String::from("classic"),
+            background: Color::BLACK,
+            player: Color::WHITE,
+            wall: Color::WHITE,
+            exit: Color::WHITE,
+            horizontal: Color::WHITE,
+            vertical: Color::WHITE,
+            tile: None,
+            margin: None,
+        };
+        blank.read(CLASSIC).expect("the classic theme is broken")
+    }
+
+    /// A theme file: `key=#rrggbb` lines for any of background, player,
+    /// wall, exit, horizontal and vertical. The rest keep their classic
+    /// colors. `tile=` a `TileSize` and `margin=` a number of pixels size
+    /// the board.
+    pub fn parse(name: &str, text: &str) -> Result<Theme, String> {
+        let theme = Theme {
+            name: name.to_string(),
+            ..Theme::classic()
+        };
+        theme.read(text)
+    }
+
+    /// This theme with the colors a theme file gives changed.
+    fn read(mut self, text: &str) -> Result<Theme, String> {
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| format!("broken line \"{}\"", line))?;
+            let value = value.trim();
+            match key.trim() {
+                "tile" => {
+                    let tile = TileSize::parse(value);
+                    self.tile = Some(tile.ok_or_else(|| format!("bad tile size \"{}\"", value))?);
+                    continue;
+                }
+                "margin" => {
+                    let margin = value.parse().ok();
+                    self.margin = Some(margin.ok_or_else(|| format!("bad margin \"{}\"", value))?);
+                    continue;
+                }
+                _ => {}
+            }
+            let color = hex(value).ok_or_else(|| format!("bad color \"{}\"", value))?;
+            match key.trim() {
+                "background" => self.background = color,
+                "player" => self.player = color,
+                "wall" => self.wall = color,
+                "exit" => self.exit = color,
+                "horizontal" => self.horizontal = color,
+                "vertical" => self.vertical = color,
+                key => return Err(format!("unknown key \"{}\"", key)),
+            }
+        }
+        Ok(self)
+    }
+
+    /// The theme the game was last played with, for commands that draw
+    /// boards without a window.
+    pub fn saved(mods: &[Mod]) -> Theme {
+        let settings = Save::load(None).settings;
+        mods.iter()
+            .filter(|m| !settings.disabled_mods.contains(&m.name))
+            .filter_map(|m| m.theme.clone())
+            .find(|theme| theme.name == settings.theme)
+            .unwrap_or_else(Theme::classic)
+    }
+
+    pub fn color(&self, block: &Block) -> Color {
+        match block.r#type {
+            BlockType::Player => self.player,
+            BlockType::Wall => self.wall,
+            BlockType::Exit => self.exit,
+            BlockType::Other(_) => match block.dir {
+                BlockDir::LeftRight => self.horizontal,
+                BlockDir::UpDown => self.vertical,
+                _ => panic!("No Static + Other blocks exist"),
+            },
+        }
+    }
+
+    /// The color of `block`, the one at `index` in its level: the player,
+    /// walls and exits as `color` has them, other blocks in a shade of
+    /// theirs picked by `index`.
+    pub fn block_color(&self, block: &Block, index: usize) -> Color {
+        let color = self.color(block);
+        if !matches!(block.r#type, BlockType::Other(_)) {
+            return color;
+        }
+        let shade = SHADES[index % SHADES.len()];
+        let towards = if shade > 0.0 { 1.0 } else { 0.0 };
+        let mix = |channel: f32| channel + (towards - channel) * shade.abs();
+        Color {
+            r: mix(color.r),
+            g: mix(color.g),
+            b: mix(color.b),
+            a: color.a,
+        }
+    }
+}
+
+/// "#rrggbb" as a color.
+fn hex(text: &str) -> Option<Color> {
+    let digits = text.strip_prefix('#')?;
+    if digits.len() != 6 {
+        return None;
+    }
+    let channel = |i: usize| {
+        u8::from_str_radix(digits.get(i..i + 2)?, 16)
+            .ok()
+            .map(|value| value as f32 / 255.0)
+    };
+    Some(rgb(channel(0)?, channel(2)?, channel(4)?))
+}
@@ -0,0 +1,166 @@
+use crate::i18n;
+use std::time::{Duration, Instant};
+
+/// A fixed-length animation measured in real time, so it plays at the same
+/// speed whatever the tick rate or frame rate.
+#[derive(Clone, Copy)]
+pub struct Animation {
+    start: Instant,
+    duration: Duration,
+}
+
+impl Animation {
+    pub fn start(duration: Duration) -> Animation {
+        Animation {
+            start: Instant::now(),
+            duration,
+        }
+    }
+
+    /// How far along the animation is, from 0 to 1.
+    pub fn progress(&self) -> f32 {
+        if self.duration == Duration::from_secs(0) {
+            return 1.0;
+        }
+        (self.start.elapsed().as_secs_f32() / self.duration.as_secs_f32()).min(1.0)
+    }
+
+    pub fn remaining(&self) -> Duration {
+        self.duration.saturating_sub(self.start.elapsed())
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.start.elapsed() >= self.duration
+    }
+}
+
+/// Measures time spent on something, excluding the time it was paused.
+pub struct Stopwatch {
+    elapsed: Duration,
+    running_since: Option<Instant>,
+}
+
+impl Stopwatch {
+    pub fn new() -> Stopwatch {
+        Stopwatch {
+            elapsed: Duration::from_secs(0),
+            running_since: None,
+        }
+    }
+
+    pub fn start(&mut self) {
+        if self.running_since.is_none() {
+            self.running_since = Some(Instant::now());
+        }
+    }
+
+    pub fn pause(&mut self) {
+        if let Some(since) = self.running_since.take() {
+            self.elapsed += since.elapsed();
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.elapsed = Duration::from_secs(0);
+        if self.running_since.is_some() {
+            self.running_since = Some(Instant::now());
+        }
+    }
+
+    /// Makes the stopwatch read `elapsed`, carrying on from there if it is
+    /// running.
+    pub fn set(&mut self, elapsed: Duration) {
+        self.reset();
+        self.elapsed = elapsed;
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+            + self
+                .running_since
+                .map_or(Duration::from_secs(0), |since| since.elapsed())
+    }
+}
+
+/// How fast a recording plays back.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Speed {
+    Half,
+    Normal,
+    Double,
+    /// Stopped, moving on only when stepped.
+    Step,
+}
+
+impl Speed {
+    fn factor(self) -> f32 {
+        match self {
+            Speed::Half => 0.5,
+            Speed::Normal => 1.0,
+            Speed::Double => 2.0,
+            Speed::Step => 0.0,
+        }
+    }
+
+    pub fn label(self) -> String {
+        match self {
+            Speed::Half => String::from("0.5x"),
+            Speed::Normal => String::from("1x"),
+            Speed::Double => String::from("2x"),
+            Speed::Step => i18n::text("demo.step"),
+        }
+    }
+}
+
+/// The clock a recording is played back by: real time at a choice of
+/// speeds, or stopped and moved on a step at a time.
+pub struct Playback {
+    // Where the clock was when the speed last changed, and when that was.
+    at: Duration,
+    since: Instant,
+    speed: Speed,
+}
+
+impl Playback {
+    pub fn new() -> Playback {
+        Playback {
+            at: Duration::from_secs(0),
+            since: Instant::now(),
+            speed: Speed::Normal,
+        }
+    }
+
+    /// How far into the recording playback is.
+    pub fn elapsed(&self) -> Duration {
+        self.at + self.since.elapsed().mul_f32(self.speed.factor())
+    }
+
+    /// Goes back to the start of the recording, keeping the speed.
+    pub fn restart(&mut self) {
+        self.at = Duration::from_secs(0);
+        self.since = Instant::now();
+    }
+
+    pub fn speed(&self) -> Speed {
+        self.speed
+    }
+
+    pub fn set_speed(&mut self, speed: Speed) {
+        self.at = self.elapsed();
+        self.since = Instant::now();
+        self.speed = speed;
+    }
+
+    /// Stops playback, if it is not stopped already, and moves it on to
+    /// `at`.
+    pub fn step_to(&mut self, at: Duration) {
+        self.set_speed(Speed::Step);
+        self.at = self.at.max(at);
+    }
+}
+
+/// Formats a duration as minutes and seconds, e.g. "2:05".
+pub fn format(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
@@ -0,0 +1,85 @@
+use crate::render::{Color, Place, Point, Rectangle, Renderer};
+use crate::text;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const DURATION: Duration = Duration::from_secs(2);
+// Older messages are dropped once more than this many are waiting.
+const VISIBLE: usize = 3;
+// Messages remembered for bug reports, shown or not.
+const KEPT: usize = 50;
+
+struct Message {
+    text: String,
+    shown: Instant,
+}
+
+// Global so that any subsystem can report something without having to be
+// handed a reference to the game.
+static MESSAGES: Mutex<VecDeque<Message>> = Mutex::new(VecDeque::new());
+static LOG: Mutex<VecDeque<Message>> = Mutex::new(VecDeque::new());
+
+/// Shows `text` at the top of the screen for a couple of seconds.
+pub fn show<S: Into<String>>(text: S) {
+    let text = text.into();
+    let mut log = LOG.lock().expect("Message log poisoned");
+    log.push_back(Message {
+        text: text.clone(),
+        shown: Instant::now(),
+    });
+    while log.len() > KEPT {
+        log.pop_front();
+    }
+    let mut messages = MESSAGES.lock().expect("Message queue poisoned");
+    messages.push_back(Message {
+        text,
+        shown: Instant::now(),
+    });
+    while messages.len() > VISIBLE {
+        messages.pop_front();
+    }
+}
+
+/// The last messages shown, oldest first, each with how many seconds ago
+/// it was shown.
+#[cfg(feature = "journal")]
+pub fn recent() -> Vec<(f64, String)> {
+    let log = LOG.lock().expect("Message log poisoned");
+    log.iter()
+        .map(|message| (message.shown.elapsed().as_secs_f64(), message.text.clone()))
+        .collect()
+}
+
+pub fn draw(renderer: &mut impl Renderer) {
+    let mut messages = MESSAGES.lock().expect("Message queue poisoned");
+    messages.retain(|message| message.shown.elapsed() < DURATION);
+    if messages.is_empty() {
+        return;
+    }
+    let mut y = 60.0;
+    for message in messages.iter() {
+        let (width, height) = renderer.measure_text(&message.text, text::MEDIUM);
+        let area = Rectangle {
+            x: (renderer.width() - width) / 2.0 - 10.0,
+            y,
+            width: width + 20.0,
+            height: height + 10.0,
+        };
+        renderer.draw_rect(
+            area,
+            Color {
+                a: 0.8,
+                ..Color::BLACK
+            },
+            None,
+        );
+        renderer.draw_text(
+            &message.text,
+            Place::At(Point::new(area.x + 10.0, area.y + 5.0)),
+            text::MEDIUM,
+            Color::WHITE,
+        );
+        y += area.height + 5.0;
+    }
+}
@@ -0,0 +1,126 @@
+use crate::i18n;
+use crate::render::{Color, Place, Point, Rectangle, Renderer};
+use crate::text;
+use crate::{Action, Board};
+use unblock::level::{BlockType, Level};
+use unblock::prompt;
+
+/// The lessons of the first level, in the order they are taught.
+#[derive(Clone, Copy, PartialEq)]
+enum Step {
+    Drag,
+    Player,
+    Undo,
+    Reset,
+    Exit,
+}
+
+/// Onboarding overlay shown on the first level until it has been solved once.
+pub struct Tutorial {
+    step: Option<Step>,
+}
+
+impl Tutorial {
+    pub fn new(done: bool) -> Tutorial {
+        Tutorial {
+            step: if done { None } else { Some(Step::Drag) },
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.step.is_some()
+    }
+
+    /// Advances to the next lesson once the player has performed the action
+    /// the current one asks for. Returns true when the tutorial is finished.
+    pub fn observe(&mut self, action: &Action, level: &Level) -> bool {
+        let step = match self.step {
+            Some(step) => step,
+            None => return false,
+        };
+        self.step = match (step, action) {
+            (_, Action::Solved) => None,
+            (Step::Drag, Action::Moved(_)) => Some(Step::Player),
+            (Step::Player, Action::Moved(block))
+                if level.blocks[*block].r#type == BlockType::Player =>
+            {
+                Some(Step::Undo)
+            }
+            (Step::Undo, Action::Undo) => Some(Step::Reset),
+            (Step::Reset, Action::Reset) => Some(Step::Exit),
+            _ => Some(step),
+        };
+        self.step.is_none()
+    }
+
+    pub fn draw(&self, board: &Board, renderer: &mut impl Renderer) {
+        let step = match self.step {
+            Some(step) => step,
+            None => return,
+        };
+        let level = &board.level;
+        let (content, target) = match step {
+            Step::Drag => (
+                "tutorial.drag",
+                level
+                    .blocks
+                    .iter()
+                    .position(|b| matches!(b.r#type, BlockType::Other(_))),
+            ),
+            Step::Player => (
+                "tutorial.player",
+                level
+                    .blocks
+                    .iter()
+                    .position(|b| b.r#type == BlockType::Player),
+            ),
+            Step::Undo => ("tutorial.undo", None),
+            Step::Reset => ("tutorial.reset", None),
+            Step::Exit => (
+                "tutorial.exit",
+                level
+                    .blocks
+                    .iter()
+                    .position(|b| b.r#type == BlockType::Exit),
+            ),
+        };
+        banner(&i18n::text(content), renderer);
+
+        if let Some(target) = target {
+            let area = board.area(target);
+            let tip = Point::new(area.x + area.width / 2.0, area.y + 10.0);
+            renderer.draw_line(vec![Point::new(tip.x, tip.y - 40.0), tip], Color::WHITE, 3);
+            renderer.draw_line(
+                vec![
+                    Point::new(tip.x - 8.0, tip.y - 8.0),
+                    tip,
+                    Point::new(tip.x + 8.0, tip.y - 8.0),
+                ],
+                Color::WHITE,
+                3,
+            );
+        }
+    }
+}
+
+/// Shows what the pack has to say about the level as it stands, if
+/// anything, where the lessons of the tutorial go.
+pub fn draw_prompt(level: &Level, renderer: &mut impl Renderer) {
+    if let Some(prompt) = prompt::current(level) {
+        banner(&prompt.text, renderer);
+    }
+}
+
+fn banner(content: &str, renderer: &mut impl Renderer) {
+    renderer.draw_text(
+        content,
+        Place::Centered(Rectangle {
+            x: 0.0,
+            y: 0.0,
+            width: renderer.width(),
+            height: 50.0,
+        }),
+        text::MEDIUM,
+        Color::WHITE,
+    );
+}
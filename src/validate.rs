@@ -0,0 +1,106 @@
+use crate::level::{
+    pos_to_xy, BlockDir, BlockType, Level, EXIT, FLOOR, LEFTRIGHT1, LEFTRIGHT2, PLAYER, UPDOWN1,
+    UPDOWN2, WALL,
+};
+use crate::solver::{self, SolverConfig};
+use crate::win::WinCondition;
+use std::fmt;
+
+/// Something wrong with a level that keeps it from being played.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Problem {
+    UnknownCharacter {
+        ch: u8,
+        x: usize,
+        y: usize,
+    },
+    /// A movable block shorter than two cells.
+    ShortBlock {
+        x: usize,
+        y: usize,
+    },
+    Players(usize),
+    NoExit,
+    Unsolvable,
+    /// The goal allows fewer moves than the level takes.
+    OverLimit {
+        fewest: usize,
+        limit: usize,
+    },
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::UnknownCharacter { ch, x, y } => write!(
+                f,
+                "unknown character '{}' at column {}, row {}",
+                *ch as char,
+                x + 1,
+                y + 1
+            ),
+            Problem::ShortBlock { x, y } => write!(
+                f,
+                "block at column {}, row {} is only one cell long",
+                x + 1,
+                y + 1
+            ),
+            Problem::Players(0) => write!(f, "no player"),
+            Problem::Players(n) => write!(f, "{} players, expected one", n),
+            Problem::NoExit => write!(f, "no exit"),
+            Problem::Unsolvable => write!(f, "cannot be solved"),
+            Problem::OverLimit { fewest, limit } => {
+                write!(f, "takes {} moves but the goal allows {}", fewest, limit)
+            }
+        }
+    }
+}
+
+/// Checks a level can be played, returning the fewest moves it takes to
+/// solve, or None if the solver does not apply to it (see
+/// `solver::applies`) or to its goal, as it only knows how to get out.
+/// Solvability is only checked once everything else is right.
+pub fn check(level: &Level) -> Result<Option<usize>, Vec<Problem>> {
+    let mut problems = Vec::new();
+    for (pos, &ch) in level.template().iter().enumerate() {
+        match ch {
+            FLOOR | WALL | EXIT | PLAYER | LEFTRIGHT1 | LEFTRIGHT2 | UPDOWN1 | UPDOWN2 => {}
+            ch => {
+                let (x, y) = pos_to_xy(pos);
+                problems.push(Problem::UnknownCharacter { ch, x, y });
+            }
+        }
+    }
+    for block in &level.blocks {
+        if block.dir != BlockDir::Static && (block.x1, block.y1) == (block.x2, block.y2) {
+            problems.push(Problem::ShortBlock {
+                x: block.x1,
+                y: block.y1,
+            });
+        }
+    }
+    let count = |r#type| level.blocks.iter().filter(|b| b.r#type == r#type).count();
+    let players = count(BlockType::Player);
+    if players != 1 {
+        problems.push(Problem::Players(players));
+    }
+    if count(BlockType::Exit) == 0 && level.win.needs_exit() {
+        problems.push(Problem::NoExit);
+    }
+    if !problems.is_empty() {
+        return Err(problems);
+    }
+    if !level.win.needs_exit() || !solver::applies(level) {
+        return Ok(None);
+    }
+    match (solver::solve(level, SolverConfig::default()), &level.win) {
+        (Some(solution), WinCondition::ExitWithin(limit)) if solution.len() > *limit => {
+            Err(vec![Problem::OverLimit {
+                fewest: solution.len(),
+                limit: *limit,
+            }])
+        }
+        (Some(solution), _) => Ok(Some(solution.len())),
+        (None, _) => Err(vec![Problem::Unsolvable]),
+    }
+}
@@ -0,0 +1,220 @@
+use crate::dialog::Answer;
+use crate::i18n;
+use crate::input::UnblockInput;
+use crate::render::{leading, Color, Point, Rectangle, Renderer};
+use crate::text;
+use coffee::input::keyboard::KeyCode;
+use unblock::level::Level;
+
+/// Height of the strip along the top of the window the tree is drawn in.
+pub const HEIGHT: f32 = 46.0;
+
+// Most room a move or a branch takes, squeezed when there are many.
+const COLUMN: f32 = 12.0;
+const ROW: f32 = 8.0;
+const MARGIN: f32 = 8.0;
+
+const GREY: Color = Color {
+    r: 0.5,
+    g: 0.5,
+    b: 0.5,
+    a: 1.0,
+};
+
+// Height of the line under the strip a note is shown and written in.
+const NOTE: f32 = 24.0;
+
+/// A point of the tree: a variation, or None for the start.
+struct Dot {
+    node: Option<usize>,
+    parent: Option<Point>,
+    at: Point,
+}
+
+/// The tree of lines tried on `level`, like the move tree of a chess
+/// program: a dot for the start and after each move, a move further to the
+/// right, with the branches below the line first played from the same
+/// point. The current line is white and the position it is at ringed.
+pub fn draw(level: &Level, renderer: &mut impl Renderer) {
+    renderer.draw_rect(
+        Rectangle {
+            x: 0.0,
+            y: 0.0,
+            width: renderer.width(),
+            height: HEIGHT,
+        },
+        Color {
+            a: 0.6,
+            ..Color::BLACK
+        },
+        None,
+    );
+    let line = level.line(level.variation());
+    let dots = layout(level, renderer.width());
+    let on_line = |node: Option<usize>| node.is_none_or(|node| line.contains(&node));
+    for dot in &dots {
+        if let Some(parent) = dot.parent {
+            let color = if on_line(dot.node) {
+                Color::WHITE
+            } else {
+                GREY
+            };
+            renderer.draw_line(vec![parent, dot.at], color, 1);
+        }
+    }
+    for dot in &dots {
+        let color = if on_line(dot.node) {
+            Color::WHITE
+        } else {
+            GREY
+        };
+        renderer.draw_rect(square(dot.at, 2.5), color, None);
+        if dot.node == level.variation() {
+            let clear = Color {
+                a: 0.0,
+                ..Color::BLACK
+            };
+            renderer.draw_rect(square(dot.at, 4.5), clear, Some((color, 1)));
+        }
+    }
+}
+
+/// Shows `note`, the one on the move just made, on a line under the strip.
+pub fn draw_note(note: &str, renderer: &mut impl Renderer) {
+    renderer.draw_rect(
+        Rectangle {
+            x: 0.0,
+            y: HEIGHT,
+            width: renderer.width(),
+            height: NOTE,
+        },
+        Color {
+            a: 0.6,
+            ..Color::BLACK
+        },
+        None,
+    );
+    renderer.draw_text(
+        note,
+        leading(MARGIN, renderer.width() - MARGIN, HEIGHT + 4.0),
+        text::SMALL,
+        Color::WHITE,
+    );
+}
+
+/// A note being written on a step of the replay, in the line under the
+/// strip. Enter keeps it, a blank one taking the note away, and Escape
+/// leaves the note as it was.
+pub struct NoteEditor {
+    step: usize,
+    text: String,
+}
+
+impl NoteEditor {
+    /// Starts writing on the step at `step`, from the note it has.
+    pub fn new(step: usize, text: &str) -> NoteEditor {
+        NoteEditor {
+            step,
+            text: text.to_string(),
+        }
+    }
+
+    /// Once it is written, the step and the note to put on it.
+    pub fn interact(&mut self, input: &UnblockInput) -> Answer<(usize, String)> {
+        if input.was_key_released(KeyCode::Escape) {
+            return Answer::No;
+        }
+        if input.was_key_released(KeyCode::Return) {
+            return Answer::Yes((self.step, self.text.clone()));
+        }
+        if input.was_key_repeated(KeyCode::Back) {
+            self.text.pop();
+        }
+        self.text += input.text_entered();
+        Answer::Pending
+    }
+
+    pub fn draw(&self, renderer: &mut impl Renderer) {
+        draw_note(
+            &i18n::fill("note.editing", &[&(self.step + 1), &self.text]),
+            renderer,
+        );
+    }
+}
+
+/// The variation whose dot is at `point`, Some(None) for the start, or None
+/// if no dot is.
+pub fn hit(level: &Level, width: f32, point: Point) -> Option<Option<usize>> {
+    layout(level, width)
+        .into_iter()
+        .find(|dot| (dot.at.x - point.x).abs() <= 5.0 && (dot.at.y - point.y).abs() <= 5.0)
+        .map(|dot| dot.node)
+}
+
+/// Where each dot goes in a strip `width` wide: its column is how many
+/// moves in it is, and its row the branch it is on. A move's first branch
+/// stays on its row and each later one takes a new row below those used.
+fn layout(level: &Level, width: f32) -> Vec<Dot> {
+    let mut cells = Vec::new();
+    let mut rows = 0;
+    place(level, None, 0, 0, &mut rows, &mut cells);
+    let columns = cells
+        .iter()
+        .map(|&(_, column, _)| column)
+        .max()
+        .unwrap_or(0)
+        + 1;
+    let column = COLUMN.min((width - 2.0 * MARGIN) / columns as f32);
+    let row = ROW.min((HEIGHT - 2.0 * MARGIN) / (rows + 1) as f32);
+    let point = |(_, x, y): (Option<usize>, usize, usize)| {
+        Point::new(MARGIN + x as f32 * column, MARGIN + y as f32 * row)
+    };
+    let points: Vec<Point> = cells.iter().copied().map(point).collect();
+    cells
+        .iter()
+        .zip(&points)
+        .map(|(&(node, _, _), &at)| Dot {
+            node,
+            parent: node.map(|node| {
+                let parent = level.variations()[node].parent;
+                let index = cells
+                    .iter()
+                    .position(|&(other, _, _)| other == parent)
+                    .expect("Parents are placed before their branches");
+                points[index]
+            }),
+            at,
+        })
+        .collect()
+}
+
+/// Places `node` at `column` and `row`, then its branches after it.
+fn place(
+    level: &Level,
+    node: Option<usize>,
+    column: usize,
+    row: usize,
+    rows: &mut usize,
+    cells: &mut Vec<(Option<usize>, usize, usize)>,
+) {
+    cells.push((node, column, row));
+    for (i, branch) in level.branches(node).into_iter().enumerate() {
+        let row = if i == 0 {
+            row
+        } else {
+            *rows += 1;
+            *rows
+        };
+        place(level, Some(branch), column + 1, row, rows, cells);
+    }
+}
+
+/// A square `radius` from `center` to each side.
+fn square(center: Point, radius: f32) -> Rectangle<f32> {
+    Rectangle {
+        x: center.x - radius,
+        y: center.y - radius,
+        width: radius * 2.0,
+        height: radius * 2.0,
+    }
+}
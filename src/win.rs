@@ -0,0 +1,147 @@
+use crate::level::{BlockDir, Level, TILES_HIGH, TILES_WIDE};
+use std::fmt;
+
+/// What a level asks of the player, from its `# goal:` comment. Cells are
+/// named like the solver names them, `a1` being the top left corner.
+#[derive(Clone, Debug, PartialEq)]
+pub enum WinCondition {
+    /// `exit`: get the player onto the exit. The goal of every level
+    /// without a goal comment.
+    Exit,
+    /// `exit within N`: get the player onto the exit in N moves or fewer.
+    ExitWithin(usize),
+    /// `move c2 to e5`: move the block covering the first cell so its top
+    /// left corner is on the second.
+    Reach {
+        block: usize,
+        from: (usize, usize),
+        target: (usize, usize),
+    },
+    /// `clear vertical from top`: move every block going along `dir` out of
+    /// the row or column just inside that edge of the board.
+    Clear { dir: BlockDir, edge: Edge },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Edge {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+impl WinCondition {
+    /// Reads a goal comment for `level`. None if it makes no sense, such as
+    /// moving a block that is not there.
+    pub fn parse(text: &str, level: &Level) -> Option<WinCondition> {
+        let words: Vec<&str> = text.split_whitespace().collect();
+        match words[..] {
+            ["exit"] => Some(WinCondition::Exit),
+            ["exit", "within", moves] => moves.parse().ok().map(WinCondition::ExitWithin),
+            ["move", from, "to", to] => {
+                let (x, y) = cell(from)?;
+                Some(WinCondition::Reach {
+                    block: level.block_at(x, y)?,
+                    from: (x, y),
+                    target: cell(to)?,
+                })
+            }
+            ["clear", dir, "from", edge] => Some(WinCondition::Clear {
+                dir: match dir {
+                    "horizontal" => BlockDir::LeftRight,
+                    "vertical" => BlockDir::UpDown,
+                    _ => return None,
+                },
+                edge: match edge {
+                    "top" => Edge::Top,
+                    "bottom" => Edge::Bottom,
+                    "left" => Edge::Left,
+                    "right" => Edge::Right,
+                    _ => return None,
+                },
+            }),
+            _ => None,
+        }
+    }
+
+    /// True if the goal is met with the level as it stands.
+    pub fn is_met(&self, level: &Level) -> bool {
+        match *self {
+            WinCondition::Exit => level.bitboard().is_solved(),
+            WinCondition::ExitWithin(moves) => {
+                level.bitboard().is_solved() && level.moves.len() <= moves
+            }
+            WinCondition::Reach { block, target, .. } => {
+                let block = &level.blocks[block];
+                (block.x1, block.y1) == target
+            }
+            WinCondition::Clear { dir, edge } => !level.blocks.iter().any(|block| {
+                block.dir == dir
+                    && match edge {
+                        Edge::Top => block.y1 <= 1,
+                        Edge::Bottom => block.y2 >= TILES_HIGH - 2,
+                        Edge::Left => block.x1 <= 1,
+                        Edge::Right => block.x2 >= TILES_WIDE - 2,
+                    }
+            }),
+        }
+    }
+
+    /// True if getting out is part of the goal, so the solver's answers
+    /// apply to it.
+    pub fn needs_exit(&self) -> bool {
+        matches!(self, WinCondition::Exit | WinCondition::ExitWithin(_))
+    }
+}
+
+/// What to do, for showing the player.
+impl fmt::Display for WinCondition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WinCondition::Exit => write!(f, "get out"),
+            WinCondition::ExitWithin(moves) => write!(f, "get out in {} moves", moves),
+            WinCondition::Reach { from, target, .. } => {
+                write!(
+                    f,
+                    "move the block from {} to {}",
+                    name(*from),
+                    name(*target)
+                )
+            }
+            WinCondition::Clear { dir, edge } => write!(
+                f,
+                "clear {} blocks from the {}",
+                match dir {
+                    BlockDir::UpDown => "vertical",
+                    _ => "horizontal",
+                },
+                match edge {
+                    Edge::Top => "top",
+                    Edge::Bottom => "bottom",
+                    Edge::Left => "left",
+                    Edge::Right => "right",
+                }
+            ),
+        }
+    }
+}
+
+/// "c2" as a grid cell.
+pub(crate) fn cell(name: &str) -> Option<(usize, usize)> {
+    let mut chars = name.chars();
+    let column = chars.next()?;
+    let row: usize = chars.as_str().parse().ok()?;
+    if !column.is_ascii_lowercase() || row == 0 {
+        return None;
+    }
+    let (x, y) = (column as usize - 'a' as usize, row - 1);
+    if x < TILES_WIDE && y < TILES_HIGH {
+        Some((x, y))
+    } else {
+        None
+    }
+}
+
+fn name((x, y): (usize, usize)) -> String {
+    format!("{}{}", (b'a' + x as u8) as char, y + 1)
+}
@@ -0,0 +1,24 @@
+#![cfg(feature = "gui")]
+
+use std::fs;
+use std::process::Command;
+
+/// Every script in tests/input, played by the game as `input-test` does.
+#[test]
+fn input_scripts_leave_the_boards_they_expect() {
+    let mut scripts: Vec<_> = fs::read_dir("tests/input")
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    scripts.sort();
+    let output = Command::new(env!("CARGO_BIN_EXE_unblock"))
+        .arg("input-test")
+        .args(&scripts)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+}
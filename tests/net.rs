@@ -0,0 +1,135 @@
+#![cfg(feature = "net")]
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::{Duration, Instant};
+use unblock::net::{self, Client, Message};
+use unblock::rng::Rng;
+
+#[test]
+fn messages_round_trip() {
+    let messages = [
+        Message::Hello(net::VERSION),
+        Message::Progress {
+            moves: 3,
+            solved: true,
+        },
+        Message::Ready,
+        Message::Wait,
+        Message::Start {
+            seed: u64::MAX,
+            moves: 8,
+        },
+        Message::Opponent {
+            moves: 0,
+            solved: false,
+        },
+        Message::Left,
+    ];
+    for message in &messages {
+        assert_eq!(Message::parse(&message.to_string()).as_ref(), Some(message));
+    }
+    assert_eq!(Message::parse("START 12"), None);
+}
+
+/// Waits for the next message other than `WAIT`.
+fn next(client: &Client) -> Message {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while Instant::now() < deadline {
+        match client.poll() {
+            Some(Message::Wait) | None => thread::sleep(Duration::from_millis(10)),
+            Some(message) => return message,
+        }
+    }
+    panic!("No message from the server");
+}
+
+/// Starts a server on a port the system picks, returning its address.
+fn server() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    thread::spawn(move || net::serve_on(listener, Rng::new(1)));
+    address
+}
+
+#[test]
+fn server_pairs_clients_on_the_same_puzzle() {
+    let address = server();
+    let mut first = Client::connect(&address).unwrap();
+    let second = Client::connect(&address).unwrap();
+    let start = next(&first);
+    assert!(matches!(start, Message::Start { .. }));
+    assert_eq!(next(&second), start);
+    first
+        .send(&Message::Progress {
+            moves: 2,
+            solved: false,
+        })
+        .unwrap();
+    assert_eq!(
+        next(&second),
+        Message::Opponent {
+            moves: 2,
+            solved: false
+        }
+    );
+    drop(first);
+    assert_eq!(next(&second), Message::Left);
+}
+
+#[test]
+fn server_skips_a_waiting_client_that_left() {
+    let address = server();
+    let gone = TcpStream::connect(&address).unwrap();
+    thread::sleep(Duration::from_millis(100));
+    drop(gone);
+    thread::sleep(Duration::from_millis(100));
+    let first = Client::connect(&address).unwrap();
+    let second = Client::connect(&address).unwrap();
+    let start = next(&first);
+    assert!(matches!(start, Message::Start { .. }));
+    assert_eq!(next(&second), start);
+}
+
+/// Reads what the server sends `stream` until it closes the connection,
+/// failing if that takes more than a few seconds.
+fn closed(stream: &TcpStream) -> Vec<String> {
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    BufReader::new(stream)
+        .lines()
+        .map(|line| line.expect("The server kept the connection open"))
+        .collect()
+}
+
+#[test]
+fn server_disconnects_a_client_sending_an_overlong_line() {
+    let address = server();
+    let mut flood = TcpStream::connect(&address).unwrap();
+    let other = Client::connect(&address).unwrap();
+    assert!(matches!(next(&other), Message::Start { .. }));
+    let _ = flood.write_all(&[b'A'; net::MAX_LINE * 4]);
+    assert_eq!(next(&other), Message::Left);
+    flood
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    // Reset rather than closed cleanly if the flood was not all read, but
+    // not left open.
+    if let Err(e) = flood.read_to_end(&mut Vec::new()) {
+        assert_eq!(e.kind(), io::ErrorKind::ConnectionReset);
+    }
+}
+
+#[test]
+fn server_disconnects_a_client_with_another_version() {
+    let address = server();
+    let mut old = TcpStream::connect(&address).unwrap();
+    writeln!(old, "{}", Message::Hello(net::VERSION + 1)).unwrap();
+    let other = Client::connect(&address).unwrap();
+    assert!(matches!(next(&other), Message::Start { .. }));
+    assert_eq!(next(&other), Message::Left);
+    let lines = closed(&old);
+    assert!(lines.iter().any(|line| line.starts_with("START")));
+}
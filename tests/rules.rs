@@ -0,0 +1,1494 @@
+use proptest::prelude::*;
+use std::time::Duration;
+use unblock::generate::{self, Preset};
+use unblock::import::{self, Image};
+use unblock::level::{
+    pos_to_xy, xy_to_pos, BlockDir, BlockType, Cell, Level, EXIT, FLOOR, LEFTRIGHT1, LEFTRIGHT2,
+    PLAYER, UPDOWN1, UPDOWN2, WALL,
+};
+use unblock::replay::Replay;
+use unblock::rng::Rng;
+use unblock::solver::{Solver, SolverConfig};
+use unblock::validate::{self, Problem};
+use unblock::win::{Edge, WinCondition};
+use unblock::{blocking, limits, pack, prompt, solver};
+
+/// A block to try placing: whether it is vertical, its length and its top
+/// left cell.
+type Candidate = (bool, usize, usize, usize);
+
+/// Builds a walled level with the player and exit on row 3, then adds every
+/// candidate block that fits. Each block gets whichever of its two
+/// characters keeps it from running into a neighbour of the same kind.
+fn build(player_x: usize, candidates: &[Candidate]) -> [u8; 64] {
+    let mut data = [FLOOR; 64];
+    for i in 0..8 {
+        data[xy_to_pos(i, 0)] = WALL;
+        data[xy_to_pos(i, 7)] = WALL;
+        data[xy_to_pos(0, i)] = WALL;
+        data[xy_to_pos(7, i)] = WALL;
+    }
+    data[xy_to_pos(7, 3)] = EXIT;
+    data[xy_to_pos(player_x, 3)] = PLAYER;
+    data[xy_to_pos(player_x + 1, 3)] = PLAYER;
+    for &(vertical, len, x, y) in candidates {
+        let cells: Vec<(usize, usize)> = (0..len)
+            .map(|i| if vertical { (x, y + i) } else { (x + i, y) })
+            .collect();
+        if cells
+            .iter()
+            .any(|&(cx, cy)| cx > 6 || cy > 6 || data[xy_to_pos(cx, cy)] != FLOOR)
+        {
+            continue;
+        }
+        let (before, after) = if vertical {
+            (xy_to_pos(x, y - 1), xy_to_pos(x, y + len))
+        } else {
+            (xy_to_pos(x - 1, y), xy_to_pos(x + len, y))
+        };
+        let (first, second) = if vertical {
+            (UPDOWN1, UPDOWN2)
+        } else {
+            (LEFTRIGHT1, LEFTRIGHT2)
+        };
+        let free = |ch| data[before] != ch && data[after] != ch;
+        let ch = if free(first) {
+            first
+        } else if free(second) {
+            second
+        } else {
+            continue;
+        };
+        for (cx, cy) in cells {
+            data[xy_to_pos(cx, cy)] = ch;
+        }
+    }
+    data
+}
+
+fn level() -> impl Strategy<Value = Level> {
+    (
+        1..6usize,
+        prop::collection::vec((any::<bool>(), 2..4usize, 1..7usize, 1..7usize), 0..16),
+    )
+        .prop_map(|(player_x, candidates)| {
+            Level::from(&mut build(player_x, &candidates).iter().copied())
+        })
+}
+
+/// Checks no two blocks share a cell, and that the occupancy grid agrees
+/// with the blocks. Only the player may cover the exit.
+fn check_invariants(level: &Level) -> Result<(), TestCaseError> {
+    prop_assert_eq!(level.check_invariants(), Ok(()));
+    let mut owner = [None; 64];
+    for (index, block) in level.blocks.iter().enumerate() {
+        prop_assert!(block.x2 < 8 && block.y2 < 8);
+        if block.r#type == BlockType::Exit {
+            continue;
+        }
+        for x in block.x1..block.x2 + 1 {
+            for y in block.y1..block.y2 + 1 {
+                let pos = xy_to_pos(x, y);
+                prop_assert_eq!(owner[pos], None, "blocks overlap at {:?}", (x, y));
+                owner[pos] = Some(index);
+                prop_assert_eq!(level.occupant(x, y), Some(index));
+                if block.dir != BlockDir::Static {
+                    prop_assert_eq!(level.data[pos], Cell::Block(index));
+                }
+            }
+        }
+    }
+    for (pos, owner) in owner.iter().enumerate() {
+        if owner.is_none() {
+            prop_assert!(matches!(level.data[pos], Cell::Floor | Cell::Exit));
+            let (x, y) = pos_to_xy(pos);
+            prop_assert_eq!(level.occupant(x, y), None);
+        }
+    }
+    Ok(())
+}
+
+proptest! {
+    #[test]
+    fn serialize_round_trips(level in level()) {
+        let parsed = Level::from(&mut level.serialize().iter().copied());
+        prop_assert_eq!(parsed.blocks, level.blocks);
+    }
+
+    #[test]
+    fn legal_moves_keep_blocks_apart(
+        mut level in level(),
+        moves in prop::collection::vec((any::<prop::sample::Index>(), -7..8isize), 0..40),
+    ) {
+        let start = level.serialize();
+        let movable: Vec<usize> = (0..level.blocks.len())
+            .filter(|&i| level.blocks[i].dir != BlockDir::Static)
+            .collect();
+        let mut cells = 0;
+        for (block, delta) in moves {
+            if level.solved {
+                break;
+            }
+            let block = *block.get(&movable);
+            let target = level.slide_limit(block, delta);
+            prop_assert!(level.can_move(block, target));
+            let before = (level.blocks[block].x2 - level.blocks[block].x1,
+                level.blocks[block].y2 - level.blocks[block].y1);
+            cells += (level.blocks[block].x1 as isize - target.0 as isize).unsigned_abs()
+                + (level.blocks[block].y1 as isize - target.1 as isize).unsigned_abs();
+            level.move_block(block, target);
+            prop_assert_eq!(level.cells_moved(), cells);
+            prop_assert_eq!(before, (level.blocks[block].x2 - level.blocks[block].x1,
+                level.blocks[block].y2 - level.blocks[block].y1));
+            check_invariants(&level)?;
+        }
+        while level.undo() {
+            check_invariants(&level)?;
+        }
+        prop_assert_eq!(&level.serialize()[..], &start[..]);
+    }
+
+    /// Packs come from anywhere, so no bytes may panic the parser: mostly
+    /// level characters, to get past the framing, behind the comments that
+    /// change how levels are read.
+    #[test]
+    fn packs_of_any_bytes_parse_without_panicking(
+        comment in prop::sample::select(vec![
+            "",
+            "# phases: 2\n",
+            "# phases: 99999\n",
+            "# gravity: on\n",
+            "# goal: clear\n",
+            "# stars: 3 5\n",
+        ]),
+        data in prop::collection::vec(
+            prop_oneof![
+                4 => prop::sample::select(b"*&-_|(=^".to_vec()),
+                1 => prop::sample::select(b" \r\n#:".to_vec()),
+                1 => any::<u8>(),
+            ],
+            0..300,
+        ),
+    ) {
+        let mut pack = comment.as_bytes().to_vec();
+        pack.extend(data);
+        for mut level in pack::parse(&pack) {
+            let _ = validate::check(&level);
+            let _ = level.canonical_hash();
+            level.reset();
+        }
+    }
+}
+
+/// The same puzzle mirrored, turned half way round and redrawn with the other characters.
+#[test]
+fn canonical_hash_ignores_symmetry_and_characters() {
+    let level = "\
+        &&&&&&&&\
+        &---**|&\
+        &**|**|&\
+        &==|**|^\
+        &|*|*--&\
+        &|***|*&\
+        &---*|*&\
+        &&&&&&&&";
+    let mirrored = "\
+        &&&&&&&&\
+        &|**___&\
+        &|**|**&\
+        ^|**|==&\
+        &--*|*|&\
+        &*|***|&\
+        &*|*___&\
+        &&&&&&&&";
+    let turned = "\
+        &&&&&&&&\
+        &*|*---&\
+        &*|***(&\
+        &--*|*(&\
+        ^|**|==&\
+        &|**|**&\
+        &|**___&\
+        &&&&&&&&";
+    let parse = |data: &str| Level::from(&mut data.bytes());
+    let hash = parse(level).canonical_hash();
+    assert_eq!(parse(mirrored).canonical_hash(), hash);
+    assert_eq!(parse(turned).canonical_hash(), hash);
+    let mut moved = parse(level);
+    let index = moved.block_at(1, 1).unwrap();
+    moved.move_block(index, (2, 1));
+    assert_ne!(moved.canonical_hash(), hash);
+}
+
+#[test]
+fn gravity_and_phases_are_part_of_the_canonical_hash() {
+    let board = "\
+&&&&&&&&
+&******&
+&******&
+&******&
+&******&
+&**|***&
+&==|***^
+&&&&&&&&
+";
+    let plain = pack::parse(board.as_bytes()).remove(0);
+    let falling = pack::parse(format!("# gravity: on\n{}", board).as_bytes()).remove(0);
+    assert_ne!(plain.canonical_hash(), falling.canonical_hash());
+    // The block falls back into the lane every time it is moved out.
+    let mut solver = Solver::new();
+    assert_eq!(solver.remaining(&plain), Some(2));
+    assert_eq!(solver.remaining(&falling), None);
+    assert!(pack::duplicates(&[plain.clone(), falling.clone()]).is_empty());
+
+    // Down stays down under the left to right mirror, and only under it.
+    let mirrored = "\
+&&&&&&&&
+&******&
+&******&
+&******&
+&******&
+&***|**&
+^***|==&
+&&&&&&&&
+";
+    let mirrored = pack::parse(format!("# gravity: on\n{}", mirrored).as_bytes()).remove(0);
+    assert_eq!(mirrored.canonical_hash(), falling.canonical_hash());
+    let mut upside_down = falling.rotated().rotated();
+    upside_down.set_gravity(true);
+    assert_ne!(upside_down.canonical_hash(), falling.canonical_hash());
+
+    let phased = format!("# phases: 2\n{}\n{}", board, board.replace('^', "&"));
+    let phased = pack::parse(phased.as_bytes()).remove(0);
+    assert_ne!(phased.canonical_hash(), plain.canonical_hash());
+}
+
+#[test]
+fn transforms_keep_levels_solvable_in_as_many_moves() {
+    for level in pack::parse(pack::BUILTIN) {
+        let moves = solver::solve(&level, SolverConfig::default()).map(|solution| solution.len());
+        assert!(moves.is_some());
+        for transformed in [level.mirrored(), level.rotated(), level.rotated().rotated()] {
+            assert_eq!(transformed.canonical_hash(), level.canonical_hash());
+            assert_eq!(
+                solver::solve(&transformed, SolverConfig::default()).map(|solution| solution.len()),
+                moves
+            );
+        }
+        assert_eq!(level.mirrored().mirrored().serialize(), level.serialize());
+        let turned = level.rotated().rotated().rotated().rotated();
+        assert_eq!(turned.serialize(), level.serialize());
+    }
+}
+
+#[test]
+fn validate_reports_what_is_wrong() {
+    let level = "\
+        &&&&&&&&\
+        &-**x*|&\
+        &**|**|&\
+        &**|**|&\
+        &|*|*--&\
+        &|***|*&\
+        &---*|*&\
+        &&&&&&&&";
+    let problems = validate::check(&Level::from(&mut level.bytes())).unwrap_err();
+    assert_eq!(
+        problems,
+        vec![
+            Problem::UnknownCharacter {
+                ch: b'x',
+                x: 4,
+                y: 1
+            },
+            Problem::ShortBlock { x: 1, y: 1 },
+            Problem::Players(0),
+            Problem::NoExit,
+        ]
+    );
+    for level in pack::parse(pack::BUILTIN) {
+        assert!(validate::check(&level).is_ok());
+    }
+}
+
+#[test]
+fn goal_comments_change_what_solves_a_level() {
+    let board = "\
+&&&&&&&&
+&---**|&
+&**|**|&
+&==|**|^
+&|*|*--&
+&|***|*&
+&---*|*&
+&&&&&&&&
+";
+    let pack = |goal: &str| format!("# goal: {}\n{}", goal, board);
+
+    let mut level = pack::parse(pack("move b2 to c2").as_bytes()).remove(0);
+    let block = level.block_at(1, 1).unwrap();
+    assert_eq!(
+        level.win,
+        WinCondition::Reach {
+            block,
+            from: (1, 1),
+            target: (2, 1)
+        }
+    );
+    assert!(level.can_move(block, (2, 1)));
+    level.move_block(block, (2, 1));
+    assert!(level.solved);
+    level.undo();
+    assert!(!level.solved);
+
+    let level = &pack::parse(pack("clear vertical from top").as_bytes())[0];
+    assert_eq!(
+        level.win,
+        WinCondition::Clear {
+            dir: BlockDir::UpDown,
+            edge: Edge::Top
+        }
+    );
+    assert!(!level.win.is_met(level));
+
+    // The first level takes 15 moves.
+    let level = &pack::parse(pack("exit within 14").as_bytes())[0];
+    assert_eq!(level.win, WinCondition::ExitWithin(14));
+    assert_eq!(
+        validate::check(level).unwrap_err(),
+        vec![Problem::OverLimit {
+            fewest: 15,
+            limit: 14
+        }]
+    );
+
+    for nonsense in &[
+        "fly",
+        "move h9 to c2",
+        "move a4 to c2",
+        "clear diagonal from top",
+    ] {
+        assert_eq!(
+            pack::parse(pack(nonsense).as_bytes())[0].win,
+            WinCondition::Exit
+        );
+    }
+}
+
+#[test]
+fn goals_other_than_getting_out_need_no_exit_and_get_no_fewest_moves() {
+    let board = "\
+&&&&&&&&
+&---**|&
+&**|**|&
+&==|**|^
+&|*|*--&
+&|***|*&
+&---*|*&
+&&&&&&&&
+";
+    let closed = board.replace('^', "&");
+    let pack = |goal: &str, board: &str| format!("# goal: {}\n{}", goal, board);
+    for goal in ["move b2 to c2", "clear vertical from top"] {
+        // The solver would only say how to get out.
+        let level = &pack::parse(pack(goal, board).as_bytes())[0];
+        assert_eq!(validate::check(level), Ok(None));
+        let level = &pack::parse(pack(goal, &closed).as_bytes())[0];
+        assert_eq!(validate::check(level), Ok(None));
+    }
+    let level = &pack::parse(pack("exit", &closed).as_bytes())[0];
+    assert_eq!(validate::check(level), Err(vec![Problem::NoExit]));
+}
+
+#[test]
+fn getting_out_of_a_phase_brings_in_the_next() {
+    let data = b"\
+# phases: 2
+&&&&&&&&
+&******&
+&******&
+&***==*^
+&******&
+&******&
+&******&
+&&&&&&&&
+
+&&&&&&&&
+&******&
+&**&***&
+^******&
+&******&
+&******&
+&******&
+&&&&&&&&
+";
+    let mut level = pack::parse(data).remove(0);
+    assert_eq!(level.phases(), 2);
+    let player = |level: &Level| {
+        level
+            .blocks
+            .iter()
+            .position(|b| b.r#type == BlockType::Player)
+            .unwrap()
+    };
+
+    let mut replay = Replay::new();
+    let mut record = |level: &Level| {
+        for &(block, to) in level.last_placed() {
+            replay.record(Duration::from_secs(0), block, to);
+        }
+    };
+
+    let index = player(&level);
+    level.move_block(index, level.slide_limit(index, 10));
+    record(&level);
+    assert_eq!((level.phase, level.solved), (1, false));
+    // The new walls are in, the old exit is gone and undo stops here.
+    let wall = level.occupant(3, 2).unwrap();
+    assert_eq!(level.block(wall).r#type, BlockType::Wall);
+    assert!(!level.undo());
+    assert_eq!(level.moves.len(), 1);
+
+    let index = player(&level);
+    level.move_block(index, level.slide_limit(index, -10));
+    record(&level);
+    assert_eq!((level.blocks[index].x1, level.blocks[index].y1), (0, 3));
+    assert!(level.solved);
+    // Played back from the start, the moves go through the same phases.
+    assert!(replay.play(&level, 2).solved);
+
+    level.reset();
+    assert_eq!((level.phase, level.solved), (0, false));
+    assert_eq!(level.occupant(3, 2), None);
+    // The same first grid alone is another level.
+    assert_ne!(level.id(), pack::parse(&data[12..84]).remove(0).id());
+}
+
+#[test]
+fn the_solver_only_answers_from_the_last_phase() {
+    let data = b"\
+# phases: 2
+&&&&&&&&
+&******&
+&******&
+&***==*^
+&******&
+&******&
+&******&
+&&&&&&&&
+
+&&&&&&&&
+&******&
+&**&***&
+^******&
+&******&
+&******&
+&******&
+&&&&&&&&
+";
+    let mut level = pack::parse(data).remove(0);
+    // Getting out of the first phase would look like solving the level.
+    assert!(solver::solve(&level, SolverConfig::default()).is_none());
+    assert_eq!(validate::check(&level), Ok(None));
+    let mut incremental = Solver::new();
+    assert_eq!(incremental.remaining(&level), None);
+
+    let player = level
+        .blocks
+        .iter()
+        .position(|b| b.r#type == BlockType::Player)
+        .unwrap();
+    level.move_block(player, level.slide_limit(player, 10));
+    assert_eq!(level.phase, 1);
+    let fresh = solver::solve(&level, SolverConfig::default()).map(|s| s.len());
+    assert_eq!(fresh, Some(1));
+    assert_eq!(incremental.remaining(&level), fresh);
+}
+
+#[test]
+fn a_goal_block_is_followed_into_the_next_phase() {
+    let data = b"\
+# phases: 2
+# goal: move f5 to f2
+&&&&&&&&
+&******&
+&******&
+&***==*^
+&****|*&
+&****|*&
+&******&
+&&&&&&&&
+
+&&&&&&&&
+&**&&**&
+&******&
+&******&
+&******&
+&******&
+&******&
+&&&&&&&&
+";
+    let mut level = pack::parse(data).remove(0);
+    for _ in 0..2 {
+        let player = level
+            .blocks
+            .iter()
+            .position(|b| b.r#type == BlockType::Player)
+            .unwrap();
+        level.move_block(player, level.slide_limit(player, 10));
+        assert_eq!((level.phase, level.solved), (1, false));
+        // The new walls come before the goal's block, so it is renumbered.
+        let block = level.block_at(5, 4).unwrap();
+        assert_eq!(
+            level.win,
+            WinCondition::Reach {
+                block,
+                from: (5, 4),
+                target: (5, 1)
+            }
+        );
+        level.move_block(block, level.slide_limit(block, -10));
+        assert!(level.solved);
+        level.reset();
+    }
+}
+
+#[test]
+fn with_gravity_vertical_blocks_fall_after_every_move() {
+    let data = b"\
+# gravity: on
+&&&&&&&&
+&******&
+&****|*&
+&==**|*^
+&***--*&
+&******&
+&******&
+&&&&&&&&
+";
+    let mut level = pack::parse(data).remove(0);
+    assert!(level.gravity());
+    let vertical = level.block_at(5, 2).unwrap();
+    let under = level.block_at(4, 4).unwrap();
+
+    // Lifting the vertical block out of the way does not last, so the
+    // block holding it up has to go instead.
+    let solution = solver::solve(&level, SolverConfig::default()).unwrap();
+    assert_eq!(solution.len(), 2);
+    assert_eq!(solution.steps[0].block, under);
+    let mut floating = level.clone();
+    floating.set_gravity(false);
+    let solution = solver::solve(&floating, SolverConfig::default()).unwrap();
+    assert_eq!(solution.steps[0].block, vertical);
+
+    let mut replay = Replay::new();
+    let mut record = |level: &Level| {
+        for &(block, to) in level.last_placed() {
+            replay.record(Duration::from_secs(0), block, to);
+        }
+    };
+    assert!(level.move_block(vertical, (5, 1)));
+    assert_eq!(level.blocks[vertical].y1, 2);
+    record(&level);
+    assert!(level.move_block(under, (3, 4)));
+    assert_eq!(level.blocks[vertical].y1, 5);
+    assert_eq!(level.last_placed().len(), 2);
+    assert_eq!(level.moves.len(), 2);
+    record(&level);
+    let fallen = level.serialize();
+
+    // Undo puts back the block that fell along with the one that moved.
+    assert!(level.undo());
+    assert_eq!((level.blocks[under].x1, level.blocks[vertical].y1), (4, 2));
+    record(&level);
+    assert!(level.move_block(under, (3, 4)));
+    record(&level);
+    assert_eq!(level.serialize(), fallen);
+    assert_eq!(replay.play(&level, replay.steps.len()).serialize(), fallen);
+}
+
+#[test]
+fn annotate_only_touches_par_comments() {
+    let level = "&&&&&&&&\r\n&---**|&\r\n&**|**|&\r\n&==|**|^\r\n\
+        &|*|*--&\r\n&|***|*&\r\n&---*|*&\r\n&&&&&&&&\r\n";
+    let data = format!(
+        "# first\r\n{}\r\n# par: 99\r\n# second\r\n{}trailing",
+        level, level
+    );
+    let annotated = pack::annotate(data.as_bytes(), &[Some(15), Some(15)]);
+    let expected = format!(
+        "# first\r\n# par: 15\r\n{}\r\n# par: 15\r\n# second\r\n{}trailing",
+        level, level
+    );
+    assert_eq!(String::from_utf8(annotated).unwrap(), expected);
+    let levels = pack::parse(expected.as_bytes());
+    assert_eq!(levels[0].par, Some(15));
+    assert_eq!(levels[1].par, Some(15));
+}
+
+#[test]
+fn edited_grids_are_written_in_place_and_new_ones_added_at_the_end() {
+    let level = "&&&&&&&&\r\n&---**|&\r\n&**|**|&\r\n&==|**|^\r\n\
+        &|*|*--&\r\n&|***|*&\r\n&---*|*&\r\n&&&&&&&&\r\n";
+    let data = format!("# name: One\r\n{}\r\n# name: Two\r\n{}", level, level);
+    let mut grid = *pack::parse(data.as_bytes())[1].template();
+    grid[xy_to_pos(1, 1)] = FLOOR;
+    let edited = pack::set_grid(data.as_bytes(), 1, &grid);
+    let changed = level.replacen("&---**|&", "&*--**|&", 1);
+    let expected = format!("# name: One\r\n{}\r\n# name: Two\r\n{}", level, changed);
+    assert_eq!(String::from_utf8(edited.clone()).unwrap(), expected);
+    let added = pack::push_level(&edited, &grid);
+    assert_eq!(
+        String::from_utf8(added.clone()).unwrap(),
+        format!("{}\r\n{}", expected, changed)
+    );
+    let levels = pack::parse(&added);
+    assert_eq!(levels.len(), 3);
+    assert_eq!(levels[2].template(), &grid);
+    assert_eq!(levels[1].name.as_deref(), Some("Two"));
+    assert_eq!(
+        pack::parse(&pack::push_level(b"", &grid))[0].template(),
+        &grid
+    );
+}
+
+#[test]
+fn pasted_levels_go_in_before_the_given_one_with_their_comments() {
+    let one = "&&&&&&&&\n&---**|&\n&**|**|&\n&==|**|^\n\
+        &|*|*--&\n&|***|*&\n&---*|*&\n&&&&&&&&\n";
+    let two = one.replacen("&---**|&", "&*--**|&", 1);
+    let data = format!(
+        "# title: Pack\n# name: One\n{}\n# name: Three\n{}",
+        one, one
+    );
+    let pasted = format!("# name: Two\n{}", two);
+    let inserted = pack::insert(data.as_bytes(), 1, pasted.as_bytes());
+    let expected = format!(
+        "# title: Pack\n\n# name: One\n{}\n# name: Two\n{}\n# name: Three\n{}",
+        one, two, one
+    );
+    assert_eq!(String::from_utf8(inserted).unwrap(), expected);
+    let appended = pack::insert(data.as_bytes(), 5, pasted.as_bytes());
+    let names: Vec<_> = pack::parse(&appended)
+        .into_iter()
+        .map(|level| level.name.unwrap())
+        .collect();
+    assert_eq!(names, ["One", "Three", "Two"]);
+    assert_eq!(pack::info(&appended).title.as_deref(), Some("Pack"));
+}
+
+#[test]
+fn star_thresholds_are_written_beside_the_par_and_read_back() {
+    let level = "&&&&&&&&\n&---**|&\n&**|**|&\n&==|**|^\n\
+        &|*|*--&\n&|***|*&\n&---*|*&\n&&&&&&&&\n";
+    let data = format!("# par: 15\n# stars: 1 2\n{}\n# par: 15\n{}", level, level);
+    let starred = pack::set_stars(data.as_bytes(), &[Some((18, 24)), Some((20, 30))]);
+    let expected = format!(
+        "# par: 15\n# stars: 18 24\n{}\n# par: 15\n# stars: 20 30\n{}",
+        level, level
+    );
+    assert_eq!(String::from_utf8(starred).unwrap(), expected);
+    let levels = pack::parse(expected.as_bytes());
+    assert_eq!(levels[0].stars, Some((18, 24)));
+    assert_eq!(levels[1].stars, Some((20, 30)));
+    assert_eq!(levels[1].par, Some(15));
+    assert_eq!(pack::parse(level.as_bytes())[0].stars, None);
+}
+
+#[test]
+fn rewriting_a_pack_keeps_each_level_with_its_comments() {
+    let first = "&&&&&&&&\n&---**|&\n&**|**|&\n&==|**|^\n\
+        &|*|*--&\n&|***|*&\n&---*|*&\n&&&&&&&&\n";
+    let second = "&&&&&&&&\n&**|***&\n&**|***&\n&==|***^\n\
+        &***---&\n&******&\n&******&\n&&&&&&&&\n";
+    let data = format!(
+        "# title: Jams\n# name: One\n{}\n\n# par: 3\n{}junk\n",
+        first, second
+    );
+    assert_eq!(
+        pack::info(data.as_bytes()),
+        pack::Info {
+            title: Some(String::from("Jams")),
+            ..Default::default()
+        }
+    );
+    let info = pack::Info {
+        title: Some(String::from("Jams")),
+        author: Some(String::from("Me")),
+        description: None,
+    };
+    let rewritten = pack::rewrite(data.as_bytes(), &info, &[1, 0]);
+    let expected = format!(
+        "# title: Jams\n# author: Me\n\n# par: 3\n{}\n# name: One\n{}",
+        second, first
+    );
+    assert_eq!(String::from_utf8(rewritten).unwrap(), expected);
+    assert_eq!(pack::info(expected.as_bytes()), info);
+    let levels = pack::parse(expected.as_bytes());
+    assert_eq!(levels[0].par, Some(3));
+    assert_eq!(levels[1].name.as_deref(), Some("One"));
+}
+
+#[test]
+fn generated_levels_are_valid_and_reproducible() {
+    let mut rng = Rng::new(7);
+    let level = generate::level(&mut rng, 6);
+    assert_eq!(validate::check(&level).ok().flatten(), level.par);
+    let again = generate::level(&mut Rng::new(7), 6);
+    assert_eq!(again.serialize(), level.serialize());
+}
+
+#[test]
+fn kids_levels_have_few_blocks_and_short_solutions() {
+    let mut rng = Rng::new(3);
+    for moves in 1..5 {
+        let level = generate::preset(&mut rng, Preset::Kids, moves);
+        assert_eq!(validate::check(&level).ok().flatten(), level.par);
+        assert!(level.par.unwrap() <= moves.max(2));
+        let others = level
+            .blocks
+            .iter()
+            .filter(|block| block.dir != BlockDir::Static && block.r#type != BlockType::Player)
+            .count();
+        assert!((1..=3).contains(&others));
+    }
+}
+
+#[test]
+fn redo_steps_forward_through_what_undo_took_back() {
+    let mut level = pack::parse(pack::BUILTIN).remove(0);
+    let start = level.serialize();
+    let solution = solver::solve(&level, SolverConfig::default()).unwrap();
+    let mut positions = vec![start];
+    for (i, step) in solution.steps.iter().take(3).enumerate() {
+        level.clock = Duration::from_secs(i as u64);
+        let from = (level.blocks[step.block].x1, level.blocks[step.block].y1);
+        level.move_block(step.block, (step.x, step.y));
+        let made = level.moves.last().unwrap();
+        assert_eq!((made.from, made.to), (from, (step.x, step.y)));
+        assert_eq!((made.index, made.at), (i, level.clock));
+        positions.push(level.serialize());
+    }
+    assert!(!level.redo());
+
+    // Back to the start and forward again, one move at a time.
+    for position in positions.iter().rev().skip(1) {
+        assert!(level.undo());
+        assert_eq!(&level.serialize(), position);
+    }
+    assert!(!level.undo());
+    level.clock = Duration::from_secs(10);
+    for position in positions.iter().skip(1) {
+        assert!(level.redo());
+        assert_eq!(&level.serialize(), position);
+    }
+    assert!(!level.redo());
+    let indices: Vec<usize> = level.moves.iter().map(|made| made.index).collect();
+    assert_eq!(indices, vec![0, 1, 2]);
+    assert_eq!(level.moves[0].at, Duration::from_secs(10));
+
+    // A new move forgets what was undone.
+    level.undo();
+    level.undo();
+    let other = solver::moves(&level)
+        .into_iter()
+        .find(|&step| step != solution.steps[1])
+        .unwrap();
+    level.move_block(other.block, (other.x, other.y));
+    assert!(!level.redo());
+    assert_eq!(level.moves.last().unwrap().index, 1);
+}
+
+#[test]
+fn undoing_and_moving_again_branches_and_any_branch_can_be_gone_back_to() {
+    let mut level = pack::parse(pack::BUILTIN).remove(0);
+    let start = level.serialize();
+    let first = solver::moves(&level);
+    let (a, b) = (first[0], first[1]);
+    level.move_block(a.block, (a.x, a.y));
+    let after_a = level.serialize();
+    let second = solver::moves(&level)[0];
+    level.move_block(second.block, (second.x, second.y));
+    let after_second = level.serialize();
+    let deepest = level.variation();
+    assert!(level.undo() && level.undo());
+    level.move_block(b.block, (b.x, b.y));
+    let after_b = level.serialize();
+    assert_eq!(level.branches(None).len(), 2);
+    assert_eq!(level.variations().len(), 3);
+
+    // Going back to the first line makes its moves again.
+    assert!(level.goto(deepest));
+    assert_eq!(level.serialize(), after_second);
+    assert_eq!(level.moves.len(), 2);
+    assert_eq!(level.line(deepest).len(), 2);
+
+    // Redo follows the branch last played from each point.
+    assert!(level.goto(None));
+    assert_eq!(level.serialize(), start);
+    assert!(level.redo());
+    assert_eq!(level.serialize(), after_a);
+    assert!(level.undo());
+
+    // Trying a move again goes down its old branch rather than a new one.
+    level.move_block(b.block, (b.x, b.y));
+    assert_eq!(level.serialize(), after_b);
+    assert_eq!(level.variations().len(), 3);
+    assert!(!level.redo());
+}
+
+#[test]
+fn resuming_a_level_brings_back_its_moves_to_undo() {
+    let mut level = pack::parse(pack::BUILTIN).remove(0);
+    let start = level.clone();
+    let solution = solver::solve(&level, SolverConfig::default()).unwrap();
+    let mut positions = vec![level.serialize()];
+    for (i, step) in solution.steps.iter().take(4).enumerate() {
+        level.clock = Duration::from_secs(i as u64);
+        level.move_block(step.block, (step.x, step.y));
+        positions.push(level.serialize());
+    }
+    let history = Replay::decode(&level.history().encode()).unwrap();
+    let mut resumed = start.clone();
+    resumed.resume(&history);
+    assert_eq!(resumed.serialize(), level.serialize());
+    assert_eq!(resumed.moves.len(), 4);
+    assert_eq!(resumed.moves[3].at, Duration::from_secs(3));
+    for position in positions.iter().rev().skip(1) {
+        assert!(resumed.undo());
+        assert_eq!(&resumed.serialize(), position);
+    }
+
+    // Moves that do not fit the level are not made.
+    let mut other = start.clone();
+    other.resume(&Replay::decode("0:99,1,1").unwrap());
+    assert!(other.moves.is_empty());
+}
+
+#[test]
+fn resuming_a_damaged_history_stops_at_the_first_move_that_cannot_be_made() {
+    let start = pack::parse(pack::BUILTIN).remove(0);
+    let step = solver::solve(&start, SolverConfig::default())
+        .unwrap()
+        .steps[0];
+    let player = start
+        .blocks
+        .iter()
+        .position(|block| block.r#type == BlockType::Player)
+        .unwrap();
+    let wall = start
+        .blocks
+        .iter()
+        .position(|block| block.r#type == BlockType::Wall)
+        .unwrap();
+    let first = format!("0:{},{},{}", step.block, step.x, step.y);
+    for bad in [
+        format!("{};10:{},1,1", first, player),
+        format!("{};10:{},2,2", first, wall),
+        format!("{};10:{},6,50", first, step.block),
+    ] {
+        let mut level = start.clone();
+        level.resume(&Replay::decode(&bad).unwrap());
+        assert_eq!(level.moves.len(), 1);
+        assert!(level.check_invariants().is_ok());
+    }
+}
+
+#[test]
+fn blocks_are_lettered_in_reading_order_and_keep_their_letters() {
+    let mut level = pack::parse(pack::BUILTIN).remove(0);
+    let labels: Vec<Option<char>> = (0..level.blocks.len()).map(|i| level.label(i)).collect();
+    let mut movable = Vec::new();
+    for (i, block) in level.blocks.iter().enumerate() {
+        match block.r#type {
+            BlockType::Player => assert_eq!(labels[i], Some('A')),
+            BlockType::Other(_) => movable.push(labels[i].unwrap()),
+            BlockType::Wall | BlockType::Exit => assert_eq!(labels[i], None),
+        }
+    }
+    let expected: Vec<char> = ('B'..='Z').take(movable.len()).collect();
+    assert_eq!(movable, expected);
+
+    let solution = solver::solve(&level, SolverConfig::default()).unwrap();
+    let step = solution.steps[0];
+    level.move_block(step.block, (step.x, step.y));
+    let moved: Vec<Option<char>> = (0..level.blocks.len()).map(|i| level.label(i)).collect();
+    assert_eq!(moved, labels);
+}
+
+#[test]
+fn reachable_cells_are_what_single_moves_can_cover() {
+    let level = pack::parse(pack::BUILTIN).remove(0);
+    for (index, block) in level.blocks.iter().enumerate() {
+        let mut reachable = level.reachable_cells(index);
+        reachable.sort_unstable();
+        if block.dir == BlockDir::Static {
+            assert!(reachable.is_empty());
+            continue;
+        }
+        let (width, height) = (block.x2 - block.x1, block.y2 - block.y1);
+        let mut expected = Vec::new();
+        for (x, y) in (0..8).flat_map(|y| (0..8).map(move |x| (x, y))) {
+            if (x, y) == (block.x1, block.y1) || !level.can_move(index, (x, y)) {
+                continue;
+            }
+            for cy in y..=y + height {
+                for cx in x..=x + width {
+                    let covered =
+                        (block.x1..=block.x2).contains(&cx) && (block.y1..=block.y2).contains(&cy);
+                    if !covered && !expected.contains(&(cx, cy)) {
+                        expected.push((cx, cy));
+                    }
+                }
+            }
+        }
+        expected.sort_unstable();
+        assert_eq!(reachable, expected, "block {}", index);
+    }
+}
+
+#[test]
+fn slides_stop_at_the_walls_and_the_first_block_in_the_way() {
+    let deltas: Vec<isize> = (-10..=10).chain([isize::MIN, isize::MAX]).collect();
+    for vertical in [false, true] {
+        for len in 2..=3 {
+            // Along row 5 for horizontal blocks and column 6 for vertical
+            // ones, clear of the player, alone or with a block across the
+            // line at `stop`.
+            for stop in std::iter::once(None).chain((1..=6).map(Some)) {
+                for start in 1..=7 - len {
+                    if stop.is_some_and(|stop| (start..start + len).contains(&stop)) {
+                        continue;
+                    }
+                    let mut candidates = vec![if vertical {
+                        (true, len, 6, start)
+                    } else {
+                        (false, len, start, 5)
+                    }];
+                    if let Some(stop) = stop {
+                        candidates.push(if vertical {
+                            (false, 2, 5, stop)
+                        } else {
+                            (true, 2, stop, 5)
+                        });
+                    }
+                    let level = Level::from(&mut build(1, &candidates).iter().copied());
+                    let (x, y) = if vertical { (6, start) } else { (start, 5) };
+                    let index = level.block_at(x, y).unwrap();
+                    let (low, high) = match stop {
+                        Some(stop) if stop < start => (stop + 1, 7 - len),
+                        Some(stop) => (1, stop - len),
+                        None => (1, 7 - len),
+                    };
+                    for &delta in &deltas {
+                        let along = (start as isize)
+                            .saturating_add(delta)
+                            .clamp(low as isize, high as isize)
+                            as usize;
+                        let expected = if vertical { (6, along) } else { (along, 5) };
+                        assert_eq!(
+                            level.slide_limit(index, delta),
+                            expected,
+                            "{} cells {} from {} by {} with a block at {:?}",
+                            len,
+                            if vertical { "down" } else { "across" },
+                            start,
+                            delta,
+                            stop,
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn setting_a_block_places_it_anywhere_it_fits_without_a_move() {
+    let mut level = pack::parse(pack::BUILTIN).remove(0);
+    let index = (0..level.blocks.len())
+        .find(|&i| level.blocks[i].dir != BlockDir::Static)
+        .unwrap();
+    let free = (0..8)
+        .flat_map(|y| (0..8).map(move |x| (x, y)))
+        .find(|&target| level.fits(index, target) && !level.can_move(index, target))
+        .unwrap();
+    assert!(level.set_block(index, free));
+    assert_eq!((level.blocks[index].x1, level.blocks[index].y1), free);
+    assert!(level.moves.is_empty());
+    assert!(!level.undo());
+
+    let wall = (0..level.blocks.len())
+        .find(|&i| level.blocks[i].r#type == BlockType::Wall)
+        .unwrap();
+    let (x, y) = (level.blocks[wall].x1, level.blocks[wall].y1);
+    assert!(!level.set_block(index, (x, y)));
+    assert!(!level.set_block(wall, free));
+}
+
+#[test]
+fn the_lane_to_the_exit_is_clear_once_nothing_is_in_it() {
+    let mut rows = b"\
+&&&&&&&&
+&******&
+&******&
+&==*|**^
+&***|**&
+&******&
+&******&
+&&&&&&&&
+"
+    .iter()
+    .copied();
+    let mut level = Level::from(&mut rows);
+    assert!(level.clear_lane().is_empty());
+    let blocker = level.block_at(4, 3).unwrap();
+    level.move_block(blocker, (4, 4));
+    assert_eq!(level.clear_lane(), vec![(3, 3), (4, 3), (5, 3), (6, 3)]);
+    let player = level.block_at(1, 3).unwrap();
+    level.move_block(player, (3, 3));
+    assert_eq!(level.clear_lane(), vec![(5, 3), (6, 3)]);
+    level.move_block(player, (6, 3));
+    assert!(level.solved);
+    assert!(level.clear_lane().is_empty());
+}
+
+#[test]
+fn the_blocking_graph_follows_blocks_in_the_way_out_from_the_player() {
+    let mut rows = b"\
+&&&&&&&&
+&******&
+&******&
+&==*|**^
+&***|**&
+&**--**&
+&******&
+&&&&&&&&
+"
+    .iter()
+    .copied();
+    let mut level = Level::from(&mut rows);
+    let player = level.block_at(1, 3).unwrap();
+    let vertical = level.block_at(4, 3).unwrap();
+    let horizontal = level.block_at(3, 5).unwrap();
+    assert_eq!(
+        blocking::graph(&level),
+        vec![(player, vertical), (vertical, horizontal)]
+    );
+    level.move_block(vertical, (4, 1));
+    assert!(blocking::graph(&level).is_empty());
+}
+
+/// A picture of `rows`, each cell `size` pixels across with a dark line
+/// along its top and left: black walls, white floor, a yellow exit, a red
+/// player and a colour of its own for each letter.
+fn picture(rows: &[&str], size: usize) -> Image {
+    let colour = |ch: u8| match ch {
+        b'&' => [0, 0, 0],
+        b'*' => [240, 240, 240],
+        b'^' => [250, 220, 40],
+        b'=' => [220, 30, 30],
+        letter => {
+            let i = (letter - b'a') as u16;
+            [(40 + i * 70 % 200) as u8, (90 + i * 50 % 150) as u8, 200]
+        }
+    };
+    let (width, height) = (rows[0].len() * size, rows.len() * size);
+    let mut pixels = Vec::new();
+    for y in 0..height {
+        for x in 0..width {
+            pixels.push(if x % size == 0 || y % size == 0 {
+                [30, 30, 30]
+            } else {
+                colour(rows[y / size].as_bytes()[x / size])
+            });
+        }
+    }
+    Image {
+        width,
+        height,
+        pixels,
+    }
+}
+
+#[test]
+fn pictures_of_boards_import_as_levels() {
+    let board = [
+        "&&&&&&&&", "&aa**b*&", "&****b*&", "&==c***^", "&**c*dd&", "&**c*ee&", "&ffgg**&",
+        "&&&&&&&&",
+    ];
+    let level = import::level(&picture(&board, 6)).unwrap();
+    let expected = b"\
+&&&&&&&&\
+&--**|*&\
+&****|*&\
+&==|***^\
+&**|*--&\
+&**|*--&\
+&--__**&\
+&&&&&&&&";
+    assert_eq!(&level.serialize()[..], &expected[..]);
+
+    // Classic boards leave out the walls and the exit.
+    let board = ["aa***b", "*****b", "==c***", "**c***", "**c*dd", "******"];
+    let level = import::level(&picture(&board, 9)).unwrap();
+    let expected = b"\
+&&&&&&&&\
+&--***|&\
+&*****|&\
+&==|***^\
+&**|***&\
+&**|*--&\
+&******&\
+&&&&&&&&";
+    assert_eq!(&level.serialize()[..], &expected[..]);
+    assert_eq!(
+        solver::solve(&level, SolverConfig::default()).map(|s| s.len()),
+        Some(2)
+    );
+
+    let bent = ["aa****", "*a***b", "==****", "******", "******", "******"];
+    assert!(import::level(&picture(&bent, 6))
+        .unwrap_err()
+        .contains("straight"));
+    let mut smudged = picture(&board, 6);
+    smudged.pixels[7 * 36 + 9] = [0, 200, 0];
+    assert!(import::level(&smudged).unwrap_err().contains("grid"));
+}
+
+#[test]
+fn replays_round_trip_and_play_back_the_solve() {
+    let start = pack::parse(pack::BUILTIN).remove(0);
+    let mut level = start.clone();
+    let mut replay = Replay::new();
+    let solution = solver::solve(&level, SolverConfig::default()).unwrap();
+    for (i, step) in solution.steps.iter().enumerate() {
+        level.move_block(step.block, (step.x, step.y));
+        replay.record(
+            Duration::from_millis(250 * i as u64),
+            step.block,
+            (step.x, step.y),
+        );
+    }
+    assert!(level.solved);
+    let decoded = Replay::decode(&replay.encode()).unwrap();
+    assert_eq!(decoded, replay);
+    assert_eq!(replay.steps_at(Duration::from_millis(600)), 3);
+    assert!(replay.play(&start, replay.steps.len()).solved);
+    assert_eq!(Replay::decode("12:x,1,2"), None);
+}
+
+#[test]
+fn replay_notes_survive_encoding_and_show_once_their_step_is_played() {
+    let mut replay = Replay::decode("0:1,2,3;250:2,4,5;500:1,2,4").unwrap();
+    replay.annotate(1, "Clear the way: 50% of the work; the rest\nfollows");
+    replay.annotate(2, "   ");
+    assert_eq!(replay.notes.len(), 1);
+    let text = replay.encode();
+    assert_eq!(text.split(';').count(), 3);
+    assert_eq!(Replay::decode(&text).unwrap(), replay);
+    assert_eq!(replay.note(1), None);
+    assert_eq!(
+        replay.note(2),
+        Some("Clear the way: 50% of the work; the rest\nfollows")
+    );
+    assert_eq!(replay.note(3), None);
+    assert_eq!(Replay::decode("0:1,2,3:bad %zz"), None);
+
+    let start = pack::parse(pack::BUILTIN).remove(0);
+    let step = solver::solve(&start, SolverConfig::default())
+        .unwrap()
+        .steps[0];
+    let with_walkthrough = |replay: &str| {
+        let mut data = format!("# replay: {}\n", replay).into_bytes();
+        data.extend_from_slice(pack::BUILTIN);
+        pack::parse(&data).remove(0).walkthrough
+    };
+    let first = format!("0:{},{},{}", step.block, step.x, step.y);
+    let walkthrough = with_walkthrough(&format!("{}:Slide it down", first)).unwrap();
+    assert_eq!(walkthrough.note(1), Some("Slide it down"));
+}
+
+#[test]
+fn walkthroughs_with_moves_that_cannot_be_made_are_left_out() {
+    let start = pack::parse(pack::BUILTIN).remove(0);
+    let step = solver::solve(&start, SolverConfig::default())
+        .unwrap()
+        .steps[0];
+    let with_walkthrough = |replay: &str| {
+        let mut data = format!("# replay: {}\n", replay).into_bytes();
+        data.extend_from_slice(pack::BUILTIN);
+        pack::parse(&data).remove(0).walkthrough
+    };
+    let first = format!("0:{},{},{}", step.block, step.x, step.y);
+    assert!(with_walkthrough(&first).is_some());
+    // A block past the last one, and a position off the board.
+    assert!(with_walkthrough("0:99,1,1").is_none());
+    assert!(with_walkthrough(&format!("{};10:{},6,50", first, step.block)).is_none());
+    assert!(with_walkthrough("0:1,6,50").is_none());
+}
+
+#[test]
+fn prompts_show_from_the_start_and_once_their_block_reaches_its_cell() {
+    let level = pack::parse(pack::BUILTIN).remove(0);
+    let step = solver::solve(&level, SolverConfig::default())
+        .unwrap()
+        .steps[0];
+    let block = level.block(step.block);
+    let cell = |x: usize, y: usize| format!("{}{}", (b'a' + x as u8) as char, y + 1);
+    let mut data = format!(
+        "# prompt: Look for the block in the way\n\
+         # prompt: {} to {}: Tip: now the next one\n\
+         # prompt: z9 to a1: Never shown\n",
+        cell(block.x1, block.y1),
+        cell(step.x, step.y)
+    )
+    .into_bytes();
+    data.extend_from_slice(pack::BUILTIN);
+    let mut level = pack::parse(&data).remove(0);
+    assert_eq!(level.prompts.len(), 2);
+    let text = |level: &Level| prompt::current(level).map(|prompt| prompt.text.clone());
+    assert_eq!(text(&level).unwrap(), "Look for the block in the way");
+    level.move_block(step.block, (step.x, step.y));
+    assert_eq!(text(&level).unwrap(), "Tip: now the next one");
+    level.undo();
+    assert_eq!(text(&level).unwrap(), "Look for the block in the way");
+    assert_eq!(text(&pack::parse(pack::BUILTIN).remove(0)), None);
+}
+
+#[test]
+fn incremental_solver_agrees_with_a_fresh_search() {
+    // With gravity on, moves cannot always be undone.
+    let falling = pack::parse(
+        b"\
+# gravity: on
+&&&&&&&&
+&******&
+&****|*&
+&==**|*^
+&***--*&
+&******&
+&******&
+&&&&&&&&
+",
+    )
+    .remove(0);
+    let mut heavy = pack::parse(pack::BUILTIN).remove(1);
+    heavy.set_gravity(true);
+    let levels = vec![pack::parse(pack::BUILTIN).remove(3), falling, heavy];
+    let mut rng = Rng::new(11);
+    for mut level in levels {
+        let mut incremental = solver::Solver::new();
+        for _ in 0..20 {
+            let fresh =
+                solver::solve(&level, SolverConfig::default()).map(|solution| solution.len());
+            assert_eq!(incremental.remaining(&level), fresh);
+            let solution = incremental.solution(&level).map(|solution| solution.len());
+            assert_eq!(solution, fresh);
+            let moves = solver::moves(&level);
+            let step = moves[rng.below(moves.len())];
+            level.move_block(step.block, (step.x, step.y));
+            if level.solved {
+                break;
+            }
+        }
+    }
+}
+
+#[test]
+fn incremental_solver_solutions_are_shortest_and_solve_the_level() {
+    let mut solver = solver::Solver::new();
+    for level in pack::parse(pack::BUILTIN).into_iter().take(4) {
+        let solution = solver.solution(&level).unwrap();
+        assert_eq!(Some(solution.len()), solver.remaining(&level));
+        let mut played = level.clone();
+        for step in &solution.steps {
+            assert!(played.move_block(step.block, (step.x, step.y)));
+        }
+        assert!(played.solved);
+    }
+}
+
+#[test]
+fn bitboard_hash_depends_only_on_the_position() {
+    let level = pack::parse(pack::BUILTIN).remove(0);
+    let moves = solver::moves(&level);
+    let (a, b) = (moves[0], moves[moves.len() - 1]);
+    assert_ne!(a.block, b.block);
+    let mut one = level.clone();
+    one.move_block(a.block, (a.x, a.y));
+    one.move_block(b.block, (b.x, b.y));
+    let mut other = level.clone();
+    other.move_block(b.block, (b.x, b.y));
+    other.move_block(a.block, (a.x, a.y));
+    assert!(one.bitboard() == other.bitboard());
+    assert_eq!(one.bitboard().zobrist(), other.bitboard().zobrist());
+    other.undo();
+    other.undo();
+    assert_eq!(other.bitboard().zobrist(), level.bitboard().zobrist());
+    assert_ne!(one.bitboard().zobrist(), level.bitboard().zobrist());
+}
+
+#[test]
+fn pack_analysis_keeps_level_order_and_reports_progress() {
+    let levels = pack::parse(pack::BUILTIN);
+    let reported = std::sync::Mutex::new(Vec::new());
+    let pars = pack::analyze(
+        &levels,
+        |level| solver::solve(level, SolverConfig::default()).map(|solution| solution.len()),
+        |done| reported.lock().unwrap().push(done),
+    );
+    let expected: Vec<Option<usize>> = levels.iter().map(|level| level.par).collect();
+    assert_eq!(pars, expected);
+    let mut reported = reported.into_inner().unwrap();
+    reported.sort_unstable();
+    assert_eq!(reported, (1..=levels.len()).collect::<Vec<_>>());
+}
+
+#[test]
+fn traced_search_proves_the_solution_is_shortest() {
+    let level = pack::parse(pack::BUILTIN).remove(0);
+    let (solution, trace) = solver::solve_traced(&level);
+    let solution = solution.unwrap();
+    assert_eq!(
+        solution.len(),
+        solver::solve(&level, SolverConfig::default())
+            .unwrap()
+            .len()
+    );
+    assert!(trace.layers[0][0] == *level.bitboard());
+    assert_eq!(trace.layers.len(), solution.len() + 1);
+    for layer in &trace.layers[..solution.len()] {
+        assert!(layer.iter().all(|position| !position.is_solved()));
+    }
+    assert!(trace.layers[solution.len()].iter().any(|p| p.is_solved()));
+}
+
+#[test]
+fn ida_star_finds_solutions_as_short_as_breadth_first() {
+    let ida = SolverConfig {
+        strategy: solver::Strategy::IdaStar,
+        ..SolverConfig::default()
+    };
+    for level in pack::parse(pack::BUILTIN).iter().take(3) {
+        let (bfs, bfs_stats) = solver::solve_with(level, SolverConfig::default());
+        let (solution, stats) = solver::solve_with(level, ida);
+        let solution = solution.unwrap();
+        assert_eq!(solution.len(), bfs.unwrap().len());
+        assert!(stats.nodes > 0 && bfs_stats.stored > 0);
+        let mut played = level.clone();
+        for step in &solution.steps {
+            assert!(played.can_move(step.block, (step.x, step.y)));
+            played.move_block(step.block, (step.x, step.y));
+        }
+        assert!(played.solved);
+    }
+}
+
+#[test]
+fn searches_give_up_when_over_budget() {
+    let level = &pack::parse(pack::BUILTIN)[0];
+    for strategy in [solver::Strategy::BreadthFirst, solver::Strategy::IdaStar] {
+        let (solution, stats) = solver::solve_with(
+            level,
+            SolverConfig {
+                strategy,
+                budget: Some(10),
+            },
+        );
+        assert!(solution.is_none());
+        assert!(stats.nodes <= 10);
+    }
+    let mut small = Solver::with_budget(10);
+    assert_eq!(small.remaining(level), None);
+    assert!(small.gave_up(level));
+    let mut roomy = Solver::with_budget(limits::SOLVER_POSITIONS);
+    assert_eq!(roomy.remaining(level), level.par);
+    assert!(!roomy.gave_up(level));
+}
+
+#[test]
+fn packs_from_outside_are_turned_away_when_too_big() {
+    assert_eq!(
+        limits::parse(pack::BUILTIN).map(|levels| levels.len()),
+        Ok(pack::parse(pack::BUILTIN).len())
+    );
+    let one = &pack::BUILTIN[..pack::entries(pack::BUILTIN)[0].end];
+    let many = [one, b"\n"].concat().repeat(limits::MAX_LEVELS + 1);
+    assert!(many.len() <= limits::MAX_BYTES);
+    assert_eq!(
+        limits::parse(&many).err(),
+        Some(limits::Exceeded::Levels(limits::MAX_LEVELS + 1))
+    );
+    let big = vec![b' '; limits::MAX_BYTES + 1];
+    assert_eq!(
+        limits::parse(&big).err(),
+        Some(limits::Exceeded::Bytes(limits::MAX_BYTES + 1))
+    );
+    let phased = [b"# phases: 99\n", one].concat();
+    assert_eq!(
+        limits::parse(&phased).err(),
+        Some(limits::Exceeded::Phases {
+            level: 0,
+            phases: 99
+        })
+    );
+}
+
+#[cfg(feature = "script")]
+#[test]
+fn scripts_move_blocks_as_undoable_moves() {
+    use unblock::script::Script;
+
+    let mut level = pack::parse(pack::BUILTIN)[0].clone();
+    let start = level.data;
+    let block = level
+        .blocks
+        .iter()
+        .position(|b| b.dir != BlockDir::Static)
+        .unwrap();
+    let script = Script::compile(
+        r#"
+        fn on_move(level, block) {
+            for y in 1..7 {
+                for x in 1..7 {
+                    if level.teleport(block, x, y) {
+                        print(`block ${block} to ${x},${y}`);
+                        return;
+                    }
+                }
+            }
+        }
+        "#,
+    )
+    .unwrap();
+    let effects = script.on_move(&mut level, 1, block).unwrap();
+    assert_eq!(effects.moved.len(), 1);
+    assert_eq!(effects.moved[0].0, block);
+    assert_eq!(effects.printed.len(), 1);
+    assert_eq!(level.moves.len(), 1);
+    assert_ne!(level.data, start);
+    assert!(level.undo());
+    assert_eq!(level.data, start);
+
+    // Hooks a script does not define do nothing.
+    let effects = script.on_solve(&mut level, 1).unwrap();
+    assert!(effects.moved.is_empty() && effects.printed.is_empty());
+}
+
+#[cfg(feature = "script")]
+#[test]
+fn scripts_are_stopped_and_give_the_level_back() {
+    use unblock::script::Script;
+
+    let mut level = pack::parse(pack::BUILTIN)[0].clone();
+    let script = Script::compile("fn on_tick(level, seconds) { loop { level.moves; } }").unwrap();
+    assert!(script
+        .on_tick(&mut level, 1, Duration::from_secs(1))
+        .is_err());
+    assert_eq!(level.data, pack::parse(pack::BUILTIN)[0].data);
+
+    let script = Script::compile("fn on_solve(level) { level.x(99) }").unwrap();
+    assert!(script.on_solve(&mut level, 1).is_err());
+    assert!(Script::compile(r#"fn on_solve(level) { eval("1") }"#).is_err());
+}